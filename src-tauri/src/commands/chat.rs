@@ -0,0 +1,208 @@
+use crate::modules::artifacts::{self, ArtifactInfo};
+use crate::modules::chat_db::{self, ChatMessage, TaskSession};
+use crate::proxy::mappers::context_manager;
+
+/// 裁剪会话，仅保留最近 `keep_last` 条消息，返回删除的消息数
+#[tauri::command]
+pub async fn trim_chat_session(session_id: String, keep_last: usize) -> Result<u64, String> {
+    if chat_db::get_session(&session_id)?.is_none() {
+        return Err(format!("Session not found: {}", session_id));
+    }
+
+    let removed = chat_db::trim_session(&session_id, keep_last)?;
+    tracing::info!(
+        "[Chat] Trimmed session {} to last {} messages ({} removed)",
+        session_id,
+        keep_last,
+        removed
+    );
+    Ok(removed)
+}
+
+/// 发送前估算消息的 token 数 (按模型族的启发式估算，用于 UI 计数)
+#[tauri::command]
+pub fn estimate_tokens(text: String, model: Option<String>) -> Result<usize, String> {
+    Ok(context_manager::estimate_text_tokens(&text, model.as_deref()) as usize)
+}
+
+/// 将会话导出为 Markdown 文本 (标题/仓库作为文档头，按角色分节并附时间戳)
+#[tauri::command]
+pub async fn export_session_markdown(session_id: String) -> Result<String, String> {
+    let session = chat_db::get_session(&session_id)?
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    let messages = chat_db::get_messages(&session_id)?;
+    Ok(render_session_markdown(&session, &messages))
+}
+
+/// 导出会话为 JSON (会话及全部消息，可用于备份/分享)
+#[tauri::command]
+pub async fn export_session_json(session_id: String) -> Result<String, String> {
+    let export = chat_db::export_session(&session_id)?
+        .ok_or_else(|| format!("Session not found: {}", session_id))?;
+    serde_json::to_string_pretty(&export).map_err(|e| e.to_string())
+}
+
+/// 从 JSON 导入会话，总是分配新的会话 id，返回新建的会话
+#[tauri::command]
+pub async fn import_session_json(data: String) -> Result<TaskSession, String> {
+    let export: chat_db::SessionExport =
+        serde_json::from_str(&data).map_err(|e| format!("Invalid session export: {}", e))?;
+    let session = chat_db::import_session(&export)?;
+    tracing::info!(
+        "[Chat] Imported session {} ({} messages) as {}",
+        export.session.id,
+        export.messages.len(),
+        session.id
+    );
+    Ok(session)
+}
+
+/// 重命名会话 (标题会被 trim 并清理，空标题或超长标题将被拒绝)
+#[tauri::command]
+pub async fn rename_session(session_id: String, title: String) -> Result<TaskSession, String> {
+    chat_db::update_session_title(&session_id, &title)
+}
+
+/// 列出会话工作区中的产物 (如 /plan 生成的计划)
+#[tauri::command]
+pub async fn list_session_artifacts(session_id: String) -> Result<Vec<ArtifactInfo>, String> {
+    artifacts::list(&session_id)
+}
+
+/// 读取会话工作区中的某个产物
+#[tauri::command]
+pub async fn load_session_artifact(session_id: String, name: String) -> Result<String, String> {
+    artifacts::load(&session_id, &name)
+}
+
+fn render_session_markdown(session: &TaskSession, messages: &[ChatMessage]) -> String {
+    let mut out = format!("# {}\n\n", escape_inline(&session.title));
+    out.push_str(&format!("- **Repository:** {}\n", escape_inline(&session.repo_name)));
+    if let Some(branch) = session.branch_name.as_deref().filter(|b| !b.is_empty()) {
+        out.push_str(&format!("- **Branch:** {}\n", escape_inline(branch)));
+    }
+    out.push_str(&format!("- **Status:** {}\n", session.status));
+    if let Some(created) = chrono::DateTime::from_timestamp(session.created_at, 0) {
+        out.push_str(&format!("- **Created:** {}\n", created.format("%Y-%m-%d %H:%M:%S UTC")));
+    }
+
+    for message in messages {
+        let timestamp = chrono::DateTime::from_timestamp_millis(message.created_at)
+            .map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "\n---\n\n## {} · {}\n\n{}\n",
+            role_heading(&message.role),
+            timestamp,
+            balance_code_fences(message.content.trim_end())
+        ));
+    }
+    out
+}
+
+fn role_heading(role: &str) -> String {
+    match role {
+        "user" => "User".to_string(),
+        "assistant" => "Assistant".to_string(),
+        "system" => "System".to_string(),
+        other => escape_inline(other),
+    }
+}
+
+/// 转义单行文本中的 Markdown 控制字符 (用于标题、仓库名等元数据)
+fn escape_inline(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '`' | '*' | '_' | '[' | ']' | '#' | '<' | '>' | '|' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            '\r' | '\n' => escaped.push(' '),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// 补齐未闭合的代码块，避免其吞掉后续消息的标题
+fn balance_code_fences(content: &str) -> String {
+    // 当前打开的代码块: (围栏字符, 长度)
+    let mut open: Option<(char, usize)> = None;
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let Some(marker) = trimmed.chars().next().filter(|c| *c == '`' || *c == '~') else {
+            continue;
+        };
+        let run = trimmed.chars().take_while(|c| *c == marker).count();
+        if run < 3 {
+            continue;
+        }
+        open = match open {
+            None => Some((marker, run)),
+            // 闭合围栏: 同种字符、长度不短于开启围栏、且后面没有信息字符串
+            Some((open_marker, open_run))
+                if marker == open_marker && run >= open_run && trimmed[run..].trim().is_empty() =>
+            {
+                None
+            }
+            still_open => still_open,
+        };
+    }
+
+    match open {
+        Some((marker, run)) => format!("{}\n{}", content, marker.to_string().repeat(run)),
+        None => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(id: i64, role: &str, content: &str, created_at: i64) -> ChatMessage {
+        ChatMessage {
+            id,
+            session_id: "s1".to_string(),
+            role: role.to_string(),
+            content: content.to_string(),
+            created_at,
+        }
+    }
+
+    #[test]
+    fn test_render_session_markdown() {
+        let session = TaskSession {
+            id: "s1".to_string(),
+            title: "Fix *login* bug".to_string(),
+            repo_name: "acme/web".to_string(),
+            branch_name: Some("main".to_string()),
+            status: "completed".to_string(),
+            created_at: 1_700_000_000,
+        };
+        let messages = vec![
+            message(1, "user", "Why does login fail?", 1_700_000_001_000),
+            // Unterminated fence must not swallow the next message
+            message(2, "assistant", "Try this:\n```rust\nlet x = 1;", 1_700_000_002_000),
+            message(3, "user", "Thanks", 1_700_000_003_000),
+        ];
+
+        let markdown = render_session_markdown(&session, &messages);
+        let expected = "# Fix \\*login\\* bug\n\n\
+            - **Repository:** acme/web\n\
+            - **Branch:** main\n\
+            - **Status:** completed\n\
+            - **Created:** 2023-11-14 22:13:20 UTC\n\
+            \n---\n\n## User · 2023-11-14 22:13:21 UTC\n\nWhy does login fail?\n\
+            \n---\n\n## Assistant · 2023-11-14 22:13:22 UTC\n\nTry this:\n```rust\nlet x = 1;\n```\n\
+            \n---\n\n## User · 2023-11-14 22:13:23 UTC\n\nThanks\n";
+        assert_eq!(markdown, expected);
+    }
+
+    #[test]
+    fn test_balance_code_fences() {
+        assert_eq!(balance_code_fences("```\ncode\n```"), "```\ncode\n```");
+        assert_eq!(balance_code_fences("````md\n```\ninner"), "````md\n```\ninner\n````");
+        assert_eq!(balance_code_fences("~~~\nx"), "~~~\nx\n~~~");
+    }
+}
@@ -15,6 +15,8 @@ pub mod security;
 pub mod workflows;
 // 导出 skills 命令 (BM25 router)
 pub mod skills;
+// 导出 chat 命令 (会话管理)
+pub mod chat;
 
 /// 列出所有账号
 #[tauri::command]
@@ -341,6 +343,10 @@ pub async fn save_config(
 ) -> Result<(), String> {
     modules::save_app_config(&config)?;
 
+    // 同步聊天会话配置
+    modules::chat_db::apply_config(&config.proxy.chat);
+    workflows::apply_widget_config(&config.proxy.widget);
+
     // 通知托盘配置已更新
     let _ = app.emit("config://updated", ());
 
@@ -358,6 +364,13 @@ pub async fn save_config(
         instance.axum_server.update_security(&config.proxy).await;
         // 更新 z.ai 配置
         instance.axum_server.update_zai(&config.proxy).await;
+        // 更新按模型限流
+        instance.axum_server.update_rate_limits(&config.proxy);
+        // 更新请求/响应体大小限制
+        crate::proxy::common::body_limit::apply_config(&config.proxy);
+        crate::proxy::middleware::cors::apply_config(&config.proxy);
+        // 更新上游重试策略
+        crate::proxy::upstream::retry::apply_config(&config.proxy);
         // 更新实验性配置
         instance
             .axum_server
@@ -845,6 +858,15 @@ pub async fn get_token_stats_summary(hours: i64) -> Result<TokenStatsSummary, St
     crate::modules::token_stats::get_summary_stats(hours)
 }
 
+/// Usage in `[from, to)` (unix seconds), aggregated by model and by account
+#[tauri::command]
+pub async fn get_usage_stats(
+    from: i64,
+    to: i64,
+) -> Result<crate::modules::token_stats::UsageSummary, String> {
+    crate::modules::token_stats::get_usage_stats(from, to)
+}
+
 #[tauri::command]
 pub async fn get_token_stats_by_model(
     hours: i64,
@@ -143,8 +143,7 @@ pub async fn internal_start_proxy_service(
         .unwrap_or(0);
 
     if active_accounts == 0 {
-        let zai_enabled = config.zai.enabled
-            && !matches!(config.zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
+        let zai_enabled = config.zai.dispatch_enabled();
         if !zai_enabled {
             tracing::warn!("沒有可用賬號，反代邏輯將暫停，請通過管理界面添加。");
             return Ok(ProxyStatus {
@@ -183,6 +182,24 @@ pub async fn internal_start_proxy_service(
     })
 }
 
+/// Check whether `bind_address:port` can be bound right now.
+/// Returns Ok(false) when the port is in use, Err for an invalid address or other bind failures.
+#[tauri::command]
+pub fn check_port_available(port: u16, bind_address: &str) -> Result<bool, String> {
+    let ip: std::net::IpAddr = bind_address
+        .parse()
+        .map_err(|e| format!("Invalid bind address {}: {}", bind_address, e))?;
+
+    match std::net::TcpListener::bind((ip, port)) {
+        Ok(listener) => {
+            drop(listener);
+            Ok(true)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::AddrInUse => Ok(false),
+        Err(e) => Err(format!("Failed to bind {}:{}: {}", bind_address, port, e)),
+    }
+}
+
 /// 确保管理服务器正在运行
 pub async fn ensure_admin_server(
     config: ProxyConfig,
@@ -209,12 +226,27 @@ pub async fn ensure_admin_server(
         monitor_lock.as_ref().unwrap().clone()
     };
 
+    // 启动前检查端口占用，给出友好的错误提示
+    if !check_port_available(config.port, config.get_bind_address())? {
+        return Err(format!(
+            "端口 {} 已被占用，请在设置中更换端口或关闭占用该端口的程序 (Port {} is already in use)",
+            config.port, config.port
+        ));
+    }
+
     // 默认空 TokenManager 用于管理界面
     let app_data_dir = crate::modules::account::get_data_dir()?;
     let token_manager = Arc::new(TokenManager::new(app_data_dir));
     // [NEW] 加载账号数据，否则管理界面统计为 0
     let _ = token_manager.load_accounts().await;
 
+    // 正则模型映射只在启动 (及热更新) 时编译一次；无效规则记录日志后跳过，不阻止管理服务器启动
+    crate::proxy::common::model_mapping::set_valid_regex_mappings(&config.custom_mapping_regex);
+    // 请求/响应体大小限制需在构建路由前生效
+    crate::proxy::common::body_limit::apply_config(&config);
+    crate::proxy::middleware::cors::apply_config(&config);
+    crate::proxy::upstream::retry::apply_config(&config);
+
     let (axum_server, server_handle) =
         match crate::proxy::AxumServer::start(
             config.get_bind_address().to_string(),
@@ -243,6 +275,7 @@ pub async fn ensure_admin_server(
 
     // [NEW] Initialize UA rotation from config
     axum_server.update_user_agent(&config).await;
+    axum_server.update_rate_limits(&config);
 
     Ok(())
 }
@@ -252,17 +285,17 @@ pub async fn ensure_admin_server(
 pub async fn stop_proxy_service(
     state: State<'_, ProxyServiceState>,
 ) -> Result<(), String> {
-    let mut instance_lock = state.instance.write().await;
-
-    if instance_lock.is_none() {
+    // 取出实例后立即释放写锁，排空期间状态查询等命令不被阻塞
+    let instance = state.instance.write().await.take();
+    let Some(instance) = instance else {
         return Err("服务未运行".to_string());
-    }
+    };
 
     // 停止 Axum 服务器 (仅逻辑停止，不杀死进程)
-    if let Some(instance) = instance_lock.take() {
-        instance.axum_server.set_running(false).await;
-        // 已移除 instance.axum_server.stop() 调用，防止杀死 Admin Server
-    }
+    instance.axum_server.set_running(false).await;
+    // 让进行中的聊天会话写完助手回复后再关闭连接
+    instance.axum_server.drain_connections(crate::proxy::shutdown::DRAIN_TIMEOUT).await;
+    // 已移除 instance.axum_server.stop() 调用，防止杀死 Admin Server
 
     Ok(())
 }
@@ -500,6 +533,7 @@ pub async fn update_model_mapping(
     // 2. 无论是否运行，都保存到全局配置持久化
     let mut app_config = crate::modules::config::load_app_config().map_err(|e| e)?;
     app_config.proxy.custom_mapping = config.custom_mapping;
+    app_config.proxy.custom_mapping_regex = config.custom_mapping_regex;
     crate::modules::config::save_app_config(&app_config).map_err(|e| e)?;
 
     Ok(())
@@ -580,9 +614,7 @@ pub async fn fetch_zai_models(
     let url = join_base_url(&zai.base_url, "/v1/models");
 
     let mut builder = reqwest::Client::builder().timeout(Duration::from_secs(request_timeout.max(5)));
-    if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
-        let proxy = reqwest::Proxy::all(&upstream_proxy.url)
-            .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
+    if let Some(proxy) = upstream_proxy.build_proxy()? {
         builder = builder.proxy(proxy);
     }
     let client = builder
@@ -616,6 +648,137 @@ pub async fn fetch_zai_models(
     Ok(models)
 }
 
+/// Result of a z.ai connectivity test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZaiTestResult {
+    pub success: bool,
+    /// HTTP status of the upstream response (None when the request never got one)
+    pub status: Option<u16>,
+    pub latency_ms: u64,
+    pub model: String,
+    /// Error body or transport error on failure, with the api_key masked
+    pub error: Option<String>,
+}
+
+/// Replace every occurrence of the api_key so it can't leak through echoed error bodies
+fn mask_api_key(text: &str, api_key: &str) -> String {
+    let key = api_key.trim();
+    if key.is_empty() {
+        return text.to_string();
+    }
+    text.replace(key, "***")
+}
+
+/// Send a minimal Anthropic-format request to the configured z.ai endpoint and report
+/// whether it succeeded, using the saved `upstream_proxy` and `request_timeout`.
+#[tauri::command]
+pub async fn test_zai_connection() -> Result<ZaiTestResult, String> {
+    let config = crate::modules::config::load_app_config()?.proxy;
+    let zai = &config.zai;
+    if zai.base_url.trim().is_empty() {
+        return Err("z.ai base_url is empty".to_string());
+    }
+    if zai.api_key.trim().is_empty() {
+        return Err("z.ai api_key is not set".to_string());
+    }
+
+    let url = join_base_url(&zai.base_url, "/v1/messages");
+    let model = zai.models.haiku.clone();
+
+    let mut builder =
+        reqwest::Client::builder().timeout(Duration::from_secs(config.request_timeout.max(5)));
+    if let Some(proxy) = config.upstream_proxy.build_proxy()? {
+        builder = builder.proxy(proxy);
+    }
+    let client = builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {}", e))?;
+
+    let body = serde_json::json!({
+        "model": model,
+        "max_tokens": 1,
+        "messages": [{ "role": "user", "content": "ping" }],
+    });
+
+    let started = std::time::Instant::now();
+    let result = client
+        .post(&url)
+        .header("x-api-key", &zai.api_key)
+        .header("anthropic-version", "2023-06-01")
+        .header("content-type", "application/json")
+        .json(&body)
+        .send()
+        .await;
+
+    let (status, error) = match result {
+        Ok(resp) => {
+            let status = resp.status();
+            if status.is_success() {
+                (Some(status.as_u16()), None)
+            } else {
+                let text = resp.text().await.unwrap_or_default();
+                let preview: String = text.chars().take(4000).collect();
+                (Some(status.as_u16()), Some(mask_api_key(&preview, &zai.api_key)))
+            }
+        }
+        Err(e) => (
+            None,
+            Some(mask_api_key(&format!("Upstream request failed: {}", e), &zai.api_key)),
+        ),
+    };
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    match &error {
+        None => tracing::info!("[z.ai] Connection test succeeded in {}ms", latency_ms),
+        Some(err) => tracing::warn!(
+            "[z.ai] Connection test failed (status {:?}, {}ms): {}",
+            status,
+            latency_ms,
+            err
+        ),
+    }
+
+    Ok(ZaiTestResult {
+        success: error.is_none(),
+        status,
+        latency_ms,
+        model,
+        error,
+    })
+}
+
+/// Show which mapping layer decides the target for `model` under the saved config
+/// (works without a running proxy; see `current_backend_for` for the live decision)
+#[tauri::command]
+pub async fn preview_model_mapping(
+    model: String,
+) -> Result<crate::proxy::common::model_mapping::ModelResolution, String> {
+    let config = crate::modules::config::load_app_config()?;
+    Ok(crate::proxy::common::model_mapping::resolve_model(&model, &config.proxy))
+}
+
+/// Inspect which backend (Google pool or z.ai) a model would be dispatched to right now
+#[tauri::command]
+pub async fn current_backend_for(
+    state: State<'_, ProxyServiceState>,
+    model: String,
+) -> Result<crate::proxy::providers::dispatch::BackendSelection, String> {
+    let admin_lock = state.admin_server.read().await;
+    if let Some(admin) = admin_lock.as_ref() {
+        Ok(admin.axum_server.current_backend_for(&model).await)
+    } else {
+        Err("服务未运行".to_string())
+    }
+}
+
+/// Replay a captured OpenAI SSE stream file through the non-streaming collector
+#[tauri::command]
+pub async fn replay_openai_capture(
+    path: String,
+) -> Result<crate::proxy::mappers::openai::OpenAIResponse, String> {
+    crate::proxy::mappers::openai::collector::collect_from_file(&path).await
+}
+
 /// 获取当前调度配置
 #[tauri::command]
 pub async fn get_proxy_scheduling_config(
@@ -658,6 +821,74 @@ pub async fn clear_proxy_session_bindings(
     }
 }
 
+// ===== User-Agent 轮换池管理 =====
+
+/// 修改并持久化 User-Agent 池；服务运行中则立即热更新轮换设置
+async fn update_user_agent_pool<T>(
+    state: &ProxyServiceState,
+    mutate: impl FnOnce(&mut ProxyConfig) -> Result<T, String>,
+) -> Result<T, String> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| format!("加载配置失败: {}", e))?;
+    let result = mutate(&mut app_config.proxy)?;
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| format!("保存配置失败: {}", e))?;
+
+    let mut instance_lock = state.instance.write().await;
+    if let Some(instance) = instance_lock.as_mut() {
+        instance.config.user_agent_pool = app_config.proxy.user_agent_pool.clone();
+        instance.config.user_agent_weights = app_config.proxy.user_agent_weights.clone();
+        instance.axum_server.update_user_agent(&instance.config).await;
+    }
+    Ok(result)
+}
+
+/// 获取 User-Agent 轮换池
+#[tauri::command]
+pub async fn get_user_agent_pool() -> Result<Vec<String>, String> {
+    Ok(crate::modules::config::load_app_config()?.proxy.user_agent_pool)
+}
+
+/// 向 User-Agent 轮换池追加一项 (校验格式，拒绝重复)
+#[tauri::command]
+pub async fn add_user_agent(
+    state: State<'_, ProxyServiceState>,
+    ua: String,
+) -> Result<Vec<String>, String> {
+    update_user_agent_pool(&state, |config| {
+        config.add_user_agent(&ua)?;
+        Ok(config.user_agent_pool.clone())
+    })
+    .await
+}
+
+/// 删除 User-Agent 轮换池中指定位置的项
+#[tauri::command]
+pub async fn remove_user_agent(
+    state: State<'_, ProxyServiceState>,
+    index: usize,
+) -> Result<Vec<String>, String> {
+    update_user_agent_pool(&state, |config| {
+        config.remove_user_agent(index)?;
+        Ok(config.user_agent_pool.clone())
+    })
+    .await
+}
+
+/// 调整 User-Agent 轮换池顺序 (将 from 位置的项移动到 to)
+#[tauri::command]
+pub async fn reorder_user_agent(
+    state: State<'_, ProxyServiceState>,
+    from: usize,
+    to: usize,
+) -> Result<Vec<String>, String> {
+    update_user_agent_pool(&state, |config| {
+        config.reorder_user_agent(from, to)?;
+        Ok(config.user_agent_pool.clone())
+    })
+    .await
+}
+
 // ===== [FIX #820] 固定账号模式命令 =====
 
 /// 设置优先使用的账号（固定账号模式）
@@ -734,3 +965,34 @@ pub async fn clear_all_proxy_rate_limits(
         Err("服务未运行".to_string())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_port_available_reports_bound_port() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        assert_eq!(check_port_available(port, "127.0.0.1"), Ok(false));
+
+        drop(listener);
+        assert_eq!(check_port_available(port, "127.0.0.1"), Ok(true));
+    }
+
+    #[test]
+    fn test_check_port_available_rejects_invalid_address() {
+        assert!(check_port_available(8045, "not-an-ip").is_err());
+    }
+
+    #[test]
+    fn test_mask_api_key_hides_echoed_key() {
+        let body = r#"{"error":{"message":"invalid key zai-secret-123"}}"#;
+        let masked = mask_api_key(body, "zai-secret-123");
+        assert!(!masked.contains("zai-secret-123"));
+        assert!(masked.contains("invalid key ***"));
+        // An empty key leaves the text untouched
+        assert_eq!(mask_api_key(body, ""), body);
+    }
+}
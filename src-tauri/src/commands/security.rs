@@ -101,10 +101,8 @@ pub async fn get_ip_blacklist() -> Result<Vec<security_db::IpBlacklistEntry>, St
 pub async fn add_ip_to_blacklist(
     request: AddBlacklistRequest,
 ) -> Result<(), String> {
-    // 验证 IP 格式
-    if !is_valid_ip_pattern(&request.ip_pattern) {
-        return Err("Invalid IP pattern. Use IP address or CIDR notation (e.g., 192.168.1.0/24)".to_string());
-    }
+    // 验证 IP 格式 (IPv4/IPv6 地址或 CIDR)
+    security_db::parse_ip_pattern(&request.ip_pattern)?;
     
     security_db::add_to_blacklist(
         &request.ip_pattern,
@@ -146,6 +144,12 @@ pub async fn check_ip_in_blacklist(ip: String) -> Result<bool, String> {
     security_db::is_ip_in_blacklist(&ip)
 }
 
+/// 获取当前被自动封禁的 IP (含过期时间)
+#[tauri::command]
+pub async fn get_auto_banned_ips() -> Result<Vec<security_db::IpBlacklistEntry>, String> {
+    security_db::get_auto_banned_ips()
+}
+
 // ==================== IP 白名单命令 ====================
 
 /// 获取 IP 白名单列表
@@ -159,10 +163,8 @@ pub async fn get_ip_whitelist() -> Result<Vec<security_db::IpWhitelistEntry>, St
 pub async fn add_ip_to_whitelist(
     request: AddWhitelistRequest,
 ) -> Result<(), String> {
-    // 验证 IP 格式
-    if !is_valid_ip_pattern(&request.ip_pattern) {
-        return Err("Invalid IP pattern. Use IP address or CIDR notation (e.g., 192.168.1.0/24)".to_string());
-    }
+    // 验证 IP 格式 (IPv4/IPv6 地址或 CIDR)
+    security_db::parse_ip_pattern(&request.ip_pattern)?;
     
     security_db::add_to_whitelist(
         &request.ip_pattern,
@@ -251,6 +253,61 @@ pub async fn update_security_config(
     Ok(())
 }
 
+/// 轮换反代 api_key: 生成新密钥并持久化；服务运行中则立即生效，旧密钥在宽限期内仍可用
+#[tauri::command]
+pub async fn rotate_api_key(
+    app_state: State<'_, crate::commands::proxy::ProxyServiceState>,
+) -> Result<String, String> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| format!("Failed to load config: {}", e))?;
+    let new_key = app_config.proxy.rotate_api_key(chrono::Utc::now().timestamp());
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| format!("Failed to save config: {}", e))?;
+
+    {
+        let mut instance_lock = app_state.instance.write().await;
+        if let Some(instance) = instance_lock.as_mut() {
+            instance.config.api_key = new_key.clone();
+            instance.config.retired_api_key = app_config.proxy.retired_api_key.clone();
+            instance.axum_server.update_security(&instance.config).await;
+        }
+    }
+
+    tracing::info!(
+        "[Security] API key rotated (previous key accepted for {}s)",
+        app_config.proxy.api_key_grace_secs
+    );
+    Ok(new_key)
+}
+
+/// 设置 Web UI 管理密码: 仅持久化加盐哈希；空字符串表示清除 (回退使用 api_key)
+#[tauri::command]
+pub async fn set_admin_password(
+    password: String,
+    app_state: State<'_, crate::commands::proxy::ProxyServiceState>,
+) -> Result<(), String> {
+    let mut app_config = crate::modules::config::load_app_config()
+        .map_err(|e| format!("Failed to load config: {}", e))?;
+    app_config.proxy.set_admin_password(&password);
+    crate::modules::config::save_app_config(&app_config)
+        .map_err(|e| format!("Failed to save config: {}", e))?;
+
+    {
+        let mut instance_lock = app_state.instance.write().await;
+        if let Some(instance) = instance_lock.as_mut() {
+            instance.config.admin_password = None;
+            instance.config.admin_password_hash = app_config.proxy.admin_password_hash.clone();
+            instance.axum_server.update_security(&instance.config).await;
+        }
+    }
+
+    tracing::info!(
+        "[Security] Web UI password {}",
+        if password.is_empty() { "cleared (falls back to API key)" } else { "updated" }
+    );
+    Ok(())
+}
+
 // ==================== 统计分析命令 ====================
 
 /// 获取 IP Token 消耗统计
@@ -267,55 +324,14 @@ pub async fn get_ip_token_stats(
 
 // ==================== 辅助函数 ====================
 
-/// 验证 IP 模式格式 (支持单个 IP 和 CIDR)
-fn is_valid_ip_pattern(pattern: &str) -> bool {
-    // 检查是否为 CIDR 格式
-    if pattern.contains('/') {
-        let parts: Vec<&str> = pattern.split('/').collect();
-        if parts.len() != 2 {
-            return false;
-        }
-        
-        // 验证 IP 部分
-        if !is_valid_ip(parts[0]) {
-            return false;
-        }
-        
-        // 验证掩码部分
-        if let Ok(mask) = parts[1].parse::<u8>() {
-            return mask <= 32;
-        }
-        return false;
-    }
-    
-    // 单个 IP 地址
-    is_valid_ip(pattern)
-}
-
-/// 验证 IP 地址格式
-fn is_valid_ip(ip: &str) -> bool {
-    let parts: Vec<&str> = ip.split('.').collect();
-    if parts.len() != 4 {
-        return false;
-    }
-    
-    for part in parts {
-        if let Ok(num) = part.parse::<u8>() {
-            if num > 255 {
-                return false;
-            }
-        } else {
-            return false;
-        }
-    }
-    
-    true
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn is_valid_ip_pattern(pattern: &str) -> bool {
+        security_db::parse_ip_pattern(pattern).is_ok()
+    }
+
     #[test]
     fn test_valid_ip_patterns() {
         assert!(is_valid_ip_pattern("192.168.1.1"));
@@ -323,6 +339,8 @@ mod tests {
         assert!(is_valid_ip_pattern("172.16.0.0/16"));
         assert!(is_valid_ip_pattern("192.168.1.0/24"));
         assert!(is_valid_ip_pattern("8.8.8.8/32"));
+        assert!(is_valid_ip_pattern("2001:db8::1"));
+        assert!(is_valid_ip_pattern("2001:db8::/32"));
     }
 
     #[test]
@@ -332,5 +350,6 @@ mod tests {
         assert!(!is_valid_ip_pattern("192.168.1.1/33"));
         assert!(!is_valid_ip_pattern("192.168.1.1/"));
         assert!(!is_valid_ip_pattern("invalid"));
+        assert!(!is_valid_ip_pattern("2001:db8::/129"));
     }
 }
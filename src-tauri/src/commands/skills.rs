@@ -1,10 +1,15 @@
 // Skills router integration commands
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use once_cell::sync::{Lazy, OnceCell};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::SystemTime;
 use tauri::State;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+use crate::proxy::config::SkillsConfig;
 
 /// Skill selection result from BM25 router
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -34,10 +39,66 @@ pub struct SelectionLimits {
 }
 
 /// Skill metadata from index
-#[derive(Debug, Deserialize)]
-struct SkillMetadata {
-    id: String,
-    path: String,
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct SkillMetadata {
+    pub id: String,
+    pub path: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub size_bytes: Option<usize>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub category: Option<String>,
+}
+
+impl SkillMetadata {
+    /// Indexed size, falling back to the file size on disk (0 when unreadable)
+    pub fn effective_size(&self) -> usize {
+        self.size_bytes.unwrap_or_else(|| {
+            std::fs::metadata(&self.path).map(|m| m.len() as usize).unwrap_or(0)
+        })
+    }
+}
+
+/// Index entry as exposed to the skills browser
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct SkillInfo {
+    pub id: String,
+    pub name: String,
+    pub path: String,
+    pub size_bytes: usize,
+    pub category: Option<String>,
+    pub tags: Vec<String>,
+    /// Personas whose category matches this skill's category
+    pub personas: Vec<String>,
+}
+
+impl SkillInfo {
+    fn from_metadata(skill: &SkillMetadata, persona_categories: &HashMap<String, String>) -> Self {
+        let mut personas: Vec<String> = match &skill.category {
+            Some(category) => persona_categories
+                .iter()
+                .filter(|(_, c)| *c == category)
+                .map(|(p, _)| p.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+        personas.sort();
+
+        Self {
+            id: skill.id.clone(),
+            name: skill.name.clone().unwrap_or_else(|| skill.id.clone()),
+            path: skill.path.clone(),
+            size_bytes: skill.effective_size(),
+            category: skill.category.clone(),
+            tags: skill.tags.clone(),
+            personas,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -45,49 +106,369 @@ struct SkillsIndex {
     skills: Vec<SkillMetadata>,
 }
 
-/// Select top-K skills using BM25 router
+/// Path of the skills index written by the indexer (`~/.agent/skills-index.json`)
+fn skills_index_path() -> Result<PathBuf, String> {
+    #[cfg(test)]
+    let home = crate::modules::account::home_dir().ok_or("HOME/USERPROFILE not set")?;
+    #[cfg(not(test))]
+    let home = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map_err(|_| "HOME/USERPROFILE not set".to_string())?;
+
+    Ok(PathBuf::from(home).join(".agent").join("skills-index.json"))
+}
+
+/// Parsed index plus the mtime it was read at
+struct CachedSkillsIndex {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    skills: Arc<Vec<SkillMetadata>>,
+}
+
+/// In-memory copy of `skills-index.json`, reloaded when the file's mtime changes
+static SKILLS_INDEX_CACHE: Lazy<RwLock<Option<CachedSkillsIndex>>> = Lazy::new(|| RwLock::new(None));
+
+/// Read all skill entries from the index (cached; re-read only when the file changes)
+pub fn load_skills_index() -> Result<Vec<SkillMetadata>, String> {
+    let index_path = skills_index_path()?;
+    let skills = load_index_cached(&index_path, &SKILLS_INDEX_CACHE)?;
+    Ok(skills.as_ref().clone())
+}
+
+fn load_index_cached(
+    index_path: &Path,
+    cache: &RwLock<Option<CachedSkillsIndex>>,
+) -> Result<Arc<Vec<SkillMetadata>>, String> {
+    let modified = match std::fs::metadata(index_path) {
+        Ok(meta) => meta.modified().ok(),
+        Err(_) => {
+            return Err(format!(
+                "Skills index not found at: {}. Run: npm run index",
+                index_path.display()
+            ));
+        }
+    };
+
+    if let Some(cached) = cache.read().unwrap().as_ref() {
+        if cached.path == index_path && modified.is_some() && cached.modified == modified {
+            return Ok(cached.skills.clone());
+        }
+    }
+
+    let index_content = std::fs::read_to_string(index_path)
+        .map_err(|e| format!("Failed to read index: {}", e))?;
+
+    let index: SkillsIndex = serde_json::from_str(&index_content)
+        .map_err(|e| format!("Failed to parse index: {}", e))?;
+
+    let skills = Arc::new(index.skills);
+    debug!("Loaded skills index ({} skills) from {}", skills.len(), index_path.display());
+    *cache.write().unwrap() = Some(CachedSkillsIndex {
+        path: index_path.to_path_buf(),
+        modified,
+        skills: skills.clone(),
+    });
+
+    Ok(skills)
+}
+
+/// Drop the cached skills index so the next request re-reads it (call after re-indexing)
+#[tauri::command]
+pub async fn reload_skills_index() -> Result<usize, String> {
+    *SKILLS_INDEX_CACHE.write().unwrap() = None;
+    let count = load_skills_index()?.len();
+    info!("Skills index reloaded: {} skills", count);
+    Ok(count)
+}
+
+/// Apply per-message skill overrides on top of a BM25 selection.
+/// Excluded skills are removed; included skills must exist in the index (and in
+/// `allowlist` when given) and are appended only while they fit in `max_bytes`.
+/// Returns the ids of included skills skipped for budget.
+pub fn apply_skill_overrides(
+    selection: &mut SkillSelection,
+    include: &[String],
+    exclude: &[String],
+    index: &[SkillMetadata],
+    allowlist: Option<&[String]>,
+    max_bytes: usize,
+) -> Result<Vec<String>, String> {
+    for id in include {
+        if !index.iter().any(|s| &s.id == id) {
+            return Err(format!("Unknown skill in include_skills: {}", id));
+        }
+        if let Some(allowed) = allowlist {
+            if !allowed.contains(id) {
+                return Err(format!("Skill '{}' is not allowed in widget mode", id));
+            }
+        }
+    }
+
+    selection.skills.retain(|s| !exclude.contains(&s.id));
+    selection.total_bytes = selection.skills.iter().map(|s| s.size_bytes).sum();
+
+    let mut skipped = Vec::new();
+    for id in include {
+        if exclude.contains(id) || selection.skills.iter().any(|s| &s.id == id) {
+            continue;
+        }
+        // Validated above
+        let meta = index.iter().find(|s| &s.id == id).unwrap();
+        let size_bytes = meta.effective_size();
+
+        if selection.total_bytes + size_bytes > max_bytes {
+            skipped.push(id.clone());
+            continue;
+        }
+
+        selection.total_bytes += size_bytes;
+        selection.skills.push(SkillScore {
+            id: meta.id.clone(),
+            name: meta.name.clone().unwrap_or_else(|| meta.id.clone()),
+            score: 0.0,
+            matched_terms: Vec::new(),
+            size_bytes,
+        });
+    }
+
+    selection.limits.actual_skills = selection.skills.len();
+    selection.limits.actual_bytes = selection.total_bytes;
+
+    Ok(skipped)
+}
+
+/// Cap a selection at the configured per-message ceiling.
+/// Keeps the first `max_skills` skills in rank order, then drops from the tail until the
+/// total fits `max_bytes`. Widget limits are applied afterwards and are stricter.
+pub fn apply_skill_ceiling(selection: &mut SkillSelection, max_skills: usize, max_bytes: usize) {
+    selection.skills.truncate(max_skills);
+    let mut total: usize = selection.skills.iter().map(|s| s.size_bytes).sum();
+    while total > max_bytes {
+        match selection.skills.pop() {
+            Some(dropped) => total -= dropped.size_bytes,
+            None => break,
+        }
+    }
+
+    selection.total_bytes = total;
+    selection.limits.max_skills = max_skills;
+    selection.limits.max_bytes = max_bytes;
+    selection.limits.actual_skills = selection.skills.len();
+    selection.limits.actual_bytes = total;
+}
+
+/// Default router location relative to the repo root / resource directory
+const DEFAULT_ROUTER_SCRIPT: &str = "tools/skills-indexer/src/02-router.ts";
+
+/// App resource directory (set at startup for bundled builds)
+static RESOURCE_DIR: OnceCell<PathBuf> = OnceCell::new();
+
+/// Record the app resource directory so bundled builds can locate the router
+pub fn set_resource_dir(dir: PathBuf) {
+    let _ = RESOURCE_DIR.set(dir);
+}
+
+/// Candidate router script paths in lookup order
+fn router_script_candidates(
+    configured: Option<&str>,
+    cwd: Option<&Path>,
+    resource_dir: Option<&Path>,
+) -> Vec<PathBuf> {
+    match configured.map(str::trim).filter(|p| !p.is_empty()) {
+        Some(path) if Path::new(path).is_absolute() => vec![PathBuf::from(path)],
+        Some(path) => resource_dir
+            .into_iter()
+            .chain(cwd)
+            .map(|base| base.join(path))
+            .collect(),
+        None => cwd
+            .into_iter()
+            .chain(resource_dir)
+            .map(|base| base.join(DEFAULT_ROUTER_SCRIPT))
+            .collect(),
+    }
+}
+
+/// Resolve the router script, listing every attempted path when none exists
+pub(crate) fn find_router_script(
+    configured: Option<&str>,
+    cwd: Option<&Path>,
+    resource_dir: Option<&Path>,
+) -> Result<PathBuf, String> {
+    let candidates = router_script_candidates(configured, cwd, resource_dir);
+    if let Some(found) = candidates.iter().find(|p| p.exists()) {
+        return Ok(found.clone());
+    }
+
+    let attempted = candidates
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    Err(format!("Skills router not found. Tried: {}", attempted))
+}
+
+/// Load the skills router configuration, falling back to defaults
+pub fn load_skills_config() -> SkillsConfig {
+    crate::modules::config::load_app_config()
+        .map(|config| config.proxy.skills)
+        .unwrap_or_default()
+}
+
+/// Compute the effective K for a query.
+/// With adaptive mode off, `base_k` is returned unchanged; otherwise K grows with the
+/// query's token count within `[adaptive_k_min, adaptive_k_max]`. Widget caps are
+/// applied after selection and still win.
+pub fn effective_k(query: &str, base_k: usize, config: &SkillsConfig) -> usize {
+    if !config.adaptive_k {
+        return base_k;
+    }
+
+    let min_k = config.adaptive_k_min.max(1);
+    let max_k = config.adaptive_k_max.max(min_k);
+    let tokens = query.split_whitespace().count();
+    let extra = tokens / config.adaptive_k_tokens_per_skill.max(1);
+
+    (min_k + extra).min(max_k)
+}
+
+/// Common words that carry no signal for skill matching
+const EXPANSION_STOPWORDS: &[&str] = &[
+    "about", "after", "again", "also", "been", "before", "being", "could", "does", "doing",
+    "from", "have", "here", "into", "just", "like", "make", "more", "need", "only", "please",
+    "should", "some", "than", "that", "then", "there", "these", "they", "this", "those",
+    "want", "were", "what", "when", "where", "which", "while", "will", "with", "would", "your",
+];
+
+/// Expand a terse query with key terms from recent session messages.
+/// Terms are ranked by frequency (ties broken by recency) and only terms not already in
+/// the query are appended, so repeated expansion is stable.
+pub fn expand_query(query: &str, context: &[String], max_terms: usize) -> String {
+    let tokenize = |text: &str| -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric() && c != '-' && c != '_')
+            .map(|t| t.trim_matches('-').to_lowercase())
+            .filter(|t| t.len() >= 4 && !EXPANSION_STOPWORDS.contains(&t.as_str()))
+            .collect()
+    };
+
+    let query_terms: std::collections::HashSet<String> = tokenize(query).into_iter().collect();
+
+    // term -> (frequency, most recent message index)
+    let mut scores: HashMap<String, (usize, usize)> = HashMap::new();
+    for (idx, message) in context.iter().enumerate() {
+        for term in tokenize(message) {
+            if query_terms.contains(&term) {
+                continue;
+            }
+            let entry = scores.entry(term).or_insert((0, idx));
+            entry.0 += 1;
+            entry.1 = idx;
+        }
+    }
+
+    let mut ranked: Vec<(String, (usize, usize))> = scores.into_iter().collect();
+    ranked.sort_by(|a, b| {
+        b.1 .0
+            .cmp(&a.1 .0)
+            .then(b.1 .1.cmp(&a.1 .1))
+            .then(a.0.cmp(&b.0))
+    });
+
+    let extra: Vec<String> = ranked.into_iter().take(max_terms).map(|(t, _)| t).collect();
+    if extra.is_empty() {
+        return query.to_string();
+    }
+
+    format!("{} {}", query, extra.join(" "))
+}
+
+/// Select top-K skills using the native BM25 router (TS subprocess as optional fallback)
+/// `context` carries the session's recent messages for optional query expansion.
 #[tauri::command]
 pub async fn select_skills(
     query: String,
     k: Option<usize>,
     max_bytes: Option<usize>,
+    context: Option<Vec<String>>,
 ) -> Result<SkillSelection, String> {
-    let k = k.unwrap_or(8);
+    let skills_config = load_skills_config();
+    let k = effective_k(&query, k.unwrap_or(8), &skills_config);
     let max_bytes = max_bytes.unwrap_or(80000);
 
+    let query = match context {
+        Some(ref messages) if skills_config.query_expansion && !messages.is_empty() => {
+            let start = messages.len().saturating_sub(skills_config.query_expansion_messages);
+            let expanded = expand_query(&query, &messages[start..], skills_config.query_expansion_terms);
+            debug!("Expanded query with session context: {}", expanded);
+            expanded
+        }
+        _ => query,
+    };
+
     debug!("Selecting skills for query: {}", query);
     debug!("  K: {}, Max bytes: {}", k, max_bytes);
 
+    match run_native_router(&query, k, max_bytes, &skills_config) {
+        Err(e) if skills_config.ts_router_fallback => {
+            warn!("Native skills router failed ({}), falling back to TS router", e);
+            run_ts_router(&query, k, max_bytes, &skills_config)
+        }
+        result => result,
+    }
+}
+
+/// Run the TypeScript BM25 router (`tools/skills-indexer/src/02-router.ts` by default)
+fn run_ts_router(
+    query: &str,
+    k: usize,
+    max_bytes: usize,
+    skills_config: &SkillsConfig,
+) -> Result<SkillSelection, String> {
     // Get project root (where tools/ lives)
     let project_root = std::env::current_dir()
         .map_err(|e| format!("Failed to get current directory: {}", e))?;
 
-    let router_script = project_root
-        .join("tools")
-        .join("skills-indexer")
-        .join("src")
-        .join("02-router.ts");
+    let router_script = find_router_script(
+        skills_config.router_path.as_deref(),
+        Some(&project_root),
+        RESOURCE_DIR.get().map(|p| p.as_path()),
+    )?;
 
-    if !router_script.exists() {
-        return Err(format!(
-            "Skills router not found at: {}",
-            router_script.display()
-        ));
-    }
+    run_ts_router_script(&router_script, &project_root, None, query, k, max_bytes, skills_config)
+}
 
+/// Run a specific router script. `home` overrides HOME/USERPROFILE for the router process,
+/// which reads `.agent/skills-index.json` from there (used to rank a fixture index).
+pub(crate) fn run_ts_router_script(
+    router_script: &Path,
+    project_root: &Path,
+    home: Option<&Path>,
+    query: &str,
+    k: usize,
+    max_bytes: usize,
+    skills_config: &SkillsConfig,
+) -> Result<SkillSelection, String> {
     // Run TypeScript router via npx tsx
-    let output = Command::new("npx")
+    let mut command = Command::new("npx");
+    if let Some(home) = home {
+        command.env("HOME", home).env("USERPROFILE", home);
+    }
+    let output = command
         .args(&[
             "tsx",
             router_script.to_str().unwrap(),
-            &query,
+            query,
             "--k",
             &k.to_string(),
             "--max-bytes",
             &max_bytes.to_string(),
+            "--k1",
+            &skills_config.bm25_k1.to_string(),
+            "--b",
+            &skills_config.bm25_b.to_string(),
             "--json",
         ])
-        .current_dir(&project_root)
+        .current_dir(project_root)
         .output()
         .map_err(|e| format!("Failed to execute router: {}", e))?;
 
@@ -110,59 +491,226 @@ pub async fn select_skills(
     Ok(result)
 }
 
+/// Score difference for one skill between the two routers
+#[derive(Debug, Serialize, Clone)]
+pub struct ScoreDelta {
+    pub id: String,
+    pub ts_score: Option<f64>,
+    pub native_score: Option<f64>,
+    /// `native - ts` when both routers selected the skill
+    pub delta: Option<f64>,
+}
+
+/// Side-by-side result of running one query through both routers
+#[derive(Debug, Serialize, Clone)]
+pub struct RouterComparison {
+    pub query: String,
+    pub ts_skills: Vec<String>,
+    pub native_skills: Vec<String>,
+    pub score_deltas: Vec<ScoreDelta>,
+    /// Whether both routers picked the same top-k set (order ignored)
+    pub top_k_agree: bool,
+}
+
+/// Native (in-process) BM25 router, see `modules::skill_router`
+fn run_native_router(
+    query: &str,
+    k: usize,
+    max_bytes: usize,
+    skills_config: &SkillsConfig,
+) -> Result<SkillSelection, String> {
+    let params = crate::modules::skill_router::Bm25Params::from_config(skills_config);
+    crate::modules::skill_router::route(query, k, max_bytes, params)
+}
+
+/// Diff two selections for the same query
+pub fn compare_selections(query: &str, ts: &SkillSelection, native: &SkillSelection) -> RouterComparison {
+    let ts_skills: Vec<String> = ts.skills.iter().map(|s| s.id.clone()).collect();
+    let native_skills: Vec<String> = native.skills.iter().map(|s| s.id.clone()).collect();
+
+    let mut ids: Vec<String> = ts_skills.clone();
+    for id in &native_skills {
+        if !ids.contains(id) {
+            ids.push(id.clone());
+        }
+    }
+
+    let score_deltas = ids
+        .into_iter()
+        .map(|id| {
+            let ts_score = ts.skills.iter().find(|s| s.id == id).map(|s| s.score);
+            let native_score = native.skills.iter().find(|s| s.id == id).map(|s| s.score);
+            let delta = match (ts_score, native_score) {
+                (Some(t), Some(n)) => Some(n - t),
+                _ => None,
+            };
+            ScoreDelta { id, ts_score, native_score, delta }
+        })
+        .collect();
+
+    let ts_set: std::collections::HashSet<&String> = ts_skills.iter().collect();
+    let native_set: std::collections::HashSet<&String> = native_skills.iter().collect();
+
+    RouterComparison {
+        query: query.to_string(),
+        top_k_agree: ts_set == native_set,
+        ts_skills,
+        native_skills,
+        score_deltas,
+    }
+}
+
+/// Run the same query through the TS and native routers and report the differences
+#[tauri::command]
+pub async fn compare_routers(
+    query: String,
+    k: Option<usize>,
+    max_bytes: Option<usize>,
+) -> Result<RouterComparison, String> {
+    let k = k.unwrap_or(8);
+    let max_bytes = max_bytes.unwrap_or(80000);
+
+    let skills_config = load_skills_config();
+    let ts = run_ts_router(&query, k, max_bytes, &skills_config)?;
+    let native = run_native_router(&query, k, max_bytes, &skills_config)?;
+
+    let comparison = compare_selections(&query, &ts, &native);
+    info!(
+        "Router comparison for '{}': agree={}, ts={:?}, native={:?}",
+        query, comparison.top_k_agree, comparison.ts_skills, comparison.native_skills
+    );
+
+    Ok(comparison)
+}
+
+/// Skill contents plus the ids that were replaced by a placeholder or skipped
+#[derive(Debug, Default, Serialize)]
+pub struct LoadedSkills {
+    pub contents: HashMap<String, String>,
+    pub substituted: Vec<String>,
+    pub skipped: Vec<SkippedSkill>,
+    pub total_bytes: usize,
+}
+
+/// A skill left out of the load because its file failed validation
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct SkippedSkill {
+    pub id: String,
+    pub reason: String,
+}
+
+/// Placeholder used in lenient mode when a skill file is missing on disk
+pub fn missing_skill_placeholder(skill_id: &str) -> String {
+    format!("> Note: skill `{}` is unavailable (file missing since indexing).", skill_id)
+}
+
 /// Load skill content from disk
 #[tauri::command]
-pub async fn load_skill_content(skill_ids: Vec<String>) -> Result<HashMap<String, String>, String> {
+pub async fn load_skill_content(skill_ids: Vec<String>) -> Result<LoadedSkills, String> {
     debug!("Loading content for {} skills", skill_ids.len());
 
     // Read skills index
-    let home = std::env::var("HOME")
-        .or_else(|_| std::env::var("USERPROFILE"))
-        .map_err(|_| "HOME/USERPROFILE not set".to_string())?;
+    let index = load_skills_index()?;
+    let skills_config = load_skills_config();
 
-    let index_path = PathBuf::from(home)
-        .join(".agent")
-        .join("skills-index.json");
+    let loaded = load_skills_from_index(
+        &index,
+        &skill_ids,
+        skills_config.lenient_missing_skills,
+        skills_config.max_skill_file_bytes,
+    )?;
+    if !loaded.substituted.is_empty() {
+        warn!(
+            "Substituted placeholders for missing skill files: {:?}",
+            loaded.substituted
+        );
+    }
 
-    if !index_path.exists() {
-        return Err(format!(
-            "Skills index not found at: {}. Run: npm run index",
-            index_path.display()
-        ));
+    Ok(loaded)
+}
+
+/// Read a SKILL.md, rejecting files over `max_bytes` or that are not valid UTF-8.
+/// The outer error is an I/O failure; the inner one is a validation failure.
+fn read_skill_file(path: &str, max_bytes: usize) -> std::io::Result<Result<String, String>> {
+    let size = std::fs::metadata(path)?.len();
+    if size > max_bytes as u64 {
+        return Ok(Err(format!("{} bytes exceeds max_skill_file_bytes ({})", size, max_bytes)));
     }
 
-    let index_content = std::fs::read_to_string(&index_path)
-        .map_err(|e| format!("Failed to read index: {}", e))?;
+    let bytes = std::fs::read(path)?;
+    Ok(String::from_utf8(bytes).map_err(|e| format!("not valid UTF-8: {}", e)))
+}
 
-    let index: SkillsIndex = serde_json::from_str(&index_content)
-        .map_err(|e| format!("Failed to parse index: {}", e))?;
+/// Read each skill's SKILL.md. In `lenient` mode a missing file yields a placeholder
+/// (recorded in `substituted`) instead of failing the whole load. Files larger than
+/// `max_file_bytes` or not valid UTF-8 are skipped (recorded in `skipped`).
+/// Duplicate ids are loaded once, keeping the first occurrence.
+pub fn load_skills_from_index(
+    index: &[SkillMetadata],
+    skill_ids: &[String],
+    lenient: bool,
+    max_file_bytes: usize,
+) -> Result<LoadedSkills, String> {
+    let mut loaded = LoadedSkills::default();
 
-    // Load each skill
-    let mut contents = HashMap::new();
-    let mut total_bytes = 0;
+    let requested = skill_ids.len();
+    let mut seen = HashSet::new();
+    let skill_ids: Vec<&String> = skill_ids.iter().filter(|id| seen.insert(*id)).collect();
+    if skill_ids.len() < requested {
+        debug!("Collapsed {} duplicate skill ids", requested - skill_ids.len());
+    }
 
     for skill_id in skill_ids {
         // Find skill in index
         let skill = index
-            .skills
             .iter()
-            .find(|s| s.id == skill_id)
+            .find(|s| &s.id == skill_id)
             .ok_or_else(|| format!("Skill not found: {}", skill_id))?;
 
         // Read SKILL.md
-        let content = std::fs::read_to_string(&skill.path)
-            .map_err(|e| format!("Failed to read skill {}: {}", skill_id, e))?;
+        let content = match read_skill_file(&skill.path, max_file_bytes) {
+            Ok(Ok(content)) => content,
+            Ok(Err(reason)) => {
+                warn!("Skipping skill {}: {}", skill_id, reason);
+                loaded.skipped.push(SkippedSkill { id: skill_id.clone(), reason });
+                continue;
+            }
+            Err(e) if lenient && e.kind() == std::io::ErrorKind::NotFound => {
+                loaded.substituted.push(skill_id.clone());
+                missing_skill_placeholder(skill_id)
+            }
+            Err(e) => return Err(format!("Failed to read skill {}: {}", skill_id, e)),
+        };
 
         let content_len = content.len();
-        total_bytes += content_len;
-        contents.insert(skill_id.clone(), content);
+        loaded.total_bytes += content_len;
+        loaded.contents.insert(skill_id.clone(), content);
 
         debug!("  Loaded {} ({} bytes)", skill_id, content_len);
     }
 
-    info!("Loaded {} skills, {} bytes total", contents.len(), total_bytes);
+    info!(
+        "Loaded {} skills, {} bytes total ({} skipped)",
+        loaded.contents.len(),
+        loaded.total_bytes,
+        loaded.skipped.len()
+    );
 
-    Ok(contents)
+    Ok(loaded)
+}
+
+/// List every skill in the index with its metadata
+#[tauri::command]
+pub async fn list_skills() -> Result<Vec<SkillInfo>, String> {
+    let skills = load_skills_index()?;
+    let persona_categories = crate::modules::config::load_app_config()
+        .map(|config| config.proxy.workflows.persona_categories)
+        .unwrap_or_else(|_| crate::proxy::config::WorkflowConfig::default().persona_categories);
+
+    Ok(skills
+        .iter()
+        .map(|skill| SkillInfo::from_metadata(skill, &persona_categories))
+        .collect())
 }
 
 /// Get skill router statistics
@@ -188,3 +736,390 @@ pub async fn get_skill_stats() -> Result<serde_json::Value, String> {
 
     Ok(stats)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn adaptive_config() -> SkillsConfig {
+        SkillsConfig {
+            adaptive_k: true,
+            ..SkillsConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_skill_info_maps_category_to_personas() {
+        let persona_categories: HashMap<String, String> = [
+            ("devops-engineer", "devops"),
+            ("sre", "devops"),
+            ("frontend-dev", "frontend"),
+        ]
+        .into_iter()
+        .map(|(p, c)| (p.to_string(), c.to_string()))
+        .collect();
+
+        let tagged = SkillMetadata {
+            id: "docker-compose".to_string(),
+            path: "/skills/docker-compose/SKILL.md".to_string(),
+            size_bytes: Some(1200),
+            tags: vec!["docker".to_string()],
+            category: Some("devops".to_string()),
+            ..Default::default()
+        };
+        let info = SkillInfo::from_metadata(&tagged, &persona_categories);
+        assert_eq!(info.name, "docker-compose");
+        assert_eq!(info.size_bytes, 1200);
+        assert_eq!(info.personas, vec!["devops-engineer", "sre"]);
+
+        let untagged = SkillMetadata {
+            id: "misc".to_string(),
+            path: "/skills/misc/SKILL.md".to_string(),
+            name: Some("Misc".to_string()),
+            size_bytes: Some(10),
+            ..Default::default()
+        };
+        let info = SkillInfo::from_metadata(&untagged, &persona_categories);
+        assert_eq!(info.name, "Misc");
+        assert!(info.category.is_none());
+        assert!(info.personas.is_empty());
+    }
+
+    #[test]
+    fn test_adaptive_k_scales_with_query_length() {
+        let config = adaptive_config();
+        let short = "docker";
+        let long = "my docker compose stack fails to start because the reverse proxy \
+                    container cannot resolve the upstream service name on the internal \
+                    bridge network after I upgraded the engine and changed the subnet";
+
+        let short_k = effective_k(short, 8, &config);
+        let long_k = effective_k(long, 8, &config);
+
+        assert!(long_k > short_k);
+        assert!(short_k >= config.adaptive_k_min);
+        assert!(long_k <= config.adaptive_k_max);
+    }
+
+    #[test]
+    fn test_expand_query_adds_context_terms_to_terse_follow_up() {
+        let context = vec![
+            "Traefik returns 502 for the docker compose stack".to_string(),
+            "The docker bridge network cannot resolve the traefik upstream".to_string(),
+        ];
+
+        let expanded = expand_query("still broken", &context, 4);
+
+        assert_ne!(expanded, "still broken");
+        assert!(expanded.starts_with("still broken "));
+        assert!(expanded.contains("docker"));
+        assert!(expanded.contains("traefik"));
+    }
+
+    #[test]
+    fn test_expand_query_without_context_is_unchanged() {
+        assert_eq!(expand_query("docker networking", &[], 8), "docker networking");
+
+        // Terms already present in the query are not duplicated
+        let context = vec!["docker networking".to_string()];
+        assert_eq!(expand_query("docker networking", &context, 8), "docker networking");
+    }
+
+    fn skill(id: &str, size_bytes: usize) -> SkillScore {
+        SkillScore {
+            id: id.to_string(),
+            name: id.to_string(),
+            score: 1.0,
+            matched_terms: Vec::new(),
+            size_bytes,
+        }
+    }
+
+    fn index_entry(id: &str, size_bytes: usize) -> SkillMetadata {
+        SkillMetadata {
+            id: id.to_string(),
+            path: format!("/skills/{}/SKILL.md", id),
+            name: None,
+            size_bytes: Some(size_bytes),
+            ..Default::default()
+        }
+    }
+
+    fn selection_of(skills: Vec<SkillScore>) -> SkillSelection {
+        let total_bytes = skills.iter().map(|s| s.size_bytes).sum();
+        SkillSelection {
+            persona: "engineer".to_string(),
+            category: "devops".to_string(),
+            limits: SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: skills.len(),
+                actual_bytes: total_bytes,
+            },
+            skills,
+            total_bytes,
+        }
+    }
+
+    #[test]
+    fn test_skill_ceiling_truncates_larger_selection() {
+        let mut selection = selection_of(
+            (0..6).map(|i| skill(&format!("skill-{}", i), 100)).collect(),
+        );
+        apply_skill_ceiling(&mut selection, 3, 80000);
+
+        let ids: Vec<&str> = selection.skills.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["skill-0", "skill-1", "skill-2"]);
+        assert_eq!(selection.total_bytes, 300);
+        assert_eq!(selection.limits.max_skills, 3);
+        assert_eq!(selection.limits.actual_skills, 3);
+
+        // The byte ceiling drops the lowest-ranked skills first
+        apply_skill_ceiling(&mut selection, 3, 250);
+        assert_eq!(selection.skills.len(), 2);
+        assert_eq!(selection.limits.actual_bytes, 200);
+    }
+
+    #[test]
+    fn test_compare_selections_reports_agreement_and_disagreement() {
+        let ts = selection_of(vec![skill("docker", 100), skill("traefik", 100)]);
+
+        // Same set in a different order with shifted scores still agrees
+        let mut same = selection_of(vec![skill("traefik", 100), skill("docker", 100)]);
+        same.skills[0].score = 1.5;
+        let agree = compare_selections("docker proxy", &ts, &same);
+        assert!(agree.top_k_agree);
+        let traefik = agree.score_deltas.iter().find(|d| d.id == "traefik").unwrap();
+        assert_eq!(traefik.delta, Some(0.5));
+
+        let different = selection_of(vec![skill("docker", 100), skill("nginx", 100)]);
+        let disagree = compare_selections("docker proxy", &ts, &different);
+        assert!(!disagree.top_k_agree);
+        let nginx = disagree.score_deltas.iter().find(|d| d.id == "nginx").unwrap();
+        assert_eq!(nginx.ts_score, None);
+        assert_eq!(nginx.delta, None);
+    }
+
+    #[test]
+    fn test_skill_overrides_exclude_and_include() {
+        let mut selection = SkillSelection {
+            persona: "engineer".to_string(),
+            category: "devops".to_string(),
+            skills: vec![skill("docker", 1000), skill("kubernetes", 2000)],
+            total_bytes: 3000,
+            limits: SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: 2,
+                actual_bytes: 3000,
+            },
+        };
+        let index = vec![
+            index_entry("docker", 1000),
+            index_entry("kubernetes", 2000),
+            index_entry("traefik", 500),
+        ];
+
+        let skipped = apply_skill_overrides(
+            &mut selection,
+            &["traefik".to_string()],
+            &["kubernetes".to_string()],
+            &index,
+            None,
+            80000,
+        )
+        .unwrap();
+
+        let ids: Vec<&str> = selection.skills.iter().map(|s| s.id.as_str()).collect();
+        assert!(skipped.is_empty());
+        assert_eq!(ids, vec!["docker", "traefik"]);
+        assert_eq!(selection.total_bytes, 1500);
+    }
+
+    #[test]
+    fn test_skill_overrides_reject_unknown_or_disallowed() {
+        let mut selection = SkillSelection {
+            persona: "engineer".to_string(),
+            category: "devops".to_string(),
+            skills: Vec::new(),
+            total_bytes: 0,
+            limits: SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: 0,
+                actual_bytes: 0,
+            },
+        };
+        let index = vec![index_entry("docker", 1000)];
+
+        assert!(apply_skill_overrides(&mut selection, &["missing".to_string()], &[], &index, None, 80000).is_err());
+
+        let allowlist = vec!["traefik".to_string()];
+        assert!(apply_skill_overrides(
+            &mut selection,
+            &["docker".to_string()],
+            &[],
+            &index,
+            Some(&allowlist),
+            80000
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_missing_skill_file_uses_placeholder_when_lenient() {
+        let tmp = tempfile::tempdir().unwrap();
+        let present = tmp.path().join("docker.md");
+        let stale = tmp.path().join("traefik.md");
+        std::fs::write(&present, "# Docker").unwrap();
+        std::fs::write(&stale, "# Traefik").unwrap();
+        std::fs::remove_file(&stale).unwrap();
+
+        let index = vec![
+            SkillMetadata {
+                id: "docker".to_string(),
+                path: present.to_string_lossy().to_string(),
+                name: None,
+                size_bytes: None,
+                ..Default::default()
+            },
+            SkillMetadata {
+                id: "traefik".to_string(),
+                path: stale.to_string_lossy().to_string(),
+                name: None,
+                size_bytes: None,
+                ..Default::default()
+            },
+        ];
+        let ids = vec!["docker".to_string(), "traefik".to_string()];
+
+        // Strict mode keeps the old hard failure
+        assert!(load_skills_from_index(&index, &ids, false, 80000).is_err());
+
+        let loaded = load_skills_from_index(&index, &ids, true, 80000).unwrap();
+        assert_eq!(loaded.contents["docker"], "# Docker");
+        assert_eq!(loaded.contents["traefik"], missing_skill_placeholder("traefik"));
+        assert_eq!(loaded.substituted, vec!["traefik".to_string()]);
+    }
+
+    #[test]
+    fn test_oversized_and_binary_skills_are_skipped() {
+        let tmp = tempfile::tempdir().unwrap();
+        let entry = |id: &str, content: &[u8]| {
+            let path = tmp.path().join(format!("{}.md", id));
+            std::fs::write(&path, content).unwrap();
+            SkillMetadata {
+                id: id.to_string(),
+                path: path.to_string_lossy().to_string(),
+                ..Default::default()
+            }
+        };
+        let index = vec![
+            entry("docker", b"# Docker"),
+            entry("huge", "x".repeat(200).as_bytes()),
+            entry("binary", &[0xff, 0xfe, 0x00, 0x01]),
+            entry("traefik", b"# Traefik"),
+        ];
+        let ids: Vec<String> = ["docker", "huge", "binary", "traefik"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        let loaded = load_skills_from_index(&index, &ids, false, 100).unwrap();
+        assert_eq!(loaded.contents.len(), 2);
+        assert_eq!(loaded.contents["docker"], "# Docker");
+        assert_eq!(loaded.contents["traefik"], "# Traefik");
+        assert_eq!(loaded.total_bytes, "# Docker".len() + "# Traefik".len());
+
+        let skipped: Vec<&str> = loaded.skipped.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(skipped, vec!["huge", "binary"]);
+        assert!(loaded.skipped[0].reason.contains("max_skill_file_bytes"));
+        assert!(loaded.skipped[1].reason.contains("UTF-8"));
+    }
+
+    #[test]
+    fn test_duplicate_skill_ids_are_loaded_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let index: Vec<SkillMetadata> = [("a", "# A"), ("b", "# Bee")]
+            .iter()
+            .map(|(id, content)| {
+                let path = tmp.path().join(format!("{}.md", id));
+                std::fs::write(&path, content).unwrap();
+                SkillMetadata {
+                    id: id.to_string(),
+                    path: path.to_string_lossy().to_string(),
+                    ..Default::default()
+                }
+            })
+            .collect();
+        let ids = vec!["a".to_string(), "b".to_string(), "a".to_string()];
+
+        let loaded = load_skills_from_index(&index, &ids, false, 80000).unwrap();
+        assert_eq!(loaded.contents.len(), 2);
+        assert_eq!(loaded.total_bytes, "# A".len() + "# Bee".len());
+    }
+
+    #[test]
+    fn test_skills_index_cache_reloads_on_mtime_change() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("skills-index.json");
+        let cache = RwLock::new(None);
+        let write_index = |ids: &[&str], modified: SystemTime| {
+            let skills: Vec<String> = ids
+                .iter()
+                .map(|id| format!(r#"{{"id":"{}","path":"/skills/{}/SKILL.md"}}"#, id, id))
+                .collect();
+            std::fs::write(&path, format!(r#"{{"skills":[{}]}}"#, skills.join(","))).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+
+        assert!(load_index_cached(&path, &cache).is_err());
+
+        let t0 = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        write_index(&["docker"], t0);
+        let first = load_index_cached(&path, &cache).unwrap();
+        assert_eq!(first.len(), 1);
+
+        // Unchanged mtime: served from the cache (same allocation)
+        let again = load_index_cached(&path, &cache).unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        // Re-indexed file: reloaded
+        write_index(&["docker", "traefik"], t0 + std::time::Duration::from_secs(60));
+        let reloaded = load_index_cached(&path, &cache).unwrap();
+        assert_eq!(reloaded.len(), 2);
+    }
+
+    #[test]
+    fn test_router_path_resolution() {
+        let tmp = tempfile::tempdir().unwrap();
+        let cwd = tmp.path().join("cwd");
+        let resources = tmp.path().join("resources");
+        std::fs::create_dir_all(resources.join("router")).unwrap();
+        std::fs::write(resources.join("router/02-router.ts"), "").unwrap();
+
+        // Configured relative path resolves against the resource directory
+        let found = find_router_script(Some("router/02-router.ts"), Some(&cwd), Some(&resources)).unwrap();
+        assert_eq!(found, resources.join("router/02-router.ts"));
+
+        // Default lookup reports both attempted locations
+        let err = find_router_script(None, Some(&cwd), Some(&resources)).unwrap_err();
+        assert!(err.contains(&cwd.join(DEFAULT_ROUTER_SCRIPT).display().to_string()));
+        assert!(err.contains(&resources.join(DEFAULT_ROUTER_SCRIPT).display().to_string()));
+    }
+
+    #[test]
+    fn test_fixed_k_when_adaptive_disabled() {
+        let config = SkillsConfig::default();
+        assert_eq!(effective_k("docker", 8, &config), 8);
+        assert_eq!(
+            effective_k("a much longer query with many more words in it than the short one", 8, &config),
+            8
+        );
+    }
+}
@@ -1,9 +1,12 @@
 // Workflow command parsing and routing
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::sync::{Arc, RwLock};
 use once_cell::sync::Lazy;
 
+use crate::commands::skills::{SkillScore, SkillSelection};
+use crate::proxy::config::{EmptySkillsPolicy, WidgetConfig, WorkflowCommandConfig, WorkflowConfig};
+
 /// Workflow command types
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -14,13 +17,41 @@ pub enum WorkflowCommand {
     Debug,
     /// /create - Generate new feature (future)
     Create,
-    /// /test - Write and run tests (future)
+    /// /test - Run the configured test command and report results
     Test,
-    /// /deploy - Deployment procedures (future)
+    /// /deploy - Dry-run an allowlisted deploy command
     Deploy,
+    /// /summarize - Condense the session or pasted text (read-only)
+    Summarize,
 }
 
 impl WorkflowCommand {
+    /// All workflow commands
+    pub const ALL: [WorkflowCommand; 6] = [
+        WorkflowCommand::Plan,
+        WorkflowCommand::Debug,
+        WorkflowCommand::Create,
+        WorkflowCommand::Test,
+        WorkflowCommand::Deploy,
+        WorkflowCommand::Summarize,
+    ];
+
+    /// Command name as used in config and after the slash
+    pub fn name(&self) -> &'static str {
+        match self {
+            WorkflowCommand::Plan => "plan",
+            WorkflowCommand::Debug => "debug",
+            WorkflowCommand::Create => "create",
+            WorkflowCommand::Test => "test",
+            WorkflowCommand::Deploy => "deploy",
+            WorkflowCommand::Summarize => "summarize",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().find(|cmd| cmd.name() == name).cloned()
+    }
+
     /// Get the forced persona for this workflow
     pub fn get_persona(&self) -> &'static str {
         match self {
@@ -29,6 +60,7 @@ impl WorkflowCommand {
             WorkflowCommand::Create => "builder",
             WorkflowCommand::Test => "qa-engineer",
             WorkflowCommand::Deploy => "devops-engineer",
+            WorkflowCommand::Summarize => "analyst",
         }
     }
 
@@ -40,49 +72,147 @@ impl WorkflowCommand {
             WorkflowCommand::Create => "Generating new feature",
             WorkflowCommand::Test => "Writing and executing tests",
             WorkflowCommand::Deploy => "Executing deployment procedures",
+            WorkflowCommand::Summarize => "Summarizing conversation and pasted content",
         }
     }
 }
 
-/// Parse workflow command from user message
+/// Parse workflow command from user message, returning it with the trimmed argument
+/// text that follows (original casing kept; empty when the command stands alone).
+/// The command must be followed by whitespace or the end of the message, so
+/// `/planning` is not mistaken for `/plan`. Configured aliases (`/p` -> `/plan`) resolve
+/// to their workflow.
 /// SECURITY: Server-side only - never trust client input
-pub fn parse_workflow_command(message: &str) -> Option<WorkflowCommand> {
-    let trimmed = message.trim_start().to_lowercase();
-
-    if trimmed.starts_with("/plan") {
-        Some(WorkflowCommand::Plan)
-    } else if trimmed.starts_with("/debug") {
-        Some(WorkflowCommand::Debug)
-    } else if trimmed.starts_with("/create") {
-        Some(WorkflowCommand::Create)
-    } else if trimmed.starts_with("/test") {
-        Some(WorkflowCommand::Test)
-    } else if trimmed.starts_with("/deploy") {
-        Some(WorkflowCommand::Deploy)
+pub fn parse_workflow_command(message: &str, config: &WorkflowConfig) -> Option<(WorkflowCommand, String)> {
+    let trimmed = message.trim_start();
+    let token_end = trimmed.find(char::is_whitespace).unwrap_or(trimmed.len());
+    let name = trimmed[..token_end].strip_prefix('/')?.to_lowercase();
+    let name = config.aliases.get(&name).unwrap_or(&name);
+
+    let command = WorkflowCommand::from_name(name)?;
+    Some((command, trimmed[token_end..].trim().to_string()))
+}
+
+/// Configured settings of a workflow (defaults when the config has no entry for it)
+pub fn workflow_settings(workflow: &WorkflowCommand, config: &WorkflowConfig) -> WorkflowCommandConfig {
+    config.commands.get(workflow.name()).cloned().unwrap_or_default()
+}
+
+/// Persona a workflow runs as: the configured override, else the built-in one
+pub fn workflow_persona(workflow: &WorkflowCommand, config: &WorkflowConfig) -> String {
+    workflow_settings(workflow, config)
+        .persona
+        .unwrap_or_else(|| workflow.get_persona().to_string())
+}
+
+/// Reject workflows switched off with `enabled = false`
+pub fn check_workflow_enabled(workflow: &WorkflowCommand, config: &WorkflowConfig) -> Result<(), String> {
+    if workflow_settings(workflow, config).enabled {
+        Ok(())
     } else {
-        None
+        Err(format!("The /{} workflow is disabled", workflow.name()))
     }
 }
 
-/// Widget mode session tracking
-/// SECURITY: Server-side state - client cannot bypass
-static WIDGET_SESSIONS: Lazy<Arc<RwLock<HashSet<String>>>> =
-    Lazy::new(|| Arc::new(RwLock::new(HashSet::new())));
+/// Skill limits for a workflow: its own `max_skills` / `max_bytes` when configured,
+/// never above the global ceiling
+pub fn workflow_skill_limits(
+    workflow: &WorkflowCommand,
+    config: &WorkflowConfig,
+    max_skills: usize,
+    max_bytes: usize,
+) -> (usize, usize) {
+    let settings = workflow_settings(workflow, config);
+    (
+        settings.max_skills.map_or(max_skills, |limit| limit.min(max_skills)),
+        settings.max_bytes.map_or(max_bytes, |limit| limit.min(max_bytes)),
+    )
+}
+
+/// Next step for a workflow after skill selection
+#[derive(Debug, Clone, PartialEq)]
+pub enum EmptySkillsAction {
+    /// Run the workflow with the current selection
+    Proceed,
+    /// Re-run selection with a broadened query
+    RetryFuzzy,
+}
+
+/// Apply the configured empty-selection policy to a workflow's skill set.
+/// Returns Err when the policy blocks a workflow that has no skills.
+pub fn check_workflow_skills(
+    workflow: &WorkflowCommand,
+    skill_count: usize,
+    policy: &EmptySkillsPolicy,
+) -> Result<EmptySkillsAction, String> {
+    if skill_count > 0 {
+        return Ok(EmptySkillsAction::Proceed);
+    }
+
+    match policy {
+        EmptySkillsPolicy::Error => Err(format!(
+            "No skills selected for {:?} workflow; refine the request or re-index skills",
+            workflow
+        )),
+        EmptySkillsPolicy::Warn => {
+            tracing::warn!("{:?} workflow proceeding with no skills selected", workflow);
+            Ok(EmptySkillsAction::Proceed)
+        }
+        EmptySkillsPolicy::Fuzzy => Ok(EmptySkillsAction::RetryFuzzy),
+    }
+}
+
+/// Broadened query used by the fuzzy fallback: the request plus workflow persona/description
+pub fn fuzzy_workflow_query(workflow: &WorkflowCommand, query: &str) -> String {
+    format!(
+        "{} {} {}",
+        query,
+        workflow.get_persona().replace('-', " "),
+        workflow.get_description()
+    )
+}
+
+/// Widget mode session tracking (session id -> registration timestamp)
+/// SECURITY: Server-side state - client cannot bypass. Mirrored into chat.db so a
+/// restart doesn't silently lift the widget restrictions of an embedded session.
+static WIDGET_SESSIONS: Lazy<Arc<RwLock<HashMap<String, i64>>>> =
+    Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+
+/// Load persisted widget sessions into memory (call once the chat database is initialized)
+pub fn load_widget_sessions() -> Result<usize, String> {
+    let persisted = crate::modules::chat_db::load_widget_sessions()?;
+    Ok(restore_widget_sessions(persisted))
+}
+
+/// Merge a persisted snapshot into the in-memory set, returning the number of sessions tracked
+fn restore_widget_sessions(persisted: HashMap<String, i64>) -> usize {
+    let mut sessions = WIDGET_SESSIONS.write().unwrap();
+    for (session_id, registered_at) in persisted {
+        sessions.entry(session_id).or_insert(registered_at);
+    }
+    sessions.len()
+}
 
 /// Check if session is in widget mode
 pub fn is_widget_mode(session_id: &str) -> bool {
     WIDGET_SESSIONS
         .read()
         .unwrap()
-        .contains(session_id)
+        .contains_key(session_id)
 }
 
 /// Register a session as widget mode
 pub fn register_widget_session(session_id: String) {
-    WIDGET_SESSIONS
+    let registered_at = *WIDGET_SESSIONS
         .write()
         .unwrap()
-        .insert(session_id);
+        .entry(session_id.clone())
+        .or_insert_with(|| chrono::Utc::now().timestamp());
+
+    // The in-memory entry still enforces the restrictions for this run if persisting fails
+    if let Err(e) = crate::modules::chat_db::insert_widget_session(&session_id, registered_at) {
+        tracing::warn!("[Widget] Failed to persist widget session {}: {}", session_id, e);
+    }
 }
 
 /// Unregister widget session
@@ -91,33 +221,146 @@ pub fn unregister_widget_session(session_id: &str) {
         .write()
         .unwrap()
         .remove(session_id);
+
+    if let Err(e) = crate::modules::chat_db::delete_widget_session(session_id) {
+        tracing::warn!("[Widget] Failed to remove persisted widget session {}: {}", session_id, e);
+    }
+}
+
+/// Session persona pins (session id -> persona), set by the `SetPersona` client message
+/// and applied to every non-workflow message of that session
+static SESSION_PERSONAS: Lazy<Arc<RwLock<HashMap<String, String>>>> =
+    Lazy::new(|| Arc::new(RwLock::new(HashMap::new())));
+
+/// Pin a persona for a session. Only personas with a configured skill category are accepted.
+pub fn set_session_persona(
+    session_id: &str,
+    persona: &str,
+    persona_categories: &HashMap<String, String>,
+) -> Result<(), String> {
+    if !persona_categories.contains_key(persona) {
+        let mut known: Vec<&str> = persona_categories.keys().map(String::as_str).collect();
+        known.sort();
+        return Err(format!(
+            "Unknown persona '{}'. Known personas: {}",
+            persona,
+            known.join(", ")
+        ));
+    }
+
+    SESSION_PERSONAS
+        .write()
+        .unwrap()
+        .insert(session_id.to_string(), persona.to_string());
+    Ok(())
 }
 
-/// Get allowed workflows for widget mode
-pub fn get_widget_allowed_workflows() -> Vec<WorkflowCommand> {
-    vec![WorkflowCommand::Debug] // Only debugging allowed in widget mode
+/// Persona pinned for a session, if any
+pub fn get_session_persona(session_id: &str) -> Option<String> {
+    SESSION_PERSONAS.read().unwrap().get(session_id).cloned()
+}
+
+/// Remove a session's persona pin
+pub fn clear_session_persona(session_id: &str) {
+    SESSION_PERSONAS.write().unwrap().remove(session_id);
+}
+
+/// Prepend the persona's configured prompt to a system message. Personas without a
+/// (non-blank) prompt leave the system message unchanged.
+pub fn apply_persona_prompt(
+    system: &str,
+    persona: &str,
+    persona_prompts: &HashMap<String, String>,
+) -> String {
+    match persona_prompts.get(persona).map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        Some(prompt) if system.is_empty() => prompt.to_string(),
+        Some(prompt) => format!("{}\n\n{}", prompt, system),
+        None => system.to_string(),
+    }
+}
+
+/// Get allowed workflows for widget mode (`widget_workflows`, minus disabled workflows)
+pub fn get_widget_allowed_workflows(config: &WorkflowConfig) -> Vec<WorkflowCommand> {
+    config
+        .widget_workflows
+        .iter()
+        .filter_map(|name| WorkflowCommand::from_name(name))
+        .filter(|cmd| workflow_settings(cmd, config).enabled)
+        .collect()
+}
+
+/// Widget configuration, populated from `ProxyConfig.widget` at startup and on config save
+static WIDGET_CONFIG: Lazy<RwLock<WidgetConfig>> = Lazy::new(|| RwLock::new(WidgetConfig::default()));
+
+/// Apply widget settings from config
+pub fn apply_widget_config(config: &WidgetConfig) {
+    *WIDGET_CONFIG.write().unwrap() = config.clone();
+}
+
+/// Current widget configuration
+pub fn widget_config() -> WidgetConfig {
+    WIDGET_CONFIG.read().unwrap().clone()
 }
 
 /// Get allowed skill IDs for widget mode (security allowlist)
+/// Curated list only - no unrestricted access
 pub fn get_widget_allowed_skills() -> Vec<String> {
-    vec![
-        "awesome-troubleshooting".to_string(),
-        "awesome-error-analysis".to_string(),
-        "awesome-debugging-mindset".to_string(),
-        "awesome-root-cause-analysis".to_string(),
-        // Curated list only - no unrestricted access
-    ]
+    WIDGET_CONFIG.read().unwrap().allowed_skills.clone()
 }
 
 /// Widget mode constraints
 pub const WIDGET_MAX_SKILLS: usize = 3;
 pub const WIDGET_MAX_BYTES: usize = 30_000; // 30KB max
 
+/// Active widget session as reported by the debug snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct WidgetSessionInfo {
+    pub session_id: String,
+    pub registered_at: i64,
+}
+
+/// Consolidated widget-mode state for runtime inspection
+#[derive(Debug, Clone, Serialize)]
+pub struct WidgetDebugInfo {
+    pub sessions: Vec<WidgetSessionInfo>,
+    pub allowed_skills: Vec<String>,
+    pub allowed_workflows: Vec<WorkflowCommand>,
+    pub max_skills: usize,
+    pub max_bytes: usize,
+}
+
+/// Snapshot widget sessions, allowlist, and limits for debugging
+#[tauri::command]
+pub fn widget_debug_snapshot() -> WidgetDebugInfo {
+    let workflow_config = crate::modules::config::load_app_config()
+        .map(|config| config.proxy.workflows)
+        .unwrap_or_default();
+    let mut sessions: Vec<WidgetSessionInfo> = WIDGET_SESSIONS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|(id, registered_at)| WidgetSessionInfo {
+            session_id: id.clone(),
+            registered_at: *registered_at,
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.registered_at.cmp(&b.registered_at).then(a.session_id.cmp(&b.session_id)));
+
+    WidgetDebugInfo {
+        sessions,
+        allowed_skills: get_widget_allowed_skills(),
+        allowed_workflows: get_widget_allowed_workflows(&workflow_config),
+        max_skills: WIDGET_MAX_SKILLS,
+        max_bytes: WIDGET_MAX_BYTES,
+    }
+}
+
 /// Validate workflow is allowed for widget mode
 /// Returns Err if blocked
 pub fn validate_widget_workflow(
     session_id: &str,
     workflow: &Option<WorkflowCommand>,
+    config: &WorkflowConfig,
 ) -> Result<(), String> {
     if !is_widget_mode(session_id) {
         return Ok(()); // Not widget mode, no restrictions
@@ -125,7 +368,7 @@ pub fn validate_widget_workflow(
 
     match workflow {
         Some(cmd) => {
-            let allowed = get_widget_allowed_workflows();
+            let allowed = get_widget_allowed_workflows(config);
             if !allowed.contains(cmd) {
                 return Err(format!(
                     "Widget mode: only {:?} workflows allowed",
@@ -138,6 +381,139 @@ pub fn validate_widget_workflow(
     }
 }
 
+/// Check workflow config for inconsistencies, reporting all of them at once
+pub fn validate_workflow_config(config: &WorkflowConfig) -> Result<(), Vec<String>> {
+    let mut errors = Vec::new();
+
+    let mut names: Vec<&String> = config.commands.keys().collect();
+    names.sort();
+    for name in names {
+        let command = &config.commands[name];
+        let Some(workflow) = WorkflowCommand::from_name(name) else {
+            errors.push(format!("Unknown workflow '{}' in commands", name));
+            continue;
+        };
+
+        let persona = command.persona.as_deref().unwrap_or(workflow.get_persona());
+        if !config.persona_categories.contains_key(persona) {
+            errors.push(format!(
+                "Persona '{}' (workflow '{}') has no skill category",
+                persona, name
+            ));
+        }
+        if command.max_skills == Some(0) {
+            errors.push(format!("Workflow '{}' has max_skills = 0", name));
+        }
+        if command.max_bytes == Some(0) {
+            errors.push(format!("Workflow '{}' has max_bytes = 0", name));
+        }
+    }
+
+    let mut aliases: Vec<(&String, &String)> = config.aliases.iter().collect();
+    aliases.sort();
+    for (alias, target) in aliases {
+        if !config.commands.contains_key(target) {
+            errors.push(format!(
+                "Alias '{}' points to undefined workflow '{}'",
+                alias, target
+            ));
+        }
+        if config.commands.contains_key(alias) {
+            errors.push(format!("Alias '{}' shadows a workflow of the same name", alias));
+        }
+    }
+
+    let mut targets: Vec<(&String, &Vec<String>)> = config.deploy.commands.iter().collect();
+    targets.sort();
+    for (target, template) in targets {
+        if template.first().map_or(true, |program| program.trim().is_empty()) {
+            errors.push(format!("Deploy target '{}' has an empty command", target));
+        }
+    }
+    if let Some(default) = &config.deploy.default_target {
+        if !config.deploy.commands.contains_key(default) {
+            errors.push(format!("Default deploy target '{}' is not configured", default));
+        }
+    }
+    if !(0.0..=1.0).contains(&config.debug_confidence_threshold) {
+        errors.push(format!(
+            "debug_confidence_threshold must be between 0.0 and 1.0 (got {})",
+            config.debug_confidence_threshold
+        ));
+    }
+    if config.debug_max_log_bytes == 0 {
+        errors.push("debug_max_log_bytes must be greater than 0".to_string());
+    }
+    if config.deploy.timeout_secs == 0 {
+        errors.push("deploy.timeout_secs must be greater than 0".to_string());
+    }
+    if config.test.timeout_secs == 0 {
+        errors.push("test.timeout_secs must be greater than 0".to_string());
+    }
+
+    for name in &config.widget_workflows {
+        match config.commands.get(name) {
+            None => errors.push(format!("Widget-allowed workflow '{}' is not defined", name)),
+            Some(command) if !command.enabled => {
+                errors.push(format!("Widget-allowed workflow '{}' is disabled", name))
+            }
+            Some(_) => {}
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Validate the saved workflow config; returns the list of problems (empty if consistent)
+#[tauri::command]
+pub fn check_workflow_config() -> Result<Vec<String>, String> {
+    let config = crate::modules::config::load_app_config()?;
+    Ok(validate_workflow_config(&config.proxy.workflows).err().unwrap_or_default())
+}
+
+/// Apply the widget allowlist and skill cap to a selection.
+/// Returns the grace notice when filtering removed every selected skill (and the
+/// configured message is non-empty), so terse answers come with an explanation.
+pub fn apply_widget_limits(
+    selection: &mut SkillSelection,
+    allowed: &[String],
+    grace_message: &str,
+) -> Option<String> {
+    let selected_before = selection.skills.len();
+
+    selection.skills.retain(|s| allowed.contains(&s.id));
+    selection.skills.truncate(WIDGET_MAX_SKILLS);
+    enforce_widget_byte_budget(&mut selection.skills);
+    selection.total_bytes = selection.skills.iter().map(|s| s.size_bytes).sum();
+
+    if selected_before > 0 && selection.skills.is_empty() && !grace_message.trim().is_empty() {
+        Some(grace_message.to_string())
+    } else {
+        None
+    }
+}
+
+/// Drop the lowest-scoring skills until the total size fits `WIDGET_MAX_BYTES`.
+/// Order of the remaining skills is preserved.
+pub fn enforce_widget_byte_budget(skills: &mut Vec<SkillScore>) {
+    let mut total: usize = skills.iter().map(|s| s.size_bytes).sum();
+
+    while total > WIDGET_MAX_BYTES {
+        let Some((idx, _)) = skills
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.score.total_cmp(&b.score))
+        else {
+            break;
+        };
+        total -= skills.remove(idx).size_bytes;
+    }
+}
+
 /// Filter skills to widget allowlist
 /// Modifies skill_ids in place
 pub fn filter_skills_for_widget(
@@ -159,12 +535,139 @@ pub fn filter_skills_for_widget(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_persona_prompt_is_prepended() {
+        let prompts: HashMap<String, String> = [(
+            "architect".to_string(),
+            "You are a pragmatic software architect.".to_string(),
+        )]
+        .into_iter()
+        .collect();
+        let system = "## Skill: docker-compose\n...";
+
+        assert_eq!(
+            apply_persona_prompt(system, "architect", &prompts),
+            format!("You are a pragmatic software architect.\n\n{}", system)
+        );
+        assert_eq!(
+            apply_persona_prompt("", "architect", &prompts),
+            "You are a pragmatic software architect."
+        );
+        // Unknown persona: nothing injected
+        assert_eq!(apply_persona_prompt(system, "bard", &prompts), system);
+    }
+
+    #[test]
+    fn test_session_persona_pin_is_validated() {
+        let categories = WorkflowConfig::default().persona_categories;
+        let session = "persona-test-session";
+
+        let err = set_session_persona(session, "security-auditor", &categories).unwrap_err();
+        assert!(err.contains("Unknown persona 'security-auditor'"));
+        assert!(err.contains("architect"));
+        assert_eq!(get_session_persona(session), None);
+
+        set_session_persona(session, "architect", &categories).unwrap();
+        assert_eq!(get_session_persona(session).as_deref(), Some("architect"));
+
+        let mut custom = categories.clone();
+        custom.insert("security-auditor".to_string(), "security".to_string());
+        set_session_persona(session, "security-auditor", &custom).unwrap();
+        assert_eq!(get_session_persona(session).as_deref(), Some("security-auditor"));
+
+        clear_session_persona(session);
+        assert_eq!(get_session_persona(session), None);
+    }
+
     #[test]
     fn test_parse_workflow_commands() {
-        assert_eq!(parse_workflow_command("/plan"), Some(WorkflowCommand::Plan));
-        assert_eq!(parse_workflow_command("/debug issue"), Some(WorkflowCommand::Debug));
-        assert_eq!(parse_workflow_command("  /PLAN  "), Some(WorkflowCommand::Plan));
-        assert_eq!(parse_workflow_command("regular message"), None);
+        assert_eq!(
+            parse_workflow_command("/plan", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Plan)
+        );
+        assert_eq!(
+            parse_workflow_command("/debug issue", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Debug)
+        );
+        assert_eq!(
+            parse_workflow_command("  /PLAN  ", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Plan)
+        );
+        assert_eq!(parse_workflow_command("regular message", &WorkflowConfig::default()), None);
+        assert_eq!(
+            parse_workflow_command("/summarize the logs below", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Summarize)
+        );
+        assert_eq!(WorkflowCommand::from_name("summarize"), Some(WorkflowCommand::Summarize));
+    }
+
+    #[test]
+    fn test_parse_workflow_command_returns_arguments() {
+        assert_eq!(
+            parse_workflow_command("/debug", &WorkflowConfig::default()),
+            Some((WorkflowCommand::Debug, String::new()))
+        );
+        assert_eq!(
+            parse_workflow_command("  /debug   ", &WorkflowConfig::default()),
+            Some((WorkflowCommand::Debug, String::new()))
+        );
+        assert_eq!(
+            parse_workflow_command("/DEBUG the Login  crash\n", &WorkflowConfig::default()),
+            Some((WorkflowCommand::Debug, "the Login  crash".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_parse_workflow_command_requires_word_boundary() {
+        assert_eq!(
+            parse_workflow_command("/plan", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Plan)
+        );
+        assert_eq!(
+            parse_workflow_command("/plan do X", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Plan)
+        );
+        assert_eq!(
+            parse_workflow_command("/Plan\tdo X", &WorkflowConfig::default()).map(|(cmd, _)| cmd),
+            Some(WorkflowCommand::Plan)
+        );
+        assert_eq!(parse_workflow_command("/planning", &WorkflowConfig::default()), None);
+        assert_eq!(parse_workflow_command("/planner please help", &WorkflowConfig::default()), None);
+        assert_eq!(parse_workflow_command("/debugger", &WorkflowConfig::default()), None);
+        assert_eq!(parse_workflow_command("/", &WorkflowConfig::default()), None);
+        assert_eq!(parse_workflow_command("", &WorkflowConfig::default()), None);
+    }
+
+    #[test]
+    fn test_summarize_persona_mapping() {
+        let persona = WorkflowCommand::Summarize.get_persona();
+        assert_eq!(persona, "analyst");
+        assert!(WorkflowConfig::default().persona_categories.contains_key(persona));
+    }
+
+    #[test]
+    fn test_empty_skills_error_policy_blocks_workflow() {
+        let result = check_workflow_skills(&WorkflowCommand::Plan, 0, &EmptySkillsPolicy::Error);
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("No skills selected"));
+
+        // A non-empty selection is never blocked
+        assert_eq!(
+            check_workflow_skills(&WorkflowCommand::Plan, 2, &EmptySkillsPolicy::Error),
+            Ok(EmptySkillsAction::Proceed)
+        );
+    }
+
+    #[test]
+    fn test_empty_skills_warn_and_fuzzy_policies() {
+        assert_eq!(
+            check_workflow_skills(&WorkflowCommand::Debug, 0, &EmptySkillsPolicy::Warn),
+            Ok(EmptySkillsAction::Proceed)
+        );
+        assert_eq!(
+            check_workflow_skills(&WorkflowCommand::Debug, 0, &EmptySkillsPolicy::Fuzzy),
+            Ok(EmptySkillsAction::RetryFuzzy)
+        );
     }
 
     #[test]
@@ -179,17 +682,217 @@ mod tests {
         assert!(!is_widget_mode(session));
     }
 
+    #[test]
+    fn test_widget_session_restored_after_reload() {
+        let session = "widget-reload-test";
+        assert!(!is_widget_mode(session));
+
+        // Simulate a restart: the in-memory set is empty, the persisted snapshot is not
+        let persisted = HashMap::from([(session.to_string(), 1_700_000_000)]);
+        restore_widget_sessions(persisted);
+        assert!(is_widget_mode(session));
+        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Plan), &WorkflowConfig::default()).is_err());
+
+        unregister_widget_session(session);
+        assert!(!is_widget_mode(session));
+    }
+
+    #[test]
+    fn test_widget_debug_snapshot() {
+        let session = "widget-snapshot-test";
+        register_widget_session(session.to_string());
+
+        let snapshot = widget_debug_snapshot();
+        let entry = snapshot
+            .sessions
+            .iter()
+            .find(|s| s.session_id == session)
+            .expect("registered session should appear in snapshot");
+        assert!(entry.registered_at > 0);
+        assert_eq!(snapshot.max_skills, WIDGET_MAX_SKILLS);
+        assert_eq!(snapshot.max_bytes, WIDGET_MAX_BYTES);
+        assert_eq!(snapshot.allowed_skills, get_widget_allowed_skills());
+        assert_eq!(
+            snapshot.allowed_workflows,
+            vec![WorkflowCommand::Debug, WorkflowCommand::Summarize]
+        );
+
+        unregister_widget_session(session);
+        assert!(!widget_debug_snapshot().sessions.iter().any(|s| s.session_id == session));
+    }
+
+    fn selection_with(ids: &[&str]) -> SkillSelection {
+        use crate::commands::skills::{SelectionLimits, SkillScore};
+
+        SkillSelection {
+            persona: "troubleshooter".to_string(),
+            category: "debugging".to_string(),
+            skills: ids
+                .iter()
+                .map(|id| SkillScore {
+                    id: id.to_string(),
+                    name: id.to_string(),
+                    score: 1.0,
+                    matched_terms: Vec::new(),
+                    size_bytes: 100,
+                })
+                .collect(),
+            total_bytes: ids.len() * 100,
+            limits: SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: ids.len(),
+                actual_bytes: ids.len() * 100,
+            },
+        }
+    }
+
+    #[test]
+    fn test_widget_grace_notice_when_all_skills_stripped() {
+        let allowed = get_widget_allowed_skills();
+        let grace = WidgetConfig::default().grace_message;
+
+        let mut stripped = selection_with(&["docker-compose", "kubernetes"]);
+        let notice = apply_widget_limits(&mut stripped, &allowed, &grace);
+        assert!(stripped.skills.is_empty());
+        assert_eq!(notice, Some(grace.clone()));
+
+        // Some skills survive: no notice
+        let mut partial = selection_with(&["docker-compose", "awesome-troubleshooting"]);
+        assert_eq!(apply_widget_limits(&mut partial, &allowed, &grace), None);
+        assert_eq!(partial.skills.len(), 1);
+
+        // Empty message disables the notice
+        let mut disabled = selection_with(&["docker-compose"]);
+        assert_eq!(apply_widget_limits(&mut disabled, &allowed, ""), None);
+    }
+
+    #[test]
+    fn test_workflow_config_drives_routing() {
+        let mut config = WorkflowConfig::default();
+        config.aliases.insert("p".to_string(), "plan".to_string());
+        config.commands.insert(
+            "plan".to_string(),
+            WorkflowCommandConfig {
+                persona: Some("strategist".to_string()),
+                max_skills: Some(2),
+                ..WorkflowCommandConfig::default()
+            },
+        );
+        config.commands.get_mut("create").unwrap().enabled = false;
+        config.widget_workflows = vec!["summarize".to_string(), "create".to_string()];
+
+        assert_eq!(
+            parse_workflow_command("/P add caching", &config),
+            Some((WorkflowCommand::Plan, "add caching".to_string()))
+        );
+        assert_eq!(workflow_persona(&WorkflowCommand::Plan, &config), "strategist");
+        assert_eq!(workflow_persona(&WorkflowCommand::Debug, &config), "troubleshooter");
+        assert_eq!(workflow_skill_limits(&WorkflowCommand::Plan, &config, 8, 80000), (2, 80000));
+        // Per-workflow limits never raise the global ceiling
+        assert_eq!(workflow_skill_limits(&WorkflowCommand::Plan, &config, 1, 80000), (1, 80000));
+
+        assert!(check_workflow_enabled(&WorkflowCommand::Create, &config).is_err());
+        assert!(check_workflow_enabled(&WorkflowCommand::Plan, &config).is_ok());
+        // Disabled workflows are not allowed in widget mode even when listed
+        assert_eq!(get_widget_allowed_workflows(&config), vec![WorkflowCommand::Summarize]);
+    }
+
+    #[test]
+    fn test_default_workflow_config_is_valid() {
+        assert_eq!(validate_workflow_config(&WorkflowConfig::default()), Ok(()));
+    }
+
+    #[test]
+    fn test_workflow_config_reports_all_inconsistencies() {
+        let mut config = WorkflowConfig::default();
+        config.aliases.insert("p".to_string(), "planning".to_string());
+        config.commands.insert(
+            "plan".to_string(),
+            WorkflowCommandConfig {
+                persona: Some("strategist".to_string()),
+                max_skills: Some(0),
+                ..WorkflowCommandConfig::default()
+            },
+        );
+        config.commands.get_mut("debug").unwrap().enabled = false;
+        config.widget_workflows.push("review".to_string());
+
+        let errors = validate_workflow_config(&config).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "Persona 'strategist' (workflow 'plan') has no skill category".to_string(),
+                "Workflow 'plan' has max_skills = 0".to_string(),
+                "Alias 'p' points to undefined workflow 'planning'".to_string(),
+                "Widget-allowed workflow 'debug' is disabled".to_string(),
+                "Widget-allowed workflow 'review' is not defined".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_deploy_config_inconsistencies() {
+        let mut config = WorkflowConfig::default();
+        config.deploy.commands.insert("staging".to_string(), Vec::new());
+        config.deploy.default_target = Some("prod".to_string());
+
+        let errors = validate_workflow_config(&config).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                "Deploy target 'staging' has an empty command".to_string(),
+                "Default deploy target 'prod' is not configured".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_widget_byte_budget_drops_lowest_scoring() {
+        let skill = |id: &str, score: f64, size_bytes: usize| SkillScore {
+            id: id.to_string(),
+            name: id.to_string(),
+            score,
+            matched_terms: Vec::new(),
+            size_bytes,
+        };
+        let mut skills = vec![
+            skill("high", 9.0, 12_000),
+            skill("low", 1.0, 12_000),
+            skill("mid", 5.0, 12_000),
+        ];
+
+        enforce_widget_byte_budget(&mut skills);
+
+        let total: usize = skills.iter().map(|s| s.size_bytes).sum();
+        assert!(total <= WIDGET_MAX_BYTES);
+        let ids: Vec<&str> = skills.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["high", "mid"]);
+    }
+
+    #[test]
+    fn test_empty_widget_allowlist_blocks_all_skills() {
+        let mut selection = selection_with(&["awesome-troubleshooting", "awesome-error-analysis"]);
+        apply_widget_limits(&mut selection, &[], "");
+        assert!(selection.skills.is_empty());
+        assert_eq!(selection.total_bytes, 0);
+
+        // Defaults keep the curated four
+        assert_eq!(WidgetConfig::default().allowed_skills.len(), 4);
+    }
+
     #[test]
     fn test_widget_workflow_validation() {
         let session = "widget-test";
 
         // Normal mode - all allowed
-        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Plan)).is_ok());
+        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Plan), &WorkflowConfig::default()).is_ok());
 
         // Widget mode - only debug allowed
         register_widget_session(session.to_string());
-        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Debug)).is_ok());
-        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Plan)).is_err());
+        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Debug), &WorkflowConfig::default()).is_ok());
+        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Summarize), &WorkflowConfig::default()).is_ok());
+        assert!(validate_widget_workflow(session, &Some(WorkflowCommand::Plan), &WorkflowConfig::default()).is_err());
 
         unregister_widget_session(session);
     }
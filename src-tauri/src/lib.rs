@@ -3,6 +3,7 @@ mod modules;
 mod commands;
 mod utils;
 mod proxy;  // Proxy service module
+mod workflows; // Slash-command workflow executors
 pub mod error;
 pub mod constants;
 
@@ -69,7 +70,26 @@ pub fn run() {
     // Initialize chat database
     if let Err(e) = modules::chat_db::init_db() {
         error!("Failed to initialize chat database: {}", e);
+    } else {
+        match commands::workflows::load_widget_sessions() {
+            Ok(count) if count > 0 => info!("Restored {} widget session(s)", count),
+            Ok(_) => {}
+            Err(e) => error!("Failed to load widget sessions: {}", e),
+        }
+    }
+    if let Ok(config) = modules::config::load_app_config() {
+        modules::chat_db::apply_config(&config.proxy.chat);
+        commands::workflows::apply_widget_config(&config.proxy.widget);
+
+        // Report workflow misconfigurations up front (non-fatal)
+        if let Err(issues) = commands::workflows::validate_workflow_config(&config.proxy.workflows) {
+            for issue in issues {
+                warn!("[Workflows] Config issue: {}", issue);
+            }
+        }
     }
+    // Prune expired chat sessions now and hourly (no-op unless session_retention_days is set)
+    modules::chat_db::start_retention_cleanup();
 
     if is_headless {
         info!("Starting in HEADLESS mode...");
@@ -114,7 +134,7 @@ pub fn run() {
                     if let Some(pwd) = env_web_password {
                         if !pwd.trim().is_empty() {
                             info!("Using Web UI Password from environment variable");
-                            config.proxy.admin_password = Some(pwd);
+                            config.proxy.set_admin_password(&pwd);
                         }
                     }
 
@@ -145,8 +165,8 @@ pub fn run() {
                     info!("🚀 Headless mode proxy service starting...");
                     info!("📍 Port: {}", config.proxy.port);
                     info!("🔑 Current API Key: {}", config.proxy.api_key);
-                    if let Some(ref pwd) = config.proxy.admin_password {
-                        info!("🔐 Web UI Password: {}", pwd);
+                    if config.proxy.admin_password_hash.is_some() {
+                        info!("🔐 Web UI Password: (Set, stored as hash)");
                     } else {
                         info!("🔐 Web UI Password: (Same as API Key)");
                     }
@@ -212,6 +232,11 @@ pub fn run() {
             // Initialize log bridge with app handle for debug console
             modules::log_bridge::init_log_bridge(app.handle().clone());
 
+            // Bundled builds ship the skills router under the resource directory
+            if let Ok(resource_dir) = app.path().resource_dir() {
+                commands::skills::set_resource_dir(resource_dir);
+            }
+
             // Linux: Workaround for transparent window crash/freeze
             // The transparent window feature is unstable on Linux with WebKitGTK
             // We disable the visual alpha channel to prevent softbuffer-related crashes
@@ -369,6 +394,15 @@ pub fn run() {
             commands::proxy::reload_proxy_accounts,
             commands::proxy::update_model_mapping,
             commands::proxy::fetch_zai_models,
+            commands::proxy::test_zai_connection,
+            commands::proxy::preview_model_mapping,
+            commands::proxy::get_user_agent_pool,
+            commands::proxy::add_user_agent,
+            commands::proxy::remove_user_agent,
+            commands::proxy::reorder_user_agent,
+            commands::proxy::current_backend_for,
+            commands::proxy::replay_openai_capture,
+            commands::proxy::check_port_available,
             commands::proxy::get_proxy_scheduling_config,
             commands::proxy::update_proxy_scheduling_config,
             commands::proxy::clear_proxy_session_bindings,
@@ -392,6 +426,7 @@ pub fn run() {
             commands::get_token_stats_by_account,
             commands::get_token_stats_summary,
             commands::get_token_stats_by_model,
+            commands::get_usage_stats,
             commands::get_token_stats_model_trend_hourly,
             commands::get_token_stats_model_trend_daily,
             commands::get_token_stats_account_trend_hourly,
@@ -410,6 +445,7 @@ pub fn run() {
             commands::security::remove_ip_from_blacklist,
             commands::security::clear_ip_blacklist,
             commands::security::check_ip_in_blacklist,
+            commands::security::get_auto_banned_ips,
             commands::security::get_ip_whitelist,
             commands::security::add_ip_to_whitelist,
             commands::security::remove_ip_from_whitelist,
@@ -417,10 +453,27 @@ pub fn run() {
             commands::security::check_ip_in_whitelist,
             commands::security::get_security_config,
             commands::security::update_security_config,
+            commands::security::rotate_api_key,
+            commands::security::set_admin_password,
             // Skills router commands
             commands::skills::select_skills,
             commands::skills::load_skill_content,
             commands::skills::get_skill_stats,
+            commands::skills::list_skills,
+            commands::skills::compare_routers,
+            commands::skills::reload_skills_index,
+            // Chat session commands
+            commands::chat::trim_chat_session,
+            commands::chat::estimate_tokens,
+            commands::chat::export_session_markdown,
+            commands::chat::export_session_json,
+            commands::chat::import_session_json,
+            commands::chat::rename_session,
+            commands::chat::list_session_artifacts,
+            commands::chat::load_session_artifact,
+            // Workflow commands
+            commands::workflows::widget_debug_snapshot,
+            commands::workflows::check_workflow_config,
             // Cloudflared commands
             commands::cloudflared::cloudflared_check,
             commands::cloudflared::cloudflared_install,
@@ -21,9 +21,25 @@ const ACCOUNTS_INDEX: &str = "accounts.json";
 const ACCOUNTS_DIR: &str = "accounts";
 
 // ... existing functions get_data_dir, get_accounts_dir, load_account_index, save_account_index ...
+/// Private home directory for tests, so they never read or write the real data directory
+#[cfg(test)]
+static TEST_HOME: Lazy<tempfile::TempDir> =
+    Lazy::new(|| tempfile::tempdir().expect("failed to create test home"));
+
+/// User home directory
+#[cfg(not(test))]
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    dirs::home_dir()
+}
+
+#[cfg(test)]
+pub(crate) fn home_dir() -> Option<PathBuf> {
+    Some(TEST_HOME.path().to_path_buf())
+}
+
 /// Get data directory path
 pub fn get_data_dir() -> Result<PathBuf, String> {
-    let home = dirs::home_dir().ok_or("failed_to_get_home_dir")?;
+    let home = home_dir().ok_or("failed_to_get_home_dir")?;
     let data_dir = home.join(DATA_DIR);
 
     // Ensure directory exists
@@ -0,0 +1,174 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// Subdirectory of the app data dir holding one workspace per chat session
+const WORKSPACES_DIR: &str = "workspaces";
+
+/// A saved workflow artifact
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactInfo {
+    pub name: String,
+    /// Full path of the file on disk
+    pub path: String,
+    /// Size in bytes
+    pub size: u64,
+    /// Last modification, Unix timestamp in milliseconds
+    pub modified_at: i64,
+}
+
+/// Save `content` as artifact `name` of a session (overwriting an existing one)
+pub fn save(session_id: &str, name: &str, content: &str) -> Result<ArtifactInfo, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    save_in(&data_dir, session_id, name, content)
+}
+
+/// Read artifact `name` of a session
+pub fn load(session_id: &str, name: &str) -> Result<String, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    load_in(&data_dir, session_id, name)
+}
+
+/// Artifacts of a session, by name
+pub fn list(session_id: &str) -> Result<Vec<ArtifactInfo>, String> {
+    let data_dir = crate::modules::account::get_data_dir()?;
+    list_in(&data_dir, session_id)
+}
+
+pub(crate) fn save_in(data_dir: &Path, session_id: &str, name: &str, content: &str) -> Result<ArtifactInfo, String> {
+    let dir = session_dir(data_dir, session_id)?;
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create workspace {:?}: {}", dir, e))?;
+    let path = artifact_path(&existing_dir(&dir, data_dir)?, name)?;
+    std::fs::write(&path, content).map_err(|e| format!("Failed to save artifact {:?}: {}", path, e))?;
+    artifact_info(&path)
+}
+
+fn load_in(data_dir: &Path, session_id: &str, name: &str) -> Result<String, String> {
+    let not_found = || format!("Artifact not found: {}", name);
+    let dir = session_dir(data_dir, session_id)?;
+    if !dir.is_dir() {
+        return Err(not_found());
+    }
+    let path = artifact_path(&existing_dir(&dir, data_dir)?, name)?;
+    if !path.is_file() {
+        return Err(not_found());
+    }
+    std::fs::read_to_string(&path).map_err(|e| format!("Failed to read artifact {:?}: {}", path, e))
+}
+
+fn list_in(data_dir: &Path, session_id: &str) -> Result<Vec<ArtifactInfo>, String> {
+    let dir = session_dir(data_dir, session_id)?;
+    // A session that never saved anything has no workspace; reads don't create one
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let dir = existing_dir(&dir, data_dir)?;
+    let entries = std::fs::read_dir(&dir).map_err(|e| format!("Failed to read workspace {:?}: {}", dir, e))?;
+
+    let mut artifacts = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        if path.is_file() {
+            artifacts.push(artifact_info(&path)?);
+        }
+    }
+    artifacts.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(artifacts)
+}
+
+/// Workspace of a session (`<data dir>/workspaces/<session id>`). Only saving creates it.
+fn session_dir(data_dir: &Path, session_id: &str) -> Result<PathBuf, String> {
+    if !is_single_name(session_id) {
+        return Err(format!("Invalid session id for workspace: {:?}", session_id));
+    }
+    Ok(data_dir.join(WORKSPACES_DIR).join(session_id))
+}
+
+/// Canonicalized workspace that exists, so a symlink pointing out of `data_dir` is caught too
+fn existing_dir(dir: &Path, data_dir: &Path) -> Result<PathBuf, String> {
+    crate::utils::path::validate_data_path(dir, data_dir)
+}
+
+/// Path of artifact `name` inside `dir`. Traversal is rejected by the path validator,
+/// subdirectories by the single-name check.
+fn artifact_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = crate::utils::path::validate_data_path(dir.join(name), dir)?;
+    if !is_single_name(name) {
+        return Err(format!("Invalid artifact name: {:?}", name));
+    }
+    Ok(path)
+}
+
+/// Whether `name` is exactly one normal path component (no separators, `.` or `..`)
+fn is_single_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(first)), None) if first == name
+    )
+}
+
+fn artifact_info(path: &Path) -> Result<ArtifactInfo, String> {
+    let metadata = std::fs::metadata(path).map_err(|e| format!("Failed to stat artifact {:?}: {}", path, e))?;
+    let modified_at = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |since_epoch| since_epoch.as_millis() as i64);
+
+    Ok(ArtifactInfo {
+        name: path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().into_owned(),
+        size: metadata.len(),
+        modified_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let data_dir = tempdir().unwrap();
+        let content = "# Implementation Plan\n\n- step one\n";
+
+        let saved = save_in(data_dir.path(), "session-a", "plan.md", content).unwrap();
+        assert_eq!(saved.name, "plan.md");
+        assert_eq!(saved.size, content.len() as u64);
+        let workspace = data_dir.path().canonicalize().unwrap().join(WORKSPACES_DIR).join("session-a");
+        assert!(Path::new(&saved.path).starts_with(&workspace));
+
+        assert_eq!(load_in(data_dir.path(), "session-a", "plan.md").unwrap(), content);
+        assert_eq!(list_in(data_dir.path(), "session-a").unwrap(), vec![saved]);
+
+        // Sessions don't see each other's artifacts
+        assert!(list_in(data_dir.path(), "session-b").unwrap().is_empty());
+        assert!(load_in(data_dir.path(), "session-b", "plan.md").is_err());
+    }
+
+    #[test]
+    fn test_reads_do_not_create_workspace() {
+        let data_dir = tempdir().unwrap();
+
+        assert!(list_in(data_dir.path(), "session-a").unwrap().is_empty());
+        let err = load_in(data_dir.path(), "session-a", "plan.md").unwrap_err();
+        assert!(err.contains("Artifact not found"), "unexpected error: {}", err);
+        assert!(!data_dir.path().join(WORKSPACES_DIR).exists());
+    }
+
+    #[test]
+    fn test_traversal_in_name_rejected() {
+        let data_dir = tempdir().unwrap();
+
+        let err = save_in(data_dir.path(), "session-a", "../escape.md", "x").unwrap_err();
+        assert!(err.contains("Path traversal"), "unexpected error: {}", err);
+        assert!(load_in(data_dir.path(), "session-a", "../../config.json").is_err());
+        assert!(save_in(data_dir.path(), "session-a", "nested/plan.md", "x").is_err());
+        assert!(save_in(data_dir.path(), "../session", "plan.md", "x").is_err());
+        assert!(!data_dir.path().join(WORKSPACES_DIR).join("escape.md").exists());
+    }
+}
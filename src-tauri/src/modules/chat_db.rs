@@ -1,6 +1,44 @@
-use rusqlite::{params, Connection};
+use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::proxy::config::ChatConfig;
+
+/// Statuses a session can be in ("pending" on creation)
+pub const SESSION_STATUSES: &[&str] = &["pending", "running", "completed", "failed", "cancelled"];
+
+/// Message roles accepted when importing a session
+const MESSAGE_ROLES: &[&str] = &["user", "assistant", "system"];
+
+/// Version written to (and required from) session JSON exports
+pub const SESSION_EXPORT_VERSION: u32 = 1;
+
+/// Renamed titles larger than this (in bytes, before sanitizing) are rejected outright
+pub const MAX_TITLE_BYTES: usize = 4096;
+
+/// Maximum number of hits returned by `search_messages`
+const SEARCH_RESULT_LIMIT: i64 = 100;
+
+/// Attempts for a write that keeps hitting SQLITE_BUSY/SQLITE_LOCKED
+const WRITE_RETRY_ATTEMPTS: u32 = 5;
+/// Initial backoff between write retries (doubled on each attempt)
+const WRITE_RETRY_BASE_MS: u64 = 20;
+
+/// Runtime chat settings, populated from `ProxyConfig.chat` at startup and on config save
+static SETTINGS: Lazy<RwLock<ChatConfig>> = Lazy::new(|| RwLock::new(ChatConfig::default()));
+
+/// Apply chat settings from config
+pub fn apply_config(config: &ChatConfig) {
+    *SETTINGS.write().unwrap() = config.clone();
+}
+
+pub(crate) fn settings() -> ChatConfig {
+    SETTINGS.read().unwrap().clone()
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TaskSession {
@@ -12,6 +50,16 @@ pub struct TaskSession {
     pub created_at: i64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub id: i64,
+    pub session_id: String,
+    pub role: String,
+    pub content: String,
+    /// Unix timestamp in milliseconds
+    pub created_at: i64,
+}
+
 pub fn get_db_path() -> Result<PathBuf, String> {
     let data_dir = crate::modules::account::get_data_dir()?;
     Ok(data_dir.join("chat.db"))
@@ -20,48 +68,399 @@ pub fn get_db_path() -> Result<PathBuf, String> {
 fn connect_db() -> Result<Connection, String> {
     let db_path = get_db_path()?;
     let conn = Connection::open(db_path).map_err(|e| e.to_string())?;
+    configure_connection(&conn)?;
+    Ok(conn)
+}
 
+/// Per-connection settings (SQLite does not persist these in the database file)
+fn configure_connection(conn: &Connection) -> Result<(), String> {
     // Enable WAL mode for better concurrency
     conn.pragma_update(None, "journal_mode", "WAL").map_err(|e| e.to_string())?;
     conn.pragma_update(None, "busy_timeout", 5000).map_err(|e| e.to_string())?;
     conn.pragma_update(None, "synchronous", "NORMAL").map_err(|e| e.to_string())?;
+    // Off by default per connection; without it ON DELETE CASCADE on messages is inert
+    conn.pragma_update(None, "foreign_keys", "ON").map_err(|e| e.to_string())?;
+    Ok(())
+}
 
-    Ok(conn)
+/// Lock contention errors that are worth retrying (as opposed to genuine failures)
+fn is_busy_error(err: &rusqlite::Error) -> bool {
+    match err {
+        rusqlite::Error::SqliteFailure(e, _) => {
+            matches!(e.code, ErrorCode::DatabaseBusy | ErrorCode::DatabaseLocked)
+        }
+        _ => false,
+    }
+}
+
+/// Run a write operation, retrying with exponential backoff on SQLITE_BUSY/SQLITE_LOCKED.
+/// `busy_timeout` covers most contention, but under heavy concurrent WebSocket load a
+/// write can still surface BUSY; that should not abort the turn. Other errors fail fast.
+fn with_write_retry<T, F>(mut op: F) -> Result<T, String>
+where
+    F: FnMut() -> rusqlite::Result<T>,
+{
+    let mut attempt = 0;
+    loop {
+        match op() {
+            Ok(value) => return Ok(value),
+            Err(e) if is_busy_error(&e) && attempt + 1 < WRITE_RETRY_ATTEMPTS => {
+                let backoff = WRITE_RETRY_BASE_MS << attempt;
+                tracing::warn!(
+                    "[ChatDB] Database busy (attempt {}/{}), retrying in {}ms",
+                    attempt + 1,
+                    WRITE_RETRY_ATTEMPTS,
+                    backoff
+                );
+                std::thread::sleep(Duration::from_millis(backoff));
+                attempt += 1;
+            }
+            Err(e) => return Err(e.to_string()),
+        }
+    }
 }
 
 pub fn init_db() -> Result<(), String> {
     let conn = connect_db()?;
+    create_schema(&conn)
+}
 
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS sessions (
-            id TEXT PRIMARY KEY,
-            title TEXT NOT NULL,
-            repo_name TEXT NOT NULL,
-            branch_name TEXT,
-            status TEXT NOT NULL,
-            created_at INTEGER NOT NULL
-        )",
-        [],
-    ).map_err(|e| e.to_string())?;
+fn create_schema(conn: &Connection) -> Result<(), String> {
+    with_write_retry(|| {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                repo_name TEXT NOT NULL,
+                branch_name TEXT,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+    })?;
 
-    conn.execute(
-        "CREATE INDEX IF NOT EXISTS idx_sessions_created_at ON sessions (created_at DESC)",
-        [],
-    ).map_err(|e| e.to_string())?;
+    with_write_retry(|| {
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_sessions_created_at ON sessions (created_at DESC)",
+            [],
+        )
+    })?;
+
+    with_write_retry(|| {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            )",
+            [],
+        )
+    })?;
+
+    // Widget-mode membership outlives the process so embedded sessions keep their
+    // restrictions across restarts (no FK: widget sessions may not be stored chats)
+    with_write_retry(|| {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS widget_sessions (
+                session_id TEXT PRIMARY KEY,
+                registered_at INTEGER NOT NULL
+            )",
+            [],
+        )
+    })?;
+
+    let rebuilt = migrate_messages_foreign_key(conn)?;
+
+    with_write_retry(|| {
+        conn.execute(
+            "CREATE INDEX IF NOT EXISTS idx_messages_session ON messages (session_id, created_at)",
+            [],
+        )
+    })?;
+
+    create_search_index(conn, rebuilt)
+}
+
+/// Early `messages` tables were created without the foreign key to `sessions`. SQLite
+/// can't add a constraint in place, so the table is rebuilt (ids kept, messages of
+/// already-deleted sessions dropped). Returns whether a rebuild happened.
+fn migrate_messages_foreign_key(conn: &Connection) -> Result<bool, String> {
+    let fk_count: i64 = conn
+        .query_row("SELECT COUNT(*) FROM pragma_foreign_key_list('messages')", [], |row| row.get(0))
+        .map_err(|e| e.to_string())?;
+    if fk_count > 0 {
+        return Ok(false);
+    }
+
+    with_write_retry(|| {
+        conn.execute_batch(
+            "BEGIN;
+             CREATE TABLE messages_migrated (
+                 id INTEGER PRIMARY KEY AUTOINCREMENT,
+                 session_id TEXT NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+                 role TEXT NOT NULL,
+                 content TEXT NOT NULL,
+                 created_at INTEGER NOT NULL
+             );
+             INSERT INTO messages_migrated (id, session_id, role, content, created_at)
+                 SELECT id, session_id, role, content, created_at FROM messages
+                 WHERE session_id IN (SELECT id FROM sessions);
+             DROP TABLE messages;
+             ALTER TABLE messages_migrated RENAME TO messages;
+             COMMIT;",
+        )
+    })?;
+
+    tracing::info!("[ChatDB] Rebuilt messages table with session foreign key");
+    Ok(true)
+}
+
+/// Full-text index over `messages.content` (FTS5, external content), kept in sync by
+/// triggers. Databases created before the index existed (or whose messages table was just
+/// rebuilt) get it built from the existing rows.
+fn create_search_index(conn: &Connection, force_rebuild: bool) -> Result<(), String> {
+    let existed: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(|e| e.to_string())?;
+
+    with_write_retry(|| {
+        conn.execute_batch(
+            "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts
+                 USING fts5(content, content = 'messages', content_rowid = 'id');
+             CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+                 INSERT INTO messages_fts (rowid, content) VALUES (new.id, new.content);
+             END;
+             CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+                 INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.id, old.content);
+             END;
+             CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE OF content ON messages BEGIN
+                 INSERT INTO messages_fts (messages_fts, rowid, content) VALUES ('delete', old.id, old.content);
+                 INSERT INTO messages_fts (rowid, content) VALUES (new.id, new.content);
+             END;",
+        )
+    })?;
+
+    if !existed || force_rebuild {
+        with_write_retry(|| {
+            conn.execute("INSERT INTO messages_fts (messages_fts) VALUES ('rebuild')", [])
+        })?;
+        tracing::info!("[ChatDB] Built full-text index for existing messages");
+    }
 
     Ok(())
 }
 
+/// Normalize a session title: control characters (including embedded newlines) become
+/// spaces, whitespace runs collapse, and titles longer than `max_len` characters are
+/// truncated with an ellipsis rather than rejected.
+pub fn sanitize_session_title(title: &str, max_len: usize) -> String {
+    let cleaned: String = title
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if max_len == 0 || cleaned.chars().count() <= max_len {
+        return cleaned;
+    }
+
+    let mut truncated: String = cleaned.chars().take(max_len.saturating_sub(1)).collect();
+    truncated = truncated.trim_end().to_string();
+    truncated.push('…');
+    truncated
+}
+
+/// Create a new session and return the stored row
+pub fn create_session(
+    title: &str,
+    repo_name: &str,
+    branch_name: Option<&str>,
+) -> Result<TaskSession, String> {
+    let conn = connect_db()?;
+    create_session_on(&conn, title, repo_name, branch_name, settings().max_title_len)
+}
+
+fn create_session_on(
+    conn: &Connection,
+    title: &str,
+    repo_name: &str,
+    branch_name: Option<&str>,
+    max_title_len: usize,
+) -> Result<TaskSession, String> {
+    let session = TaskSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: sanitize_session_title(title, max_title_len),
+        repo_name: repo_name.to_string(),
+        branch_name: branch_name.map(|b| b.to_string()),
+        status: "pending".to_string(),
+        created_at: chrono::Utc::now().timestamp(),
+    };
+
+    with_write_retry(|| {
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, branch_name, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session.id,
+                session.title,
+                session.repo_name,
+                session.branch_name,
+                session.status,
+                session.created_at
+            ],
+        )
+    })?;
+
+    Ok(session)
+}
+
+/// Rename a session and return the updated row. The title is trimmed and sanitized like
+/// on creation; empty titles and titles over `MAX_TITLE_BYTES` are rejected.
+pub fn update_session_title(id: &str, title: &str) -> Result<TaskSession, String> {
+    let conn = connect_db()?;
+    update_session_title_on(&conn, id, title, settings().max_title_len)
+}
+
+fn update_session_title_on(
+    conn: &Connection,
+    id: &str,
+    title: &str,
+    max_len: usize,
+) -> Result<TaskSession, String> {
+    let title = title.trim();
+    if title.len() > MAX_TITLE_BYTES {
+        return Err(format!(
+            "Session title is too long ({} bytes, max {})",
+            title.len(),
+            MAX_TITLE_BYTES
+        ));
+    }
+    let title = sanitize_session_title(title, max_len);
+    if title.is_empty() {
+        return Err("Session title must not be empty".to_string());
+    }
+
+    let updated = with_write_retry(|| {
+        conn.execute("UPDATE sessions SET title = ?1 WHERE id = ?2", params![title, id])
+    })?;
+    if updated == 0 {
+        return Err(format!("Session not found: {}", id));
+    }
+
+    get_session_on(conn, id)?.ok_or_else(|| format!("Session not found: {}", id))
+}
+
+/// Get a single session by id
+pub fn get_session(id: &str) -> Result<Option<TaskSession>, String> {
+    let conn = connect_db()?;
+    get_session_on(&conn, id)
+}
+
+fn get_session_on(conn: &Connection, id: &str) -> Result<Option<TaskSession>, String> {
+    conn.query_row(
+        "SELECT id, title, repo_name, branch_name, status, created_at
+         FROM sessions
+         WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(TaskSession {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                repo_name: row.get(2)?,
+                branch_name: row.get(3)?,
+                status: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        },
+    )
+    .optional()
+    .map_err(|e| e.to_string())
+}
+
+/// Reject anything outside `SESSION_STATUSES`
+pub fn validate_session_status(status: &str) -> Result<(), String> {
+    if SESSION_STATUSES.contains(&status) {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid session status '{}' (expected one of: {})",
+            status,
+            SESSION_STATUSES.join(", ")
+        ))
+    }
+}
+
+/// Move a session to `status` and return the updated row
+pub fn update_session_status(id: &str, status: &str) -> Result<TaskSession, String> {
+    validate_session_status(status)?;
+    let conn = connect_db()?;
+
+    let updated = with_write_retry(|| {
+        conn.execute("UPDATE sessions SET status = ?1 WHERE id = ?2", params![status, id])
+    })?;
+    if updated == 0 {
+        return Err(format!("Session not found: {}", id));
+    }
+
+    get_session(id)?.ok_or_else(|| format!("Session not found: {}", id))
+}
+
+/// One page of a listing. `has_more` is set when rows exist past the end of the page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub has_more: bool,
+}
+
+/// SQL `LIMIT`/`OFFSET` values for a page. One extra row is fetched to detect `has_more`;
+/// no limit maps to SQLite's unbounded `LIMIT -1`.
+fn page_bounds(limit: Option<usize>, offset: usize) -> (i64, i64) {
+    let limit = limit.map_or(-1, |limit| {
+        i64::try_from(limit).unwrap_or(i64::MAX).saturating_add(1)
+    });
+    (limit, i64::try_from(offset).unwrap_or(i64::MAX))
+}
+
+fn into_page<T>(mut items: Vec<T>, limit: Option<usize>) -> Page<T> {
+    let has_more = limit.is_some_and(|limit| items.len() > limit);
+    if let Some(limit) = limit {
+        items.truncate(limit);
+    }
+    Page { items, has_more }
+}
+
+/// All sessions, newest first
 pub fn list_sessions() -> Result<Vec<TaskSession>, String> {
+    list_sessions_page(None, 0).map(|page| page.items)
+}
+
+/// Sessions newest first, skipping `offset` and returning at most `limit` (None = all)
+pub fn list_sessions_page(limit: Option<usize>, offset: usize) -> Result<Page<TaskSession>, String> {
     let conn = connect_db()?;
+    list_sessions_on(&conn, limit, offset)
+}
 
+fn list_sessions_on(
+    conn: &Connection,
+    limit: Option<usize>,
+    offset: usize,
+) -> Result<Page<TaskSession>, String> {
+    let (sql_limit, sql_offset) = page_bounds(limit, offset);
     let mut stmt = conn.prepare(
         "SELECT id, title, repo_name, branch_name, status, created_at
          FROM sessions
-         ORDER BY created_at DESC"
+         ORDER BY created_at DESC, id ASC
+         LIMIT ?1 OFFSET ?2"
     ).map_err(|e| e.to_string())?;
 
-    let session_iter = stmt.query_map([], |row| {
+    let session_iter = stmt.query_map(params![sql_limit, sql_offset], |row| {
         Ok(TaskSession {
             id: row.get(0)?,
             title: row.get(1)?,
@@ -77,7 +476,522 @@ pub fn list_sessions() -> Result<Vec<TaskSession>, String> {
         sessions.push(session.map_err(|e| e.to_string())?);
     }
 
-    Ok(sessions)
+    Ok(into_page(sessions, limit))
+}
+
+/// Append a message to a session
+pub fn add_message(session_id: &str, role: &str, content: &str) -> Result<ChatMessage, String> {
+    let conn = connect_db()?;
+    with_write_retry(|| insert_message(&conn, session_id, role, content))
+}
+
+/// Single INSERT (no retry), usable on a plain connection or inside a transaction
+fn insert_message(
+    conn: &Connection,
+    session_id: &str,
+    role: &str,
+    content: &str,
+) -> rusqlite::Result<ChatMessage> {
+    let created_at = chrono::Utc::now().timestamp_millis();
+
+    conn.execute(
+        "INSERT INTO messages (session_id, role, content, created_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![session_id, role, content, created_at],
+    )?;
+
+    Ok(ChatMessage {
+        id: conn.last_insert_rowid(),
+        session_id: session_id.to_string(),
+        role: role.to_string(),
+        content: content.to_string(),
+        created_at,
+    })
+}
+
+/// A newly stored message plus the ids of older messages pruned to stay under the cap
+#[derive(Debug, Clone)]
+pub struct AppendedMessage {
+    pub message: ChatMessage,
+    /// Oldest first; empty when the session was under `max_messages_per_session`
+    pub pruned_ids: Vec<i64>,
+}
+
+/// Add a message and, in the same transaction, prune the session's oldest messages
+/// beyond `max_messages_per_session`
+pub fn append_message(session_id: &str, role: &str, content: &str) -> Result<AppendedMessage, String> {
+    let mut conn = connect_db()?;
+    append_message_on(&mut conn, session_id, role, content, settings().max_messages_per_session)
+}
+
+fn append_message_on(
+    conn: &mut Connection,
+    session_id: &str,
+    role: &str,
+    content: &str,
+    max_messages: usize,
+) -> Result<AppendedMessage, String> {
+    let keep = i64::try_from(max_messages).unwrap_or(i64::MAX);
+
+    let (message, pruned_ids) = with_write_retry(|| {
+        let tx = conn.transaction()?;
+        let message = insert_message(&tx, session_id, role, content)?;
+
+        let mut pruned_ids = Vec::new();
+        if max_messages > 0 {
+            let mut stmt = tx.prepare(
+                "SELECT id FROM messages
+                 WHERE session_id = ?1
+                 ORDER BY created_at DESC, id DESC
+                 LIMIT -1 OFFSET ?2",
+            )?;
+            pruned_ids = stmt
+                .query_map(params![session_id, keep], |row| row.get::<_, i64>(0))?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+        }
+        for pruned in &pruned_ids {
+            tx.execute("DELETE FROM messages WHERE id = ?1", params![pruned])?;
+        }
+
+        tx.commit()?;
+        pruned_ids.sort_unstable();
+        Ok((message, pruned_ids))
+    })?;
+
+    Ok(AppendedMessage { message, pruned_ids })
+}
+
+/// Messages of a session, oldest first
+pub fn get_messages(session_id: &str) -> Result<Vec<ChatMessage>, String> {
+    let conn = connect_db()?;
+    query_messages(&conn, session_id)
+}
+
+/// Messages of a session oldest first, skipping `offset` and returning at most `limit`
+/// (None = all)
+pub fn get_messages_page(
+    session_id: &str,
+    limit: Option<usize>,
+    offset: usize,
+) -> Result<Page<ChatMessage>, String> {
+    let conn = connect_db()?;
+    query_messages_page(&conn, session_id, limit, offset)
+}
+
+fn query_messages(conn: &Connection, session_id: &str) -> Result<Vec<ChatMessage>, String> {
+    query_messages_page(conn, session_id, None, 0).map(|page| page.items)
+}
+
+fn query_messages_page(
+    conn: &Connection,
+    session_id: &str,
+    limit: Option<usize>,
+    offset: usize,
+) -> Result<Page<ChatMessage>, String> {
+    let (sql_limit, sql_offset) = page_bounds(limit, offset);
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, role, content, created_at
+         FROM messages
+         WHERE session_id = ?1
+         ORDER BY created_at ASC, id ASC
+         LIMIT ?2 OFFSET ?3"
+    ).map_err(|e| e.to_string())?;
+
+    let message_iter = stmt.query_map(params![session_id, sql_limit, sql_offset], |row| {
+        Ok(ChatMessage {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut messages = Vec::new();
+    for message in message_iter {
+        messages.push(message.map_err(|e| e.to_string())?);
+    }
+
+    Ok(into_page(messages, limit))
+}
+
+/// Turn free text into an FTS5 query: every word is quoted (so operators and punctuation
+/// are matched literally) and all words must appear.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Full-text search across all sessions, best matches first
+pub fn search_messages(query: &str) -> Result<Vec<ChatMessage>, String> {
+    let conn = connect_db()?;
+    search_messages_on(&conn, query)
+}
+
+fn search_messages_on(conn: &Connection, query: &str) -> Result<Vec<ChatMessage>, String> {
+    let fts = fts_query(query);
+    if fts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT m.id, m.session_id, m.role, m.content, m.created_at
+         FROM messages_fts
+         JOIN messages m ON m.id = messages_fts.rowid
+         WHERE messages_fts MATCH ?1
+         ORDER BY rank, m.created_at DESC
+         LIMIT ?2"
+    ).map_err(|e| e.to_string())?;
+
+    let message_iter = stmt.query_map(params![fts, SEARCH_RESULT_LIMIT], |row| {
+        Ok(ChatMessage {
+            id: row.get(0)?,
+            session_id: row.get(1)?,
+            role: row.get(2)?,
+            content: row.get(3)?,
+            created_at: row.get(4)?,
+        })
+    }).map_err(|e| e.to_string())?;
+
+    let mut messages = Vec::new();
+    for message in message_iter {
+        messages.push(message.map_err(|e| e.to_string())?);
+    }
+
+    Ok(messages)
+}
+
+/// Keep only the most recent `keep_last` messages of a session (by timestamp).
+/// Runs in a single transaction and returns the number of messages removed.
+pub fn trim_session(session_id: &str, keep_last: usize) -> Result<u64, String> {
+    let mut conn = connect_db()?;
+    trim_session_on(&mut conn, session_id, keep_last)
+}
+
+fn trim_session_on(conn: &mut Connection, session_id: &str, keep_last: usize) -> Result<u64, String> {
+    let keep_last = i64::try_from(keep_last).unwrap_or(i64::MAX);
+
+    with_write_retry(|| {
+        let tx = conn.transaction()?;
+        let removed = tx.execute(
+            "DELETE FROM messages
+             WHERE session_id = ?1
+               AND id NOT IN (
+                   SELECT id FROM messages
+                   WHERE session_id = ?1
+                   ORDER BY created_at DESC, id DESC
+                   LIMIT ?2
+               )",
+            params![session_id, keep_last],
+        )?;
+        tx.commit()?;
+        Ok(removed as u64)
+    })
+}
+
+/// Characters of each compacted message quoted in the summary
+const SUMMARY_EXCERPT_CHARS: usize = 120;
+
+/// Synthesize the system message that stands in for compacted messages:
+/// one line per message with its role and a single-line excerpt.
+pub fn summarize_messages(messages: &[ChatMessage]) -> String {
+    let mut summary = format!("Summary of {} earlier messages:", messages.len());
+    for message in messages {
+        let flat = message.content.split_whitespace().collect::<Vec<_>>().join(" ");
+        let mut excerpt: String = flat.chars().take(SUMMARY_EXCERPT_CHARS).collect();
+        if flat.chars().count() > SUMMARY_EXCERPT_CHARS {
+            excerpt.push('…');
+        }
+        summary.push_str(&format!("\n- {}: {}", message.role, excerpt));
+    }
+    summary
+}
+
+/// Atomically replace all messages of a session. Role, content and timestamp of each
+/// message are kept; ids are reassigned. Returns the stored rows, oldest first.
+pub fn replace_messages(session_id: &str, messages: &[ChatMessage]) -> Result<Vec<ChatMessage>, String> {
+    let mut conn = connect_db()?;
+    replace_messages_on(&mut conn, session_id, messages)
+}
+
+fn replace_messages_on(
+    conn: &mut Connection,
+    session_id: &str,
+    messages: &[ChatMessage],
+) -> Result<Vec<ChatMessage>, String> {
+    with_write_retry(|| {
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM messages WHERE session_id = ?1", params![session_id])?;
+
+        let mut stored = Vec::with_capacity(messages.len());
+        for message in messages {
+            tx.execute(
+                "INSERT INTO messages (session_id, role, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![session_id, message.role, message.content, message.created_at],
+            )?;
+            stored.push(ChatMessage {
+                id: tx.last_insert_rowid(),
+                session_id: session_id.to_string(),
+                role: message.role.clone(),
+                content: message.content.clone(),
+                created_at: message.created_at,
+            });
+        }
+
+        tx.commit()?;
+        Ok(stored)
+    })
+}
+
+/// Replace everything older than the last `keep_last` messages with a single "system"
+/// summary message. Returns the session's messages after compaction.
+pub fn compact_session(session_id: &str, keep_last: usize) -> Result<Vec<ChatMessage>, String> {
+    let mut conn = connect_db()?;
+    compact_session_on(&mut conn, session_id, keep_last)
+}
+
+fn compact_session_on(
+    conn: &mut Connection,
+    session_id: &str,
+    keep_last: usize,
+) -> Result<Vec<ChatMessage>, String> {
+    let messages = query_messages(conn, session_id)?;
+    if messages.len() <= keep_last {
+        return Ok(messages);
+    }
+
+    let split = messages.len() - keep_last;
+    let (old, recent) = messages.split_at(split);
+
+    let mut compacted = Vec::with_capacity(keep_last + 1);
+    compacted.push(ChatMessage {
+        id: 0,
+        session_id: session_id.to_string(),
+        role: "system".to_string(),
+        content: summarize_messages(old),
+        // Sorts before the kept messages
+        created_at: old.last().map(|m| m.created_at).unwrap_or_default(),
+    });
+    compacted.extend_from_slice(recent);
+
+    replace_messages_on(conn, session_id, &compacted)
+}
+
+/// Delete a session and (via ON DELETE CASCADE) its messages.
+/// Returns false if no such session exists.
+pub fn delete_session(id: &str) -> Result<bool, String> {
+    let conn = connect_db()?;
+    delete_session_on(&conn, id)
+}
+
+fn delete_session_on(conn: &Connection, id: &str) -> Result<bool, String> {
+    let removed = with_write_retry(|| conn.execute("DELETE FROM sessions WHERE id = ?1", params![id]))?;
+    Ok(removed > 0)
+}
+
+/// Delete sessions created before `older_than_ts` (Unix seconds) together with their
+/// messages (ON DELETE CASCADE). Returns the number of sessions removed.
+pub fn cleanup_old_sessions(older_than_ts: i64) -> Result<usize, String> {
+    let conn = connect_db()?;
+    cleanup_old_sessions_on(&conn, older_than_ts)
+}
+
+fn cleanup_old_sessions_on(conn: &Connection, older_than_ts: i64) -> Result<usize, String> {
+    with_write_retry(|| {
+        conn.execute("DELETE FROM sessions WHERE created_at < ?1", params![older_than_ts])
+    })
+}
+
+/// How often the retention task re-checks for expired sessions
+const RETENTION_CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+
+/// Apply `session_retention_days` once now and then every hour. The setting is re-read
+/// on each pass, so config changes take effect without a restart.
+pub fn start_retention_cleanup() {
+    tauri::async_runtime::spawn(async {
+        let mut interval = tokio::time::interval(RETENTION_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let Some(days) = settings().session_retention_days.filter(|days| *days > 0) else {
+                continue;
+            };
+            let cutoff = chrono::Utc::now().timestamp() - i64::from(days) * 86_400;
+            match cleanup_old_sessions(cutoff) {
+                Ok(0) => {}
+                Ok(removed) => tracing::info!(
+                    "[ChatDB] Retention cleanup: removed {} session(s) older than {} days",
+                    removed,
+                    days
+                ),
+                Err(e) => tracing::error!("[ChatDB] Retention cleanup failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Delete a single message. Returns false if no such message exists.
+pub fn delete_message(message_id: i64) -> Result<bool, String> {
+    let conn = connect_db()?;
+    delete_message_on(&conn, message_id)
+}
+
+fn delete_message_on(conn: &Connection, message_id: i64) -> Result<bool, String> {
+    let removed = with_write_retry(|| {
+        conn.execute("DELETE FROM messages WHERE id = ?1", params![message_id])
+    })?;
+    Ok(removed > 0)
+}
+
+/// Persist a widget-mode session. An existing row keeps its original registration time.
+pub fn insert_widget_session(session_id: &str, registered_at: i64) -> Result<(), String> {
+    let conn = connect_db()?;
+    insert_widget_session_on(&conn, session_id, registered_at)
+}
+
+fn insert_widget_session_on(conn: &Connection, session_id: &str, registered_at: i64) -> Result<(), String> {
+    with_write_retry(|| {
+        conn.execute(
+            "INSERT OR IGNORE INTO widget_sessions (session_id, registered_at) VALUES (?1, ?2)",
+            params![session_id, registered_at],
+        )
+    })?;
+    Ok(())
+}
+
+/// Remove a widget-mode session. Returns false if it was not registered.
+pub fn delete_widget_session(session_id: &str) -> Result<bool, String> {
+    let conn = connect_db()?;
+    delete_widget_session_on(&conn, session_id)
+}
+
+fn delete_widget_session_on(conn: &Connection, session_id: &str) -> Result<bool, String> {
+    let removed = with_write_retry(|| {
+        conn.execute("DELETE FROM widget_sessions WHERE session_id = ?1", params![session_id])
+    })?;
+    Ok(removed > 0)
+}
+
+/// All persisted widget-mode sessions (session id -> registration timestamp)
+pub fn load_widget_sessions() -> Result<HashMap<String, i64>, String> {
+    let conn = connect_db()?;
+    load_widget_sessions_on(&conn)
+}
+
+fn load_widget_sessions_on(conn: &Connection) -> Result<HashMap<String, i64>, String> {
+    let mut stmt = conn
+        .prepare("SELECT session_id, registered_at FROM widget_sessions")
+        .map_err(|e| e.to_string())?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))
+        .map_err(|e| e.to_string())?;
+    rows.collect::<rusqlite::Result<HashMap<_, _>>>()
+        .map_err(|e| e.to_string())
+}
+
+/// Self-contained session backup (session row plus all of its messages)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionExport {
+    pub version: u32,
+    pub session: TaskSession,
+    pub messages: Vec<ChatMessage>,
+}
+
+/// Export a session with its messages. Returns None if the session does not exist.
+pub fn export_session(session_id: &str) -> Result<Option<SessionExport>, String> {
+    let conn = connect_db()?;
+    export_session_on(&conn, session_id)
+}
+
+fn export_session_on(conn: &Connection, session_id: &str) -> Result<Option<SessionExport>, String> {
+    let Some(session) = get_session_on(conn, session_id)? else {
+        return Ok(None);
+    };
+    let messages = query_messages(conn, session_id)?;
+    Ok(Some(SessionExport {
+        version: SESSION_EXPORT_VERSION,
+        session,
+        messages,
+    }))
+}
+
+/// Reject exports this version can't import faithfully
+fn validate_session_export(export: &SessionExport) -> Result<(), String> {
+    if export.version != SESSION_EXPORT_VERSION {
+        return Err(format!(
+            "Unsupported session export version {} (expected {})",
+            export.version, SESSION_EXPORT_VERSION
+        ));
+    }
+    validate_session_status(&export.session.status)?;
+    if export.session.repo_name.trim().is_empty() {
+        return Err("Session export has an empty repo_name".to_string());
+    }
+
+    let max_messages = settings().max_messages_per_session;
+    if max_messages > 0 && export.messages.len() > max_messages {
+        return Err(format!(
+            "Session export has {} messages, more than max_messages_per_session ({})",
+            export.messages.len(),
+            max_messages
+        ));
+    }
+    for (position, message) in export.messages.iter().enumerate() {
+        if !MESSAGE_ROLES.contains(&message.role.as_str()) {
+            return Err(format!("Message {} has unknown role '{}'", position, message.role));
+        }
+        if message.created_at < 0 {
+            return Err(format!("Message {} has a negative timestamp", position));
+        }
+    }
+    Ok(())
+}
+
+/// Import an exported session as a new session. Ids from the export are never reused:
+/// the session gets a fresh id and messages fresh row ids, all in one transaction.
+pub fn import_session(export: &SessionExport) -> Result<TaskSession, String> {
+    let mut conn = connect_db()?;
+    import_session_on(&mut conn, export)
+}
+
+fn import_session_on(conn: &mut Connection, export: &SessionExport) -> Result<TaskSession, String> {
+    validate_session_export(export)?;
+
+    let session = TaskSession {
+        id: uuid::Uuid::new_v4().to_string(),
+        title: sanitize_session_title(&export.session.title, settings().max_title_len),
+        ..export.session.clone()
+    };
+
+    with_write_retry(|| {
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO sessions (id, title, repo_name, branch_name, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                session.id,
+                session.title,
+                session.repo_name,
+                session.branch_name,
+                session.status,
+                session.created_at
+            ],
+        )?;
+        for message in &export.messages {
+            tx.execute(
+                "INSERT INTO messages (session_id, role, content, created_at)
+                 VALUES (?1, ?2, ?3, ?4)",
+                params![session.id, message.role, message.content, message.created_at],
+            )?;
+        }
+        tx.commit()
+    })?;
+
+    Ok(session)
 }
 
 /// Helper for testing: Insert a dummy session
@@ -86,11 +1000,551 @@ pub fn insert_dummy_session(id: &str, title: &str) -> Result<(), String> {
     let conn = connect_db()?;
     let now = chrono::Utc::now().timestamp();
 
-    conn.execute(
-        "INSERT INTO sessions (id, title, repo_name, branch_name, status, created_at)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-        params![id, title, "test-repo", "main", "pending", now],
-    ).map_err(|e| e.to_string())?;
+    with_write_retry(|| {
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, branch_name, status, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![id, title, "test-repo", "main", "pending", now],
+        )
+    })?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_retry_succeeds_after_contention() {
+        let tmp = tempdir().unwrap();
+        let db_path = tmp.path().join("contention.db");
+
+        let holder = Connection::open(&db_path).unwrap();
+        holder
+            .execute("CREATE TABLE t (id INTEGER PRIMARY KEY)", [])
+            .unwrap();
+        holder.execute_batch("BEGIN EXCLUSIVE").unwrap();
+
+        // Second connection fails immediately instead of waiting on busy_timeout
+        let writer = Connection::open(&db_path).unwrap();
+        writer.pragma_update(None, "busy_timeout", 0).unwrap();
+
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(Duration::from_millis(50));
+            holder.execute_batch("COMMIT").unwrap();
+        });
+
+        let attempts = AtomicU32::new(0);
+        let result = with_write_retry(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            writer.execute("INSERT INTO t (id) VALUES (1)", [])
+        });
+        release.join().unwrap();
+
+        assert_eq!(result, Ok(1));
+        assert!(attempts.load(Ordering::SeqCst) > 1, "write should have been retried");
+    }
+
+    #[test]
+    fn test_sanitize_title_truncates_with_ellipsis() {
+        let long_title = "x".repeat(250);
+        let title = sanitize_session_title(&long_title, 200);
+
+        assert_eq!(title.chars().count(), 200);
+        assert!(title.ends_with('…'));
+    }
+
+    #[test]
+    fn test_sanitize_title_strips_newlines() {
+        let title = sanitize_session_title("Fix Docker\nNetworking\r\n\tnow", 200);
+        assert_eq!(title, "Fix Docker Networking now");
+    }
+
+    #[test]
+    fn test_create_session_sanitizes_title() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        let session = create_session_on(&conn, "Line one\nline two", "atnplex/test", None, 200).unwrap();
+        assert_eq!(session.title, "Line one line two");
+
+        let stored = get_session_on(&conn, &session.id).unwrap().expect("session should exist");
+        assert_eq!(stored.title, "Line one line two");
+    }
+
+    #[test]
+    fn test_update_session_title_renames() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, status, created_at)
+             VALUES ('s', 'Old title', 'repo', 'pending', 1)",
+            [],
+        )
+        .unwrap();
+
+        let session = update_session_title_on(&conn, "s", "  Fix the\nlogin flow  ", 200).unwrap();
+        assert_eq!(session.title, "Fix the login flow");
+        assert_eq!(get_session_on(&conn, "s").unwrap().unwrap().title, "Fix the login flow");
+
+        assert!(update_session_title_on(&conn, "missing", "Title", 200).is_err());
+    }
+
+    #[test]
+    fn test_update_session_title_rejects_empty_and_oversized() {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, status, created_at)
+             VALUES ('s', 'Keep me', 'repo', 'pending', 1)",
+            [],
+        )
+        .unwrap();
+
+        assert!(update_session_title_on(&conn, "s", "", 200).is_err());
+        assert!(update_session_title_on(&conn, "s", " \t\n ", 200).is_err());
+        assert!(update_session_title_on(&conn, "s", &"x".repeat(MAX_TITLE_BYTES + 1), 200).is_err());
+        assert_eq!(get_session_on(&conn, "s").unwrap().unwrap().title, "Keep me");
+    }
+
+    #[test]
+    fn test_trim_session_keeps_last_messages() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+
+        for i in 0..10 {
+            insert_message(&conn, "s1", "user", &format!("message {}", i)).unwrap();
+        }
+        insert_message(&conn, "other", "user", "untouched").unwrap();
+
+        let removed = trim_session_on(&mut conn, "s1", 3).unwrap();
+        assert_eq!(removed, 7);
+
+        let remaining: Vec<String> = query_messages(&conn, "s1")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(remaining, vec!["message 7", "message 8", "message 9"]);
+        assert_eq!(query_messages(&conn, "other").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_append_message_prunes_beyond_cap() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        insert_message(&conn, "other", "user", "untouched").unwrap();
+
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let appended = append_message_on(&mut conn, "s1", "user", &format!("message {}", i), 3).unwrap();
+            ids.push(appended.message.id);
+            assert!(query_messages(&conn, "s1").unwrap().len() <= 3);
+            // Past the cap, each insert prunes exactly the oldest message
+            match i {
+                0..=2 => assert!(appended.pruned_ids.is_empty()),
+                _ => assert_eq!(appended.pruned_ids, vec![ids[i - 3]]),
+            }
+        }
+
+        let remaining: Vec<String> = query_messages(&conn, "s1")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(remaining, vec!["message 2", "message 3", "message 4"]);
+        assert_eq!(query_messages(&conn, "other").unwrap().len(), 1);
+
+        // 0 disables the cap
+        let appended = append_message_on(&mut conn, "s1", "user", "message 5", 0).unwrap();
+        assert!(appended.pruned_ids.is_empty());
+        assert_eq!(query_messages(&conn, "s1").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_write_retry_does_not_retry_genuine_errors() {
+        let conn = Connection::open_in_memory().unwrap();
+        let attempts = AtomicU32::new(0);
+
+        let result = with_write_retry(|| {
+            attempts.fetch_add(1, Ordering::SeqCst);
+            conn.execute("INSERT INTO missing_table (id) VALUES (1)", [])
+        });
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_compact_session_summarizes_old_messages() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+
+        for i in 0..6 {
+            insert_message(&conn, "s1", "user", &format!("message {}", i)).unwrap();
+        }
+
+        let compacted = compact_session_on(&mut conn, "s1", 2).unwrap();
+        assert_eq!(compacted.len(), 3);
+        assert_eq!(compacted[0].role, "system");
+        assert!(compacted[0].content.starts_with("Summary of 4 earlier messages:"));
+        assert!(compacted[0].content.contains("- user: message 3"));
+        assert!(!compacted[0].content.contains("message 4"));
+
+        // Stored state matches what was returned
+        let stored: Vec<String> = query_messages(&conn, "s1")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.content)
+            .collect();
+        assert_eq!(stored[1..], ["message 4".to_string(), "message 5".to_string()]);
+        assert_eq!(stored[0], compacted[0].content);
+
+        // Nothing left to compact
+        assert_eq!(compact_session_on(&mut conn, "s1", 5).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_search_messages_indexes_existing_and_new_rows() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        // Database from before the search index existed
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                repo_name TEXT NOT NULL,
+                branch_name TEXT,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO sessions (id, title, repo_name, status, created_at)
+                VALUES ('old', 'Old', 'repo', 'pending', 1);
+            CREATE TABLE messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO messages (session_id, role, content, created_at)
+                VALUES ('old', 'user', 'Traefik keeps returning 502 errors', 1);",
+        )
+        .unwrap();
+
+        create_schema(&conn).unwrap();
+        insert_message(&conn, "new", "assistant", "Check the traefik router labels").unwrap();
+        insert_message(&conn, "new", "user", "Docker compose is fine").unwrap();
+
+        let hits = search_messages_on(&conn, "traefik").unwrap();
+        let sessions: Vec<&str> = hits.iter().map(|m| m.session_id.as_str()).collect();
+        assert_eq!(hits.len(), 2);
+        assert!(sessions.contains(&"old") && sessions.contains(&"new"));
+
+        // All words must match; FTS syntax in user input is treated literally
+        assert_eq!(search_messages_on(&conn, "traefik 502").unwrap().len(), 1);
+        assert!(search_messages_on(&conn, "\"docker OR (").unwrap().is_empty());
+        assert!(search_messages_on(&conn, "   ").unwrap().is_empty());
+
+        // Deleted rows leave the index
+        trim_session_on(&mut conn, "new", 1).unwrap();
+        assert_eq!(search_messages_on(&conn, "traefik").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_messages_table_gains_foreign_key() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                id TEXT PRIMARY KEY,
+                title TEXT NOT NULL,
+                repo_name TEXT NOT NULL,
+                branch_name TEXT,
+                status TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO sessions (id, title, repo_name, status, created_at)
+                VALUES ('live', 'Live', 'repo', 'pending', 1);
+            CREATE TABLE messages (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                role TEXT NOT NULL,
+                content TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );
+            INSERT INTO messages (id, session_id, role, content, created_at) VALUES
+                (7, 'live', 'user', 'kept message', 1),
+                (8, 'deleted', 'user', 'orphaned message', 2);",
+        )
+        .unwrap();
+
+        create_schema(&conn).unwrap();
+
+        let fk_count: i64 = conn
+            .query_row("SELECT COUNT(*) FROM pragma_foreign_key_list('messages')", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(fk_count, 1);
+
+        let live = query_messages(&conn, "live").unwrap();
+        assert_eq!(live.len(), 1);
+        assert_eq!(live[0].id, 7);
+        assert!(query_messages(&conn, "deleted").unwrap().is_empty());
+        assert_eq!(search_messages_on(&conn, "message").unwrap().len(), 1);
+
+        // Second run is a no-op
+        create_schema(&conn).unwrap();
+        assert!(delete_message_on(&conn, 7).unwrap());
+        assert!(!delete_message_on(&conn, 7).unwrap());
+        assert!(query_messages(&conn, "live").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_list_sessions_pages() {
+        let conn = Connection::open_in_memory().unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+
+        // s0 is the newest, s4 the oldest
+        for i in 0..5 {
+            conn.execute(
+                "INSERT INTO sessions (id, title, repo_name, status, created_at)
+                 VALUES (?1, 'Session', 'repo', 'pending', ?2)",
+                params![format!("s{}", i), 100 - i],
+            )
+            .unwrap();
+        }
+        let ids = |page: &Page<TaskSession>| page.items.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+
+        let first = list_sessions_on(&conn, Some(2), 0).unwrap();
+        assert_eq!(ids(&first), vec!["s0", "s1"]);
+        assert!(first.has_more);
+
+        let middle = list_sessions_on(&conn, Some(2), 2).unwrap();
+        assert_eq!(ids(&middle), vec!["s2", "s3"]);
+        assert!(middle.has_more);
+
+        let last = list_sessions_on(&conn, Some(2), 4).unwrap();
+        assert_eq!(ids(&last), vec!["s4"]);
+        assert!(!last.has_more);
+
+        // No limit keeps the old unbounded behaviour
+        let all = list_sessions_on(&conn, None, 0).unwrap();
+        assert_eq!(all.items.len(), 5);
+        assert!(!all.has_more);
+    }
+
+    #[test]
+    fn test_get_messages_pages() {
+        let conn = Connection::open_in_memory().unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, status, created_at)
+             VALUES ('s', 'Session', 'repo', 'pending', 1)",
+            [],
+        )
+        .unwrap();
+        for i in 0..7 {
+            conn.execute(
+                "INSERT INTO messages (session_id, role, content, created_at) VALUES ('s', 'user', ?1, ?2)",
+                params![format!("m{}", i), i],
+            )
+            .unwrap();
+        }
+        let contents = |page: &Page<ChatMessage>| page.items.iter().map(|m| m.content.clone()).collect::<Vec<_>>();
+
+        let first = query_messages_page(&conn, "s", Some(3), 0).unwrap();
+        assert_eq!(contents(&first), vec!["m0", "m1", "m2"]);
+        assert!(first.has_more);
+
+        let middle = query_messages_page(&conn, "s", Some(3), 3).unwrap();
+        assert_eq!(contents(&middle), vec!["m3", "m4", "m5"]);
+        assert!(middle.has_more);
+
+        let last = query_messages_page(&conn, "s", Some(3), 6).unwrap();
+        assert_eq!(contents(&last), vec!["m6"]);
+        assert!(!last.has_more);
+
+        // An exactly full final page has nothing more
+        let exact = query_messages_page(&conn, "s", Some(7), 0).unwrap();
+        assert_eq!(exact.items.len(), 7);
+        assert!(!exact.has_more);
+    }
+
+    #[test]
+    fn test_cleanup_old_sessions_prunes_only_expired() {
+        let conn = Connection::open_in_memory().unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+
+        let now = chrono::Utc::now().timestamp();
+        for (id, created_at) in [("old", now - 40 * 86_400), ("recent", now - 3600)] {
+            conn.execute(
+                "INSERT INTO sessions (id, title, repo_name, status, created_at)
+                 VALUES (?1, 'Session', 'repo', 'pending', ?2)",
+                params![id, created_at],
+            )
+            .unwrap();
+            insert_message(&conn, id, "user", "hello").unwrap();
+        }
+
+        let cutoff = now - 30 * 86_400;
+        assert_eq!(cleanup_old_sessions_on(&conn, cutoff).unwrap(), 1);
+
+        assert!(get_session_on(&conn, "old").unwrap().is_none());
+        assert!(query_messages(&conn, "old").unwrap().is_empty());
+        assert!(get_session_on(&conn, "recent").unwrap().is_some());
+        assert_eq!(query_messages(&conn, "recent").unwrap().len(), 1);
+
+        // Nothing left to prune
+        assert_eq!(cleanup_old_sessions_on(&conn, cutoff).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_delete_session_cascades_to_messages() {
+        let tmp = tempdir().unwrap();
+        let conn = Connection::open(tmp.path().join("chat.db")).unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+
+        for id in ["doomed", "survivor"] {
+            conn.execute(
+                "INSERT INTO sessions (id, title, repo_name, status, created_at)
+                 VALUES (?1, 'Session', 'repo', 'pending', 1)",
+                params![id],
+            )
+            .unwrap();
+            insert_message(&conn, id, "user", "hello cascade").unwrap();
+            insert_message(&conn, id, "assistant", "hi").unwrap();
+        }
+
+        assert!(delete_session_on(&conn, "doomed").unwrap());
+
+        assert!(query_messages(&conn, "doomed").unwrap().is_empty());
+        assert_eq!(query_messages(&conn, "survivor").unwrap().len(), 2);
+        // Cascaded deletes fire the search index triggers too
+        assert_eq!(search_messages_on(&conn, "cascade").unwrap().len(), 1);
+
+        // Messages for unknown sessions are rejected once enforcement is on
+        assert!(insert_message(&conn, "doomed", "user", "orphan").is_err());
+    }
+
+    #[test]
+    fn test_widget_sessions_survive_reconnect() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("chat.db");
+        {
+            let conn = Connection::open(&path).unwrap();
+            configure_connection(&conn).unwrap();
+            create_schema(&conn).unwrap();
+            insert_widget_session_on(&conn, "widget-1", 100).unwrap();
+            // Re-registering keeps the original timestamp
+            insert_widget_session_on(&conn, "widget-1", 200).unwrap();
+            insert_widget_session_on(&conn, "widget-2", 300).unwrap();
+            assert!(delete_widget_session_on(&conn, "widget-2").unwrap());
+            assert!(!delete_widget_session_on(&conn, "widget-2").unwrap());
+        }
+
+        let conn = Connection::open(&path).unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+        let sessions = load_widget_sessions_on(&conn).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions.get("widget-1"), Some(&100));
+    }
+
+    #[test]
+    fn test_session_json_round_trip() {
+        let tmp = tempdir().unwrap();
+        let mut conn = Connection::open(tmp.path().join("chat.db")).unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+
+        conn.execute(
+            "INSERT INTO sessions (id, title, repo_name, branch_name, status, created_at)
+             VALUES ('original', 'Backup me', 'repo', 'main', 'completed', 42)",
+            [],
+        )
+        .unwrap();
+        insert_message(&conn, "original", "user", "Question with ```code```").unwrap();
+        insert_message(&conn, "original", "assistant", "Answer").unwrap();
+
+        let export = export_session_on(&conn, "original").unwrap().unwrap();
+        let json = serde_json::to_string(&export).unwrap();
+        let parsed: SessionExport = serde_json::from_str(&json).unwrap();
+
+        let imported = import_session_on(&mut conn, &parsed).unwrap();
+        assert_ne!(imported.id, "original");
+        assert_eq!(imported.title, "Backup me");
+        assert_eq!(imported.branch_name.as_deref(), Some("main"));
+
+        let strip = |messages: Vec<ChatMessage>| {
+            messages
+                .into_iter()
+                .map(|m| (m.role, m.content, m.created_at))
+                .collect::<Vec<_>>()
+        };
+        let original = query_messages(&conn, "original").unwrap();
+        let copied = query_messages(&conn, &imported.id).unwrap();
+        assert!(copied.iter().all(|m| original.iter().all(|o| o.id != m.id)));
+        assert_eq!(strip(copied), strip(original));
+
+        // Importing the same export again never collides with the first copy
+        let again = import_session_on(&mut conn, &parsed).unwrap();
+        assert_ne!(again.id, imported.id);
+    }
+
+    #[test]
+    fn test_import_rejects_malformed_export() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        configure_connection(&conn).unwrap();
+        create_schema(&conn).unwrap();
+
+        let valid = SessionExport {
+            version: SESSION_EXPORT_VERSION,
+            session: TaskSession {
+                id: "x".to_string(),
+                title: "t".to_string(),
+                repo_name: "repo".to_string(),
+                branch_name: None,
+                status: "pending".to_string(),
+                created_at: 1,
+            },
+            messages: vec![ChatMessage {
+                id: 1,
+                session_id: "x".to_string(),
+                role: "user".to_string(),
+                content: "hi".to_string(),
+                created_at: 1,
+            }],
+        };
+
+        let mut bad_version = valid.clone();
+        bad_version.version = 99;
+        let mut bad_status = valid.clone();
+        bad_status.session.status = "exploded".to_string();
+        let mut bad_role = valid.clone();
+        bad_role.messages[0].role = "hacker".to_string();
+        for export in [bad_version, bad_status, bad_role] {
+            assert!(import_session_on(&mut conn, &export).is_err());
+        }
+
+        // Nothing was written by the rejected imports
+        let sessions: i64 = conn
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(sessions, 0);
+        assert!(import_session_on(&mut conn, &valid).is_ok());
+    }
+
+    #[test]
+    fn test_session_status_validation() {
+        for status in SESSION_STATUSES {
+            assert!(validate_session_status(status).is_ok());
+        }
+        assert!(validate_session_status("done").is_err());
+        assert!(validate_session_status("Completed").is_err());
+        assert!(update_session_status("any-session", "bogus").is_err());
+    }
+}
@@ -64,8 +64,19 @@ pub fn load_app_config() -> Result<AppConfig, String> {
         }
     }
 
-    let config: AppConfig = serde_json::from_value(v)
+    let mut config: AppConfig = serde_json::from_value(v)
         .map_err(|e| format!("failed_to_convert_config_after_migration: {}", e))?;
+    // 语义校验只在保存时拒绝；已有配置即使不合规也照常加载，仅记录警告
+    if let Err(errors) = config.proxy.validate() {
+        for error in errors {
+            tracing::warn!("[Config] Invalid proxy setting: {}", error);
+        }
+    }
+
+    // 旧版本明文保存的管理密码: 哈希后清空明文
+    if config.proxy.migrate_admin_password() {
+        modified = true;
+    }
     
     // If migration occurred, auto-save once to clean up the file
     if modified {
@@ -75,12 +86,26 @@ pub fn load_app_config() -> Result<AppConfig, String> {
     Ok(config)
 }
 
+/// Validate the proxy section, joining every problem into one error message
+fn validate_proxy_config(config: &AppConfig) -> Result<(), String> {
+    config
+        .proxy
+        .validate()
+        .map_err(|errors| format!("invalid_proxy_config: {}", errors.join("; ")))
+}
+
 /// Save application configuration
 pub fn save_app_config(config: &AppConfig) -> Result<(), String> {
+    validate_proxy_config(config)?;
+
+    // 明文管理密码永不落盘
+    let mut config = config.clone();
+    config.proxy.migrate_admin_password();
+
     let data_dir = get_data_dir()?;
     let config_path = data_dir.join(CONFIG_FILE);
     
-    let content = serde_json::to_string_pretty(config)
+    let content = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("failed_to_serialize_config: {}", e))?;
     
     fs::write(&config_path, content)
@@ -22,6 +22,8 @@ pub mod cache;
 pub mod log_bridge;
 pub mod security_db;
 pub mod chat_db;
+pub mod artifacts;
+pub mod skill_router;
 
 use crate::models;
 
@@ -1,9 +1,12 @@
 //! Security Database Module
 //! 安全监控相关的数据库操作
 
+use ipnet::IpNet;
 use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
 use r2d2::PooledConnection;
 use r2d2_sqlite::SqliteConnectionManager;
 use once_cell::sync::Lazy;
@@ -405,6 +408,7 @@ pub fn add_to_blacklist(
     expires_at: Option<i64>,
     created_by: &str,
 ) -> Result<IpBlacklistEntry, String> {
+    parse_ip_pattern(ip_pattern)?;
     let conn = connect_db()?;
 
     let id = uuid::Uuid::new_v4().to_string();
@@ -416,6 +420,7 @@ pub fn add_to_blacklist(
         params![id, ip_pattern, reason, now, expires_at, created_by],
     )
     .map_err(|e| e.to_string())?;
+    invalidate_blacklist_cache();
 
     Ok(IpBlacklistEntry {
         id,
@@ -434,6 +439,7 @@ pub fn remove_from_blacklist(id: &str) -> Result<(), String> {
 
     conn.execute("DELETE FROM ip_blacklist WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
+    invalidate_blacklist_cache();
 
     Ok(())
 }
@@ -471,103 +477,170 @@ pub fn get_blacklist() -> Result<Vec<IpBlacklistEntry>, String> {
     Ok(entries)
 }
 
+/// 自动封禁条目的 `created_by` 标记
+pub const AUTO_BAN_CREATOR: &str = "auto_ban";
+
+/// 获取当前仍然生效的自动封禁条目 (按过期时间升序)
+pub fn get_auto_banned_ips() -> Result<Vec<IpBlacklistEntry>, String> {
+    let now = chrono::Utc::now().timestamp();
+    let mut entries: Vec<IpBlacklistEntry> = get_blacklist()?
+        .into_iter()
+        .filter(|e| e.created_by == AUTO_BAN_CREATOR)
+        .filter(|e| e.expires_at.map_or(true, |expires_at| expires_at > now))
+        .collect();
+    entries.sort_by_key(|e| e.expires_at.unwrap_or(i64::MAX));
+    Ok(entries)
+}
+
 /// 检查 IP 是否在黑名单中
 pub fn is_ip_in_blacklist(ip: &str) -> Result<bool, String> {
     get_blacklist_entry_for_ip(ip).map(|entry| entry.is_some())
 }
 
-/// 获取 IP 对应的黑名单条目（如果存在）
+/// 获取 IP 对应的黑名单条目（如果存在）。返回条目的 `hit_count` 为缓存加载时的值，实时计数见 `get_blacklist`
 pub fn get_blacklist_entry_for_ip(ip: &str) -> Result<Option<IpBlacklistEntry>, String> {
+    let blacklist = cached_blacklist()?;
+
+    // CIDR 匹配 (单个 IP 视为 /32 或 /128)，多条命中时取最精确的网段
+    let Some(entry) = most_specific_match(ip, &blacklist.entries).cloned() else {
+        return Ok(None);
+    };
+
+    // 增加命中计数
     let conn = connect_db()?;
+    let _ = conn.execute(
+        "UPDATE ip_blacklist SET hit_count = hit_count + 1 WHERE id = ?1",
+        [&entry.id],
+    );
+    Ok(Some(entry))
+}
+
+// ============================================================================
+// 名单缓存
+// ============================================================================
+
+/// 已解析的黑名单: (网段, 条目) 以及其中最早的过期时间
+struct ParsedBlacklist {
+    entries: Vec<(IpNet, IpBlacklistEntry)>,
+    next_expiry: Option<i64>,
+}
+
+/// 黑/白名单缓存。每次请求都要查名单，因此只在首次查询、增删条目或有条目过期时才从数据库重新加载并解析
+static BLACKLIST_CACHE: Lazy<RwLock<Option<Arc<ParsedBlacklist>>>> = Lazy::new(|| RwLock::new(None));
+static WHITELIST_CACHE: Lazy<RwLock<Option<Arc<Vec<(IpNet, IpWhitelistEntry)>>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+fn invalidate_blacklist_cache() {
+    *BLACKLIST_CACHE.write().unwrap() = None;
+}
+
+fn invalidate_whitelist_cache() {
+    *WHITELIST_CACHE.write().unwrap() = None;
+}
+
+/// 获取缓存的黑名单；缓存为空或有条目已过期时清理过期条目并重新加载
+fn cached_blacklist() -> Result<Arc<ParsedBlacklist>, String> {
     let now = chrono::Utc::now().timestamp();
+    let is_fresh = |list: &ParsedBlacklist| list.next_expiry.map_or(true, |expiry| expiry >= now);
+
+    if let Some(list) = BLACKLIST_CACHE.read().unwrap().as_ref() {
+        if is_fresh(list) {
+            return Ok(list.clone());
+        }
+    }
+
+    // 持有写锁完成加载，避免与并发的增删操作交错后缓存旧数据
+    let mut cache = BLACKLIST_CACHE.write().unwrap();
+    if let Some(list) = cache.as_ref() {
+        if is_fresh(list) {
+            return Ok(list.clone());
+        }
+    }
 
     // 清理过期的黑名单条目
+    let conn = connect_db()?;
     let _ = conn.execute(
         "DELETE FROM ip_blacklist WHERE expires_at IS NOT NULL AND expires_at < ?1",
         [now],
     );
 
-    // 精确匹配
-    let entry_result = conn.query_row(
-        "SELECT id, ip_pattern, reason, created_at, expires_at, created_by, hit_count
-         FROM ip_blacklist WHERE ip_pattern = ?1",
-        [ip],
-        |row| {
-            Ok(IpBlacklistEntry {
-                id: row.get(0)?,
-                ip_pattern: row.get(1)?,
-                reason: row.get(2)?,
-                created_at: row.get(3)?,
-                expires_at: row.get(4)?,
-                created_by: row.get(5)?,
-                hit_count: row.get(6)?,
-            })
-        },
-    );
+    let entries = parse_list_entries(get_blacklist()?, |e| e.ip_pattern.as_str());
+    let next_expiry = entries.iter().filter_map(|(_, e)| e.expires_at).min();
+    let list = Arc::new(ParsedBlacklist { entries, next_expiry });
+    *cache = Some(list.clone());
+    Ok(list)
+}
 
-    if let Ok(entry) = entry_result {
-        // 增加命中计数
-        let _ = conn.execute(
-            "UPDATE ip_blacklist SET hit_count = hit_count + 1 WHERE ip_pattern = ?1",
-            [ip],
-        );
-        return Ok(Some(entry));
+/// 获取缓存的白名单，缓存为空时从数据库加载
+fn cached_whitelist() -> Result<Arc<Vec<(IpNet, IpWhitelistEntry)>>, String> {
+    if let Some(list) = WHITELIST_CACHE.read().unwrap().as_ref() {
+        return Ok(list.clone());
     }
 
-    // CIDR 匹配
-    let entries = get_blacklist()?;
-    for entry in entries {
-        if entry.ip_pattern.contains('/') {
-            if cidr_match(ip, &entry.ip_pattern) {
-                // 增加命中计数
-                let _ = conn.execute(
-                    "UPDATE ip_blacklist SET hit_count = hit_count + 1 WHERE id = ?1",
-                    [&entry.id],
-                );
-                return Ok(Some(entry));
-            }
-        }
+    let mut cache = WHITELIST_CACHE.write().unwrap();
+    if let Some(list) = cache.as_ref() {
+        return Ok(list.clone());
     }
 
-    Ok(None)
+    let list = Arc::new(parse_list_entries(get_whitelist()?, |e| e.ip_pattern.as_str()));
+    *cache = Some(list.clone());
+    Ok(list)
 }
 
-/// 简单的 CIDR 匹配
-fn cidr_match(ip: &str, cidr: &str) -> bool {
-    let parts: Vec<&str> = cidr.split('/').collect();
-    if parts.len() != 2 {
-        return false;
-    }
+/// 加载时解析名单中的每条模式。无法解析的条目只在加载时记录一次并跳过
+fn parse_list_entries<T, F>(entries: Vec<T>, pattern_of: F) -> Vec<(IpNet, T)>
+where
+    F: Fn(&T) -> &str,
+{
+    entries
+        .into_iter()
+        .filter_map(|entry| match parse_ip_pattern(pattern_of(&entry)) {
+            Ok(net) => Some((net, entry)),
+            Err(e) => {
+                tracing::warn!("[Security] Skipping malformed IP list entry: {}", e);
+                None
+            }
+        })
+        .collect()
+}
 
-    let network = parts[0];
-    let prefix_len: u8 = match parts[1].parse() {
-        Ok(p) => p,
-        Err(_) => return false,
-    };
+/// 解析 IP 模式: CIDR 网段 (`10.0.0.0/8`, `2001:db8::/32`) 或单个 IPv4/IPv6 地址
+pub fn parse_ip_pattern(pattern: &str) -> Result<IpNet, String> {
+    let pattern = pattern.trim();
+    if pattern.contains('/') {
+        pattern
+            .parse::<IpNet>()
+            .map_err(|_| format!("Invalid CIDR '{}': expected e.g. 192.168.1.0/24 or 2001:db8::/32", pattern))
+    } else {
+        pattern
+            .parse::<IpAddr>()
+            .map(IpNet::from)
+            .map_err(|_| format!("Invalid IP address '{}'", pattern))
+    }
+}
 
-    let ip_parts: Vec<u8> = ip
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
-    let net_parts: Vec<u8> = network
-        .split('.')
-        .filter_map(|s| s.parse().ok())
-        .collect();
+/// 解析客户端 IP (IPv4-mapped IPv6 地址按 IPv4 处理)
+fn parse_client_ip(ip: &str) -> Option<IpAddr> {
+    ip.trim().parse::<IpAddr>().ok().map(|addr| addr.to_canonical())
+}
 
-    if ip_parts.len() != 4 || net_parts.len() != 4 {
-        return false;
+/// IP 是否匹配模式 (单个 IP 或 CIDR 网段)
+pub fn ip_matches_pattern(ip: &str, pattern: &str) -> bool {
+    match (parse_client_ip(ip), parse_ip_pattern(pattern)) {
+        (Some(addr), Ok(net)) => net.contains(&addr),
+        _ => false,
     }
+}
 
-    let ip_u32 = u32::from_be_bytes([ip_parts[0], ip_parts[1], ip_parts[2], ip_parts[3]]);
-    let net_u32 = u32::from_be_bytes([net_parts[0], net_parts[1], net_parts[2], net_parts[3]]);
-
-    let mask = if prefix_len == 0 {
-        0
-    } else {
-        !0u32 << (32 - prefix_len)
-    };
+/// 在已解析的名单中查找包含该 IP 的条目，返回前缀最长 (最精确) 的一条
+fn most_specific_match<'a, T>(ip: &str, entries: &'a [(IpNet, T)]) -> Option<&'a T> {
+    let addr = parse_client_ip(ip)?;
 
-    (ip_u32 & mask) == (net_u32 & mask)
+    entries
+        .iter()
+        .filter(|(net, _)| net.contains(&addr))
+        .max_by_key(|(net, _)| net.prefix_len())
+        .map(|(_, entry)| entry)
 }
 
 // ============================================================================
@@ -576,6 +649,7 @@ fn cidr_match(ip: &str, cidr: &str) -> bool {
 
 /// 添加 IP 到白名单
 pub fn add_to_whitelist(ip_pattern: &str, description: Option<&str>) -> Result<IpWhitelistEntry, String> {
+    parse_ip_pattern(ip_pattern)?;
     let conn = connect_db()?;
 
     let id = uuid::Uuid::new_v4().to_string();
@@ -587,6 +661,7 @@ pub fn add_to_whitelist(ip_pattern: &str, description: Option<&str>) -> Result<I
         params![id, ip_pattern, description, now],
     )
     .map_err(|e| e.to_string())?;
+    invalidate_whitelist_cache();
 
     Ok(IpWhitelistEntry {
         id,
@@ -602,6 +677,7 @@ pub fn remove_from_whitelist(id: &str) -> Result<(), String> {
 
     conn.execute("DELETE FROM ip_whitelist WHERE id = ?1", [id])
         .map_err(|e| e.to_string())?;
+    invalidate_whitelist_cache();
 
     Ok(())
 }
@@ -638,32 +714,8 @@ pub fn get_whitelist() -> Result<Vec<IpWhitelistEntry>, String> {
 
 /// 检查 IP 是否在白名单中
 pub fn is_ip_in_whitelist(ip: &str) -> Result<bool, String> {
-    let conn = connect_db()?;
-
-    // 精确匹配
-    let count: i64 = conn
-        .query_row(
-            "SELECT COUNT(*) FROM ip_whitelist WHERE ip_pattern = ?1",
-            [ip],
-            |row| row.get(0),
-        )
-        .map_err(|e| e.to_string())?;
-
-    if count > 0 {
-        return Ok(true);
-    }
-
-    // CIDR 匹配
-    let entries = get_whitelist()?;
-    for entry in entries {
-        if entry.ip_pattern.contains('/') {
-            if cidr_match(ip, &entry.ip_pattern) {
-                return Ok(true);
-            }
-        }
-    }
-
-    Ok(false)
+    let whitelist = cached_whitelist()?;
+    Ok(most_specific_match(ip, &whitelist).is_some())
 }
 
 /// 清空所有 IP 访问日志
@@ -0,0 +1,460 @@
+// Native BM25 skills router (in-process port of tools/skills-indexer/src/02-router.ts)
+// Scores the entries of skills-index.json against a query without spawning Node.js.
+
+use std::collections::{HashMap, HashSet};
+
+use tracing::info;
+
+use crate::commands::skills::{SelectionLimits, SkillMetadata, SkillScore, SkillSelection};
+
+/// BM25 term-frequency saturation
+pub const BM25_K1: f64 = 1.2;
+/// BM25 document-length normalisation
+pub const BM25_B: f64 = 0.75;
+
+/// Tunable BM25 parameters (`skills.bm25_k1` / `skills.bm25_b`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bm25Params {
+    pub k1: f64,
+    pub b: f64,
+}
+
+impl Default for Bm25Params {
+    fn default() -> Self {
+        Self { k1: BM25_K1, b: BM25_B }
+    }
+}
+
+impl Bm25Params {
+    pub fn from_config(config: &crate::proxy::config::SkillsConfig) -> Self {
+        Self {
+            k1: config.bm25_k1 as f64,
+            b: config.bm25_b as f64,
+        }
+    }
+}
+
+/// Persona / category used when no selected skill carries a category
+const DEFAULT_PERSONA: &str = "generalist";
+const DEFAULT_CATEGORY: &str = "general";
+
+/// Words that carry no signal for skill matching
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "for", "from", "how", "in",
+    "is", "it", "me", "my", "of", "on", "or", "the", "this", "that", "to", "we", "what", "with",
+    "you",
+];
+
+/// Lowercase, split on non-alphanumerics, drop 1-char tokens and stopwords
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .map(|t| t.to_lowercase())
+        .filter(|t| t.chars().count() >= 2 && !STOPWORDS.contains(&t.as_str()))
+        .collect()
+}
+
+/// Searchable text of an index entry: id, name, description, tags and category
+fn document_tokens(skill: &SkillMetadata) -> Vec<String> {
+    let mut text = skill.id.clone();
+    for part in [&skill.name, &skill.description, &skill.category].into_iter().flatten() {
+        text.push(' ');
+        text.push_str(part);
+    }
+    for tag in &skill.tags {
+        text.push(' ');
+        text.push_str(tag);
+    }
+    tokenize(&text)
+}
+
+struct Document {
+    skill: SkillMetadata,
+    term_freqs: HashMap<String, usize>,
+    len: usize,
+}
+
+/// BM25 index over the skills index entries
+pub struct Bm25Index {
+    docs: Vec<Document>,
+    doc_freqs: HashMap<String, usize>,
+    avg_len: f64,
+    params: Bm25Params,
+}
+
+impl Bm25Index {
+    pub fn new(skills: Vec<SkillMetadata>) -> Self {
+        Self::with_params(skills, Bm25Params::default())
+    }
+
+    pub fn with_params(skills: Vec<SkillMetadata>, params: Bm25Params) -> Self {
+        let mut doc_freqs: HashMap<String, usize> = HashMap::new();
+        let docs: Vec<Document> = skills
+            .into_iter()
+            .map(|skill| {
+                let tokens = document_tokens(&skill);
+                let mut term_freqs: HashMap<String, usize> = HashMap::new();
+                for token in &tokens {
+                    *term_freqs.entry(token.clone()).or_insert(0) += 1;
+                }
+                for term in term_freqs.keys() {
+                    *doc_freqs.entry(term.clone()).or_insert(0) += 1;
+                }
+                Document { skill, term_freqs, len: tokens.len() }
+            })
+            .collect();
+
+        let avg_len = if docs.is_empty() {
+            0.0
+        } else {
+            docs.iter().map(|d| d.len).sum::<usize>() as f64 / docs.len() as f64
+        };
+
+        Self { docs, doc_freqs, avg_len, params }
+    }
+
+    /// Smoothed IDF (always positive): ln(1 + (N - n + 0.5) / (n + 0.5))
+    fn idf(&self, term: &str) -> f64 {
+        let n = *self.doc_freqs.get(term).unwrap_or(&0) as f64;
+        let total = self.docs.len() as f64;
+        (1.0 + (total - n + 0.5) / (n + 0.5)).ln()
+    }
+
+    fn score_doc(&self, doc: &Document, terms: &[String]) -> (f64, Vec<String>) {
+        let mut score = 0.0;
+        let mut matched = Vec::new();
+        let norm = if self.avg_len > 0.0 { doc.len as f64 / self.avg_len } else { 0.0 };
+        let Bm25Params { k1, b } = self.params;
+
+        for term in terms {
+            let Some(&tf) = doc.term_freqs.get(term) else {
+                continue;
+            };
+            let tf = tf as f64;
+            score += self.idf(term) * tf * (k1 + 1.0) / (tf + k1 * (1.0 - b + b * norm));
+            matched.push(term.clone());
+        }
+
+        (score, matched)
+    }
+
+    /// All skills with a positive score, best first (ties broken by id).
+    /// Repeated query terms count once.
+    pub fn rank(&self, query: &str) -> Vec<SkillScore> {
+        let mut seen = HashSet::new();
+        let terms: Vec<String> = tokenize(query).into_iter().filter(|t| seen.insert(t.clone())).collect();
+
+        let mut scored: Vec<SkillScore> = self
+            .docs
+            .iter()
+            .filter_map(|doc| {
+                let (score, matched_terms) = self.score_doc(doc, &terms);
+                (score > 0.0).then(|| SkillScore {
+                    id: doc.skill.id.clone(),
+                    name: doc.skill.name.clone().unwrap_or_else(|| doc.skill.id.clone()),
+                    score,
+                    matched_terms,
+                    size_bytes: doc.skill.effective_size(),
+                })
+            })
+            .collect();
+
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.id.cmp(&b.id)));
+        scored
+    }
+
+    fn category_of(&self, skill_id: &str) -> Option<&str> {
+        self.docs
+            .iter()
+            .find(|d| d.skill.id == skill_id)
+            .and_then(|d| d.skill.category.as_deref())
+    }
+
+    /// Top-k skills that fit in `max_bytes` (oversized skills are skipped, not truncated).
+    /// The category is the one with the highest summed score among the picks; the persona is
+    /// the one mapped to that category in `persona_categories`.
+    pub fn select(
+        &self,
+        query: &str,
+        k: usize,
+        max_bytes: usize,
+        persona_categories: &HashMap<String, String>,
+    ) -> SkillSelection {
+        let mut skills = Vec::new();
+        let mut total_bytes = 0;
+        for candidate in self.rank(query) {
+            if skills.len() >= k {
+                break;
+            }
+            if total_bytes + candidate.size_bytes > max_bytes {
+                continue;
+            }
+            total_bytes += candidate.size_bytes;
+            skills.push(candidate);
+        }
+
+        let mut category_scores: HashMap<&str, f64> = HashMap::new();
+        for skill in &skills {
+            if let Some(category) = self.category_of(&skill.id) {
+                *category_scores.entry(category).or_insert(0.0) += skill.score;
+            }
+        }
+        let category = category_scores
+            .into_iter()
+            .max_by(|a, b| a.1.total_cmp(&b.1).then_with(|| b.0.cmp(a.0)))
+            .map(|(c, _)| c.to_string())
+            .unwrap_or_else(|| DEFAULT_CATEGORY.to_string());
+
+        let mut personas: Vec<&String> = persona_categories
+            .iter()
+            .filter(|(_, c)| **c == category)
+            .map(|(p, _)| p)
+            .collect();
+        personas.sort();
+        let persona = personas
+            .first()
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| DEFAULT_PERSONA.to_string());
+
+        SkillSelection {
+            persona,
+            category,
+            limits: SelectionLimits {
+                max_skills: k,
+                max_bytes,
+                actual_skills: skills.len(),
+                actual_bytes: total_bytes,
+            },
+            skills,
+            total_bytes,
+        }
+    }
+}
+
+/// Route a query against `~/.agent/skills-index.json`
+pub fn route(
+    query: &str,
+    k: usize,
+    max_bytes: usize,
+    params: Bm25Params,
+) -> Result<SkillSelection, String> {
+    let skills = crate::commands::skills::load_skills_index()?;
+    let indexed = skills.len();
+    let index = Bm25Index::with_params(skills, params);
+    let persona_categories = crate::modules::config::load_app_config()
+        .map(|config| config.proxy.workflows.persona_categories)
+        .unwrap_or_else(|_| crate::proxy::config::WorkflowConfig::default().persona_categories);
+
+    let selection = index.select(query, k, max_bytes, &persona_categories);
+    info!(
+        "[SkillRouter] Selected persona: {}, {} skills, {} bytes (index: {} skills)",
+        selection.persona,
+        selection.skills.len(),
+        selection.total_bytes,
+        indexed
+    );
+
+    Ok(selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<SkillMetadata> {
+        let entry = |id: &str, name: &str, description: &str, tags: &[&str], category: &str, size: usize| {
+            SkillMetadata {
+                id: id.to_string(),
+                path: format!("/skills/{}/SKILL.md", id),
+                name: Some(name.to_string()),
+                size_bytes: Some(size),
+                description: Some(description.to_string()),
+                tags: tags.iter().map(|t| t.to_string()).collect(),
+                category: Some(category.to_string()),
+            }
+        };
+
+        vec![
+            entry(
+                "docker-compose",
+                "Docker Compose",
+                "Define and run multi-container Docker applications with compose files",
+                &["docker", "containers", "devops"],
+                "devops",
+                4000,
+            ),
+            entry(
+                "kubernetes-deploy",
+                "Kubernetes Deploy",
+                "Deploy containers to Kubernetes clusters with rolling updates",
+                &["kubernetes", "deploy", "devops"],
+                "devops",
+                6000,
+            ),
+            entry(
+                "rust-debugging",
+                "Rust Debugging",
+                "Debug Rust panics, borrow checker errors and async deadlocks",
+                &["rust", "debugging"],
+                "debugging",
+                5000,
+            ),
+            entry(
+                "api-design",
+                "API Design",
+                "Design REST APIs with versioning and pagination",
+                &["api", "architecture"],
+                "architecture",
+                3000,
+            ),
+            entry(
+                "unit-testing",
+                "Unit Testing",
+                "Write unit tests with mocks and fixtures",
+                &["testing", "tests"],
+                "testing",
+                2000,
+            ),
+        ]
+    }
+
+    /// Sample queries with the expected ranking and scores (k1 = 1.2, b = 0.75).
+    /// The scores were computed outside this crate with a standalone Okapi BM25 (smoothed
+    /// IDF, repeated query terms counted once) over the fixture text; they are pinned here
+    /// so a change to tokenizing or scoring shows up as a failure.
+    const REFERENCE_CASES: &[(&str, &[(&str, f64)])] = &[
+        (
+            "deploy docker containers",
+            &[("kubernetes-deploy", 3.210_475), ("docker-compose", 3.104_912)],
+        ),
+        ("debug rust async deadlock", &[("rust-debugging", 4.988_599)]),
+        ("design a REST api", &[("api-design", 5.946_640)]),
+    ];
+
+    fn assert_scores(ranked: &[SkillScore], expected: &[(&str, f64)], query: &str) {
+        assert_eq!(ranked.len(), expected.len(), "query: {}", query);
+        for (skill, (id, score)) in ranked.iter().zip(expected.iter()) {
+            assert_eq!(&skill.id, id, "query: {}", query);
+            assert!(
+                (skill.score - score).abs() < 1e-3,
+                "query '{}', skill {}: {} vs {}",
+                query,
+                id,
+                skill.score,
+                score
+            );
+        }
+    }
+
+    #[test]
+    fn test_scores_match_reference_bm25() {
+        let index = Bm25Index::new(fixture());
+        for (query, expected) in REFERENCE_CASES {
+            assert_scores(&index.rank(query), expected, query);
+        }
+    }
+
+    /// Parity with the TypeScript router on the same fixture index. Needs Node and
+    /// `tools/skills-indexer/src/02-router.ts`: `cargo test -- --ignored test_scores_match_ts_router`
+    #[test]
+    #[ignore = "runs the TypeScript router through npx"]
+    fn test_scores_match_ts_router() {
+        let home = tempfile::tempdir().unwrap();
+        let agent_dir = home.path().join(".agent");
+        std::fs::create_dir_all(&agent_dir).unwrap();
+        let skills: Vec<serde_json::Value> = fixture()
+            .iter()
+            .map(|s| {
+                serde_json::json!({
+                    "id": s.id,
+                    "path": s.path,
+                    "name": s.name,
+                    "size_bytes": s.size_bytes,
+                    "description": s.description,
+                    "tags": s.tags,
+                    "category": s.category,
+                })
+            })
+            .collect();
+        std::fs::write(
+            agent_dir.join("skills-index.json"),
+            serde_json::json!({ "skills": skills }).to_string(),
+        )
+        .unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        // `cargo test` runs in src-tauri; tools/ lives at the repository root
+        let project_root = cwd.parent().unwrap_or(&cwd).to_path_buf();
+        let config = crate::proxy::config::SkillsConfig::default();
+        let script = crate::commands::skills::find_router_script(None, Some(&project_root), None)
+            .expect("TypeScript router not found");
+
+        let index = Bm25Index::new(fixture());
+        for (query, _) in REFERENCE_CASES {
+            let ts = crate::commands::skills::run_ts_router_script(
+                &script,
+                &project_root,
+                Some(home.path()),
+                query,
+                8,
+                80000,
+                &config,
+            )
+            .unwrap();
+            let expected: Vec<(&str, f64)> = ts.skills.iter().map(|s| (s.id.as_str(), s.score)).collect();
+            assert_scores(&index.rank(query), &expected, query);
+        }
+    }
+
+    #[test]
+    fn test_select_respects_k_budget_and_maps_persona() {
+        let index = Bm25Index::new(fixture());
+        let personas = crate::proxy::config::WorkflowConfig::default().persona_categories;
+
+        let selection = index.select("deploy docker containers", 8, 80000, &personas);
+        assert_eq!(selection.category, "devops");
+        assert_eq!(selection.persona, "devops-engineer");
+        assert_eq!(selection.total_bytes, 10000);
+
+        // Top hit does not fit: the next one that does is taken instead
+        let selection = index.select("deploy docker containers", 8, 5000, &personas);
+        let ids: Vec<&str> = selection.skills.iter().map(|s| s.id.as_str()).collect();
+        assert_eq!(ids, vec!["docker-compose"]);
+
+        let selection = index.select("deploy docker containers", 1, 80000, &personas);
+        assert_eq!(selection.limits.actual_skills, 1);
+
+        let selection = index.select("quantum chemistry", 8, 80000, &personas);
+        assert!(selection.skills.is_empty());
+        assert_eq!(selection.persona, DEFAULT_PERSONA);
+    }
+
+    #[test]
+    fn test_params_change_ranking() {
+        let entry = |id: &str, description: &str| SkillMetadata {
+            id: id.to_string(),
+            path: format!("/skills/{}/SKILL.md", id),
+            description: Some(description.to_string()),
+            ..Default::default()
+        };
+        // "short" mentions the term once in a short document, "long" twice in a long one
+        let corpus = || {
+            vec![
+                entry("short", "kafka"),
+                entry(
+                    "long",
+                    "kafka kafka lorem ipsum dolor sit amet consectetur adipiscing elit sed eiusmod tempor",
+                ),
+                entry("other", "redis"),
+            ]
+        };
+        let top = |params: Bm25Params| {
+            Bm25Index::with_params(corpus(), params).rank("kafka")[0].id.clone()
+        };
+
+        // No length normalisation: the higher term frequency wins
+        assert_eq!(top(Bm25Params { k1: 1.2, b: 0.0 }), "long");
+        // Full length normalisation: the short document wins
+        assert_eq!(top(Bm25Params { k1: 1.2, b: 1.0 }), "short");
+        assert_eq!(top(Bm25Params::default()), "short");
+    }
+}
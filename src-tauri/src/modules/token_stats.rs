@@ -55,6 +55,21 @@ pub struct AccountTrendPoint {
     pub account_data: std::collections::HashMap<String, u64>,
 }
 
+/// Usage within an explicit time range, broken down by model and by account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageSummary {
+    /// Range start (unix seconds, inclusive)
+    pub from: i64,
+    /// Range end (unix seconds, exclusive)
+    pub to: i64,
+    pub total_input_tokens: u64,
+    pub total_output_tokens: u64,
+    pub total_tokens: u64,
+    pub total_requests: u64,
+    pub by_model: Vec<ModelTokenStats>,
+    pub by_account: Vec<AccountTokenStats>,
+}
+
 pub(crate) fn get_db_path() -> Result<PathBuf, String> {
     let data_dir = crate::modules::account::get_data_dir()?;
     Ok(data_dir.join("token_stats.db"))
@@ -78,7 +93,10 @@ fn connect_db() -> Result<Connection, String> {
 /// Initialize the token stats database
 pub fn init_db() -> Result<(), String> {
     let conn = connect_db()?;
+    create_schema(&conn)
+}
 
+fn create_schema(conn: &Connection) -> Result<(), String> {
     // Create main usage table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS token_usage (
@@ -133,7 +151,25 @@ pub fn record_usage(
     output_tokens: u32,
 ) -> Result<(), String> {
     let conn = connect_db()?;
-    let timestamp = chrono::Utc::now().timestamp();
+    record_usage_on(
+        &conn,
+        chrono::Utc::now(),
+        account_email,
+        model,
+        input_tokens,
+        output_tokens,
+    )
+}
+
+fn record_usage_on(
+    conn: &Connection,
+    at: chrono::DateTime<chrono::Utc>,
+    account_email: &str,
+    model: &str,
+    input_tokens: u32,
+    output_tokens: u32,
+) -> Result<(), String> {
+    let timestamp = at.timestamp();
     let total_tokens = input_tokens + output_tokens;
 
     // Insert into raw usage table
@@ -143,7 +179,7 @@ pub fn record_usage(
         params![timestamp, account_email, model, input_tokens, output_tokens, total_tokens],
     ).map_err(|e| e.to_string())?;
 
-    let hour_bucket = chrono::Utc::now().format("%Y-%m-%d %H:00").to_string();
+    let hour_bucket = at.format("%Y-%m-%d %H:00").to_string();
     conn.execute(
         "INSERT INTO token_stats_hourly (hour_bucket, account_email, total_input_tokens, total_output_tokens, total_tokens, request_count)
          VALUES (?1, ?2, ?3, ?4, ?5, 1)
@@ -387,6 +423,96 @@ pub fn get_model_stats(hours: i64) -> Result<Vec<ModelTokenStats>, String> {
     Ok(result)
 }
 
+/// Aggregate raw usage in `[from, to)` (unix seconds) by model and by account.
+/// Reads the per-request table, so ranges are exact rather than hour-aligned.
+pub fn get_usage_stats(from: i64, to: i64) -> Result<UsageSummary, String> {
+    if from > to {
+        return Err(format!("Invalid range: from ({}) is after to ({})", from, to));
+    }
+    let conn = connect_db()?;
+    get_usage_stats_on(&conn, from, to)
+}
+
+fn get_usage_stats_on(conn: &Connection, from: i64, to: i64) -> Result<UsageSummary, String> {
+    let (total_input, total_output, total, requests): (u64, u64, u64, u64) = conn
+        .query_row(
+            "SELECT COALESCE(SUM(input_tokens), 0),
+                COALESCE(SUM(output_tokens), 0),
+                COALESCE(SUM(total_tokens), 0),
+                COUNT(*)
+         FROM token_usage
+         WHERE timestamp >= ?1 AND timestamp < ?2",
+            params![from, to],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT model,
+                SUM(input_tokens) as input,
+                SUM(output_tokens) as output,
+                SUM(total_tokens) as total,
+                COUNT(*) as count
+         FROM token_usage
+         WHERE timestamp >= ?1 AND timestamp < ?2
+         GROUP BY model
+         ORDER BY total DESC, model ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let by_model = stmt
+        .query_map(params![from, to], |row| {
+            Ok(ModelTokenStats {
+                model: row.get(0)?,
+                total_input_tokens: row.get(1)?,
+                total_output_tokens: row.get(2)?,
+                total_tokens: row.get(3)?,
+                request_count: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT account_email,
+                SUM(input_tokens) as input,
+                SUM(output_tokens) as output,
+                SUM(total_tokens) as total,
+                COUNT(*) as count
+         FROM token_usage
+         WHERE timestamp >= ?1 AND timestamp < ?2
+         GROUP BY account_email
+         ORDER BY total DESC, account_email ASC",
+        )
+        .map_err(|e| e.to_string())?;
+    let by_account = stmt
+        .query_map(params![from, to], |row| {
+            Ok(AccountTokenStats {
+                account_email: row.get(0)?,
+                total_input_tokens: row.get(1)?,
+                total_output_tokens: row.get(2)?,
+                total_tokens: row.get(3)?,
+                request_count: row.get(4)?,
+            })
+        })
+        .map_err(|e| e.to_string())?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(|e| e.to_string())?;
+
+    Ok(UsageSummary {
+        from,
+        to,
+        total_input_tokens: total_input,
+        total_output_tokens: total_output,
+        total_tokens: total,
+        total_requests: requests,
+        by_model,
+        by_account,
+    })
+}
+
 pub fn get_model_trend_hourly(hours: i64) -> Result<Vec<ModelTrendPoint>, String> {
     let conn = connect_db()?;
     let cutoff = chrono::Utc::now().timestamp() - (hours * 3600);
@@ -556,11 +682,64 @@ pub fn get_account_trend_daily(days: i64) -> Result<Vec<AccountTrendPoint>, Stri
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+
+    fn test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        create_schema(&conn).unwrap();
+        conn
+    }
+
+    #[test]
+    fn test_usage_stats_aggregate_by_model_and_account() {
+        let conn = test_db();
+        let at = |secs: i64| chrono::Utc.timestamp_opt(secs, 0).unwrap();
+
+        record_usage_on(&conn, at(1_000), "a@example.com", "claude-sonnet-4-5", 100, 20).unwrap();
+        record_usage_on(&conn, at(1_500), "a@example.com", "gemini-3-pro", 50, 5).unwrap();
+        record_usage_on(&conn, at(1_900), "b@example.com", "claude-sonnet-4-5", 300, 40).unwrap();
+        // Outside [1000, 2000): before the start and exactly at the end
+        record_usage_on(&conn, at(999), "a@example.com", "claude-sonnet-4-5", 7, 7).unwrap();
+        record_usage_on(&conn, at(2_000), "b@example.com", "gemini-3-pro", 9, 9).unwrap();
+
+        let summary = get_usage_stats_on(&conn, 1_000, 2_000).unwrap();
+        assert_eq!(summary.total_requests, 3);
+        assert_eq!(summary.total_input_tokens, 450);
+        assert_eq!(summary.total_output_tokens, 65);
+        assert_eq!(summary.total_tokens, 515);
+
+        let sonnet = &summary.by_model[0];
+        assert_eq!(sonnet.model, "claude-sonnet-4-5");
+        assert_eq!(
+            (sonnet.total_input_tokens, sonnet.total_output_tokens, sonnet.total_tokens, sonnet.request_count),
+            (400, 60, 460, 2)
+        );
+        assert_eq!(summary.by_model[1].model, "gemini-3-pro");
+        assert_eq!(summary.by_model[1].total_tokens, 55);
+
+        let accounts: Vec<(&str, u64, u64)> = summary
+            .by_account
+            .iter()
+            .map(|a| (a.account_email.as_str(), a.total_tokens, a.request_count))
+            .collect();
+        assert_eq!(accounts, vec![("b@example.com", 340, 1), ("a@example.com", 175, 2)]);
+
+        // Per-model and per-account breakdowns each add up to the totals
+        let model_sum: u64 = summary.by_model.iter().map(|m| m.total_tokens).sum();
+        let account_sum: u64 = summary.by_account.iter().map(|a| a.total_tokens).sum();
+        assert_eq!(model_sum, summary.total_tokens);
+        assert_eq!(account_sum, summary.total_tokens);
+    }
 
     #[test]
-    fn test_record_and_query() {
-        // This would need a test database setup
-        // For now, just verify the module compiles
-        assert!(true);
+    fn test_usage_stats_empty_range() {
+        let conn = test_db();
+        let summary = get_usage_stats_on(&conn, 0, 10).unwrap();
+        assert_eq!(summary.total_requests, 0);
+        assert_eq!(summary.total_tokens, 0);
+        assert!(summary.by_model.is_empty());
+        assert!(summary.by_account.is_empty());
+
+        assert!(get_usage_stats(10, 0).is_err());
     }
 }
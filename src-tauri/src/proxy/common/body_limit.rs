@@ -0,0 +1,60 @@
+// 请求/响应体大小限制
+// 限制值来自 ProxyConfig，启动及热更新时写入全局状态，供中间件与流式收集器读取
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const DEFAULT_MAX_BYTES: usize = 100 * 1024 * 1024; // 100MB
+
+static MAX_REQUEST_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BYTES);
+static MAX_RESPONSE_BYTES: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_BYTES);
+
+/// 应用配置中的大小限制 (启动及热更新时调用)
+pub fn apply_config(config: &crate::proxy::config::ProxyConfig) {
+    MAX_REQUEST_BYTES.store(config.max_request_bytes, Ordering::Relaxed);
+    MAX_RESPONSE_BYTES.store(config.max_response_bytes, Ordering::Relaxed);
+}
+
+pub fn max_request_bytes() -> usize {
+    MAX_REQUEST_BYTES.load(Ordering::Relaxed)
+}
+
+pub fn max_response_bytes() -> usize {
+    MAX_RESPONSE_BYTES.load(Ordering::Relaxed)
+}
+
+/// 累计已读取的响应字节数，超出上限时返回错误
+pub struct ByteBudget {
+    limit: usize,
+    used: usize,
+}
+
+impl ByteBudget {
+    pub fn new(limit: usize) -> Self {
+        Self { limit, used: 0 }
+    }
+
+    pub fn consume(&mut self, bytes: usize) -> Result<(), String> {
+        self.used = self.used.saturating_add(bytes);
+        if self.used > self.limit {
+            return Err(format!(
+                "Upstream response exceeded max_response_bytes ({} bytes)",
+                self.limit
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_allows_exact_limit_and_rejects_overflow() {
+        let mut budget = ByteBudget::new(10);
+        assert!(budget.consume(4).is_ok());
+        assert!(budget.consume(6).is_ok());
+        let err = budget.consume(1).unwrap_err();
+        assert!(err.contains("max_response_bytes"));
+    }
+}
@@ -2,6 +2,7 @@
 
 // pub mod error;
 // pub mod rate_limiter;
+pub mod body_limit;
 pub mod model_mapping;
 pub mod utils;
 pub mod json_schema;
@@ -1,6 +1,9 @@
 // 模型名称映射
 use std::collections::HashMap;
+use std::sync::RwLock;
 use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::Serialize;
 
 static CLAUDE_TO_GEMINI: Lazy<HashMap<&'static str, &'static str>> = Lazy::new(|| {
     let mut m = HashMap::new();
@@ -186,8 +189,72 @@ fn wildcard_match(pattern: &str, text: &str) -> bool {
     true
 }
 
+/// 已编译的正则映射规则 (`custom_mapping_regex`)
+#[derive(Debug, Clone)]
+pub struct RegexMapping {
+    pattern: Regex,
+    template: String,
+}
+
+/// 当前生效的正则映射 (启动反代和热更新配置时编译一次)
+static REGEX_MAPPINGS: Lazy<RwLock<Vec<RegexMapping>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+/// 编译正则映射规则。模式需匹配完整模型名 (自动加 `^...$`)，
+/// 替换模板支持 `$1` / `${name}` 捕获组引用
+pub fn compile_regex_mappings(rules: &[(String, String)]) -> Result<Vec<RegexMapping>, String> {
+    rules
+        .iter()
+        .map(|(pattern, template)| compile_regex_mapping(pattern, template))
+        .collect()
+}
+
+fn compile_regex_mapping(pattern: &str, template: &str) -> Result<RegexMapping, String> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .map(|compiled| RegexMapping {
+            pattern: compiled,
+            template: template.to_string(),
+        })
+        .map_err(|e| format!("Invalid model mapping regex '{}': {}", pattern, e))
+}
+
+/// 编译正则映射规则，无效规则记录日志后跳过 (其余规则照常生效)
+pub fn compile_valid_regex_mappings(rules: &[(String, String)]) -> Vec<RegexMapping> {
+    rules
+        .iter()
+        .filter_map(|(pattern, template)| match compile_regex_mapping(pattern, template) {
+            Ok(mapping) => Some(mapping),
+            Err(e) => {
+                tracing::warn!("跳过无效的正则模型映射: {}", e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// 编译并替换当前生效的正则映射
+pub fn set_regex_mappings(rules: &[(String, String)]) -> Result<(), String> {
+    let compiled = compile_regex_mappings(rules)?;
+    *REGEX_MAPPINGS.write().unwrap() = compiled;
+    Ok(())
+}
+
+/// 编译并替换当前生效的正则映射，跳过无效规则 (用于启动时，不因单条规则阻止服务启动)
+pub fn set_valid_regex_mappings(rules: &[(String, String)]) {
+    *REGEX_MAPPINGS.write().unwrap() = compile_valid_regex_mappings(rules);
+}
+
+/// 按配置顺序返回第一条匹配的正则映射结果
+fn apply_regex_mappings(original_model: &str, mappings: &[RegexMapping]) -> Option<(String, String)> {
+    mappings.iter().find_map(|mapping| {
+        let caps = mapping.pattern.captures(original_model)?;
+        let mut target = String::new();
+        caps.expand(&mapping.template, &mut target);
+        Some((mapping.pattern.as_str().to_string(), target))
+    })
+}
+
 /// 核心模型路由解析引擎
-/// 优先级：精确匹配 > 通配符匹配 > 系统默认映射
+/// 优先级：精确匹配 > 正则匹配 > 通配符匹配 > 系统默认映射
 /// 
 /// # 参数
 /// - `original_model`: 原始模型名称
@@ -199,13 +266,48 @@ pub fn resolve_model_route(
     original_model: &str,
     custom_mapping: &std::collections::HashMap<String, String>,
 ) -> String {
+    trace_model_route(original_model, custom_mapping).0
+}
+
+/// 同 `resolve_model_route`，并返回决定结果的映射层 (供映射预览使用)
+pub fn trace_model_route(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> (String, MappingSource) {
+    let regex_mappings = REGEX_MAPPINGS.read().unwrap();
+    trace_model_route_with(original_model, custom_mapping, &regex_mappings)
+}
+
+#[cfg(test)]
+fn resolve_model_route_with(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+    regex_mappings: &[RegexMapping],
+) -> String {
+    trace_model_route_with(original_model, custom_mapping, regex_mappings).0
+}
+
+fn trace_model_route_with(
+    original_model: &str,
+    custom_mapping: &std::collections::HashMap<String, String>,
+    regex_mappings: &[RegexMapping],
+) -> (String, MappingSource) {
     // 1. 精确匹配 (最高优先级)
     if let Some(target) = custom_mapping.get(original_model) {
         crate::modules::logger::log_info(&format!("[Router] 精确映射: {} -> {}", original_model, target));
-        return target.clone();
+        return (target.clone(), MappingSource::CustomMapping);
+    }
+
+    // 2. 正则匹配 (按配置顺序，第一条命中的规则生效)
+    if let Some((pattern, target)) = apply_regex_mappings(original_model, regex_mappings) {
+        crate::modules::logger::log_info(&format!(
+            "[Router] Regex match: {} -> {} (rule: {})",
+            original_model, target, pattern
+        ));
+        return (target, MappingSource::RegexMapping);
     }
     
-    // 2. Wildcard match - most specific (highest non-wildcard chars) wins
+    // 3. Wildcard match - most specific (highest non-wildcard chars) wins
     // Note: When multiple patterns have the SAME specificity, HashMap iteration order
     // determines the result (non-deterministic). Users can avoid this by making patterns
     // more specific. Future improvement: use IndexMap + frontend sorting for full control.
@@ -225,15 +327,102 @@ pub fn resolve_model_route(
             "[Router] Wildcard match: {} -> {} (rule: {})",
             original_model, target, pattern
         ));
-        return target.to_string();
+        return (target.to_string(), MappingSource::WildcardMapping);
     }
     
-    // 3. 系统默认映射
+    // 4. 系统默认映射
     let result = map_claude_model_to_gemini(original_model);
     if result != original_model {
         crate::modules::logger::log_info(&format!("[Router] 系统默认映射: {} -> {}", original_model, result));
     }
-    result
+    (result, MappingSource::SystemDefault)
+}
+
+/// Claude 模型族，决定使用 `ZaiModelDefaults` 中的哪个默认模型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaudeFamily {
+    Opus,
+    Sonnet,
+    Haiku,
+}
+
+/// 识别 Claude 模型 id 所属的模型族 (大小写不敏感)。
+/// 兼容带日期 (`claude-3-opus-20240229`)、新命名 (`claude-opus-4`)、别名
+/// (`claude-3-5-sonnet-latest`) 以及带厂商前缀 (`anthropic/claude-3-haiku`) 的写法；
+/// 非 Claude 模型或无法识别模型族时返回 None
+pub fn claude_family(model: &str) -> Option<ClaudeFamily> {
+    let lower = model.to_lowercase();
+    let id = lower.rsplit('/').next().unwrap_or(&lower);
+    if !id.starts_with("claude") {
+        return None;
+    }
+    id.split(|c: char| matches!(c, '-' | '_' | '.' | ':' | '@'))
+        .find_map(|token| match token {
+            "opus" => Some(ClaudeFamily::Opus),
+            "sonnet" => Some(ClaudeFamily::Sonnet),
+            "haiku" => Some(ClaudeFamily::Haiku),
+            _ => None,
+        })
+}
+
+/// 模型映射结果来自哪一层
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MappingSource {
+    /// Google 路由：`ProxyConfig.custom_mapping` 精确匹配
+    CustomMapping,
+    /// Google 路由：正则映射规则
+    RegexMapping,
+    /// Google 路由：`custom_mapping` 通配符规则
+    WildcardMapping,
+    /// Google 路由：系统默认映射 (未命中时可能原样透传)
+    SystemDefault,
+    /// z.ai 路由：`ZaiConfig.model_mapping` (精确或小写匹配)
+    ZaiModelMapping,
+    /// z.ai 路由：`zai:` 前缀或 `glm-` 模型，直接使用
+    ZaiNative,
+    /// z.ai 路由：`ZaiModelDefaults` 按 Claude 模型族 (opus / sonnet / haiku)
+    ZaiFamilyDefault,
+    /// z.ai 路由：未命中任何映射，原样透传
+    Passthrough,
+}
+
+/// `resolve_model` 的解析结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ModelResolution {
+    pub model: String,
+    pub target: String,
+    pub source: MappingSource,
+}
+
+/// 按已保存的配置解析传入模型的最终目标 (纯函数，不依赖运行中的服务)，
+/// 优先级 (先命中者生效)：
+/// 1. `custom_mapping` 精确匹配
+/// 2. `zai.model_mapping` (精确或小写匹配；`zai:` 前缀与 `glm-` 模型直接使用)
+/// 3. `zai.models` 按 Claude 模型族的默认值
+/// 4. 透传原始模型名
+///
+/// 第 2、3 层仅在 z.ai 调度启用时生效
+pub fn resolve_model(incoming: &str, cfg: &crate::proxy::ProxyConfig) -> ModelResolution {
+    let resolution = |target: String, source| ModelResolution {
+        model: incoming.to_string(),
+        target,
+        source,
+    };
+
+    if let Some(target) = cfg.custom_mapping.get(incoming) {
+        return resolution(target.clone(), MappingSource::CustomMapping);
+    }
+
+    if cfg.zai.dispatch_enabled() {
+        let (target, source) = crate::proxy::providers::zai_anthropic::trace_model_for_zai(incoming, &cfg.zai);
+        if source != MappingSource::Passthrough {
+            return resolution(target, source);
+        }
+    }
+
+    resolution(incoming.to_string(), MappingSource::Passthrough)
 }
 
 /// Normalize any physical model name to one of the 3 standard protection IDs.
@@ -345,4 +534,170 @@ mod tests {
         // Multi-wildcard: "a*b*c" (3)
         assert_eq!(resolve_model_route("a-test-b-foo-c", &custom), "multi-wild");
     }
+
+    #[test]
+    fn test_regex_mapping_after_exact_match() {
+        let rules = vec![(r"claude-3-5-sonnet-\d{8}".to_string(), "gemini-3-pro-high".to_string())];
+        let regex = compile_regex_mappings(&rules).unwrap();
+        let mut custom = HashMap::new();
+        custom.insert("claude-3-5-sonnet-20240620".to_string(), "exact-target".to_string());
+
+        assert_eq!(
+            resolve_model_route_with("claude-3-5-sonnet-20241022", &custom, &regex),
+            "gemini-3-pro-high"
+        );
+        assert_eq!(
+            resolve_model_route_with("claude-3-5-sonnet-20250101", &custom, &regex),
+            "gemini-3-pro-high"
+        );
+        // Exact mapping still wins
+        assert_eq!(
+            resolve_model_route_with("claude-3-5-sonnet-20240620", &custom, &regex),
+            "exact-target"
+        );
+        // Pattern must match the whole model name
+        assert_eq!(
+            resolve_model_route_with("claude-3-5-sonnet-20241022-thinking", &custom, &regex),
+            map_claude_model_to_gemini("claude-3-5-sonnet-20241022-thinking")
+        );
+    }
+
+    #[test]
+    fn test_regex_mapping_capture_groups_and_order() {
+        let rules = vec![
+            (r"gpt-4o-(?<tier>mini|nano)".to_string(), "gemini-2.5-flash-${tier}".to_string()),
+            (r"gpt-(.*)".to_string(), "mapped-$1".to_string()),
+        ];
+        let regex = compile_regex_mappings(&rules).unwrap();
+        let custom = HashMap::new();
+
+        assert_eq!(resolve_model_route_with("gpt-4o-mini", &custom, &regex), "gemini-2.5-flash-mini");
+        assert_eq!(resolve_model_route_with("gpt-5", &custom, &regex), "mapped-5");
+    }
+
+    #[test]
+    fn test_invalid_regex_mapping_rejected() {
+        let rules = vec![(r"claude-(\d+".to_string(), "target".to_string())];
+        let err = compile_regex_mappings(&rules).unwrap_err();
+        assert!(err.contains("claude-(\\d+"));
+    }
+
+    #[test]
+    fn test_invalid_regex_mapping_skipped_when_lenient() {
+        let rules = vec![
+            (r"claude-(\d+".to_string(), "broken".to_string()),
+            (r"gpt-(.*)".to_string(), "mapped-$1".to_string()),
+        ];
+        let regex = compile_valid_regex_mappings(&rules);
+        assert_eq!(regex.len(), 1);
+        assert_eq!(resolve_model_route_with("gpt-5", &HashMap::new(), &regex), "mapped-5");
+    }
+
+    #[test]
+    fn test_trace_reports_mapping_layer() {
+        let rules = vec![(r"gpt-(.*)".to_string(), "mapped-$1".to_string())];
+        let regex = compile_regex_mappings(&rules).unwrap();
+        let mut custom = HashMap::new();
+        custom.insert("claude-opus-4".to_string(), "exact-target".to_string());
+        custom.insert("gemini-*".to_string(), "wild-target".to_string());
+
+        let trace = |model| trace_model_route_with(model, &custom, &regex);
+        assert_eq!(trace("claude-opus-4"), ("exact-target".to_string(), MappingSource::CustomMapping));
+        assert_eq!(trace("gpt-5"), ("mapped-5".to_string(), MappingSource::RegexMapping));
+        assert_eq!(trace("gemini-x"), ("wild-target".to_string(), MappingSource::WildcardMapping));
+        assert_eq!(
+            trace("claude-3-5-sonnet-20241022"),
+            (map_claude_model_to_gemini("claude-3-5-sonnet-20241022"), MappingSource::SystemDefault)
+        );
+    }
+
+    #[test]
+    fn test_claude_family_detection() {
+        let cases = [
+            ("claude-3-opus-20240229", Some(ClaudeFamily::Opus)),
+            ("claude-opus-4", Some(ClaudeFamily::Opus)),
+            ("claude-opus-4-1-20250805", Some(ClaudeFamily::Opus)),
+            ("claude-opus-4-5-thinking", Some(ClaudeFamily::Opus)),
+            ("claude-3-sonnet-20240229", Some(ClaudeFamily::Sonnet)),
+            ("claude-3-5-sonnet-20241022", Some(ClaudeFamily::Sonnet)),
+            ("claude-3-5-sonnet-latest", Some(ClaudeFamily::Sonnet)),
+            ("claude-3-7-sonnet-20250219", Some(ClaudeFamily::Sonnet)),
+            ("claude-sonnet-4-5", Some(ClaudeFamily::Sonnet)),
+            ("Claude-Sonnet-4-20250514", Some(ClaudeFamily::Sonnet)),
+            ("claude-3-haiku-20240307", Some(ClaudeFamily::Haiku)),
+            ("claude-3-5-haiku-latest", Some(ClaudeFamily::Haiku)),
+            ("claude-haiku-4-5", Some(ClaudeFamily::Haiku)),
+            ("anthropic/claude-3.5-sonnet", Some(ClaudeFamily::Sonnet)),
+            ("claude-opus-4@20250514", Some(ClaudeFamily::Opus)),
+            ("claude-2.1", None),
+            ("claude-instant-1.2", None),
+            ("gemini-2.5-pro", None),
+            ("glm-4.6", None),
+            // Family names only count as whole tokens
+            ("claude-sonnetish", None),
+        ];
+
+        for (model, expected) in cases {
+            assert_eq!(claude_family(model), expected, "model: {}", model);
+        }
+    }
+
+    fn zai_proxy_config() -> crate::proxy::ProxyConfig {
+        let mut cfg = crate::proxy::ProxyConfig::default();
+        cfg.zai.enabled = true;
+        cfg.zai.api_key = "test-key".to_string();
+        cfg.zai.dispatch_mode = crate::proxy::ZaiDispatchMode::Exclusive;
+        cfg.zai.models.opus = "glm-opus".to_string();
+        cfg.zai.models.sonnet = "glm-sonnet".to_string();
+        cfg.zai.models.haiku = "glm-haiku".to_string();
+        cfg
+    }
+
+    #[test]
+    fn test_resolve_model_custom_mapping_wins() {
+        let mut cfg = zai_proxy_config();
+        cfg.custom_mapping.insert("claude-opus-4".to_string(), "custom-target".to_string());
+        cfg.zai.model_mapping.insert("claude-opus-4".to_string(), "zai-target".to_string());
+
+        let resolved = resolve_model("claude-opus-4", &cfg);
+        assert_eq!(resolved.target, "custom-target");
+        assert_eq!(resolved.source, MappingSource::CustomMapping);
+    }
+
+    #[test]
+    fn test_resolve_model_zai_mapping_before_family_default() {
+        let mut cfg = zai_proxy_config();
+        cfg.zai.model_mapping.insert("claude-opus-4".to_string(), "glm-4.7".to_string());
+
+        let resolved = resolve_model("Claude-Opus-4", &cfg);
+        assert_eq!(resolved.target, "glm-4.7");
+        assert_eq!(resolved.source, MappingSource::ZaiModelMapping);
+    }
+
+    #[test]
+    fn test_resolve_model_zai_family_defaults() {
+        let cfg = zai_proxy_config();
+
+        assert_eq!(resolve_model("claude-opus-4-5", &cfg).target, "glm-opus");
+        assert_eq!(resolve_model("claude-3-5-haiku", &cfg).target, "glm-haiku");
+        let resolved = resolve_model("claude-sonnet-4-5", &cfg);
+        assert_eq!(resolved.target, "glm-sonnet");
+        assert_eq!(resolved.source, MappingSource::ZaiFamilyDefault);
+    }
+
+    #[test]
+    fn test_resolve_model_passthrough() {
+        // Non-Claude ids are not covered by the family defaults
+        let resolved = resolve_model("gemini-2.5-flash", &zai_proxy_config());
+        assert_eq!(resolved.target, "gemini-2.5-flash");
+        assert_eq!(resolved.source, MappingSource::Passthrough);
+
+        // z.ai layers are skipped entirely while z.ai dispatch is off
+        let mut cfg = zai_proxy_config();
+        cfg.zai.dispatch_mode = crate::proxy::ZaiDispatchMode::Off;
+        cfg.zai.model_mapping.insert("claude-opus-4".to_string(), "glm-4.7".to_string());
+        let resolved = resolve_model("claude-opus-4", &cfg);
+        assert_eq!(resolved.target, "claude-opus-4");
+        assert_eq!(resolved.source, MappingSource::Passthrough);
+    }
 }
@@ -8,15 +8,24 @@ use axum::{
 };
 use futures::{sink::SinkExt, stream::StreamExt};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant, MissedTickBehavior};
 use tracing::{debug, error, info, warn};
 
+use crate::modules::chat_db;
+use crate::proxy::request_registry::CancelToken;
 use crate::proxy::server::AppState;
-use crate::commands::skills::{select_skills, load_skill_content};
+use crate::proxy::session_hub::SessionHub;
+use crate::proxy::shutdown::{triggered, DrainTracker, DRAIN_TIMEOUT};
+use crate::commands::skills::{
+    select_skills, apply_skill_ceiling, load_skill_content, load_skills_config, load_skills_index, apply_skill_overrides,
+};
 use crate::commands::workflows::{
-    parse_workflow_command, validate_widget_workflow, filter_skills_for_widget, WorkflowCommand
+    parse_workflow_command, validate_widget_workflow, WorkflowCommand,
+    check_workflow_skills, fuzzy_workflow_query, EmptySkillsAction, apply_widget_limits,
+    widget_config, check_workflow_enabled, workflow_persona, workflow_skill_limits,
 };
-use crate::workflows::{plan, debug as debug_flow, TaskResult};
+use crate::workflows::{plan, debug as debug_flow, create, deploy, summarize, test as test_flow, stream_text, PromptContext, TaskResult};
 
 // Client -> Server messages
 #[derive(Debug, Deserialize)]
@@ -27,31 +36,109 @@ enum ClientMessage {
         repo: String,
         branch: Option<String>,
     },
-    ListSessions,
+    /// Sessions newest first; `limit`/`offset` page through them (all when `limit` is omitted)
+    ListSessions {
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        offset: usize,
+    },
+    /// Session plus its messages oldest first, paged like `ListSessions`
     LoadSession {
         session_id: String,
+        #[serde(default)]
+        limit: Option<usize>,
+        #[serde(default)]
+        offset: usize,
     },
     UserMessage {
         session_id: String,
         content: String,
+        /// Client-chosen id used to cancel this request (generated when omitted)
+        #[serde(default)]
+        request_id: Option<String>,
+        /// Skills to force into this request (subject to the byte budget)
+        #[serde(default)]
+        include_skills: Vec<String>,
+        /// Skills to drop from this request's selection
+        #[serde(default)]
+        exclude_skills: Vec<String>,
+        /// Preview what the workflow would do without running commands or writing
+        /// artifacts (only valid on workflow commands)
+        #[serde(default)]
+        dry_run: bool,
+        /// Error logs for /debug; cut to `workflows.debug_max_log_bytes`
+        #[serde(default)]
+        logs: Option<String>,
+    },
+    /// Cancel one in-flight request; other requests in the session continue
+    CancelRequest {
+        request_id: String,
+    },
+    /// Stop every in-flight workflow of a session; each returns a Cancelled result
+    CancelSession {
+        session_id: String,
+    },
+    /// Replace all but the last `keep_last` messages with a summary
+    CompactSession {
+        session_id: String,
+        keep_last: usize,
+    },
+    /// Full-text search across all sessions
+    SearchMessages {
+        query: String,
+    },
+    /// Delete a session and all of its messages
+    DeleteSession {
+        session_id: String,
+    },
+    /// Delete a single message
+    DeleteMessage {
+        message_id: i64,
+    },
+    /// Change a session's title
+    RenameSession {
+        session_id: String,
+        title: String,
+    },
+    /// Set a session's status (pending / running / completed / failed)
+    UpdateSessionStatus {
+        session_id: String,
+        status: String,
+    },
+    /// Pin a persona for every non-workflow message of a session (`null` clears the pin)
+    SetPersona {
+        session_id: String,
+        #[serde(default)]
+        persona: Option<String>,
     },
 }
 
 // Server -> Client messages
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
-enum ServerMessage {
+pub(crate) enum ServerMessage {
     SessionList {
         sessions: Vec<TaskSessionResponse>,
+        /// More sessions exist past this page
+        has_more: bool,
     },
     SessionLoaded {
         session: TaskSessionResponse,
         messages: Vec<TaskMessageResponse>,
+        /// More messages exist past this page
+        has_more: bool,
     },
     MessageAppended {
         session_id: String,
         message: TaskMessageResponse,
     },
+    /// Incremental assistant output; the `MessageAppended` that follows holds the
+    /// concatenation of all deltas
+    MessageDelta {
+        session_id: String,
+        delta: String,
+    },
     /// Skills selected for this request
     SkillsSelected {
         session_id: String,
@@ -66,13 +153,66 @@ enum ServerMessage {
         status: String,
         details: String,
     },
+    /// Matches for `SearchMessages`, best first
+    SearchResults {
+        messages: Vec<chat_db::ChatMessage>,
+    },
+    /// A single session changed (after `RenameSession` or `UpdateSessionStatus`)
+    SessionUpdated {
+        session: TaskSessionResponse,
+    },
+    /// Confirmation for `DeleteSession`
+    SessionDeleted {
+        session_id: String,
+    },
+    /// Confirmation for `DeleteMessage`
+    MessageDeleted {
+        message_id: i64,
+    },
+    /// Oldest messages pruned to keep the session under `max_messages_per_session`
+    SessionTrimmed {
+        session_id: String,
+        pruned_ids: Vec<i64>,
+    },
+    /// Confirmation for `SetPersona`
+    PersonaSet {
+        session_id: String,
+        persona: Option<String>,
+    },
+    /// An in-flight request was cancelled by the client
+    TaskCancelled {
+        session_id: String,
+        request_id: String,
+    },
     Error {
         message: String,
     },
 }
 
+/// Outgoing messages for one connection; a single writer task drains it into the socket
+type Outbox = mpsc::UnboundedSender<ServerMessage>;
+
+/// Subscribers of each session, shared by all chat connections
+pub(crate) type ChatSessionHub = SessionHub<ServerMessage>;
+
+/// Publishes a connection's session events to the other connections watching the session
+#[derive(Clone)]
+struct SessionPeers {
+    hub: std::sync::Arc<ChatSessionHub>,
+    connection_id: u64,
+}
+
+impl SessionPeers {
+    fn publish(&self, session_id: &str, message: &ServerMessage) {
+        let reached = self.hub.publish(session_id, self.connection_id, message.clone());
+        if reached > 1 {
+            debug!("Broadcast to {} watcher(s) of session {}", reached - 1, session_id);
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
-struct TaskSessionResponse {
+pub(crate) struct TaskSessionResponse {
     id: String,
     title: String,
     repo_name: String,
@@ -82,18 +222,45 @@ struct TaskSessionResponse {
 }
 
 #[derive(Debug, Serialize, Clone)]
-struct TaskMessageResponse {
+pub(crate) struct TaskMessageResponse {
     id: i64,
     role: String,
     content: String,
     created_at: i64,
 }
 
+impl From<chat_db::TaskSession> for TaskSessionResponse {
+    fn from(session: chat_db::TaskSession) -> Self {
+        Self {
+            id: session.id,
+            title: session.title,
+            repo_name: session.repo_name,
+            branch_name: session.branch_name,
+            status: session.status,
+            created_at: session.created_at,
+        }
+    }
+}
+
+impl From<chat_db::ChatMessage> for TaskMessageResponse {
+    fn from(message: chat_db::ChatMessage) -> Self {
+        Self {
+            id: message.id,
+            role: message.role,
+            content: message.content,
+            created_at: message.created_at,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
-struct SkillSummary {
+pub(crate) struct SkillSummary {
     id: String,
     name: String,
     score: f64,
+    /// Query terms that matched this skill
+    matched_terms: Vec<String>,
+    size_bytes: usize,
 }
 
 /// WebSocket handler endpoint
@@ -104,316 +271,1150 @@ pub async fn handle_chat_ws(
     ws.on_upgrade(move |socket| handle_socket(socket, state))
 }
 
+/// How long the writer gets to flush a server-initiated Close frame
+const CLOSE_GRACE: Duration = Duration::from_secs(2);
 
 /// Handle individual WebSocket connection
 async fn handle_socket(socket: WebSocket, state: AppState) {
-    let (mut sender, mut receiver): (
-        futures::stream::SplitSink<WebSocket, Message>,
-        futures::stream::SplitStream<WebSocket>
-    ) = socket.split();
+    serve_connection(socket, state, DrainTracker::new()).await;
+}
+
+/// Connection loop over any WebSocket-like transport. `requests` tracks the workflows
+/// this connection starts; shutdown waits for it to go idle before sending Close.
+async fn serve_connection<S>(socket: S, state: AppState, requests: DrainTracker)
+where
+    S: futures::Stream<Item = Result<Message, axum::Error>>
+        + futures::Sink<Message, Error = axum::Error>
+        + Send
+        + 'static,
+{
+    let (mut sender, mut receiver) = socket.split();
+    let (outbox, mut outgoing) = mpsc::unbounded_channel::<ServerMessage>();
+    // Protocol frames (ping/pong/close) bypass JSON serialization
+    let (control, mut control_rx) = mpsc::unbounded_channel::<Message>();
+
+    let peers = SessionPeers {
+        hub: state.chat_sessions.clone(),
+        connection_id: state.chat_sessions.register_connection(),
+    };
+    // Sessions this connection watches -> task forwarding other connections' events
+    let mut subscriptions: std::collections::HashMap<String, tokio::task::JoinHandle<()>> =
+        std::collections::HashMap::new();
+
+    // Stop signal from the server; the guard keeps the stop waiting until this connection is done
+    let (mut shutdown_signal, _connection_guard) = state.shutdown.track_connection();
+
+    let settings = chat_db::settings();
+    let ping_interval = Duration::from_secs(settings.ws_ping_interval_secs);
+    let idle_timeout = Duration::from_secs(settings.ws_idle_timeout_secs);
 
     info!("Chat WebSocket connected");
 
-    while let Some(msg) = receiver.next().await {
+    // Requests run concurrently, so all writes go through one task
+    let mut writer = tokio::spawn(async move {
+        loop {
+            // Queued replies go out before a Close frame queued after them
+            let frame = tokio::select! {
+                biased;
+                Some(response) = outgoing.recv() => match serde_json::to_string(&response) {
+                    Ok(text) => Message::Text(text),
+                    Err(e) => {
+                        error!("Failed to serialize response: {}", e);
+                        continue;
+                    }
+                },
+                Some(frame) = control_rx.recv() => frame,
+                else => break,
+            };
+
+            let closing = matches!(frame, Message::Close(_));
+            if let Err(e) = sender.send(frame).await {
+                error!("Failed to send WebSocket message: {}", e);
+                break;
+            }
+            if closing {
+                break;
+            }
+        }
+    });
+
+    // interval_at panics on a zero period; the branch is disabled in that case anyway
+    let ping_period = ping_interval.max(Duration::from_secs(1));
+    let mut pinger = tokio::time::interval_at(Instant::now() + ping_period, ping_period);
+    pinger.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_activity = Instant::now();
+    let mut close_sent = false;
+    let mut shutting_down = false;
+
+    loop {
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            _ = triggered(&mut shutdown_signal) => {
+                info!("Server stopping, draining chat WebSocket");
+                shutting_down = true;
+                break;
+            }
+            _ = pinger.tick(), if !ping_interval.is_zero() => {
+                if control.send(Message::Ping(Vec::new())).is_err() {
+                    break;
+                }
+                continue;
+            }
+            _ = tokio::time::sleep_until(last_activity + idle_timeout), if !idle_timeout.is_zero() => {
+                info!("Chat WebSocket idle for {}s, closing", idle_timeout.as_secs());
+                close_sent = control.send(Message::Close(None)).is_ok();
+                break;
+            }
+        };
+
         let msg = match msg {
-            Ok(msg) => msg,
-            Err(e) => {
+            Some(Ok(msg)) => msg,
+            Some(Err(e)) => {
                 error!("WebSocket error: {}", e);
                 break;
             }
+            // Stream ended without a Close frame (client vanished / TCP reset)
+            None => {
+                info!("Chat WebSocket stream ended");
+                break;
+            }
         };
+        last_activity = Instant::now();
 
-        if let Message::Text(text) = msg {
-            debug!("Received WebSocket message: {}", text);
-
-            let response = match serde_json::from_str::<ClientMessage>(&text) {
-                Ok(client_msg) => handle_client_message(client_msg, &state, &mut sender).await,
-                Err(e) => ServerMessage::Error {
-                    message: format!("Invalid message format: {}", e),
-                },
-            };
-
-            let response_text = match serde_json::to_string(&response) {
+        let text = match msg {
+            Message::Text(text) => text,
+            // Binary frames carry the same JSON protocol, just UTF-8 encoded
+            Message::Binary(bytes) => match String::from_utf8(bytes) {
                 Ok(text) => text,
                 Err(e) => {
-                    error!("Failed to serialize response: {}", e);
+                    let error = ServerMessage::Error {
+                        message: format!("Binary frame is not valid UTF-8: {}", e),
+                    };
+                    if outbox.send(error).is_err() {
+                        break;
+                    }
                     continue;
                 }
-            };
+            },
+            Message::Ping(payload) => {
+                if control.send(Message::Pong(payload)).is_err() {
+                    break;
+                }
+                continue;
+            }
+            // Only counts as activity (answer to our keepalive ping)
+            Message::Pong(_) => continue,
+            Message::Close(_) => {
+                info!("Chat WebSocket closed by client");
+                break;
+            }
+        };
 
-            if let Err(e) = sender.send(Message::Text(response_text)).await {
-                error!("Failed to send WebSocket message: {}", e);
+        debug!("Received WebSocket message: {}", text);
+
+        let response = match serde_json::from_str::<ClientMessage>(&text) {
+            Ok(client_msg) => handle_client_message(client_msg, &state, &outbox, &peers, &requests).await,
+            Err(e) => Some(ServerMessage::Error {
+                message: format!("Invalid message format: {}", e),
+            }),
+        };
+
+        match &response {
+            Some(ServerMessage::SessionLoaded { session, .. }) => {
+                subscriptions.entry(session.id.clone()).or_insert_with(|| {
+                    peers.hub.spawn_forwarder(&session.id, peers.connection_id, outbox.clone())
+                });
+            }
+            Some(ServerMessage::SessionDeleted { session_id }) => {
+                if let Some(forwarder) = subscriptions.remove(session_id) {
+                    forwarder.abort();
+                }
+                peers.hub.remove(session_id);
+            }
+            _ => {}
+        }
+
+        if let Some(response) = response {
+            if outbox.send(response).is_err() {
                 break;
             }
-        } else if let Message::Close(_) = msg {
-            info!("Chat WebSocket closed by client");
-            break;
         }
     }
 
+    if shutting_down {
+        // Let running workflows persist and deliver their replies, then say goodbye
+        if !requests.wait_idle_timeout(DRAIN_TIMEOUT).await {
+            warn!("{} chat request(s) still running at shutdown", requests.active());
+        }
+        close_sent = control.send(Message::Close(None)).is_ok();
+    }
+
+    for (session_id, forwarder) in subscriptions {
+        forwarder.abort();
+        let _ = forwarder.await;
+        peers.hub.prune(&session_id);
+    }
+
+    drop(outbox);
+    drop(control);
+    if close_sent {
+        let _ = tokio::time::timeout(CLOSE_GRACE, &mut writer).await;
+    }
+    writer.abort();
     info!("Chat WebSocket disconnected");
 }
 
 /// Send a status update message to client
-async fn send_status_update(
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
-    session_id: String,
-    status: String,
-    details: String,
-) {
-    let msg = ServerMessage::TaskStatus {
+fn send_status_update(outbox: &Outbox, session_id: String, status: String, details: String) {
+    let _ = outbox.send(ServerMessage::TaskStatus {
         session_id,
         status,
         details,
+    });
+}
+
+/// Tell this connection and the session's other watchers which messages were pruned
+fn announce_trim(session_id: &str, pruned_ids: Vec<i64>, outbox: &Outbox, peers: &SessionPeers) {
+    if pruned_ids.is_empty() {
+        return;
+    }
+    debug!("Pruned {} old message(s) from session {}", pruned_ids.len(), session_id);
+
+    let trimmed = ServerMessage::SessionTrimmed {
+        session_id: session_id.to_string(),
+        pruned_ids,
+    };
+    peers.publish(session_id, &trimmed);
+    let _ = outbox.send(trimmed);
+}
+
+/// Forward workflow deltas to the client as they arrive; returns the full streamed text
+async fn forward_deltas(
+    mut deltas: mpsc::UnboundedReceiver<String>,
+    session_id: String,
+    outbox: Outbox,
+) -> String {
+    let mut streamed = String::new();
+    while let Some(delta) = deltas.recv().await {
+        streamed.push_str(&delta);
+        let _ = outbox.send(ServerMessage::MessageDelta {
+            session_id: session_id.clone(),
+            delta,
+        });
+    }
+    streamed
+}
+
+/// Move the session to "completed" / "failed" / "cancelled" based on a request's final message
+fn record_session_outcome(session_id: &str, response: &ServerMessage) {
+    let status = match response {
+        ServerMessage::MessageAppended { .. } => "completed",
+        ServerMessage::Error { .. } => "failed",
+        ServerMessage::TaskCancelled { .. } => "cancelled",
+        _ => return,
     };
 
-    if let Ok(text) = serde_json::to_string(&msg) {
-        let _ = sender.send(Message::Text(text)).await;
+    if let Err(e) = chat_db::update_session_status(session_id, status) {
+        debug!("Could not mark session {} as {}: {}", session_id, status, e);
     }
 }
 
-/// Process client messages and return server responses
+/// Process client messages and return the immediate response (if any).
+/// User messages run as their own task so they can be cancelled by request id.
 async fn handle_client_message(
     msg: ClientMessage,
-    _state: &AppState,
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
-) -> ServerMessage {
-    match msg {
+    state: &AppState,
+    outbox: &Outbox,
+    peers: &SessionPeers,
+    requests: &DrainTracker,
+) -> Option<ServerMessage> {
+    let response = match msg {
         ClientMessage::CreateSession { title, repo, branch } => {
-            // TODO: Create session in database
             debug!("Creating session: {} for repo {}", title, repo);
 
-            // Mock response for now
-            ServerMessage::SessionList {
-                sessions: vec![TaskSessionResponse {
-                    id: "mock-session-1".to_string(),
-                    title,
-                    repo_name: repo,
-                    branch_name: branch,
-                    status: "pending".to_string(),
-                    created_at: chrono::Utc::now().timestamp(),
-                }],
-            }
-        }
-        ClientMessage::ListSessions => {
-            // TODO: Query database for sessions
-            debug!("Listing sessions");
-
-            // Mock response
-            ServerMessage::SessionList {
-                sessions: vec![
-                    TaskSessionResponse {
-                        id: "mock-session-1".to_string(),
-                        title: "Fix Docker Networking".to_string(),
-                        repo_name: "atnplex/homelab".to_string(),
-                        branch_name: None,
-                        status: "running".to_string(),
-                        created_at: chrono::Utc::now().timestamp() - 3600,
-                    },
-                    TaskSessionResponse {
-                        id: "mock-session-2".to_string(),
-                        title: "Audit Secrets".to_string(),
-                        repo_name: "atnplex/antigravity-manager".to_string(),
-                        branch_name: Some("main".to_string()),
-                        status: "completed".to_string(),
-                        created_at: chrono::Utc::now().timestamp() - 7200,
-                    },
-                ],
-            }
-        }
-        ClientMessage::LoadSession { session_id } => {
-            // TODO: Load session and messages from database
-            debug!("Loading session: {}", session_id);
-
-            // Mock response
-            ServerMessage::SessionLoaded {
-                session: TaskSessionResponse {
-                    id: session_id.clone(),
-                    title: "Mock Session".to_string(),
-                    repo_name: "atnplex/mock-repo".to_string(),
-                    branch_name: None,
-                    status: "running".to_string(),
-                    created_at: chrono::Utc::now().timestamp(),
+            match chat_db::create_session(&title, &repo, branch.as_deref()) {
+                Ok(session) => ServerMessage::SessionList {
+                    sessions: vec![session.into()],
+                    has_more: false,
                 },
-                messages: vec![
-                    TaskMessageResponse {
-                        id: 1,
-                        role: "user".to_string(),
-                        content: "Hello, start working on this task".to_string(),
-                        created_at: chrono::Utc::now().timestamp() - 120,
-                    },
-                    TaskMessageResponse {
-                        id: 2,
-                        role: "assistant".to_string(),
-                        content: "I understand. I'll begin working on this task right away.".to_string(),
-                        created_at: chrono::Utc::now().timestamp() - 60,
-                    },
-                ],
-            }
-        }
-        ClientMessage::UserMessage { session_id, content } => {
-            info!("User message in session {}: {}", session_id, content);
-
-            // Phase 5.1: Workflow Parsing & Widget Security
-
-            // 1. Parse workflow command (server-side only)
-            let workflow = parse_workflow_command(&content);
-            if let Some(cmd) = &workflow {
-                info!("Detected workflow command: {:?}", cmd);
-            }
-
-            // 2. Security Check: Widget Mode Constraints
-            if let Err(msg) = validate_widget_workflow(&session_id, &workflow) {
-                return ServerMessage::Error { message: msg };
-            }
-
-            // 3. Send status update
-            send_status_update(
-                sender,
-                session_id.clone(),
-                "selecting_skills".to_string(),
-                "Analyzing request and selecting relevant skills...".to_string(),
-            ).await;
-
-            // 4. Select skills using BM25 router
-            let mut selection_result = match select_skills(content.clone(), Some(8), Some(80000)).await {
-                Ok(selection) => selection,
                 Err(e) => {
-                    error!("Failed to select skills: {}", e);
-                    return ServerMessage::Error {
-                        message: format!("Skill selection failed: {}", e),
-                    };
+                    error!("Failed to create session: {}", e);
+                    ServerMessage::Error {
+                        message: format!("Failed to create session: {}", e),
+                    }
                 }
+            }
+        }
+        ClientMessage::ListSessions { limit, offset } => {
+            debug!("Listing sessions (limit {:?}, offset {})", limit, offset);
+
+            match chat_db::list_sessions_page(limit, offset) {
+                Ok(page) => ServerMessage::SessionList {
+                    sessions: page.items.into_iter().map(Into::into).collect(),
+                    has_more: page.has_more,
+                },
+                Err(e) => {
+                    error!("Failed to list sessions: {}", e);
+                    ServerMessage::Error {
+                        message: format!("Failed to list sessions: {}", e),
+                    }
+                }
+            }
+        }
+        ClientMessage::LoadSession { session_id, limit, offset } => {
+            debug!("Loading session: {} (limit {:?}, offset {})", session_id, limit, offset);
+
+            let loaded = chat_db::get_session(&session_id).and_then(|session| match session {
+                Some(session) => Ok(Some((session, chat_db::get_messages_page(&session_id, limit, offset)?))),
+                None => Ok(None),
+            });
+
+            match loaded {
+                Ok(Some((session, page))) => ServerMessage::SessionLoaded {
+                    session: session.into(),
+                    messages: page.items.into_iter().map(Into::into).collect(),
+                    has_more: page.has_more,
+                },
+                Ok(None) => ServerMessage::Error {
+                    message: format!("Session not found: {}", session_id),
+                },
+                Err(e) => {
+                    error!("Failed to load session {}: {}", session_id, e);
+                    ServerMessage::Error {
+                        message: format!("Failed to load session: {}", e),
+                    }
+                }
+            }
+        }
+        ClientMessage::UserMessage { session_id, content, request_id, include_skills, exclude_skills, dry_run, logs } => {
+            let request_id = request_id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            let tokens = match state.chat_requests.register(&request_id, &session_id) {
+                Ok(tokens) => tokens,
+                Err(message) => return Some(ServerMessage::Error { message }),
             };
+            let mut abort = tokens.abort;
+            let session_cancel = tokens.session;
+            let generation = tokens.generation;
+            let state = state.clone();
+            let outbox = outbox.clone();
+            let peers = peers.clone();
+            let in_flight = requests.guard();
+
+            tokio::spawn(async move {
+                let _in_flight = in_flight;
+                let response = tokio::select! {
+                    biased;
+                    _ = abort.cancelled() => {
+                        info!("Request {} in session {} cancelled", request_id, session_id);
+                        ServerMessage::TaskCancelled {
+                            session_id: session_id.clone(),
+                            request_id: request_id.clone(),
+                        }
+                    }
+                    response = handle_user_message(
+                        session_id.clone(),
+                        content,
+                        MessageOptions { include_skills, exclude_skills, dry_run, logs },
+                        &session_cancel,
+                        &outbox,
+                        &peers,
+                    ) => response,
+                };
+                state.chat_requests.complete(&request_id, generation);
+                record_session_outcome(&session_id, &response);
+                if matches!(response, ServerMessage::MessageAppended { .. }) {
+                    peers.publish(&session_id, &response);
+                }
+                let _ = outbox.send(response);
+            });
 
-            // 5. Apply Workflow Overrides & Widget Limits
-            if let Some(cmd) = &workflow {
-                // Force persona based on workflow
-                selection_result.persona = cmd.get_persona().to_string();
+            return None;
+        }
+        ClientMessage::CancelRequest { request_id } => {
+            match state.chat_requests.cancel(&request_id) {
+                // The request's own task reports TaskCancelled
+                Some(_) => return None,
+                None => ServerMessage::Error {
+                    message: format!("No in-flight request with id {}", request_id),
+                },
             }
+        }
+        ClientMessage::CancelSession { session_id } => {
+            let signalled = state
+                .chat_requests
+                .cancel_session(&session_id, "Session cancelled by client");
+            info!("Cancelling {} in-flight request(s) in session {}", signalled, session_id);
 
-            // Apply Widget allowed skills + count limit
-            let skill_ids_ref = &mut selection_result.skills.iter_mut().map(|s| s.id.clone()).collect::<Vec<_>>();
-            // Note: filter_skills_for_widget modifies a Vec<String>, we have Vec<Skill>.
-            // We need to filter the skills vector directly.
+            if signalled == 0 {
+                ServerMessage::Error {
+                    message: format!("No in-flight requests in session {}", session_id),
+                }
+            } else {
+                ServerMessage::TaskStatus {
+                    session_id,
+                    status: "cancelling".to_string(),
+                    details: format!("Stopping {} in-flight request(s)...", signalled),
+                }
+            }
+        }
+        ClientMessage::CompactSession { session_id, keep_last } => {
+            debug!("Compacting session {} (keep last {})", session_id, keep_last);
 
-            // Security: Enforce widget allowlist and max count
-            use crate::commands::workflows::is_widget_mode;
-            if is_widget_mode(&session_id) {
-                let allowed = crate::commands::workflows::get_widget_allowed_skills();
-                selection_result.skills.retain(|s| allowed.contains(&s.id));
-                selection_result.skills.truncate(crate::commands::workflows::WIDGET_MAX_SKILLS);
+            let compacted = chat_db::get_session(&session_id).and_then(|session| match session {
+                Some(session) => Ok(Some((session, chat_db::compact_session(&session_id, keep_last)?))),
+                None => Ok(None),
+            });
+
+            match compacted {
+                Ok(Some((session, messages))) => ServerMessage::SessionLoaded {
+                    session: session.into(),
+                    messages: messages.into_iter().map(Into::into).collect(),
+                    has_more: false,
+                },
+                Ok(None) => ServerMessage::Error {
+                    message: format!("Session not found: {}", session_id),
+                },
+                Err(e) => {
+                    error!("Failed to compact session {}: {}", session_id, e);
+                    ServerMessage::Error {
+                        message: format!("Failed to compact session: {}", e),
+                    }
+                }
             }
+        }
+        ClientMessage::SearchMessages { query } => {
+            debug!("Searching messages: {}", query);
 
-            info!(
-                "Selected persona: {}, {} skills, {} bytes",
-                selection_result.persona,
-                selection_result.skills.len(),
-                selection_result.total_bytes
-            );
+            match chat_db::search_messages(&query) {
+                Ok(messages) => ServerMessage::SearchResults { messages },
+                Err(e) => {
+                    error!("Failed to search messages: {}", e);
+                    ServerMessage::Error {
+                        message: format!("Failed to search messages: {}", e),
+                    }
+                }
+            }
+        }
+        ClientMessage::DeleteSession { session_id } => {
+            debug!("Deleting session: {}", session_id);
 
-            // 6. Notify client of selected skills (with forced persona)
-            let skill_summaries: Vec<SkillSummary> = selection_result.skills.iter()
-                .map(|s| SkillSummary {
-                    id: s.id.clone(),
-                    name: s.name.clone(),
-                    score: s.score,
-                })
-                .collect();
-
-            let skills_msg = ServerMessage::SkillsSelected {
-                session_id: session_id.clone(),
-                persona: selection_result.persona.clone(),
-                category: selection_result.category.clone(),
-                skills: skill_summaries.clone(),
-                total_bytes: selection_result.total_bytes,
-            };
+            // Don't let in-flight workflows write into a deleted session
+            state.chat_requests.cancel_session(&session_id, "Session deleted");
+            crate::commands::workflows::clear_session_persona(&session_id);
 
-            if let Ok(text) = serde_json::to_string(&skills_msg) {
-                let _ = sender.send(Message::Text(text)).await;
+            match chat_db::delete_session(&session_id) {
+                Ok(true) => ServerMessage::SessionDeleted { session_id },
+                Ok(false) => ServerMessage::Error {
+                    message: format!("Session not found: {}", session_id),
+                },
+                Err(e) => {
+                    error!("Failed to delete session {}: {}", session_id, e);
+                    ServerMessage::Error {
+                        message: format!("Failed to delete session: {}", e),
+                    }
+                }
             }
+        }
+        ClientMessage::RenameSession { session_id, title } => {
+            debug!("Renaming session {}", session_id);
 
-            // 7. Load skill content
-            let skill_ids: Vec<String> = selection_result.skills.iter()
-                .map(|s| s.id.clone())
-                .collect();
+            match chat_db::update_session_title(&session_id, &title) {
+                Ok(session) => ServerMessage::SessionUpdated {
+                    session: session.into(),
+                },
+                Err(e) => ServerMessage::Error {
+                    message: format!("Failed to rename session: {}", e),
+                },
+            }
+        }
+        ClientMessage::UpdateSessionStatus { session_id, status } => {
+            debug!("Updating session {} status to {}", session_id, status);
 
-            send_status_update(
-                sender,
-                session_id.clone(),
-                "loading_skills".to_string(),
-                "Loading selected skill content...".to_string(),
-            ).await;
+            match chat_db::update_session_status(&session_id, &status) {
+                Ok(session) => ServerMessage::SessionUpdated {
+                    session: session.into(),
+                },
+                Err(e) => ServerMessage::Error {
+                    message: format!("Failed to update session status: {}", e),
+                },
+            }
+        }
+        ClientMessage::SetPersona { session_id, persona } => {
+            debug!("Setting persona for session {}: {:?}", session_id, persona);
 
-            let _skill_contents = match load_skill_content(skill_ids).await {
-                Ok(contents) => contents,
+            match persona {
+                Some(persona) => {
+                    let categories = crate::modules::config::load_app_config()
+                        .map(|config| config.proxy.workflows.persona_categories)
+                        .unwrap_or_else(|_| crate::proxy::config::WorkflowConfig::default().persona_categories);
+                    match crate::commands::workflows::set_session_persona(&session_id, &persona, &categories) {
+                        Ok(()) => ServerMessage::PersonaSet {
+                            session_id,
+                            persona: Some(persona),
+                        },
+                        Err(message) => ServerMessage::Error { message },
+                    }
+                }
+                None => {
+                    crate::commands::workflows::clear_session_persona(&session_id);
+                    ServerMessage::PersonaSet {
+                        session_id,
+                        persona: None,
+                    }
+                }
+            }
+        }
+        ClientMessage::DeleteMessage { message_id } => {
+            debug!("Deleting message: {}", message_id);
+
+            match chat_db::delete_message(message_id) {
+                Ok(true) => ServerMessage::MessageDeleted { message_id },
+                Ok(false) => ServerMessage::Error {
+                    message: format!("Message not found: {}", message_id),
+                },
                 Err(e) => {
-                    warn!("Failed to load skill content: {}", e);
-                    std::collections::HashMap::new()
+                    error!("Failed to delete message {}: {}", message_id, e);
+                    ServerMessage::Error {
+                        message: format!("Failed to delete message: {}", e),
+                    }
                 }
+            }
+        }
+    };
+
+    Some(response)
+}
+
+/// Per-message options carried by `UserMessage`
+struct MessageOptions {
+    include_skills: Vec<String>,
+    exclude_skills: Vec<String>,
+    dry_run: bool,
+    logs: Option<String>,
+}
+
+/// Run one user message: skill selection, workflow execution, and the final reply
+async fn handle_user_message(
+    session_id: String,
+    content: String,
+    options: MessageOptions,
+    cancel: &CancelToken,
+    outbox: &Outbox,
+    peers: &SessionPeers,
+) -> ServerMessage {
+    let MessageOptions { include_skills, exclude_skills, dry_run, logs } = options;
+    info!("User message in session {}: {}", session_id, content);
+
+    // 0. Persist the user message (never insert orphaned rows for unknown sessions)
+    match chat_db::get_session(&session_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            return ServerMessage::Error {
+                message: format!("Session not found: {}", session_id),
+            }
+        }
+        Err(e) => {
+            return ServerMessage::Error {
+                message: format!("Failed to load session: {}", e),
+            }
+        }
+    }
+    if let Err(e) = chat_db::update_session_status(&session_id, "running") {
+        warn!("Failed to mark session {} as running: {}", session_id, e);
+    }
+
+    // Prior messages feed optional query expansion
+    let history: Vec<String> = chat_db::get_messages(&session_id)
+        .map(|messages| messages.into_iter().map(|m| m.content).collect())
+        .unwrap_or_default();
+    match chat_db::append_message(&session_id, "user", &content) {
+        Ok(appended) => {
+            // The sender already shows its own message; other watchers need it
+            peers.publish(
+                &session_id,
+                &ServerMessage::MessageAppended {
+                    session_id: session_id.clone(),
+                    message: appended.message.into(),
+                },
+            );
+            announce_trim(&session_id, appended.pruned_ids, outbox, peers);
+        }
+        Err(e) => {
+            error!("Failed to persist user message: {}", e);
+            return ServerMessage::Error {
+                message: format!("Failed to save message: {}", e),
             };
+        }
+    }
 
-            // 8. Execute Workflow Logic
-            send_status_update(
-                sender,
-                session_id.clone(),
-                "processing".to_string(),
-                format!(
-                    "Executing {} workflow as {}...",
-                    workflow.as_ref().map(|w| w.get_description()).unwrap_or("standard"),
-                    selection_result.persona
-                ),
-            ).await;
-
-            let exec_result = match workflow {
-                Some(WorkflowCommand::Plan) => plan::execute(content.clone(), &selection_result).await,
-                Some(WorkflowCommand::Debug) => debug_flow::execute(content.clone(), &selection_result).await,
-                _ => {
-                    // Standard flow (echo/mock for now)
-                    Ok(TaskResult::Completed {
-                        summary: format!(
-                            "Standard response (Persona: {}). Skills: {}",
-                            selection_result.persona,
-                            skill_summaries.len()
-                        )
-                    })
-                }
+    // Phase 5.1: Workflow Parsing & Widget Security
+
+    // 1. Parse workflow command (server-side only)
+    let workflow_config = crate::modules::config::load_app_config()
+        .map(|config| config.proxy.workflows)
+        .unwrap_or_default();
+    let (workflow, workflow_args) = match parse_workflow_command(&content, &workflow_config) {
+        Some((cmd, args)) => {
+            info!("Detected workflow command: {:?}", cmd);
+            (Some(cmd), args)
+        }
+        None => (None, content.clone()),
+    };
+    if let Some(cmd) = &workflow {
+        if let Err(message) = check_workflow_enabled(cmd, &workflow_config) {
+            return ServerMessage::Error { message };
+        }
+    }
+    if dry_run && workflow.is_none() {
+        return ServerMessage::Error {
+            message: "dry_run is only supported for workflow commands (e.g. /deploy)".to_string(),
+        };
+    }
+    if logs.is_some() && !matches!(workflow, Some(WorkflowCommand::Debug)) {
+        return ServerMessage::Error {
+            message: "logs can only be attached to /debug".to_string(),
+        };
+    }
+
+    // 2. Security Check: Widget Mode Constraints
+    if let Err(msg) = validate_widget_workflow(&session_id, &workflow, &workflow_config) {
+        return ServerMessage::Error { message: msg };
+    }
+
+    // 3. Send status update
+    send_status_update(
+        outbox,
+        session_id.clone(),
+        "selecting_skills".to_string(),
+        "Analyzing request and selecting relevant skills...".to_string(),
+    );
+
+    // 4. Select skills using BM25 router (a workflow may configure tighter limits)
+    let skills_config = load_skills_config();
+    let (max_skills, max_skill_bytes) = match &workflow {
+        Some(cmd) => workflow_skill_limits(cmd, &workflow_config, skills_config.max_skills, skills_config.max_skill_bytes),
+        None => (skills_config.max_skills, skills_config.max_skill_bytes),
+    };
+    let mut selection_result = match select_skills(
+        content.clone(),
+        Some(max_skills),
+        Some(max_skill_bytes),
+        Some(history.clone()),
+    )
+    .await
+    {
+        Ok(selection) => selection,
+        Err(e) => {
+            error!("Failed to select skills: {}", e);
+            return ServerMessage::Error {
+                message: format!("Skill selection failed: {}", e),
             };
+        }
+    };
 
-            match exec_result {
-                Ok(task_result) => {
-                    let response_content = match task_result {
-                        TaskResult::RequiresReview { artifact, next_step } => {
-                            format!(
-                                "📝 **Plan Created:** `{}`\n\n👉 **Next Step:** {}\n\n_Review the artifact to proceed._",
-                                artifact, next_step
-                            )
-                        },
-                        TaskResult::DebugDiagnosis { root_cause, proposed_fix, confidence } => {
-                            format!(
-                                "🔍 **Diagnosis:** {}\n\n🛠️ **Proposed Fix:** {}\n\n✅ **Confidence:** {:.0}%",
-                                root_cause, proposed_fix, confidence * 100.0
-                            )
-                        },
-                        TaskResult::Completed { summary } => {
-                            format!("✅ **Done:** {}\n\n_Your message: {}_", summary, content)
-                        }
-                    };
+    // 5. Apply Workflow Overrides & Widget Limits
+    if let Some(cmd) = &workflow {
+        // Force persona based on workflow (configured override first)
+        selection_result.persona = workflow_persona(cmd, &workflow_config);
+    } else if let Some(persona) = crate::commands::workflows::get_session_persona(&session_id) {
+        // Session-level persona pin (workflows take precedence)
+        selection_result.persona = persona;
+    }
 
-                    ServerMessage::MessageAppended {
-                        session_id,
-                        message: TaskMessageResponse {
-                            id: chrono::Utc::now().timestamp(),
-                            role: "assistant".to_string(),
-                            content: response_content,
-                            created_at: chrono::Utc::now().timestamp(),
-                        },
+    // Per-message overrides (before widget limits so the count cap still applies)
+    if !include_skills.is_empty() || !exclude_skills.is_empty() {
+        let index = if include_skills.is_empty() {
+            Vec::new()
+        } else {
+            match load_skills_index() {
+                Ok(index) => index,
+                Err(e) => return ServerMessage::Error { message: e },
+            }
+        };
+        let widget_allowlist = if crate::commands::workflows::is_widget_mode(&session_id) {
+            Some(crate::commands::workflows::get_widget_allowed_skills())
+        } else {
+            None
+        };
+
+        match apply_skill_overrides(
+            &mut selection_result,
+            &include_skills,
+            &exclude_skills,
+            &index,
+            widget_allowlist.as_deref(),
+            max_skill_bytes,
+        ) {
+            Ok(skipped) if !skipped.is_empty() => {
+                warn!("Included skills skipped (byte budget exceeded): {:?}", skipped);
+            }
+            Ok(_) => {}
+            Err(msg) => return ServerMessage::Error { message: msg },
+        }
+    }
+
+    // Cost guard: configured ceiling (adaptive K may exceed it)
+    apply_skill_ceiling(&mut selection_result, max_skills, max_skill_bytes);
+
+    // Security: Enforce widget allowlist and max count
+    use crate::commands::workflows::is_widget_mode;
+    let widget_config = widget_config();
+    let mut grace_notice = None;
+    if is_widget_mode(&session_id) {
+        let allowed = crate::commands::workflows::get_widget_allowed_skills();
+        grace_notice = apply_widget_limits(&mut selection_result, &allowed, &widget_config.grace_message);
+    }
+
+    // Empty selection policy (only workflows depend on skills)
+    if let Some(cmd) = &workflow {
+        match check_workflow_skills(cmd, selection_result.skills.len(), &skills_config.empty_selection) {
+            Ok(EmptySkillsAction::Proceed) => {}
+            Ok(EmptySkillsAction::RetryFuzzy) => {
+                let fuzzy_query = fuzzy_workflow_query(cmd, &content);
+                debug!("No skills selected, retrying with fuzzy query: {}", fuzzy_query);
+                if let Ok(mut retry) = select_skills(
+                    fuzzy_query,
+                    Some(max_skills),
+                    Some(max_skill_bytes),
+                    None,
+                )
+                .await
+                {
+                    apply_skill_ceiling(&mut retry, max_skills, max_skill_bytes);
+                    if is_widget_mode(&session_id) {
+                        let allowed = crate::commands::workflows::get_widget_allowed_skills();
+                        apply_widget_limits(&mut retry, &allowed, &widget_config.grace_message);
+                    }
+                    if !retry.skills.is_empty() {
+                        grace_notice = None;
                     }
+                    selection_result.skills = retry.skills;
+                    selection_result.total_bytes = retry.total_bytes;
+                }
+                if selection_result.skills.is_empty() {
+                    warn!("{:?} workflow proceeding with no skills after fuzzy retry", cmd);
+                }
+            }
+            Err(msg) => return ServerMessage::Error { message: msg },
+        }
+    }
+
+    info!(
+        "Selected persona: {}, {} skills, {} bytes",
+        selection_result.persona,
+        selection_result.skills.len(),
+        selection_result.total_bytes
+    );
+
+    // 6. Notify client of selected skills (with forced persona)
+    let skill_summaries: Vec<SkillSummary> = selection_result.skills.iter()
+        .map(|s| SkillSummary {
+            id: s.id.clone(),
+            name: s.name.clone(),
+            score: s.score,
+            matched_terms: s.matched_terms.clone(),
+            size_bytes: s.size_bytes,
+        })
+        .collect();
+
+    let skills_msg = ServerMessage::SkillsSelected {
+        session_id: session_id.clone(),
+        persona: selection_result.persona.clone(),
+        category: selection_result.category.clone(),
+        skills: skill_summaries.clone(),
+        total_bytes: selection_result.total_bytes,
+    };
+
+    let _ = outbox.send(skills_msg);
+
+    // 7. Load skill content
+    let skill_ids: Vec<String> = selection_result.skills.iter()
+        .map(|s| s.id.clone())
+        .collect();
+
+    send_status_update(
+        outbox,
+        session_id.clone(),
+        "loading_skills".to_string(),
+        "Loading selected skill content...".to_string(),
+    );
+
+    let skill_contents = match load_skill_content(skill_ids.clone()).await {
+        Ok(loaded) => {
+            for skipped in &loaded.skipped {
+                warn!("Skill {} not loaded: {}", skipped.id, skipped.reason);
+            }
+            loaded.contents
+        }
+        Err(e) => {
+            warn!("Failed to load skill content: {}", e);
+            std::collections::HashMap::new()
+        }
+    };
+
+    // System message for the LLM call: persona prompt (if configured) + skill contents
+    let skills_prompt = skill_ids
+        .iter()
+        .filter_map(|id| skill_contents.get(id))
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    let system_prompt = crate::commands::workflows::apply_persona_prompt(
+        &skills_prompt,
+        &selection_result.persona,
+        &workflow_config.persona_prompts,
+    );
+    let prompt = PromptContext {
+        skills: &selection_result,
+        system_prompt: &system_prompt,
+    };
+
+    // 8. Execute Workflow Logic
+    send_status_update(
+        outbox,
+        session_id.clone(),
+        "processing".to_string(),
+        format!(
+            "Executing {} workflow as {}...",
+            workflow.as_ref().map(|w| w.get_description()).unwrap_or("standard"),
+            selection_result.persona
+        ),
+    );
+
+    let review_label = match workflow {
+        _ if dry_run => "Dry Run Preview",
+        Some(WorkflowCommand::Debug) => "More Information Needed",
+        Some(WorkflowCommand::Create) => "Scaffold Drafted",
+        Some(WorkflowCommand::Deploy) => "Deployment Planned",
+        _ => "Plan Created",
+    };
+
+    // Workflow output and the final summary are forwarded as deltas while they are produced;
+    // the persisted message is exactly their concatenation
+    let (delta_tx, delta_rx) = mpsc::unbounded_channel::<String>();
+    let forward = forward_deltas(delta_rx, session_id.clone(), outbox.clone());
+
+    // Long-running workflows report progress through task status updates
+    let progress = |status: &str, details: &str| {
+        send_status_update(outbox, session_id.clone(), status.to_string(), details.to_string())
+    };
+
+    let run = async {
+        let deltas = delta_tx;
+        if let Some(notice) = grace_notice {
+            stream_text(&deltas, &format!("{}\n\n", notice));
+        }
+
+        let exec_result = match workflow {
+            Some(WorkflowCommand::Plan) => plan::execute(workflow_args.clone(), &prompt, &session_id, dry_run, cancel, &deltas).await,
+            Some(WorkflowCommand::Debug) => {
+                debug_flow::execute(workflow_args.clone(), logs, &prompt, &workflow_config, dry_run, cancel, &deltas).await
+            }
+            Some(WorkflowCommand::Create) => create::execute(workflow_args.clone(), &prompt, &session_id, dry_run, cancel, &deltas).await,
+            Some(WorkflowCommand::Deploy) => {
+                deploy::execute(workflow_args.clone(), &prompt, &workflow_config.deploy, dry_run, cancel, &deltas).await
+            }
+            Some(WorkflowCommand::Summarize) => {
+                summarize::execute(workflow_args.clone(), &history, &prompt, cancel, &deltas).await
+            }
+            Some(WorkflowCommand::Test) => {
+                test_flow::execute(&prompt, &workflow_config.test, dry_run, cancel, &deltas, &progress).await
+            }
+            _ if cancel.is_cancelled() => Ok(TaskResult::Cancelled {
+                reason: cancel.reason().unwrap_or_default(),
+            }),
+            _ => {
+                // Standard flow (echo/mock for now)
+                Ok(TaskResult::Completed {
+                    summary: format!(
+                        "Standard response (Persona: {}). Skills: {}",
+                        selection_result.persona,
+                        skill_summaries.len()
+                    )
+                })
+            }
+        };
+
+        exec_result.map(|task_result| {
+            let summary = match task_result {
+                TaskResult::RequiresReview { artifact, next_step } => {
+                    format!(
+                        "📝 **{}:** `{}`\n\n👉 **Next Step:** {}\n\n_Review the artifact to proceed._",
+                        review_label, artifact, next_step
+                    )
                 },
-                Err(e) => ServerMessage::Error {
-                    message: format!("Workflow execution failed: {}", e)
+                TaskResult::DebugDiagnosis { root_cause, proposed_fix, confidence } => {
+                    format!(
+                        "🔍 **Diagnosis:** {}\n\n🛠️ **Proposed Fix:** {}\n\n✅ **Confidence:** {:.0}%",
+                        root_cause, proposed_fix, confidence * 100.0
+                    )
+                },
+                TaskResult::Completed { summary } => {
+                    format!("✅ **Done:** {}\n\n_Your message: {}_", summary, content)
+                }
+                TaskResult::Cancelled { reason } => {
+                    format!("🛑 **Cancelled:** {}", reason)
+                }
+            };
+            stream_text(&deltas, &summary);
+        })
+        // `deltas` dropped here, which ends the forwarder
+    };
+
+    let (exec_result, response_content) = tokio::join!(run, forward);
+
+    match exec_result {
+        Ok(()) => match chat_db::append_message(&session_id, "assistant", &response_content) {
+            Ok(appended) => {
+                announce_trim(&session_id, appended.pruned_ids, outbox, peers);
+                ServerMessage::MessageAppended {
+                    session_id,
+                    message: appended.message.into(),
+                }
+            }
+            Err(e) => {
+                error!("Failed to persist assistant message: {}", e);
+                ServerMessage::Error {
+                    message: format!("Failed to save response: {}", e),
+                }
+            }
+        },
+        Err(e) => ServerMessage::Error {
+            message: format!("Workflow execution failed: {}", e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::channel::mpsc as channel;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    /// In-memory WebSocket: frames pushed by the test come in, frames the server sends go out
+    struct MemorySocket {
+        incoming: channel::UnboundedReceiver<Result<Message, axum::Error>>,
+        outgoing: channel::UnboundedSender<Message>,
+    }
+
+    impl futures::Stream for MemorySocket {
+        type Item = Result<Message, axum::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            self.incoming.poll_next_unpin(cx)
+        }
+    }
+
+    impl futures::Sink<Message> for MemorySocket {
+        type Error = axum::Error;
+
+        fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.outgoing.poll_ready_unpin(cx).map_err(axum::Error::new)
+        }
+
+        fn start_send(mut self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+            self.outgoing.start_send_unpin(item).map_err(axum::Error::new)
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.outgoing.poll_flush_unpin(cx).map_err(axum::Error::new)
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            self.outgoing.poll_close_unpin(cx).map_err(axum::Error::new)
+        }
+    }
+
+    async fn next_frame(frames: &mut channel::UnboundedReceiver<Message>) -> Message {
+        tokio::time::timeout(Duration::from_secs(5), frames.next())
+            .await
+            .expect("timed out waiting for a frame")
+            .expect("socket closed without a frame")
+    }
+
+    /// Next JSON frame; panics on a Close frame
+    #[cfg(unix)]
+    async fn next_event(frames: &mut channel::UnboundedReceiver<Message>) -> serde_json::Value {
+        match next_frame(frames).await {
+            Message::Text(text) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a JSON frame, got {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    fn assistant_rows(session_id: &str) -> usize {
+        chat_db::get_messages(session_id)
+            .unwrap()
+            .iter()
+            .filter(|m| m.role == "assistant")
+            .count()
+    }
+
+    /// `/test` runs a slow command and a skill in the index matches it
+    #[cfg(unix)]
+    fn configure_slow_test_workflow() {
+        let home = crate::modules::account::home_dir().unwrap();
+        let agent_dir = home.join(".agent");
+        std::fs::create_dir_all(&agent_dir).unwrap();
+        let skill_path = agent_dir.join("test-runner.md");
+        std::fs::write(&skill_path, "# Test runner").unwrap();
+        let index = serde_json::json!({
+            "skills": [{
+                "id": "test-runner",
+                "path": skill_path,
+                "name": "Test runner",
+                "description": "Run the test suite",
+                "tags": ["test"]
+            }]
+        });
+        std::fs::write(agent_dir.join("skills-index.json"), index.to_string()).unwrap();
+
+        let mut config = crate::modules::config::load_app_config().unwrap();
+        config.proxy.workflows.test.command = vec!["sleep".to_string(), "0.5".to_string()];
+        config.proxy.workflows.test.working_dir = Some(home.to_string_lossy().into_owned());
+        crate::modules::config::save_app_config(&config).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_shutdown_persists_running_workflow_reply_before_close() {
+        chat_db::init_db().unwrap();
+        configure_slow_test_workflow();
+        let session = chat_db::create_session("drain", "repo", None).unwrap();
+
+        let state = AppState::for_tests(crate::modules::account::get_data_dir().unwrap());
+        let (client, incoming) = channel::unbounded();
+        let (outgoing, mut frames) = channel::unbounded();
+        let connection = tokio::spawn(serve_connection(
+            MemorySocket { incoming, outgoing },
+            state.clone(),
+            DrainTracker::new(),
+        ));
+
+        let message = serde_json::json!({
+            "type": "user_message",
+            "session_id": session.id,
+            "content": "/test"
+        });
+        client.unbounded_send(Ok(Message::Text(message.to_string()))).unwrap();
+
+        // Wait until the workflow is running its command
+        loop {
+            let event = next_event(&mut frames).await;
+            assert_ne!(event["type"], "error", "{}", event);
+            if event["type"] == "task_status" && event["status"] == "running_tests" {
+                break;
+            }
+        }
+        assert_eq!(assistant_rows(&session.id), 0);
+
+        let shutdown = state.shutdown.clone();
+        let drain = tokio::spawn(async move { shutdown.drain(Duration::from_secs(5)).await });
+
+        // The reply is persisted and delivered, then the connection says goodbye
+        let mut appended = false;
+        loop {
+            match next_frame(&mut frames).await {
+                Message::Close(None) => break,
+                Message::Text(text) => {
+                    let event: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    assert_ne!(event["type"], "error", "{}", event);
+                    if event["type"] == "message_appended" {
+                        assert_eq!(assistant_rows(&session.id), 1);
+                        appended = true;
+                    }
                 }
+                other => panic!("unexpected frame {:?}", other),
+            }
+        }
+        assert!(appended, "Close arrived before the assistant reply");
+        assert_eq!(assistant_rows(&session.id), 1);
+
+        assert!(drain.await.unwrap());
+        assert_eq!(state.shutdown.active_connections(), 0);
+        connection.await.unwrap();
+    }
+
+    /// Next `message_appended` frame, skipping progress events
+    #[cfg(unix)]
+    async fn next_appended(frames: &mut channel::UnboundedReceiver<Message>) -> serde_json::Value {
+        loop {
+            let event = next_event(frames).await;
+            assert_ne!(event["type"], "error", "{}", event);
+            if event["type"] == "message_appended" {
+                return event;
             }
         }
     }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_second_connection_receives_messages_appended_by_first() {
+        chat_db::init_db().unwrap();
+        configure_slow_test_workflow();
+        let session = chat_db::create_session("shared", "repo", None).unwrap();
+        let state = AppState::for_tests(crate::modules::account::get_data_dir().unwrap());
+
+        let mut clients = Vec::new();
+        let mut frames = Vec::new();
+        let mut connections = Vec::new();
+        for _ in 0..2 {
+            let (client, incoming) = channel::unbounded();
+            let (outgoing, socket_frames) = channel::unbounded();
+            connections.push(tokio::spawn(serve_connection(
+                MemorySocket { incoming, outgoing },
+                state.clone(),
+                DrainTracker::new(),
+            )));
+            clients.push(client);
+            frames.push(socket_frames);
+        }
+
+        // Both sockets open the session, which subscribes them to its events
+        let load = serde_json::json!({ "type": "load_session", "session_id": session.id });
+        for (client, socket_frames) in clients.iter().zip(frames.iter_mut()) {
+            client.unbounded_send(Ok(Message::Text(load.to_string()))).unwrap();
+            assert_eq!(next_event(socket_frames).await["type"], "session_loaded");
+        }
+
+        let message = serde_json::json!({
+            "type": "user_message",
+            "session_id": session.id,
+            "content": "/test"
+        });
+        clients[0].unbounded_send(Ok(Message::Text(message.to_string()))).unwrap();
+
+        let reply = next_appended(&mut frames[0]).await;
+        assert_eq!(reply["message"]["role"], "assistant");
+
+        // The watcher sees the user's message and then the same assistant reply
+        let user = next_appended(&mut frames[1]).await;
+        assert_eq!(user["session_id"], session.id.as_str());
+        assert_eq!(user["message"]["role"], "user");
+        assert_eq!(user["message"]["content"], "/test");
+        let watched = next_appended(&mut frames[1]).await;
+        assert_eq!(watched["message"], reply["message"]);
+
+        drop(clients);
+        for connection in connections {
+            connection.await.unwrap();
+        }
+    }
+
+    #[test]
+    fn test_cancelled_request_leaves_running_state() {
+        chat_db::init_db().unwrap();
+        let session = chat_db::create_session("cancel", "repo", None).unwrap();
+        chat_db::update_session_status(&session.id, "running").unwrap();
+
+        let cancelled = ServerMessage::TaskCancelled {
+            session_id: session.id.clone(),
+            request_id: "req-1".to_string(),
+        };
+        record_session_outcome(&session.id, &cancelled);
+
+        let stored = chat_db::get_session(&session.id).unwrap().unwrap();
+        assert_eq!(stored.status, "cancelled");
+    }
 }
@@ -18,6 +18,7 @@ use crate::proxy::mappers::claude::{
     clean_cache_control_from_messages, merge_consecutive_messages,
     models::{Message, MessageContent},
 };
+use crate::proxy::model_limiter::ClientProtocol;
 use crate::proxy::server::AppState;
 use crate::proxy::mappers::context_manager::ContextManager;
 use crate::proxy::mappers::estimation_calibrator::get_calibrator;
@@ -130,7 +131,7 @@ pub async fn handle_messages(
 
     // Decide whether this request should be handled by z.ai (Anthropic passthrough) or the existing Google flow.
     let zai = state.zai.read().await.clone();
-    let zai_enabled = zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
+    let zai_enabled = zai.dispatch_enabled();
     let google_accounts = state.token_manager.len();
 
     // [CRITICAL REFACTOR] 优先解析请求以获取模型信息(用于智能兜底判断)
@@ -166,39 +167,46 @@ pub async fn handle_messages(
     let normalized_model = crate::proxy::common::model_mapping::normalize_to_standard_id(&request.model)
         .unwrap_or_else(|| request.model.clone());
 
-    let use_zai = if !zai_enabled {
-        false
+    // [Issue #703 Fix] 智能判断: Fallback 模式下检查是否有可用的 Google 账号
+    let pool = crate::proxy::providers::dispatch::pool_status_for(&zai, &state.token_manager, &normalized_model).await;
+    // Pooled: z.ai 作为池中额外的一个槽位 (仅在 Pooled 模式下推进轮询计数)
+    let pooled_slot = if zai_enabled && zai.dispatch_mode == crate::proxy::ZaiDispatchMode::Pooled {
+        state.provider_rr.fetch_add(1, Ordering::Relaxed)
     } else {
-        match zai.dispatch_mode {
-            crate::proxy::ZaiDispatchMode::Off => false,
-            crate::proxy::ZaiDispatchMode::Exclusive => true,
-            crate::proxy::ZaiDispatchMode::Fallback => {
-                if google_accounts == 0 {
-                    // 没有 Google 账号,使用兜底
-                    tracing::info!("[{}] No Google accounts available, using fallback provider", trace_id);
-                    true
-                } else {
-                    // [Issue #703 Fix] 智能判断:检查是否有可用的 Google 账号
-                    let has_available = state.token_manager.has_available_account("claude", &normalized_model).await;
-                    if !has_available {
-                        tracing::info!(
-                            "[{}] All Google accounts unavailable (rate-limited or quota-protected for {}), using fallback provider",
-                            trace_id,
-                            request.model
-                        );
-                    }
-                    !has_available
-                }
-            }
-            crate::proxy::ZaiDispatchMode::Pooled => {
-                // Treat z.ai as exactly one extra slot in the pool.
-                // No strict guarantees: it may get 0 requests if selection never hits.
-                let total = google_accounts.saturating_add(1).max(1);
-                let slot = state.provider_rr.fetch_add(1, Ordering::Relaxed) % total;
-                slot == 0
-            }
-        }
+        0
+    };
+    // Weighted: 按 zai_weight 比例随机分流
+    let weighted_roll = rand::random::<f32>();
+    let backend = if zai_enabled {
+        crate::proxy::providers::dispatch::select_backend(&zai, pool, pooled_slot, weighted_roll)
+    } else {
+        crate::proxy::providers::dispatch::Backend::Google
     };
+    let use_zai = backend == crate::proxy::providers::dispatch::Backend::Zai;
+
+    // 按模型限流 (后端选定后检查，按该后端实际请求的上游模型计数)
+    if let Some(resp) = crate::proxy::handlers::common::check_model_rate_limit(
+        &state,
+        &request.model,
+        backend,
+        ClientProtocol::Anthropic,
+    )
+    .await
+    {
+        return resp;
+    }
+
+    if use_zai && zai.dispatch_mode == crate::proxy::ZaiDispatchMode::Fallback {
+        if google_accounts == 0 {
+            tracing::info!("[{}] No Google accounts available, using fallback provider", trace_id);
+        } else {
+            tracing::info!(
+                "[{}] All Google accounts unavailable (rate-limited or quota-protected for {}), using fallback provider",
+                trace_id,
+                request.model
+            );
+        }
+    }
 
     // [CRITICAL FIX] 预先清理所有消息中的 cache_control 字段 (Issue #744)
     // 必须在序列化之前处理，以确保 z.ai 和 Google Flow 都不受历史消息缓存标记干扰
@@ -381,6 +389,8 @@ pub async fn handle_messages(
     // 3. 准备闭包
     let mut request_for_body = request.clone();
     let token_manager = state.token_manager;
+    // 客户端会话头优先于内容指纹 (用于粘性调度)
+    let header_session_id = token_manager.header_session_id(&headers).await;
 
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries (e.g. stripping signatures)
@@ -416,7 +426,9 @@ pub async fn handle_messages(
 
         // 0. 尝试提取 session_id 用于粘性调度 (Phase 2/3)
         // 使用 SessionManager 生成稳定的会话指纹
-        let session_id_str = crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body);
+        let session_id_str = header_session_id.clone().unwrap_or_else(|| {
+            crate::proxy::session_manager::SessionManager::extract_session_id(&request_for_body)
+        });
         let session_id = Some(session_id_str.as_str());
 
         let force_rotate_token = attempt > 0;
@@ -1201,7 +1213,7 @@ pub async fn handle_count_tokens(
     Json(body): Json<Value>,
 ) -> Response {
     let zai = state.zai.read().await.clone();
-    let zai_enabled = zai.enabled && !matches!(zai.dispatch_mode, crate::proxy::ZaiDispatchMode::Off);
+    let zai_enabled = zai.dispatch_enabled();
 
     if zai_enabled {
         return crate::proxy::providers::zai_anthropic::forward_anthropic_json(
@@ -186,3 +186,61 @@ pub async fn handle_detect_model(
 
     Json(response).into_response()
 }
+
+/// 按模型限流检查: 依次匹配所选后端的上游模型与客户端请求模型，超限时返回 `protocol` 格式的 429 响应。
+/// 需在后端选定之后调用，z.ai 转发的请求按 z.ai 映射后的模型 (例如 glm-4.7) 限流
+pub async fn check_model_rate_limit(
+    state: &AppState,
+    model: &str,
+    backend: crate::proxy::providers::dispatch::Backend,
+    protocol: crate::proxy::model_limiter::ClientProtocol,
+) -> Option<Response> {
+    let (upstream, _) = {
+        let zai = state.zai.read().await;
+        let mapping = state.custom_mapping.read().await;
+        crate::proxy::providers::dispatch::upstream_model_for(backend, model, &zai, &mapping)
+    };
+
+    match state.model_limiter.check(&[upstream.as_str(), model]) {
+        Ok(()) => None,
+        Err((limited_model, wait)) => Some(
+            crate::proxy::model_limiter::rate_limited_response(protocol, &limited_model, wait),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::model_limiter::ClientProtocol;
+    use crate::proxy::providers::dispatch::Backend;
+
+    #[tokio::test]
+    async fn test_rate_limit_uses_upstream_model_of_selected_backend() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state = AppState::for_tests(tmp.path().to_path_buf());
+        state
+            .zai
+            .write()
+            .await
+            .model_mapping
+            .insert("claude-sonnet-4-5".to_string(), "glm-4.7".to_string());
+        state
+            .model_limiter
+            .update_limits(&std::collections::HashMap::from([("glm-4.7".to_string(), 1)]));
+
+        let check = |backend| {
+            check_model_rate_limit(&state, "claude-sonnet-4-5", backend, ClientProtocol::Anthropic)
+        };
+
+        // Google 后端不会请求 glm-4.7，不受其限流影响
+        for _ in 0..3 {
+            assert!(check(Backend::Google).await.is_none());
+        }
+
+        // z.ai 后端按映射后的 glm-4.7 计数
+        assert!(check(Backend::Zai).await.is_none());
+        let resp = check(Backend::Zai).await.expect("second z.ai request should be limited");
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+}
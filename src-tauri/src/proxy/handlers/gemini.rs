@@ -1,9 +1,10 @@
 // Gemini Handler
-use axum::{extract::State, extract::{Json, Path}, http::StatusCode, response::IntoResponse};
+use axum::{extract::State, extract::{Json, Path}, http::{HeaderMap, StatusCode}, response::IntoResponse};
 use serde_json::{json, Value};
 use tracing::{debug, error, info};
 
 use crate::proxy::mappers::gemini::{wrap_request, unwrap_response};
+use crate::proxy::model_limiter::ClientProtocol;
 use crate::proxy::server::AppState;
 use crate::proxy::session_manager::SessionManager;
 use crate::proxy::handlers::common::{determine_retry_strategy, apply_retry_strategy, should_rotate_account, RetryStrategy};
@@ -17,6 +18,7 @@ const MAX_RETRY_ATTEMPTS: usize = 3;
 pub async fn handle_generate(
     State(state): State<AppState>,
     Path(model_action): Path<String>,
+    headers: HeaderMap,
     Json(mut body): Json<Value>  // 改为 mut 以支持修复提示词注入
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // 解析 model:method
@@ -34,6 +36,18 @@ pub async fn handle_generate(
     if method != "generateContent" && method != "streamGenerateContent" {
         return Err((StatusCode::BAD_REQUEST, format!("Unsupported method: {}", method)));
     }
+
+    // 按模型限流 (转发前检查，该协议只走 Google 后端)
+    if let Some(resp) = crate::proxy::handlers::common::check_model_rate_limit(
+        &state,
+        &model_name,
+        crate::proxy::providers::dispatch::Backend::Google,
+        ClientProtocol::Gemini,
+    )
+    .await
+    {
+        return Ok(resp);
+    }
     if debug_logger::is_enabled(&debug_cfg) {
         let original_payload = json!({
             "kind": "original_request",
@@ -57,6 +71,8 @@ pub async fn handle_generate(
     // 2. 获取 UpstreamClient 和 TokenManager
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    // 客户端会话头优先于内容指纹 (用于粘性调度)
+    let header_session_id = token_manager.header_session_id(&headers).await;
     let pool_size = token_manager.len();
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size).max(1);
 
@@ -92,7 +108,9 @@ pub async fn handle_generate(
 
         // 4. 获取 Token (使用准确的 request_type)
         // 提取 SessionId (粘性指纹)
-        let session_id = SessionManager::extract_gemini_session_id(&body, &model_name);
+        let session_id = header_session_id
+            .clone()
+            .unwrap_or_else(|| SessionManager::extract_gemini_session_id(&body, &model_name));
 
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
         let (access_token, project_id, email, _wait_ms) = match token_manager.get_token(&config.request_type, attempt > 0, Some(&session_id), &config.final_model).await {
@@ -19,9 +19,7 @@ fn build_client(
     let mut builder = reqwest::Client::builder()
         .timeout(Duration::from_secs(timeout_secs.max(5)));
 
-    if upstream_proxy.enabled && !upstream_proxy.url.is_empty() {
-        let proxy = reqwest::Proxy::all(&upstream_proxy.url)
-            .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
+    if let Some(proxy) = upstream_proxy.build_proxy()? {
         builder = builder.proxy(proxy);
     }
 
@@ -1,6 +1,7 @@
 // OpenAI Handler
 use axum::{
-    extract::Json, extract::State, http::StatusCode, response::IntoResponse, response::Response,
+    extract::Json, extract::State, http::{HeaderMap, StatusCode}, response::IntoResponse,
+    response::Response,
 };
 use base64::Engine as _;
 use bytes::Bytes;
@@ -11,6 +12,7 @@ use crate::proxy::mappers::openai::{
     transform_openai_request, transform_openai_response, OpenAIRequest,
 };
 // use crate::proxy::upstream::client::UpstreamClient; // 通过 state 获取
+use crate::proxy::model_limiter::ClientProtocol;
 use crate::proxy::server::AppState;
 use crate::proxy::debug_logger;
 
@@ -23,6 +25,7 @@ use tokio::time::Duration;
 
 pub async fn handle_chat_completions(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(mut body): Json<Value>,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
     // [FIX] 保存原始请求体的完整副本，用于日志记录
@@ -81,6 +84,18 @@ pub async fn handle_chat_completions(
     let mut openai_req: OpenAIRequest = serde_json::from_value(body)
         .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
 
+    // 按模型限流 (转发前检查，该协议只走 Google 后端)
+    if let Some(resp) = crate::proxy::handlers::common::check_model_rate_limit(
+        &state,
+        &openai_req.model,
+        crate::proxy::providers::dispatch::Backend::Google,
+        ClientProtocol::OpenAI,
+    )
+    .await
+    {
+        return Ok(resp);
+    }
+
     // Safety: Ensure messages is not empty
     if openai_req.messages.is_empty() {
         debug!("Received request with empty messages, injecting fallback...");
@@ -119,6 +134,8 @@ pub async fn handle_chat_completions(
     // 1. 获取 UpstreamClient (Clone handle)
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    // 客户端会话头优先于内容指纹 (用于粘性调度)
+    let header_session_id = token_manager.header_session_id(&headers).await;
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
@@ -147,7 +164,9 @@ pub async fn handle_chat_completions(
         );
 
         // 3. 提取 SessionId (粘性指纹)
-        let session_id = SessionManager::extract_openai_session_id(&openai_req);
+        let session_id = header_session_id
+            .clone()
+            .unwrap_or_else(|| SessionManager::extract_openai_session_id(&openai_req));
 
         // 4. 获取 Token (使用准确的 request_type)
         // 关键：在重试尝试 (attempt > 0) 时强制轮换账号
@@ -609,6 +628,7 @@ pub async fn handle_chat_completions(
 /// 将 Prompt 转换为 Chat Message 格式，复用 handle_chat_completions
 pub async fn handle_completions(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(mut body): Json<Value>,
 ) -> Response {
     debug!(
@@ -967,6 +987,18 @@ pub async fn handle_completions(
         }
     };
 
+    // 按模型限流 (转发前检查，该协议只走 Google 后端)
+    if let Some(resp) = crate::proxy::handlers::common::check_model_rate_limit(
+        &state,
+        &openai_req.model,
+        crate::proxy::providers::dispatch::Backend::Google,
+        ClientProtocol::OpenAI,
+    )
+    .await
+    {
+        return resp;
+    }
+
     // Safety: Inject empty message if needed
     if openai_req.messages.is_empty() {
         openai_req
@@ -985,6 +1017,8 @@ pub async fn handle_completions(
 
     let upstream = state.upstream.clone();
     let token_manager = state.token_manager;
+    // 客户端会话头优先于内容指纹 (用于粘性调度)
+    let header_session_id = token_manager.header_session_id(&headers).await;
     let pool_size = token_manager.len();
     // [FIX] Ensure max_attempts is at least 2 to allow for internal retries
     let max_attempts = MAX_RETRY_ATTEMPTS.min(pool_size.saturating_add(1)).max(2);
@@ -1016,7 +1050,9 @@ pub async fn handle_completions(
 
         // 3. 提取 SessionId (复用)
         // [New] 使用 TokenManager 内部逻辑提取 session_id，支持粘性调度
-        let session_id_str = SessionManager::extract_openai_session_id(&openai_req);
+        let session_id_str = header_session_id
+            .clone()
+            .unwrap_or_else(|| SessionManager::extract_openai_session_id(&openai_req));
         let session_id = Some(session_id_str.as_str());
 
         // 重试时强制轮换，除非只是简单的网络抖动但 Claude 逻辑里 attempt > 0 总是 force_rotate
@@ -30,7 +30,19 @@ fn parse_sse_line(line: &str) -> Option<(String, String)> {
 /// 此函数接收一个 SSE 字节流，解析所有事件，并重建完整的 ClaudeResponse 对象。
 /// 这使得非 Stream 客户端可以透明地享受 Stream 模式的配额优势。
 pub async fn collect_stream_to_json<S>(
+    stream: S,
+) -> Result<ClaudeResponse, String>
+where
+    S: futures::Stream<Item = Result<Bytes, io::Error>> + Unpin,
+{
+    collect_stream_to_json_with_limit(stream, crate::proxy::common::body_limit::max_response_bytes())
+        .await
+}
+
+/// 同 [`collect_stream_to_json`]，显式指定读取字节上限，超出后立即中止并返回错误
+pub async fn collect_stream_to_json_with_limit<S>(
     mut stream: S,
+    max_bytes: usize,
 ) -> Result<ClaudeResponse, String>
 where
     S: futures::Stream<Item = Result<Bytes, io::Error>> + Unpin,
@@ -38,10 +50,12 @@ where
     let mut events = Vec::new();
     let mut current_event_type = String::new();
     let mut current_data = String::new();
+    let mut budget = crate::proxy::common::body_limit::ByteBudget::new(max_bytes);
 
     // 1. 收集所有 SSE 事件
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+        budget.consume(chunk.len())?;
         let text = String::from_utf8_lossy(&chunk);
 
         for line in text.lines() {
@@ -297,4 +311,20 @@ mod tests {
             panic!("Expected Thinking block");
         }
     }
+
+    #[tokio::test]
+    async fn test_collect_aborts_when_response_exceeds_limit() {
+        let delta = format!(
+            "event: content_block_delta\ndata: {{\"type\":\"content_block_delta\",\"index\":0,\"delta\":{{\"type\":\"text_delta\",\"text\":\"{}\"}}}}\n\n",
+            "x".repeat(512)
+        );
+        let byte_stream = stream::iter(
+            (0..10).map(move |_| Ok::<Bytes, io::Error>(Bytes::from(delta.clone())))
+        );
+
+        let err = collect_stream_to_json_with_limit(byte_stream, 2048)
+            .await
+            .expect_err("超限响应不应被聚合");
+        assert!(err.contains("max_response_bytes"));
+    }
 }
@@ -13,6 +13,24 @@ use tracing::{debug, info};
 /// - Unicode/CJK: ~1.5 characters per token (Chinese, Japanese, Korean are tokenized differently)
 /// - Adds 15% safety margin to prevent underestimation
 fn estimate_tokens_from_str(s: &str) -> u32 {
+    estimate_tokens_with_density(s, 4.0)
+}
+
+/// ASCII characters per token for a model family.
+/// Claude's tokenizer splits English text more finely than Gemini/GPT (~3.5 vs ~4 chars).
+fn ascii_chars_per_token(model: Option<&str>) -> f32 {
+    match model.map(|m| m.to_lowercase()) {
+        Some(m) if m.contains("claude") => 3.5,
+        _ => 4.0,
+    }
+}
+
+/// Approximate token count of free text for a given model (pre-send estimate)
+pub fn estimate_text_tokens(text: &str, model: Option<&str>) -> u32 {
+    estimate_tokens_with_density(text, ascii_chars_per_token(model))
+}
+
+fn estimate_tokens_with_density(s: &str, ascii_per_token: f32) -> u32 {
     if s.is_empty() {
         return 0;
     }
@@ -29,7 +47,7 @@ fn estimate_tokens_from_str(s: &str) -> u32 {
     }
 
     // ASCII: ~4 chars/token, Unicode/CJK: ~1.5 chars/token
-    let ascii_tokens = (ascii_chars as f32 / 4.0).ceil() as u32;
+    let ascii_tokens = (ascii_chars as f32 / ascii_per_token).ceil() as u32;
     let unicode_tokens = (unicode_chars as f32 / 1.5).ceil() as u32;
 
     // Add 15% safety margin to account for tokenizer variations
@@ -461,6 +479,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_estimate_text_tokens_sane_range() {
+        let sample = "The quick brown fox jumps over the lazy dog. ".repeat(10);
+
+        let gemini = estimate_text_tokens(&sample, Some("gemini-2.5-pro"));
+        let claude = estimate_text_tokens(&sample, Some("claude-sonnet-4-5"));
+
+        // ~100 real tokens; the estimate errs slightly high
+        assert!((90..=160).contains(&gemini), "gemini estimate {}", gemini);
+        assert!((90..=160).contains(&claude), "claude estimate {}", claude);
+        assert!(claude >= gemini);
+        assert_eq!(estimate_text_tokens("", None), 0);
+    }
+
     #[test]
     fn test_estimate_tokens() {
         let mut req = create_test_request();
@@ -35,8 +35,13 @@ where
     let mut usage_metadata: Option<Value> = None;
     let mut finish_reason: Option<String> = None;
 
+    let mut budget = crate::proxy::common::body_limit::ByteBudget::new(
+        crate::proxy::common::body_limit::max_response_bytes(),
+    );
+
     while let Some(chunk_result) = stream.next().await {
         let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+        budget.consume(chunk.len())?;
         let text = std::str::from_utf8(&chunk).unwrap_or(""); // Ignore invalid utf8 for simplicity or handle better
 
         for line in text.lines() {
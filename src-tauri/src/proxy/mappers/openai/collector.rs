@@ -16,161 +16,331 @@ struct ToolCallBuilder {
     arguments: String,
 }
 
-/// Collects an OpenAI SSE stream into a complete OpenAIResponse
-pub async fn collect_stream_to_json<S, E>(
-    mut stream: S,
-) -> Result<OpenAIResponse, String>
-where
-    S: futures::Stream<Item = Result<Bytes, E>> + Unpin,
-    E: std::fmt::Display,
-{
-    let mut response = OpenAIResponse {
-        id: "chatcmpl-unknown".to_string(),
-        object: "chat.completion".to_string(),
-        created: chrono::Utc::now().timestamp() as u64,
-        model: "unknown".to_string(),
-        choices: Vec::new(),
-        usage: None,
-    };
-
-    let mut role: Option<String> = None;
-    let mut content_parts: Vec<String> = Vec::new();
-    let mut reasoning_parts: Vec<String> = Vec::new();
-    let mut finish_reason: Option<String> = None;
-    let mut tool_call_builders: BTreeMap<u32, ToolCallBuilder> = BTreeMap::new();
+/// Deltas accumulated for one choice (`n > 1` streams interleave several)
+#[derive(Default)]
+struct ChoiceBuilder {
+    role: Option<String>,
+    content_parts: Vec<String>,
+    reasoning_parts: Vec<String>,
+    finish_reason: Option<String>,
+    tool_call_builders: BTreeMap<u32, ToolCallBuilder>,
+}
 
-    while let Some(chunk_result) = stream.next().await {
-        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
-        let text = String::from_utf8_lossy(&chunk);
-
-        for line in text.lines() {
-            let line = line.trim();
-            if line.starts_with("data: ") {
-                let data_str = line.trim_start_matches("data: ").trim();
-                if data_str == "[DONE]" {
-                    continue;
-                }
+impl ChoiceBuilder {
+    fn apply(&mut self, choice: &Value) {
+        if let Some(delta) = choice.get("delta") {
+            // Role
+            if let Some(r) = delta.get("role").and_then(|v| v.as_str()) {
+                self.role = Some(r.to_string());
+            }
 
-                if let Ok(json) = serde_json::from_str::<Value>(data_str) {
-                    // Update meta fields
-                    if let Some(id) = json.get("id").and_then(|v| v.as_str()) {
-                        response.id = id.to_string();
-                    }
-                    if let Some(model) = json.get("model").and_then(|v| v.as_str()) {
-                        response.model = model.to_string();
-                    }
-                    if let Some(created) = json.get("created").and_then(|v| v.as_u64()) {
-                        response.created = created;
-                    }
+            // Content
+            if let Some(c) = delta.get("content").and_then(|v| v.as_str()) {
+                self.content_parts.push(c.to_string());
+            }
+
+            // Reasoning Content
+            if let Some(rc) = delta.get("reasoning_content").and_then(|v| v.as_str()) {
+                self.reasoning_parts.push(rc.to_string());
+            }
+
+            // Tool Calls
+            if let Some(tool_calls_arr) = delta.get("tool_calls").and_then(|v| v.as_array()) {
+                for tc in tool_calls_arr {
+                    if let Some(index) = tc.get("index").and_then(|v| v.as_u64()).map(|v| v as u32) {
+                        let builder = self.tool_call_builders.entry(index).or_default();
 
-                    // Collect Usage
-                    if let Some(usage) = json.get("usage") {
-                        if let Ok(u) = serde_json::from_value::<OpenAIUsage>(usage.clone()) {
-                            response.usage = Some(u);
+                        if let Some(id) = tc.get("id").and_then(|v| v.as_str()) {
+                            builder.id = Some(id.to_string());
+                        }
+                        if let Some(t) = tc.get("type").and_then(|v| v.as_str()) {
+                            builder.r#type = Some(t.to_string());
                         }
-                    }
 
-                    // Collect Choices Delta
-                    if let Some(choices) = json.get("choices").and_then(|v| v.as_array()) {
-                        if let Some(choice) = choices.first() {
-                            if let Some(delta) = choice.get("delta") {
-                                // Role
-                                if let Some(r) = delta.get("role").and_then(|v| v.as_str()) {
-                                    role = Some(r.to_string());
-                                }
-
-                                // Content
-                                if let Some(c) = delta.get("content").and_then(|v| v.as_str()) {
-                                    content_parts.push(c.to_string());
-                                }
-
-                                // Reasoning Content
-                                if let Some(rc) = delta.get("reasoning_content").and_then(|v| v.as_str()) {
-                                    reasoning_parts.push(rc.to_string());
-                                }
-
-                                // Tool Calls
-                                if let Some(tool_calls_arr) = delta.get("tool_calls").and_then(|v| v.as_array()) {
-                                    for tc in tool_calls_arr {
-                                        if let Some(index) = tc.get("index").and_then(|v| v.as_u64()).map(|v| v as u32) {
-                                            let builder = tool_call_builders.entry(index).or_default();
-
-                                            if let Some(id) = tc.get("id").and_then(|v| v.as_str()) {
-                                                builder.id = Some(id.to_string());
-                                            }
-                                            if let Some(t) = tc.get("type").and_then(|v| v.as_str()) {
-                                                builder.r#type = Some(t.to_string());
-                                            }
-
-                                            if let Some(function) = tc.get("function") {
-                                                if let Some(name) = function.get("name").and_then(|v| v.as_str()) {
-                                                    builder.name.push_str(name);
-                                                }
-                                                if let Some(args) = function.get("arguments").and_then(|v| v.as_str()) {
-                                                    builder.arguments.push_str(args);
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
+                        if let Some(function) = tc.get("function") {
+                            if let Some(name) = function.get("name").and_then(|v| v.as_str()) {
+                                builder.name.push_str(name);
                             }
-
-                            if let Some(fr) = choice.get("finish_reason").and_then(|v| v.as_str()) {
-                                finish_reason = Some(fr.to_string());
+                            if let Some(args) = function.get("arguments").and_then(|v| v.as_str()) {
+                                builder.arguments.push_str(args);
                             }
                         }
                     }
                 }
             }
         }
-    }
 
-    // Construct final message
-    let full_content = content_parts.join("");
-    let full_reasoning = if reasoning_parts.is_empty() {
-        None
-    } else {
-        Some(reasoning_parts.join(""))
-    };
-
-    let tool_calls_vec = if !tool_call_builders.is_empty() {
-        let mut calls = Vec::new();
-        // BTreeMap iterates in sorted order of keys (indices), which is what we want
-        for (index, builder) in tool_call_builders {
-            // Per OpenAI API spec, tool call ID is mandatory
-            let id = builder.id.ok_or_else(|| {
-                format!("Missing ID for tool call at index {}", index)
-            })?;
-            calls.push(ToolCall {
-                id,
-                r#type: builder.r#type.unwrap_or_else(|| "function".to_string()),
-                function: ToolFunction {
-                    name: builder.name,
-                    arguments: builder.arguments,
-                },
-            });
+        if let Some(fr) = choice.get("finish_reason").and_then(|v| v.as_str()) {
+            self.finish_reason = Some(fr.to_string());
         }
-        Some(calls)
-    } else {
-        None
-    };
-
-    let message = OpenAIMessage {
-        role: role.unwrap_or("assistant".to_string()),
-        content: Some(OpenAIContent::String(full_content)),
-        reasoning_content: full_reasoning,
-        tool_calls: tool_calls_vec,
-        tool_call_id: None,
-        name: None,
-    };
-
-    response.choices.push(Choice {
-        index: 0,
-        message,
-        finish_reason: finish_reason.or(Some("stop".to_string())),
+    }
+
+    fn build(self, index: u32) -> Result<Choice, String> {
+        // Construct final message
+        let full_content = self.content_parts.join("");
+        let full_reasoning = if self.reasoning_parts.is_empty() {
+            None
+        } else {
+            Some(self.reasoning_parts.join(""))
+        };
+
+        let tool_calls_vec = if !self.tool_call_builders.is_empty() {
+            let mut calls = Vec::new();
+            // BTreeMap iterates in sorted order of keys (indices), which is what we want
+            for (tool_index, builder) in self.tool_call_builders {
+                // Per OpenAI API spec, tool call ID is mandatory
+                let id = builder.id.ok_or_else(|| {
+                    format!("Missing ID for tool call at index {}", tool_index)
+                })?;
+                calls.push(ToolCall {
+                    id,
+                    r#type: builder.r#type.unwrap_or_else(|| "function".to_string()),
+                    function: ToolFunction {
+                        name: builder.name,
+                        arguments: builder.arguments,
+                    },
+                });
+            }
+            Some(calls)
+        } else {
+            None
+        };
+
+        let message = OpenAIMessage {
+            role: self.role.unwrap_or("assistant".to_string()),
+            content: Some(OpenAIContent::String(full_content)),
+            reasoning_content: full_reasoning,
+            tool_calls: tool_calls_vec,
+            tool_call_id: None,
+            name: None,
+        };
+
+        Ok(Choice {
+            index,
+            message,
+            // The upstream's own finish_reason (e.g. "tool_calls") wins; "stop" only as a default
+            finish_reason: self.finish_reason.or(Some("stop".to_string())),
+        })
+    }
+}
+
+/// Format an upstream `{"error": {...}}` stream event (message plus code/type when present)
+fn describe_stream_error(error: &Value) -> String {
+    let message = error
+        .get("message")
+        .and_then(|v| v.as_str())
+        .map(|m| m.to_string())
+        .or_else(|| error.as_str().map(|m| m.to_string()))
+        .unwrap_or_else(|| error.to_string());
+
+    let code = error.get("code").filter(|c| !c.is_null()).map(|c| match c.as_str() {
+        Some(code) => code.to_string(),
+        None => c.to_string(),
     });
+    let error_type = error.get("type").and_then(|v| v.as_str());
 
-    Ok(response)
+    match (code, error_type) {
+        (Some(code), _) => format!("Upstream stream error ({}): {}", code, message),
+        (None, Some(error_type)) => format!("Upstream stream error ({}): {}", error_type, message),
+        (None, None) => format!("Upstream stream error: {}", message),
+    }
+}
+
+/// Fold one event's `usage` into the running totals, keeping the maximum seen per field.
+/// Providers either repeat cumulative counts on several chunks or send only some fields
+/// per chunk (e.g. prompt tokens first, completion tokens at the end); both converge on
+/// the complete totals this way. Missing fields leave the running value untouched.
+fn merge_usage(current: &mut Option<OpenAIUsage>, usage: &Value) {
+    let field = |value: Option<&Value>| value.and_then(|v| v.as_u64()).map(|v| v as u32);
+    let prompt = field(usage.get("prompt_tokens"));
+    let completion = field(usage.get("completion_tokens"));
+    let total = field(usage.get("total_tokens"));
+    let cached = field(usage.pointer("/prompt_tokens_details/cached_tokens"));
+    let reasoning = field(usage.pointer("/completion_tokens_details/reasoning_tokens"));
+
+    if [prompt, completion, total, cached, reasoning].iter().all(Option::is_none) {
+        return;
+    }
+
+    let merged = current.get_or_insert_with(|| OpenAIUsage {
+        prompt_tokens: 0,
+        completion_tokens: 0,
+        total_tokens: 0,
+        prompt_tokens_details: None,
+        completion_tokens_details: None,
+    });
+    merged.prompt_tokens = merged.prompt_tokens.max(prompt.unwrap_or(0));
+    merged.completion_tokens = merged.completion_tokens.max(completion.unwrap_or(0));
+    merged.total_tokens = merged
+        .total_tokens
+        .max(total.unwrap_or(0))
+        .max(merged.prompt_tokens.saturating_add(merged.completion_tokens));
+
+    if let Some(cached) = cached {
+        let details = merged
+            .prompt_tokens_details
+            .get_or_insert(PromptTokensDetails { cached_tokens: None });
+        details.cached_tokens = Some(details.cached_tokens.unwrap_or(0).max(cached));
+    }
+    if let Some(reasoning) = reasoning {
+        let details = merged
+            .completion_tokens_details
+            .get_or_insert(CompletionTokensDetails { reasoning_tokens: None });
+        details.reasoning_tokens = Some(details.reasoning_tokens.unwrap_or(0).max(reasoning));
+    }
+}
+
+/// Accumulated state while collecting a stream
+struct CollectorState {
+    response: OpenAIResponse,
+    choices: BTreeMap<u32, ChoiceBuilder>,
+}
+
+impl CollectorState {
+    fn new() -> Self {
+        Self {
+            response: OpenAIResponse {
+                id: "chatcmpl-unknown".to_string(),
+                object: "chat.completion".to_string(),
+                created: chrono::Utc::now().timestamp() as u64,
+                model: "unknown".to_string(),
+                choices: Vec::new(),
+                usage: None,
+            },
+            choices: BTreeMap::new(),
+        }
+    }
+
+    /// Process one complete SSE line. An upstream `error` event aborts collection.
+    fn apply_line(&mut self, line: &str) -> Result<(), String> {
+        let line = line.trim();
+        if !line.starts_with("data: ") {
+            return Ok(());
+        }
+        let data_str = line.trim_start_matches("data: ").trim();
+        if data_str == "[DONE]" {
+            return Ok(());
+        }
+
+        if let Ok(json) = serde_json::from_str::<Value>(data_str) {
+            if let Some(error) = json.get("error").filter(|e| !e.is_null()) {
+                return Err(describe_stream_error(error));
+            }
+            self.apply_event(&json);
+        }
+        Ok(())
+    }
+
+    fn apply_event(&mut self, json: &Value) {
+        let response = &mut self.response;
+
+        // Update meta fields
+        if let Some(id) = json.get("id").and_then(|v| v.as_str()) {
+            response.id = id.to_string();
+        }
+        if let Some(model) = json.get("model").and_then(|v| v.as_str()) {
+            response.model = model.to_string();
+        }
+        if let Some(created) = json.get("created").and_then(|v| v.as_u64()) {
+            response.created = created;
+        }
+
+        // Collect Usage (may be partial or incremental, so merge rather than replace)
+        if let Some(usage) = json.get("usage").filter(|u| u.is_object()) {
+            merge_usage(&mut response.usage, usage);
+        }
+
+        // Collect Choices Delta (keyed by each choice's own `index`)
+        if let Some(choices) = json.get("choices").and_then(|v| v.as_array()) {
+            for (position, choice) in choices.iter().enumerate() {
+                let index = choice
+                    .get("index")
+                    .and_then(|v| v.as_u64())
+                    .unwrap_or(position as u64) as u32;
+                self.choices.entry(index).or_default().apply(choice);
+            }
+        }
+    }
+
+    fn finish(self) -> Result<OpenAIResponse, String> {
+        let mut response = self.response;
+        let mut choices = self.choices;
+
+        // A stream without any choice delta still yields one (empty) choice
+        if choices.is_empty() {
+            choices.insert(0, ChoiceBuilder::default());
+        }
+
+        for (index, builder) in choices {
+            response.choices.push(builder.build(index)?);
+        }
+
+        Ok(response)
+    }
+}
+
+/// Collects an OpenAI SSE stream into a complete OpenAIResponse.
+/// Network chunks don't respect line boundaries, so bytes are buffered and only
+/// complete `\n`-terminated lines are parsed; a partial line is carried into the next chunk.
+pub async fn collect_stream_to_json<S, E>(
+    stream: S,
+) -> Result<OpenAIResponse, String>
+where
+    S: futures::Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    collect_stream_to_json_with_limit(stream, crate::proxy::common::body_limit::max_response_bytes())
+        .await
+}
+
+/// Same as [`collect_stream_to_json`] with an explicit cap on the bytes read from the stream.
+/// Collection aborts with an error as soon as the cap is exceeded.
+pub async fn collect_stream_to_json_with_limit<S, E>(
+    mut stream: S,
+    max_bytes: usize,
+) -> Result<OpenAIResponse, String>
+where
+    S: futures::Stream<Item = Result<Bytes, E>> + Unpin,
+    E: std::fmt::Display,
+{
+    let mut state = CollectorState::new();
+    let mut line_buffer: Vec<u8> = Vec::new();
+    let mut budget = crate::proxy::common::body_limit::ByteBudget::new(max_bytes);
+
+    while let Some(chunk_result) = stream.next().await {
+        let chunk = chunk_result.map_err(|e| format!("Stream error: {}", e))?;
+        budget.consume(chunk.len())?;
+        line_buffer.extend_from_slice(&chunk);
+
+        while let Some(pos) = line_buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = line_buffer.drain(..=pos).collect();
+            state.apply_line(&String::from_utf8_lossy(&line))?;
+        }
+    }
+
+    // Stream ended without a trailing newline
+    if !line_buffer.is_empty() {
+        state.apply_line(&String::from_utf8_lossy(&line_buffer))?;
+    }
+
+    state.finish()
+}
+
+/// Replays a captured `.sse` file through the collector.
+/// Used to reproduce collector bugs deterministically from a saved upstream stream.
+/// The file is fed line by line, so each SSE line arrives as its own chunk.
+pub async fn collect_from_file(path: &str) -> Result<OpenAIResponse, String> {
+    let validated = crate::utils::path::validate_path(path, None)?;
+    let data = tokio::fs::read(&validated)
+        .await
+        .map_err(|e| format!("Failed to read capture {}: {}", validated.display(), e))?;
+
+    let chunks: Vec<Result<Bytes, io::Error>> = data
+        .split_inclusive(|b| *b == b'\n')
+        .map(|line| Ok(Bytes::copy_from_slice(line)))
+        .collect();
+
+    collect_stream_to_json(futures::stream::iter(chunks)).await
 }
 
 #[cfg(test)]
@@ -179,6 +349,64 @@ mod tests {
     use futures::stream;
     use serde_json::json;
 
+    const CAPTURE_FIXTURE: &str = concat!(
+        "data: {\"id\":\"chatcmpl-replay\",\"model\":\"gpt-4\",\"created\":1700000000,\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"finish_reason\":null}]}\n",
+        "\n",
+        "data: {\"id\":\"chatcmpl-replay\",\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\", world\"},\"finish_reason\":null}]}\n",
+        "\n",
+        "data: {\"id\":\"chatcmpl-replay\",\"model\":\"gpt-4\",\"choices\":[{\"index\":0,\"delta\":{},\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":3,\"total_tokens\":8}}\n",
+        "\n",
+        "data: [DONE]\n",
+        "\n",
+    );
+
+    #[tokio::test]
+    async fn test_collect_from_file_replays_capture() {
+        let tmp = tempfile::tempdir().unwrap();
+        let capture = tmp.path().join("capture.sse");
+        std::fs::write(&capture, CAPTURE_FIXTURE).unwrap();
+
+        let result = collect_from_file(capture.to_str().unwrap())
+            .await
+            .expect("Failed to replay capture");
+
+        assert_eq!(result.id, "chatcmpl-replay");
+        assert_eq!(result.model, "gpt-4");
+        assert_eq!(result.created, 1700000000);
+        assert_eq!(result.choices.len(), 1);
+        assert_eq!(
+            result.choices[0].message.content,
+            Some(OpenAIContent::String("Hello, world".to_string()))
+        );
+        assert_eq!(result.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(result.usage.as_ref().map(|u| u.total_tokens), Some(8));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_deltas_collected() {
+        let events = [
+            json!({"id": "chatcmpl-r", "model": "glm-4.6", "choices": [{"index": 0, "delta": {"role": "assistant", "reasoning_content": "Check the "}}]}),
+            json!({"id": "chatcmpl-r", "model": "glm-4.6", "choices": [{"index": 0, "delta": {"reasoning_content": "labels first."}}]}),
+            json!({"id": "chatcmpl-r", "model": "glm-4.6", "choices": [{"index": 0, "delta": {"content": "Add the router label."}, "finish_reason": "stop"}]}),
+        ];
+        let chunks: Vec<Result<Bytes, String>> = events
+            .iter()
+            .map(|e| Ok(Bytes::from(format!("data: {}\n\n", e))))
+            .collect();
+
+        let result = collect_stream_to_json(stream::iter(chunks)).await.unwrap();
+        let message = &result.choices[0].message;
+        assert_eq!(message.reasoning_content.as_deref(), Some("Check the labels first."));
+        assert_eq!(message.content, Some(OpenAIContent::String("Add the router label.".to_string())));
+    }
+
+    #[tokio::test]
+    async fn test_collect_from_file_missing_capture() {
+        let tmp = tempfile::tempdir().unwrap();
+        let missing = tmp.path().join("missing.sse");
+        assert!(collect_from_file(missing.to_str().unwrap()).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_collect_stream_with_tool_calls() {
         let chunk1 = json!({
@@ -284,4 +512,171 @@ mod tests {
         assert_eq!(tools[1].function.name, "get_time");
         assert_eq!(tools[1].function.arguments, "{}");
     }
+
+    #[tokio::test]
+    async fn test_event_split_across_chunks() {
+        let event = json!({
+            "id": "chatcmpl-split",
+            "model": "gpt-4",
+            "choices": [{
+                "index": 0,
+                "delta": {
+                    "role": "assistant",
+                    "tool_calls": [{
+                        "index": 0,
+                        "id": "call_split",
+                        "type": "function",
+                        "function": {
+                            "name": "get_weather",
+                            "arguments": "{\"location\": \"Zürich\"}"
+                        }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]
+        });
+        let bytes = format!("data: {}\n\ndata: [DONE]\n\n", event).into_bytes();
+
+        // Cut mid-object, including inside the multi-byte 'ü'
+        let umlaut = bytes.iter().position(|b| *b == 0xC3).unwrap();
+        let cuts = [20, umlaut + 1, bytes.len()];
+        let mut chunks = Vec::new();
+        let mut start = 0;
+        for end in cuts {
+            chunks.push(Ok::<Bytes, String>(Bytes::copy_from_slice(&bytes[start..end])));
+            start = end;
+        }
+        assert_eq!(chunks.len(), 3);
+
+        let result = collect_stream_to_json(stream::iter(chunks)).await.expect("Failed to collect");
+
+        assert_eq!(result.id, "chatcmpl-split");
+        let tools = result.choices[0].message.tool_calls.as_ref().expect("Tool calls should be present");
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].id, "call_split");
+        assert_eq!(tools[0].function.name, "get_weather");
+        assert_eq!(tools[0].function.arguments, "{\"location\": \"Zürich\"}");
+    }
+
+    #[tokio::test]
+    async fn test_collect_multiple_choices() {
+        let events = [
+            json!({"id": "chatcmpl-n2", "choices": [
+                {"index": 0, "delta": {"role": "assistant", "content": "Hello"}, "finish_reason": null},
+                {"index": 1, "delta": {"role": "assistant", "content": "Hi"}, "finish_reason": null}
+            ]}),
+            // Choices may arrive separately and out of order
+            json!({"id": "chatcmpl-n2", "choices": [
+                {"index": 1, "delta": {"tool_calls": [{"index": 0, "id": "call_1", "type": "function", "function": {"name": "lookup", "arguments": "{}"}}]}, "finish_reason": null}
+            ]}),
+            json!({"id": "chatcmpl-n2", "choices": [
+                {"index": 0, "delta": {"content": " there"}, "finish_reason": "length"}
+            ]}),
+            json!({"id": "chatcmpl-n2", "choices": [
+                {"index": 1, "delta": {}, "finish_reason": "tool_calls"}
+            ]}),
+        ];
+        let chunks: Vec<Result<Bytes, String>> = events
+            .iter()
+            .map(|e| Ok(Bytes::from(format!("data: {}\n\n", e))))
+            .collect();
+
+        let result = collect_stream_to_json(stream::iter(chunks)).await.expect("Failed to collect");
+
+        assert_eq!(result.choices.len(), 2);
+        assert_eq!(result.choices[0].index, 0);
+        assert_eq!(
+            result.choices[0].message.content,
+            Some(OpenAIContent::String("Hello there".to_string()))
+        );
+        assert_eq!(result.choices[0].finish_reason.as_deref(), Some("length"));
+        assert!(result.choices[0].message.tool_calls.is_none());
+
+        assert_eq!(result.choices[1].index, 1);
+        assert_eq!(
+            result.choices[1].message.content,
+            Some(OpenAIContent::String("Hi".to_string()))
+        );
+        assert_eq!(result.choices[1].finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(result.choices[1].message.tool_calls.as_ref().unwrap()[0].id, "call_1");
+    }
+
+    #[tokio::test]
+    async fn test_usage_accumulated_across_chunks() {
+        let events = [
+            // Prompt usage arrives early, completion usage only with the final chunk
+            json!({"id": "chatcmpl-usage", "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Hi"}, "finish_reason": null}],
+                   "usage": {"prompt_tokens": 12, "prompt_tokens_details": {"cached_tokens": 4}}}),
+            json!({"id": "chatcmpl-usage", "choices": [{"index": 0, "delta": {"content": "!"}, "finish_reason": null}],
+                   "usage": {"prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15}}),
+            json!({"id": "chatcmpl-usage", "choices": [{"index": 0, "delta": {}, "finish_reason": "stop"}],
+                   "usage": {"prompt_tokens": 12, "completion_tokens": 7, "total_tokens": 19,
+                             "completion_tokens_details": {"reasoning_tokens": 2}}}),
+            // Trailing chunk without usage must not reset the totals
+            json!({"id": "chatcmpl-usage", "choices": [], "usage": null}),
+        ];
+        let chunks: Vec<Result<Bytes, String>> = events
+            .iter()
+            .map(|e| Ok(Bytes::from(format!("data: {}\n\n", e))))
+            .chain(std::iter::once(Ok(Bytes::from("data: [DONE]\n\n"))))
+            .collect();
+
+        let result = collect_stream_to_json(stream::iter(chunks)).await.expect("Failed to collect");
+
+        let usage = result.usage.expect("usage should be collected");
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 7);
+        assert_eq!(usage.total_tokens, 19);
+        assert_eq!(usage.prompt_tokens_details.and_then(|d| d.cached_tokens), Some(4));
+        assert_eq!(usage.completion_tokens_details.and_then(|d| d.reasoning_tokens), Some(2));
+    }
+
+    #[test]
+    fn test_partial_usage_derives_total() {
+        let mut usage = None;
+        merge_usage(&mut usage, &json!({"prompt_tokens": 10}));
+        merge_usage(&mut usage, &json!({"completion_tokens": 5}));
+        merge_usage(&mut usage, &json!({"unrelated": true}));
+
+        let usage = usage.unwrap();
+        assert_eq!((usage.prompt_tokens, usage.completion_tokens, usage.total_tokens), (10, 5, 15));
+    }
+
+    #[tokio::test]
+    async fn test_error_event_aborts_collection() {
+        let content = json!({
+            "id": "chatcmpl-err",
+            "choices": [{"index": 0, "delta": {"role": "assistant", "content": "Partial"}, "finish_reason": null}]
+        });
+        let error = json!({
+            "error": {"message": "The server had an error while processing your request", "type": "server_error", "code": "internal_error"}
+        });
+        let chunks = vec![
+            Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", content))),
+            Ok::<Bytes, String>(Bytes::from(format!("data: {}\n\n", error))),
+            Ok::<Bytes, String>(Bytes::from("data: [DONE]\n\n")),
+        ];
+
+        let err = collect_stream_to_json(stream::iter(chunks))
+            .await
+            .expect_err("Error event must not produce a truncated success");
+        assert!(err.contains("The server had an error while processing your request"));
+        assert!(err.contains("internal_error"));
+    }
+
+    #[tokio::test]
+    async fn test_collect_aborts_when_response_exceeds_limit() {
+        let content = json!({
+            "id": "chatcmpl-big",
+            "choices": [{"index": 0, "delta": {"content": "x".repeat(512)}, "finish_reason": null}]
+        });
+        let chunks: Vec<Result<Bytes, String>> = (0..10)
+            .map(|_| Ok(Bytes::from(format!("data: {}\n\n", content))))
+            .collect();
+
+        let err = collect_stream_to_json_with_limit(stream::iter(chunks), 2048)
+            .await
+            .expect_err("Oversized response must not be collected");
+        assert!(err.contains("max_response_bytes"));
+    }
 }
@@ -9,6 +9,8 @@ use axum::{
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
+use super::auto_ban;
+use super::ip_filter::extract_client_ip;
 use crate::proxy::{ProxyAuthMode, ProxySecurityConfig};
 
 /// Constant-time string comparison to prevent timing side-channel attacks.
@@ -19,6 +21,25 @@ fn constant_time_eq(a: &str, b: &str) -> bool {
     bool::from(a.as_bytes().ct_eq(b.as_bytes()))
 }
 
+/// 校验 api_key: 当前密钥，或宽限期内的轮换前密钥
+fn api_key_matches(security: &ProxySecurityConfig, key: &str, now: i64) -> bool {
+    if constant_time_eq(key, &security.api_key) {
+        return true;
+    }
+    security
+        .retired_api_key
+        .as_ref()
+        .is_some_and(|retired| retired.is_active(now) && constant_time_eq(key, &retired.key))
+}
+
+/// 校验管理接口凭据: 已设置管理密码时对比其哈希，否则回退使用 api_key
+async fn admin_key_matches(security: &ProxySecurityConfig, key: &str, now: i64) -> bool {
+    match &security.admin_password_hash {
+        Some(hash) => crate::proxy::security::verify_admin_password_async(key, hash).await,
+        None => api_key_matches(security, key, now),
+    }
+}
+
 /// API Key 认证中间件 (代理接口使用，遵循 auth_mode)
 pub async fn auth_middleware(
     state: State<Arc<RwLock<ProxySecurityConfig>>>,
@@ -119,7 +140,7 @@ async fn auth_middleware_internal(
                 .and_then(|h| h.to_str().ok())
         });
 
-    if security.api_key.is_empty() && (security.admin_password.is_none() || security.admin_password.as_ref().unwrap().is_empty()) {
+    if security.api_key.is_empty() && security.admin_password_hash.is_none() {
         if force_strict {
              tracing::error!("Admin auth is required but both api_key and admin_password are empty; denying request");
              return Err(StatusCode::UNAUTHORIZED);
@@ -129,25 +150,30 @@ async fn auth_middleware_internal(
     }
 
     // 认证逻辑
+    let now = chrono::Utc::now().timestamp();
     let authorized = if force_strict {
-        // 管理接口：优先使用独立的 admin_password，如果没有则回退使用 api_key
-        match &security.admin_password {
-            Some(pwd) if !pwd.is_empty() => {
-                api_key.map(|k| constant_time_eq(k, pwd)).unwrap_or(false)
-            }
-            _ => {
-                // 回退使用 api_key
-                api_key.map(|k| constant_time_eq(k, &security.api_key)).unwrap_or(false)
-            }
+        // 管理接口：优先使用独立的管理密码 (哈希校验)，如果没有则回退使用 api_key
+        match api_key {
+            Some(k) => admin_key_matches(&security, k, now).await,
+            None => false,
         }
     } else {
         // AI 代理接口：仅允许使用 api_key
-        api_key.map(|k| constant_time_eq(k, &security.api_key)).unwrap_or(false)
+        api_key.map(|k| api_key_matches(&security, k, now)).unwrap_or(false)
     };
 
+    // 按客户端 IP 统计认证失败，达到阈值后自动封禁
+    let client_ip = extract_client_ip(&request, &security.security_monitor.trusted_proxies);
+
     if authorized {
+        if let Some(ip) = &client_ip {
+            auto_ban::on_auth_success(ip);
+        }
         Ok(next.run(request).await)
     } else {
+        if let Some(ip) = &client_ip {
+            auto_ban::on_auth_failure(&security.security_monitor.blacklist, ip);
+        }
         Err(StatusCode::UNAUTHORIZED)
     }
 }
@@ -162,7 +188,8 @@ mod tests {
         let security = Arc::new(RwLock::new(ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Strict,
             api_key: "sk-api".to_string(),
-            admin_password: Some("admin123".to_string()),
+            retired_api_key: None,
+            admin_password_hash: Some(crate::proxy::security::hash_admin_password("admin123")),
             allow_lan_access: true,
             port: 8045,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
@@ -179,6 +206,50 @@ mod tests {
         // 我们在 auth_middleware_internal 基础上做了逻辑校验即可
     }
 
+    #[tokio::test]
+    async fn test_admin_password_checked_against_hash() {
+        let mut security = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Strict,
+            api_key: "sk-api".to_string(),
+            retired_api_key: None,
+            admin_password_hash: Some(crate::proxy::security::hash_admin_password("admin123")),
+            allow_lan_access: true,
+            port: 8045,
+            security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+        };
+
+        assert!(admin_key_matches(&security, "admin123", 0).await);
+        assert!(!admin_key_matches(&security, "wrong-password", 0).await);
+        // 设置了管理密码后，api_key 不能用于管理接口
+        assert!(!admin_key_matches(&security, "sk-api", 0).await);
+
+        // 未设置管理密码时回退使用 api_key
+        security.admin_password_hash = None;
+        assert!(admin_key_matches(&security, "sk-api", 0).await);
+        assert!(!admin_key_matches(&security, "admin123", 0).await);
+    }
+
+    #[test]
+    fn test_retired_api_key_accepted_until_expiry() {
+        let security = ProxySecurityConfig {
+            auth_mode: ProxyAuthMode::Strict,
+            api_key: "sk-new".to_string(),
+            retired_api_key: Some(crate::proxy::config::RetiredApiKey {
+                key: "sk-old".to_string(),
+                expires_at: 1_300,
+            }),
+            admin_password_hash: None,
+            allow_lan_access: true,
+            port: 8045,
+            security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
+        };
+
+        assert!(api_key_matches(&security, "sk-new", 1_000));
+        assert!(api_key_matches(&security, "sk-old", 1_299));
+        assert!(!api_key_matches(&security, "sk-old", 1_300));
+        assert!(!api_key_matches(&security, "sk-other", 1_000));
+    }
+
     #[test]
     fn test_auth_placeholder() {
         assert!(true);
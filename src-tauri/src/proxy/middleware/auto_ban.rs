@@ -0,0 +1,129 @@
+// 认证失败自动封禁 (防暴力破解)
+// 按客户端 IP 统计滑动窗口内的认证失败次数，达到阈值后写入黑名单 (带过期时间)
+
+use dashmap::DashMap;
+use once_cell::sync::Lazy;
+use std::collections::VecDeque;
+
+use crate::modules::security_db;
+use crate::proxy::config::IpBlacklistConfig;
+
+/// 超过该数量的跟踪 IP 时清理窗口外的记录，避免内存无限增长
+const MAX_TRACKED_IPS: usize = 10_000;
+
+/// 每个 IP 的认证失败时间戳 (Unix 秒)
+#[derive(Default)]
+pub struct AuthFailureTracker {
+    failures: DashMap<String, VecDeque<i64>>,
+}
+
+impl AuthFailureTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次认证失败。窗口内失败次数达到阈值时返回 true 并重置该 IP 的计数
+    pub fn record_failure(&self, ip: &str, now: i64, window_secs: u64, threshold: u32) -> bool {
+        if threshold == 0 {
+            return false;
+        }
+        let window_start = now - window_secs as i64;
+
+        if self.failures.len() > MAX_TRACKED_IPS {
+            self.failures
+                .retain(|_, times| times.back().is_some_and(|t| *t > window_start));
+        }
+
+        let mut times = self.failures.entry(ip.to_string()).or_default();
+        while times.front().is_some_and(|t| *t <= window_start) {
+            times.pop_front();
+        }
+        times.push_back(now);
+
+        if times.len() >= threshold as usize {
+            drop(times);
+            self.failures.remove(ip);
+            return true;
+        }
+        false
+    }
+
+    /// 认证成功后清除该 IP 的失败记录
+    pub fn clear(&self, ip: &str) {
+        self.failures.remove(ip);
+    }
+}
+
+static AUTH_FAILURES: Lazy<AuthFailureTracker> = Lazy::new(AuthFailureTracker::new);
+
+/// 记录认证失败，达到阈值时自动加入黑名单。仅在黑名单启用时生效
+pub fn on_auth_failure(config: &IpBlacklistConfig, ip: &str) {
+    if !config.enabled || config.auto_ban_threshold == 0 {
+        return;
+    }
+
+    let now = chrono::Utc::now().timestamp();
+    if !AUTH_FAILURES.record_failure(ip, now, config.auto_ban_window_secs, config.auto_ban_threshold) {
+        return;
+    }
+
+    let expires_at = now + config.auto_ban_duration_secs as i64;
+    let reason = format!(
+        "Auto-banned: {} failed authentication attempts within {}s",
+        config.auto_ban_threshold, config.auto_ban_window_secs
+    );
+    match security_db::add_to_blacklist(ip, Some(&reason), Some(expires_at), security_db::AUTO_BAN_CREATOR) {
+        Ok(_) => tracing::warn!(
+            "[AutoBan] IP {} banned for {}s after {} failed authentication attempts",
+            ip,
+            config.auto_ban_duration_secs,
+            config.auto_ban_threshold
+        ),
+        Err(e) => tracing::error!("[AutoBan] Failed to ban IP {}: {}", ip, e),
+    }
+}
+
+/// 认证成功，重置该 IP 的失败计数
+pub fn on_auth_success(ip: &str) {
+    AUTH_FAILURES.clear(ip);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_within_window_triggers_ban() {
+        let tracker = AuthFailureTracker::new();
+        assert!(!tracker.record_failure("10.0.0.1", 100, 60, 3));
+        assert!(!tracker.record_failure("10.0.0.1", 110, 60, 3));
+        assert!(tracker.record_failure("10.0.0.1", 120, 60, 3));
+
+        // 触发后计数重置
+        assert!(!tracker.record_failure("10.0.0.1", 121, 60, 3));
+    }
+
+    #[test]
+    fn test_failures_outside_window_expire() {
+        let tracker = AuthFailureTracker::new();
+        assert!(!tracker.record_failure("10.0.0.1", 0, 60, 3));
+        assert!(!tracker.record_failure("10.0.0.1", 30, 60, 3));
+        // 第一次失败已滑出窗口
+        assert!(!tracker.record_failure("10.0.0.1", 61, 60, 3));
+        assert!(tracker.record_failure("10.0.0.1", 62, 60, 3));
+    }
+
+    #[test]
+    fn test_ips_tracked_independently_and_cleared_on_success() {
+        let tracker = AuthFailureTracker::new();
+        assert!(!tracker.record_failure("10.0.0.1", 0, 60, 2));
+        assert!(!tracker.record_failure("10.0.0.2", 1, 60, 2));
+
+        tracker.clear("10.0.0.1");
+        assert!(!tracker.record_failure("10.0.0.1", 2, 60, 2));
+        assert!(tracker.record_failure("10.0.0.2", 3, 60, 2));
+
+        // 阈值为 0 表示关闭
+        assert!(!tracker.record_failure("10.0.0.3", 0, 60, 0));
+    }
+}
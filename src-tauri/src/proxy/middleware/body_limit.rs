@@ -0,0 +1,50 @@
+// 请求体大小限制中间件
+// 优先按 Content-Length 拒绝；分块传输的请求在读取时计数，超出上限返回 413
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::proxy::common::body_limit;
+
+pub async fn body_limit_middleware(request: Request, next: Next) -> Response {
+    let limit = body_limit::max_request_bytes();
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(len) = declared {
+        if len > limit as u64 {
+            return payload_too_large(limit);
+        }
+        return next.run(request).await;
+    }
+
+    // 无请求体的方法 (含 WebSocket 升级) 直接放行
+    if matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+
+    let (parts, body) = request.into_parts();
+    match to_bytes(body, limit).await {
+        Ok(bytes) => next.run(Request::from_parts(parts, Body::from(bytes))).await,
+        Err(e) => {
+            tracing::warn!("[BodyLimit] Rejected chunked request body: {}", e);
+            payload_too_large(limit)
+        }
+    }
+}
+
+fn payload_too_large(limit: usize) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        format!("Request body exceeds max_request_bytes ({} bytes)", limit),
+    )
+        .into_response()
+}
@@ -1,11 +1,72 @@
 // CORS 中间件
-use tower_http::cors::{CorsLayer, Any};
-use axum::http::Method;
+// 允许的来源来自 ProxyConfig.cors_allowed_origins，启动及热更新时写入全局状态
+use axum::http::{HeaderValue, Method};
+use once_cell::sync::Lazy;
+use std::sync::RwLock;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 
-/// 创建 CORS layer
+/// 允许的来源 (已规范化为 `scheme://host[:port]`，"*" 表示任意来源)
+static ALLOWED_ORIGINS: Lazy<RwLock<Vec<String>>> =
+    Lazy::new(|| RwLock::new(vec!["*".to_string()]));
+
+/// 应用配置中的 CORS 来源 (启动及热更新时调用)，无效条目会被忽略
+pub fn apply_config(config: &crate::proxy::config::ProxyConfig) {
+    let origins = config
+        .cors_allowed_origins
+        .iter()
+        .filter_map(|origin| normalize_origin(origin).ok())
+        .collect();
+    *ALLOWED_ORIGINS.write().unwrap() = origins;
+}
+
+/// 校验来源格式: "*" 或不带路径的 http(s)://host[:port]
+pub fn validate_origin(origin: &str) -> Result<(), String> {
+    normalize_origin(origin).map(|_| ())
+}
+
+fn normalize_origin(origin: &str) -> Result<String, String> {
+    let trimmed = origin.trim();
+    if trimmed == "*" {
+        return Ok(trimmed.to_string());
+    }
+
+    let invalid = |reason: &str| format!("Invalid CORS origin '{}': {}", origin, reason);
+    let url = url::Url::parse(trimmed).map_err(|e| invalid(&e.to_string()))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("scheme must be http or https"));
+    }
+    if url.host_str().is_none() {
+        return Err(invalid("missing host"));
+    }
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err(invalid("must not contain credentials"));
+    }
+    if url.path() != "/" || url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("must not contain a path, query or fragment"));
+    }
+    Ok(url.origin().ascii_serialization())
+}
+
+/// 请求的 Origin 是否在允许列表中 (空列表 = 仅同源，浏览器同源请求无需 CORS 头)
+fn origin_allowed(allowed: &[String], origin: &HeaderValue) -> bool {
+    let Ok(origin) = origin.to_str() else {
+        return false;
+    };
+    allowed
+        .iter()
+        .any(|entry| entry == "*" || entry.eq_ignore_ascii_case(origin))
+}
+
+/// 创建 CORS layer (按当前配置的来源判断)
 pub fn cors_layer() -> CorsLayer {
+    build_cors_layer(AllowOrigin::predicate(|origin, _| {
+        origin_allowed(&ALLOWED_ORIGINS.read().unwrap(), origin)
+    }))
+}
+
+fn build_cors_layer(allow_origin: AllowOrigin) -> CorsLayer {
     CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(allow_origin)
         .allow_methods([
             Method::GET,
             Method::POST,
@@ -23,6 +84,8 @@ pub fn cors_layer() -> CorsLayer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
 
     #[test]
     fn test_cors_layer_creation() {
@@ -30,4 +93,58 @@ mod tests {
         // Layer 创建成功
         assert!(true);
     }
+
+    #[test]
+    fn test_validate_origin() {
+        assert!(validate_origin("*").is_ok());
+        assert!(validate_origin("https://app.example.com").is_ok());
+        assert!(validate_origin("http://localhost:5173/").is_ok());
+        assert!(validate_origin("app.example.com").is_err());
+        assert!(validate_origin("ftp://example.com").is_err());
+        assert!(validate_origin("https://example.com/path").is_err());
+        assert!(validate_origin("https://user:pw@example.com").is_err());
+        assert_eq!(
+            normalize_origin("HTTPS://App.Example.com:443/").unwrap(),
+            "https://app.example.com"
+        );
+    }
+
+    async fn allow_origin_header(allowed: &[&str], origin: &str) -> Option<String> {
+        let allowed: Vec<String> = allowed.iter().map(|o| normalize_origin(o).unwrap()).collect();
+        let app = Router::new()
+            .route("/v1/models", get(|| async { "ok" }))
+            .layer(build_cors_layer(AllowOrigin::predicate(move |origin, _| {
+                origin_allowed(&allowed, origin)
+            })));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/v1/models")
+                    .header("Origin", origin)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        response
+            .headers()
+            .get("access-control-allow-origin")
+            .map(|v| v.to_str().unwrap().to_string())
+    }
+
+    #[tokio::test]
+    async fn test_allow_origin_header_follows_config() {
+        let allowed = ["https://app.example.com"];
+        assert_eq!(
+            allow_origin_header(&allowed, "https://app.example.com").await.as_deref(),
+            Some("https://app.example.com")
+        );
+        assert_eq!(allow_origin_header(&allowed, "https://evil.example.com").await, None);
+
+        // Empty list: same-origin only, no CORS header for any cross-origin caller
+        assert_eq!(allow_origin_header(&[], "https://app.example.com").await, None);
+        // "*": every origin is allowed
+        assert!(allow_origin_header(&["*"], "https://anything.test").await.is_some());
+    }
 }
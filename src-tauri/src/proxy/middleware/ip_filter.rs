@@ -5,6 +5,7 @@ use axum::{
     http::StatusCode,
     body::Body,
 };
+use crate::proxy::config::SecurityMonitorConfig;
 use crate::proxy::server::AppState;
 use crate::modules::security_db;
 
@@ -14,159 +15,208 @@ pub async fn ip_filter_middleware(
     request: Request,
     next: Next,
 ) -> Response {
+    // 读取安全配置
+    let security_config = state.security.read().await.clone();
+
     // 提取客户端 IP
-    let client_ip = extract_client_ip(&request);
+    let client_ip = extract_client_ip(&request, &security_config.security_monitor.trusted_proxies);
     
     if let Some(ip) = &client_ip {
-        // 读取安全配置
-        let security_config = state.security.read().await;
-        
-        // 1. 检查白名单 (如果启用白名单模式,只允许白名单 IP)
-        if security_config.security_monitor.whitelist.enabled {
-            match security_db::is_ip_in_whitelist(ip) {
-                Ok(true) => {
-                    // 在白名单中,直接放行
-                    tracing::debug!("[IP Filter] IP {} is in whitelist, allowing", ip);
-                    return next.run(request).await;
-                }
-                Ok(false) => {
-                    // 不在白名单中,且启用了白名单模式,拒绝访问
-                    tracing::warn!("[IP Filter] IP {} not in whitelist, blocking", ip);
-                    return create_blocked_response(
-                        ip,
-                        "Access denied. Your IP is not in the whitelist.",
-                    );
-                }
-                Err(e) => {
-                    tracing::error!("[IP Filter] Failed to check whitelist: {}", e);
-                }
+        let verdict = evaluate_ip(
+            &security_config.security_monitor,
+            ip,
+            security_db::is_ip_in_whitelist,
+            security_db::get_blacklist_entry_for_ip,
+        );
+
+        match verdict {
+            IpVerdict::Allow => {
+                tracing::debug!("[IP Filter] IP {} allowed", ip);
             }
-        } else {
-            // 白名单优先模式: 如果在白名单中,跳过黑名单检查
-            if security_config.security_monitor.whitelist.whitelist_priority {
-                match security_db::is_ip_in_whitelist(ip) {
-                    Ok(true) => {
-                        tracing::debug!("[IP Filter] IP {} is in whitelist (priority mode), skipping blacklist check", ip);
-                        return next.run(request).await;
-                    }
-                    Ok(false) => {
-                        // 继续检查黑名单
-                    }
-                    Err(e) => {
-                        tracing::error!("[IP Filter] Failed to check whitelist: {}", e);
-                    }
-                }
+            IpVerdict::NotWhitelisted => {
+                // 不在白名单中,且启用了白名单模式,拒绝访问
+                tracing::warn!("[IP Filter] IP {} not in whitelist, blocking", ip);
+                return create_blocked_response(
+                    ip,
+                    "Access denied. Your IP is not in the whitelist.",
+                );
             }
-        }
+            IpVerdict::Blacklisted(entry) => {
+                tracing::warn!("[IP Filter] IP {} is in blacklist, blocking", ip);
+
+                // 构建详细的封禁消息
+                let reason = entry.reason.as_deref().unwrap_or("Malicious activity detected");
+                let ban_type = if let Some(expires_at) = entry.expires_at {
+                    let now = chrono::Utc::now().timestamp();
+                    let remaining_seconds = expires_at - now;
 
-        // 2. 检查黑名单
-        if security_config.security_monitor.blacklist.enabled {
-            match security_db::get_blacklist_entry_for_ip(ip) {
-                Ok(Some(entry)) => {
-                    tracing::warn!("[IP Filter] IP {} is in blacklist, blocking", ip);
-                    
-                    // 构建详细的封禁消息
-                    let reason = entry.reason.as_deref().unwrap_or("Malicious activity detected");
-                    let ban_type = if let Some(expires_at) = entry.expires_at {
-                        let now = chrono::Utc::now().timestamp();
-                        let remaining_seconds = expires_at - now;
-                        
-                        if remaining_seconds > 0 {
-                            let hours = remaining_seconds / 3600;
-                            let minutes = (remaining_seconds % 3600) / 60;
-                            
-                            if hours > 24 {
-                                let days = hours / 24;
-                                format!("Temporary ban. Please try again after {} day(s).", days)
-                            } else if hours > 0 {
-                                format!("Temporary ban. Please try again after {} hour(s) and {} minute(s).", hours, minutes)
-                            } else {
-                                format!("Temporary ban. Please try again after {} minute(s).", minutes)
-                            }
+                    if remaining_seconds > 0 {
+                        let hours = remaining_seconds / 3600;
+                        let minutes = (remaining_seconds % 3600) / 60;
+
+                        if hours > 24 {
+                            let days = hours / 24;
+                            format!("Temporary ban. Please try again after {} day(s).", days)
+                        } else if hours > 0 {
+                            format!("Temporary ban. Please try again after {} hour(s) and {} minute(s).", hours, minutes)
                         } else {
-                            "Temporary ban (expired, will be removed soon).".to_string()
+                            format!("Temporary ban. Please try again after {} minute(s).", minutes)
                         }
                     } else {
-                        "Permanent ban.".to_string()
-                    };
-                    
-                    let detailed_message = format!(
-                        "Access denied. Reason: {}. {}",
-                        reason,
-                        ban_type
-                    );
-                    
-                    // 记录被封禁的访问日志
-                    let log = security_db::IpAccessLog {
-                        id: uuid::Uuid::new_v4().to_string(),
-                        client_ip: ip.clone(),
-                        timestamp: chrono::Utc::now().timestamp(),
-                        method: Some(request.method().to_string()),
-                        path: Some(request.uri().to_string()),
-                        user_agent: request
-                            .headers()
-                            .get("user-agent")
-                            .and_then(|v| v.to_str().ok())
-                            .map(|s| s.to_string()),
-                        status: Some(403),
-                        duration: Some(0),
-                        api_key_hash: None,
-                        blocked: true,
-                        block_reason: Some(format!("IP in blacklist: {}", reason)),
-                    };
-                    
-                    tokio::spawn(async move {
-                        if let Err(e) = security_db::save_ip_access_log(&log) {
-                            tracing::error!("[IP Filter] Failed to save blocked access log: {}", e);
-                        }
-                    });
-                    
-                    return create_blocked_response(
-                        ip,
-                        &detailed_message,
-                    );
-                }
-                Ok(None) => {
-                    // 不在黑名单中,放行
-                    tracing::debug!("[IP Filter] IP {} not in blacklist, allowing", ip);
-                }
-                Err(e) => {
-                    tracing::error!("[IP Filter] Failed to check blacklist: {}", e);
-                }
+                        "Temporary ban (expired, will be removed soon).".to_string()
+                    }
+                } else {
+                    "Permanent ban.".to_string()
+                };
+
+                let detailed_message = format!(
+                    "{}. Reason: {}. {}",
+                    security_config.security_monitor.blacklist.block_message.trim_end_matches('.'),
+                    reason,
+                    ban_type
+                );
+
+                // 记录被封禁的访问日志
+                let log = security_db::IpAccessLog {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    client_ip: ip.clone(),
+                    timestamp: chrono::Utc::now().timestamp(),
+                    method: Some(request.method().to_string()),
+                    path: Some(request.uri().to_string()),
+                    user_agent: request
+                        .headers()
+                        .get("user-agent")
+                        .and_then(|v| v.to_str().ok())
+                        .map(|s| s.to_string()),
+                    status: Some(403),
+                    duration: Some(0),
+                    api_key_hash: None,
+                    blocked: true,
+                    block_reason: Some(format!("IP in blacklist: {}", reason)),
+                };
+
+                tokio::spawn(async move {
+                    if let Err(e) = security_db::save_ip_access_log(&log) {
+                        tracing::error!("[IP Filter] Failed to save blocked access log: {}", e);
+                    }
+                });
+
+                return create_blocked_response(
+                    ip,
+                    &detailed_message,
+                );
             }
         }
     } else {
         tracing::warn!("[IP Filter] Unable to extract client IP from request");
     }
 
-    // 放行请求
-    next.run(request).await
+    // 放行请求 (记录客户端 IP 供 PerIp UA 轮换使用)
+    match client_ip {
+        Some(ip) => {
+            crate::proxy::upstream::client::CLIENT_IP
+                .scope(ip, next.run(request))
+                .await
+        }
+        None => next.run(request).await,
+    }
+}
+
+/// 黑白名单判定结果
+#[derive(Debug)]
+enum IpVerdict {
+    Allow,
+    /// 白名单模式下不在白名单中
+    NotWhitelisted,
+    /// 命中黑名单条目
+    Blacklisted(security_db::IpBlacklistEntry),
+}
+
+/// 判定客户端 IP 是否放行。优先级 (从高到低):
+/// 1. 白名单模式 (`whitelist.enabled`): 不在白名单中的 IP 一律拒绝
+/// 2. 白名单优先 (`whitelist_priority`): 白名单中的 IP 直接放行，完全跳过黑名单检查
+/// 3. 黑名单 (`blacklist.enabled`): 命中则拒绝 (未开启白名单优先时，白名单 IP 同样会被拦截)
+/// 4. 其余放行
+/// 名单查询出错时记录日志并放行 (fail open)，避免数据库故障导致服务整体不可用
+fn evaluate_ip<W, B>(
+    config: &SecurityMonitorConfig,
+    ip: &str,
+    in_whitelist: W,
+    blacklist_entry: B,
+) -> IpVerdict
+where
+    W: Fn(&str) -> Result<bool, String>,
+    B: Fn(&str) -> Result<Option<security_db::IpBlacklistEntry>, String>,
+{
+    let whitelist = &config.whitelist;
+    let needs_whitelist = whitelist.enabled || (whitelist.whitelist_priority && config.blacklist.enabled);
+
+    let whitelisted = if needs_whitelist {
+        match in_whitelist(ip) {
+            Ok(found) => Some(found),
+            Err(e) => {
+                tracing::error!("[IP Filter] Failed to check whitelist: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // 1. 白名单模式
+    if whitelist.enabled && whitelisted == Some(false) {
+        return IpVerdict::NotWhitelisted;
+    }
+
+    // 2. 白名单优先
+    if whitelist.whitelist_priority && whitelisted == Some(true) {
+        return IpVerdict::Allow;
+    }
+
+    // 3. 黑名单
+    if config.blacklist.enabled {
+        match blacklist_entry(ip) {
+            Ok(Some(entry)) => return IpVerdict::Blacklisted(entry),
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("[IP Filter] Failed to check blacklist: {}", e);
+            }
+        }
+    }
+
+    IpVerdict::Allow
 }
 
 /// 从请求中提取客户端 IP
-fn extract_client_ip(request: &Request) -> Option<String> {
-    // 1. 优先从 X-Forwarded-For 提取 (取第一个 IP)
+/// 只有当 TCP 对端是受信任的反向代理时才读取 X-Forwarded-For (取第一个 IP) / X-Real-IP，
+/// 否则任何客户端都能通过伪造请求头绕过黑名单
+pub(crate) fn extract_client_ip(request: &Request, trusted_proxies: &[String]) -> Option<String> {
+    let peer = request
+        .extensions()
+        .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
+        .map(|info| info.0.ip().to_string())?;
+
+    let behind_trusted_proxy = trusted_proxies
+        .iter()
+        .any(|pattern| security_db::ip_matches_pattern(&peer, pattern));
+    if !behind_trusted_proxy {
+        return Some(peer);
+    }
+
     request
         .headers()
         .get("x-forwarded-for")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.split(',').next().unwrap_or(s).trim().to_string())
         .or_else(|| {
-            // 2. 备选从 X-Real-IP 提取
             request
                 .headers()
                 .get("x-real-ip")
                 .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string())
-        })
-        .or_else(|| {
-            // 3. 最后尝试从 ConnectInfo 获取 (TCP 连接 IP)
-            // 这可以解决本地开发/测试时没有代理头导致 IP 获取失败的问题
-            request
-                .extensions()
-                .get::<axum::extract::ConnectInfo<std::net::SocketAddr>>()
-                .map(|info| info.0.ip().to_string())
+                .map(|s| s.trim().to_string())
         })
+        .filter(|ip| !ip.is_empty())
+        .or(Some(peer))
 }
 
 /// 创建被封禁的响应
@@ -187,3 +237,120 @@ fn create_blocked_response(ip: &str, message: &str) -> Response {
     )
         .into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request_from(peer: &str, forwarded_for: Option<&str>) -> Request {
+        let mut builder = Request::builder().uri("/v1/messages");
+        if let Some(xff) = forwarded_for {
+            builder = builder.header("x-forwarded-for", xff);
+        }
+        let mut request = builder.body(Body::empty()).unwrap();
+        let addr: std::net::SocketAddr = format!("{}:40000", peer).parse().unwrap();
+        request.extensions_mut().insert(axum::extract::ConnectInfo(addr));
+        request
+    }
+
+    #[test]
+    fn test_forwarded_for_ignored_from_untrusted_peer() {
+        let trusted = vec!["127.0.0.1".to_string()];
+        let request = request_from("203.0.113.9", Some("10.0.0.1"));
+        assert_eq!(extract_client_ip(&request, &trusted).as_deref(), Some("203.0.113.9"));
+    }
+
+    #[test]
+    fn test_forwarded_for_honored_from_trusted_proxy() {
+        let trusted = vec!["127.0.0.1".to_string()];
+        let request = request_from("127.0.0.1", Some("198.51.100.7, 127.0.0.1"));
+        assert_eq!(extract_client_ip(&request, &trusted).as_deref(), Some("198.51.100.7"));
+
+        // Trusted proxy without forwarding headers: the proxy itself is the client
+        let request = request_from("127.0.0.1", None);
+        assert_eq!(extract_client_ip(&request, &trusted).as_deref(), Some("127.0.0.1"));
+
+        // Nothing trusted: the peer address always wins
+        let request = request_from("127.0.0.1", Some("198.51.100.7"));
+        assert_eq!(extract_client_ip(&request, &[]).as_deref(), Some("127.0.0.1"));
+    }
+
+    /// Evaluate a mock request from `peer` against in-memory lists
+    fn verdict_for(config: &SecurityMonitorConfig, peer: &str) -> &'static str {
+        let whitelist = ["10.0.0.0/24"];
+        let blacklist = ["10.0.0.5", "203.0.113.9"];
+
+        let request = request_from(peer, None);
+        let ip = extract_client_ip(&request, &config.trusted_proxies).unwrap();
+        let verdict = evaluate_ip(
+            config,
+            &ip,
+            |ip| Ok(whitelist.iter().any(|p| security_db::ip_matches_pattern(ip, p))),
+            |ip| {
+                Ok(blacklist
+                    .iter()
+                    .find(|p| security_db::ip_matches_pattern(ip, p))
+                    .map(|p| security_db::IpBlacklistEntry {
+                        id: "entry".to_string(),
+                        ip_pattern: p.to_string(),
+                        reason: None,
+                        created_at: 0,
+                        expires_at: None,
+                        created_by: "manual".to_string(),
+                        hit_count: 0,
+                    }))
+            },
+        );
+
+        match verdict {
+            IpVerdict::Allow => "allow",
+            IpVerdict::NotWhitelisted => "not_whitelisted",
+            IpVerdict::Blacklisted(_) => "blacklisted",
+        }
+    }
+
+    fn security_config(blacklist: bool, whitelist: bool, priority: bool) -> SecurityMonitorConfig {
+        let mut config = SecurityMonitorConfig::default();
+        config.blacklist.enabled = blacklist;
+        config.whitelist.enabled = whitelist;
+        config.whitelist.whitelist_priority = priority;
+        config
+    }
+
+    #[test]
+    fn test_filter_precedence_for_all_list_combinations() {
+        // Peers: in both lists, whitelist only, blacklist only, neither
+        let peers = ["10.0.0.5", "10.0.0.6", "203.0.113.9", "198.51.100.1"];
+        let cases = [
+            // (blacklist, whitelist) -> expected verdict per peer (whitelist_priority on)
+            ((false, false), ["allow", "allow", "allow", "allow"]),
+            ((true, false), ["allow", "allow", "blacklisted", "allow"]),
+            ((false, true), ["allow", "allow", "not_whitelisted", "not_whitelisted"]),
+            ((true, true), ["allow", "allow", "not_whitelisted", "not_whitelisted"]),
+        ];
+
+        for ((blacklist, whitelist), expected) in cases {
+            let config = security_config(blacklist, whitelist, true);
+            for (peer, want) in peers.iter().zip(expected) {
+                assert_eq!(
+                    verdict_for(&config, peer),
+                    want,
+                    "blacklist={} whitelist={} peer={}",
+                    blacklist,
+                    whitelist,
+                    peer
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_whitelisted_ip_is_still_blacklisted_without_priority() {
+        let config = security_config(true, true, false);
+        assert_eq!(verdict_for(&config, "10.0.0.5"), "blacklisted");
+        assert_eq!(verdict_for(&config, "10.0.0.6"), "allow");
+
+        let config = security_config(true, false, false);
+        assert_eq!(verdict_for(&config, "10.0.0.5"), "blacklisted");
+    }
+}
@@ -1,6 +1,8 @@
 // Middleware 模块 - Axum 中间件
 
 pub mod auth;
+pub mod auto_ban;
+pub mod body_limit;
 pub mod cors;
 pub mod logging;
 pub mod monitor;
@@ -8,6 +10,7 @@ pub mod ip_filter;
 
 pub mod service_status;
 
+pub use body_limit::body_limit_middleware;
 pub use cors::cors_layer;
 pub use monitor::monitor_middleware;
 pub use service_status::service_status_middleware;
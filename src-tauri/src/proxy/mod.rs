@@ -18,12 +18,16 @@ pub mod zai_vision_mcp;    // Built-in Vision MCP server state
 pub mod zai_vision_tools;  // Built-in Vision MCP tools (z.ai vision API)
 pub mod monitor;           // 监控
 pub mod rate_limit;        // 限流跟踪
+pub mod model_limiter;     // 按模型限流 (RPM)
 pub mod sticky_config;     // 粘性调度配置
 pub mod session_manager;   // 会话指纹管理
 pub mod audio;             // 音频处理模块
 pub mod signature_cache;   // Signature Cache (v3.3.16)
 pub mod cli_sync;          // CLI 配置同步 (v3.3.35)
 pub mod debug_logger;      // 调试日志
+pub mod request_registry;  // In-flight chat requests (cancellation by request id)
+pub mod session_hub;       // Per-session chat event broadcast
+pub mod shutdown;          // Graceful shutdown (drains chat WebSockets)
 
 
 pub use config::ProxyConfig;
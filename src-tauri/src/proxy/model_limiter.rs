@@ -0,0 +1,230 @@
+// 按模型限流 (requests per minute, token bucket)
+// 在请求转发到上游之前检查，超限时返回 429 + Retry-After
+
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use dashmap::DashMap;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// 单个模型的令牌桶
+#[derive(Debug, Clone)]
+pub struct TokenBucket {
+    /// 桶容量 (= 每分钟请求数，允许一分钟内的突发)
+    capacity: f64,
+    /// 当前令牌数
+    tokens: f64,
+    /// 每秒补充的令牌数
+    refill_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(requests_per_minute: u32, now: Instant) -> Self {
+        let capacity = requests_per_minute.max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_sec: capacity / 60.0,
+            last_refill: now,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.last_refill = now;
+    }
+
+    /// 尝试消耗一个令牌；失败时返回需要等待的时间
+    pub fn try_acquire(&mut self, now: Instant) -> Result<(), Duration> {
+        self.refill(now);
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            let missing = 1.0 - self.tokens;
+            Err(Duration::from_secs_f64(missing / self.refill_per_sec))
+        }
+    }
+}
+
+/// 按模型 ID 限流器 (配置: model id -> RPM)
+#[derive(Default)]
+pub struct ModelRateLimiter {
+    limits: RwLock<HashMap<String, u32>>,
+    buckets: DashMap<String, TokenBucket>,
+}
+
+impl ModelRateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 热更新限流配置 (重置所有令牌桶)
+    pub fn update_limits(&self, limits: &HashMap<String, u32>) {
+        *self.limits.write().unwrap() = limits
+            .iter()
+            .filter(|(_, rpm)| **rpm > 0)
+            .map(|(model, rpm)| (model.clone(), *rpm))
+            .collect();
+        self.buckets.clear();
+    }
+
+    /// 检查请求是否允许。`models` 按优先级排列 (如 [上游模型, 客户端模型])，
+    /// 使用第一个配置了限流的模型。超限时返回 (模型, 等待时间)。
+    pub fn check(&self, models: &[&str]) -> Result<(), (String, Duration)> {
+        let limits = self.limits.read().unwrap();
+        let Some((model, rpm)) = models
+            .iter()
+            .find_map(|m| limits.get(*m).map(|rpm| (m.to_string(), *rpm)))
+        else {
+            return Ok(());
+        };
+        drop(limits);
+
+        let now = Instant::now();
+        let mut bucket = self
+            .buckets
+            .entry(model.clone())
+            .or_insert_with(|| TokenBucket::new(rpm, now));
+        bucket.try_acquire(now).map_err(|wait| (model, wait))
+    }
+}
+
+/// 客户端使用的 API 协议，决定错误响应体的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClientProtocol {
+    Anthropic,
+    OpenAI,
+    Gemini,
+}
+
+/// 按协议构建限流错误体
+fn rate_limited_body(protocol: ClientProtocol, message: String) -> serde_json::Value {
+    match protocol {
+        ClientProtocol::Anthropic => json!({
+            "type": "error",
+            "error": {
+                "type": "rate_limit_error",
+                "message": message
+            }
+        }),
+        ClientProtocol::OpenAI => json!({
+            "error": {
+                "message": message,
+                "type": "rate_limit_error",
+                "param": null,
+                "code": "rate_limit_exceeded"
+            }
+        }),
+        ClientProtocol::Gemini => json!({
+            "error": {
+                "code": 429,
+                "message": message,
+                "status": "RESOURCE_EXHAUSTED"
+            }
+        }),
+    }
+}
+
+/// 构建 429 响应 (Retry-After 向上取整到秒)，错误体采用客户端协议的格式
+pub fn rate_limited_response(protocol: ClientProtocol, model: &str, wait: Duration) -> Response {
+    let retry_after = wait.as_secs_f64().ceil().max(1.0) as u64;
+    tracing::warn!(
+        "[ModelLimiter] Rate limit exceeded for model {}, retry after {}s",
+        model,
+        retry_after
+    );
+
+    let message = format!(
+        "Rate limit exceeded for model {}. Retry after {} seconds.",
+        model, retry_after
+    );
+    (
+        StatusCode::TOO_MANY_REQUESTS,
+        [(header::RETRY_AFTER, retry_after.to_string())],
+        Json(rate_limited_body(protocol, message)),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_refill_math() {
+        let start = Instant::now();
+        // 60 RPM -> 1 token per second, burst of 60
+        let mut bucket = TokenBucket::new(60, start);
+
+        for _ in 0..60 {
+            assert!(bucket.try_acquire(start).is_ok());
+        }
+        let wait = bucket.try_acquire(start).unwrap_err();
+        assert!((wait.as_secs_f64() - 1.0).abs() < 1e-6);
+
+        // Half a second refills half a token: still limited, half a second to go
+        let wait = bucket.try_acquire(start + Duration::from_millis(500)).unwrap_err();
+        assert!((wait.as_secs_f64() - 0.5).abs() < 1e-6);
+
+        // Another half second completes the token
+        assert!(bucket.try_acquire(start + Duration::from_secs(1)).is_ok());
+
+        // Refill is capped at capacity
+        let later = start + Duration::from_secs(3600);
+        for _ in 0..60 {
+            assert!(bucket.try_acquire(later).is_ok());
+        }
+        assert!(bucket.try_acquire(later).is_err());
+    }
+
+    #[test]
+    fn test_limiter_only_applies_to_configured_models() {
+        let limiter = ModelRateLimiter::new();
+        let mut limits = HashMap::new();
+        limits.insert("gemini-2.5-pro".to_string(), 1);
+        limiter.update_limits(&limits);
+
+        assert!(limiter.check(&["gemini-2.5-pro"]).is_ok());
+        let (model, wait) = limiter.check(&["gemini-2.5-pro"]).unwrap_err();
+        assert_eq!(model, "gemini-2.5-pro");
+        assert!(wait > Duration::ZERO);
+
+        // Unconfigured models are never limited
+        for _ in 0..10 {
+            assert!(limiter.check(&["claude-sonnet-4-5"]).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_rate_limited_body_follows_client_protocol() {
+        let anthropic = rate_limited_body(ClientProtocol::Anthropic, "slow down".to_string());
+        assert_eq!(anthropic["type"], "error");
+        assert_eq!(anthropic["error"]["type"], "rate_limit_error");
+        assert_eq!(anthropic["error"]["message"], "slow down");
+
+        let openai = rate_limited_body(ClientProtocol::OpenAI, "slow down".to_string());
+        assert!(openai.get("type").is_none());
+        assert_eq!(openai["error"]["code"], "rate_limit_exceeded");
+        assert_eq!(openai["error"]["message"], "slow down");
+
+        let gemini = rate_limited_body(ClientProtocol::Gemini, "slow down".to_string());
+        assert_eq!(gemini["error"]["code"], 429);
+        assert_eq!(gemini["error"]["status"], "RESOURCE_EXHAUSTED");
+        assert_eq!(gemini["error"]["message"], "slow down");
+    }
+
+    #[test]
+    fn test_rate_limited_response_sets_retry_after() {
+        let resp = rate_limited_response(ClientProtocol::Gemini, "gemini-2.5-pro", Duration::from_millis(1500));
+        assert_eq!(resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(resp.headers()[header::RETRY_AFTER], "2");
+    }
+}
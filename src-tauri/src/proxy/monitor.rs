@@ -69,6 +69,18 @@ impl ProxyMonitor {
         }
     }
 
+    /// 不连接日志数据库的实例，供测试构造 AppState 使用
+    #[cfg(test)]
+    pub(crate) fn detached(max_logs: usize) -> Self {
+        Self {
+            logs: RwLock::new(VecDeque::with_capacity(max_logs)),
+            stats: RwLock::new(ProxyStats::default()),
+            max_logs,
+            enabled: AtomicBool::new(false),
+            app_handle: None,
+        }
+    }
+
     pub fn set_enabled(&self, enabled: bool) {
         self.enabled.store(enabled, Ordering::Relaxed);
     }
@@ -143,18 +155,6 @@ impl ProxyMonitor {
                      tracing::error!("Failed to save security log: {}", e);
                 }
             }
-
-            // Record token stats if available
-            if let (Some(account), Some(input), Some(output)) = (
-                &log_to_save.account_email,
-                log_to_save.input_tokens,
-                log_to_save.output_tokens,
-            ) {
-                let model = log_to_save.model.clone().unwrap_or_else(|| "unknown".to_string());
-                if let Err(e) = crate::modules::token_stats::record_usage(account, &model, input, output) {
-                    tracing::debug!("Failed to record token stats: {}", e);
-                }
-            }
         });
 
         // Emit event (send summary only, without body to reduce memory)
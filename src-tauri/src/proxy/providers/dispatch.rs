@@ -0,0 +1,279 @@
+// Backend dispatch decision (z.ai vs Google pool)
+// Shared by the request path and the inspection command so both agree on routing.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::proxy::common::model_mapping::MappingSource;
+use crate::proxy::{TokenManager, ZaiConfig, ZaiDispatchMode};
+
+/// Upstream backend that would serve an Anthropic-protocol request
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    Google,
+    Zai,
+}
+
+/// Snapshot of the Google pool used for the dispatch decision
+#[derive(Debug, Clone, Copy)]
+pub struct PoolStatus {
+    /// Number of loaded Google accounts
+    pub accounts: usize,
+    /// Whether at least one account can serve the model right now and the pool
+    /// has not tripped the consecutive-failure breaker
+    pub has_available: bool,
+}
+
+/// Result of inspecting where a model would be routed
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendSelection {
+    pub model: String,
+    pub backend: Backend,
+    pub upstream_model: String,
+    /// Mapping layer that decided `upstream_model` on the selected backend
+    pub mapping_source: MappingSource,
+    pub dispatch_mode: ZaiDispatchMode,
+}
+
+/// Decide which backend serves a request.
+/// `pooled_slot` is the round-robin counter value for `Pooled` mode and `weighted_roll` a
+/// uniform sample in `[0, 1)` for `Weighted` mode (each ignored by the other modes).
+pub fn select_backend(
+    zai: &ZaiConfig,
+    pool: PoolStatus,
+    pooled_slot: usize,
+    weighted_roll: f32,
+) -> Backend {
+    if !zai.enabled {
+        return Backend::Google;
+    }
+
+    let use_zai = match zai.dispatch_mode {
+        ZaiDispatchMode::Off => false,
+        ZaiDispatchMode::Exclusive => true,
+        ZaiDispatchMode::Fallback => pool.accounts == 0 || !pool.has_available,
+        ZaiDispatchMode::Pooled => {
+            // Treat z.ai as exactly one extra slot in the pool.
+            let total = pool.accounts.saturating_add(1).max(1);
+            pooled_slot % total == 0
+        }
+        // weight 0.0 never matches (like Off), 1.0 always matches (like Exclusive).
+        // With no Google accounts any positive weight sends everything to z.ai, as Pooled does.
+        ZaiDispatchMode::Weighted => {
+            zai.zai_weight > 0.0 && (pool.accounts == 0 || weighted_roll < zai.zai_weight)
+        }
+    };
+
+    if use_zai {
+        Backend::Zai
+    } else {
+        Backend::Google
+    }
+}
+
+/// Read the current pool status for a model.
+/// Availability is only probed in `Fallback` mode, the only mode that depends on it.
+pub async fn pool_status_for(
+    zai: &ZaiConfig,
+    token_manager: &TokenManager,
+    model: &str,
+) -> PoolStatus {
+    let accounts = token_manager.len();
+    let has_available = if zai.enabled
+        && zai.dispatch_mode == ZaiDispatchMode::Fallback
+        && accounts > 0
+    {
+        let normalized_model = crate::proxy::common::model_mapping::normalize_to_standard_id(model)
+            .unwrap_or_else(|| model.to_string());
+        let healthy = super::health::global().availability(
+            Backend::Google,
+            chrono::Utc::now().timestamp(),
+            zai.fallback_failure_threshold,
+            zai.fallback_cooldown_secs,
+        ) == super::health::Availability::Available;
+        healthy
+            && token_manager
+                .has_available_account("claude", &normalized_model)
+                .await
+    } else {
+        accounts > 0
+    };
+
+    PoolStatus {
+        accounts,
+        has_available,
+    }
+}
+
+/// Model sent upstream once `backend` is chosen, with the mapping layer that decided it.
+/// Each backend has its own mapping: `custom_mapping` never applies to z.ai requests.
+pub fn upstream_model_for(
+    backend: Backend,
+    model: &str,
+    zai: &ZaiConfig,
+    custom_mapping: &std::collections::HashMap<String, String>,
+) -> (String, MappingSource) {
+    match backend {
+        Backend::Zai => super::zai_anthropic::trace_model_for_zai(model, zai),
+        Backend::Google => crate::proxy::common::model_mapping::trace_model_route(model, custom_mapping),
+    }
+}
+
+/// Run the real selection logic for `model` without sending a request.
+/// The pooled round-robin counter is only peeked, so inspection never shifts live traffic.
+/// In `Weighted` mode the backend receiving the majority of traffic is reported.
+pub async fn current_backend_for(
+    zai: &ZaiConfig,
+    token_manager: &TokenManager,
+    custom_mapping: &std::collections::HashMap<String, String>,
+    provider_rr: &AtomicUsize,
+    model: &str,
+) -> BackendSelection {
+    let pool = pool_status_for(zai, token_manager, model).await;
+    let backend = select_backend(zai, pool, provider_rr.load(Ordering::Relaxed), 0.5);
+    let (upstream_model, mapping_source) = upstream_model_for(backend, model, zai, custom_mapping);
+
+    BackendSelection {
+        model: model.to_string(),
+        backend,
+        upstream_model,
+        mapping_source,
+        dispatch_mode: zai.dispatch_mode.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback_config() -> ZaiConfig {
+        ZaiConfig {
+            enabled: true,
+            api_key: "test-key".to_string(),
+            dispatch_mode: ZaiDispatchMode::Fallback,
+            ..ZaiConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_fallback_with_healthy_pool_uses_google() {
+        let pool = PoolStatus {
+            accounts: 3,
+            has_available: true,
+        };
+        assert_eq!(select_backend(&fallback_config(), pool, 0, 0.0), Backend::Google);
+    }
+
+    #[test]
+    fn test_fallback_with_exhausted_pool_uses_zai() {
+        let exhausted = PoolStatus {
+            accounts: 3,
+            has_available: false,
+        };
+        assert_eq!(select_backend(&fallback_config(), exhausted, 0, 0.0), Backend::Zai);
+
+        let empty = PoolStatus {
+            accounts: 0,
+            has_available: false,
+        };
+        assert_eq!(select_backend(&fallback_config(), empty, 0, 0.0), Backend::Zai);
+    }
+
+    #[test]
+    fn test_disabled_zai_always_uses_google() {
+        let mut config = fallback_config();
+        config.enabled = false;
+        let pool = PoolStatus {
+            accounts: 0,
+            has_available: false,
+        };
+        assert_eq!(select_backend(&config, pool, 0, 0.0), Backend::Google);
+    }
+
+    fn weighted_config(weight: f32) -> ZaiConfig {
+        ZaiConfig {
+            enabled: true,
+            api_key: "test-key".to_string(),
+            dispatch_mode: ZaiDispatchMode::Weighted,
+            zai_weight: weight,
+            ..ZaiConfig::default()
+        }
+    }
+
+    const HEALTHY_POOL: PoolStatus = PoolStatus {
+        accounts: 3,
+        has_available: true,
+    };
+
+    #[test]
+    fn test_weighted_split_within_tolerance() {
+        use rand::{Rng, SeedableRng};
+
+        let config = weighted_config(0.3);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+        let iterations = 100_000;
+        let zai_hits = (0..iterations)
+            .filter(|_| select_backend(&config, HEALTHY_POOL, 0, rng.gen::<f32>()) == Backend::Zai)
+            .count();
+
+        let observed = zai_hits as f64 / iterations as f64;
+        assert!((observed - 0.3).abs() < 0.01, "observed z.ai share {}", observed);
+    }
+
+    #[test]
+    fn test_weighted_extremes_match_off_and_exclusive() {
+        let never = weighted_config(0.0);
+        let always = weighted_config(1.0);
+        for roll in [0.0, 0.25, 0.5, 0.999_999] {
+            assert_eq!(select_backend(&never, HEALTHY_POOL, 0, roll), Backend::Google);
+            assert_eq!(select_backend(&always, HEALTHY_POOL, 0, roll), Backend::Zai);
+        }
+        assert!(!never.dispatch_enabled());
+        assert!(always.dispatch_enabled());
+    }
+
+    #[test]
+    fn test_weighted_with_empty_pool_uses_zai() {
+        let empty = PoolStatus {
+            accounts: 0,
+            has_available: false,
+        };
+        for roll in [0.0, 0.5, 0.999_999] {
+            assert_eq!(select_backend(&weighted_config(0.3), empty, 0, roll), Backend::Zai);
+            // Weight 0 still means z.ai is off
+            assert_eq!(select_backend(&weighted_config(0.0), empty, 0, roll), Backend::Google);
+        }
+    }
+
+    #[test]
+    fn test_upstream_model_follows_backend_mapping() {
+        let mut zai = fallback_config();
+        zai.models.opus = "glm-opus".to_string();
+        zai.model_mapping.insert("claude-3-haiku".to_string(), "glm-haiku-pinned".to_string());
+        let mut custom = std::collections::HashMap::new();
+        custom.insert("claude-opus-4".to_string(), "gemini-custom".to_string());
+
+        // custom_mapping only applies on the Google path
+        assert_eq!(
+            upstream_model_for(Backend::Google, "claude-opus-4", &zai, &custom),
+            ("gemini-custom".to_string(), MappingSource::CustomMapping)
+        );
+        assert_eq!(
+            upstream_model_for(Backend::Zai, "claude-opus-4", &zai, &custom),
+            ("glm-opus".to_string(), MappingSource::ZaiFamilyDefault)
+        );
+        assert_eq!(
+            upstream_model_for(Backend::Zai, "Claude-3-Haiku", &zai, &custom),
+            ("glm-haiku-pinned".to_string(), MappingSource::ZaiModelMapping)
+        );
+        assert_eq!(
+            upstream_model_for(Backend::Zai, "zai:glm-4.6", &zai, &custom),
+            ("glm-4.6".to_string(), MappingSource::ZaiNative)
+        );
+        assert_eq!(
+            upstream_model_for(Backend::Zai, "gemini-2.5-flash", &zai, &custom),
+            ("gemini-2.5-flash".to_string(), MappingSource::Passthrough)
+        );
+    }
+}
@@ -0,0 +1,289 @@
+// 上游健康统计 (Google 池 / z.ai)
+// 记录每个上游最近一次成功/失败时间与滑动窗口内的错误率，供 /health/detailed 诊断调度行为；
+// 连续失败计数驱动 Fallback 模式下 Google 池的熔断判断
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use super::dispatch::Backend;
+
+/// 错误率统计窗口 (秒)
+const ERROR_RATE_WINDOW_SECS: i64 = 300;
+/// 窗口内最多保留的请求结果数，避免高并发下无限增长
+const MAX_OUTCOMES: usize = 1000;
+
+#[derive(Default)]
+struct UpstreamStats {
+    last_success_at: Option<i64>,
+    last_failure_at: Option<i64>,
+    /// 连续失败次数 (成功时清零)
+    consecutive_failures: u32,
+    /// (Unix 秒, 是否成功)
+    outcomes: VecDeque<(i64, bool)>,
+}
+
+impl UpstreamStats {
+    fn record(&mut self, now: i64, success: bool) {
+        if success {
+            self.last_success_at = Some(now);
+            self.consecutive_failures = 0;
+        } else {
+            self.last_failure_at = Some(now);
+            self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        }
+        self.outcomes.push_back((now, success));
+        if self.outcomes.len() > MAX_OUTCOMES {
+            self.outcomes.pop_front();
+        }
+        self.prune(now);
+    }
+
+    fn prune(&mut self, now: i64) {
+        let window_start = now - ERROR_RATE_WINDOW_SECS;
+        while self.outcomes.front().is_some_and(|(t, _)| *t <= window_start) {
+            self.outcomes.pop_front();
+        }
+    }
+}
+
+/// 单个上游的健康快照
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct UpstreamHealthSnapshot {
+    pub last_success_at: Option<i64>,
+    pub last_failure_at: Option<i64>,
+    /// 统计窗口内的请求数
+    pub recent_requests: usize,
+    /// 统计窗口内的失败数
+    pub recent_errors: usize,
+    /// 窗口内错误率 (0.0 - 1.0)，无请求时为 0
+    pub error_rate: f64,
+    pub window_secs: i64,
+    pub consecutive_failures: u32,
+}
+
+/// 上游在 Fallback 模式下的可用状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum Availability {
+    Available,
+    /// 连续失败达到阈值，冷却结束 (`until`, Unix 秒) 前视为不可用
+    Unavailable { until: i64 },
+}
+
+/// 各上游的请求结果统计
+#[derive(Default)]
+pub struct UpstreamHealth {
+    google: Mutex<UpstreamStats>,
+    zai: Mutex<UpstreamStats>,
+}
+
+impl UpstreamHealth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn stats(&self, backend: Backend) -> std::sync::MutexGuard<'_, UpstreamStats> {
+        let stats = match backend {
+            Backend::Google => &self.google,
+            Backend::Zai => &self.zai,
+        };
+        stats.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_success(&self, backend: Backend, now: i64) {
+        self.stats(backend).record(now, true);
+    }
+
+    pub fn record_failure(&self, backend: Backend, now: i64) {
+        self.stats(backend).record(now, false);
+    }
+
+    pub fn snapshot(&self, backend: Backend, now: i64) -> UpstreamHealthSnapshot {
+        let mut stats = self.stats(backend);
+        stats.prune(now);
+
+        let recent_requests = stats.outcomes.len();
+        let recent_errors = stats.outcomes.iter().filter(|(_, ok)| !ok).count();
+        let error_rate = if recent_requests == 0 {
+            0.0
+        } else {
+            recent_errors as f64 / recent_requests as f64
+        };
+
+        UpstreamHealthSnapshot {
+            last_success_at: stats.last_success_at,
+            last_failure_at: stats.last_failure_at,
+            recent_requests,
+            recent_errors,
+            error_rate,
+            window_secs: ERROR_RATE_WINDOW_SECS,
+            consecutive_failures: stats.consecutive_failures,
+        }
+    }
+
+    /// 熔断状态机:
+    /// - 连续失败达到 `failure_threshold` 次 -> 不可用
+    /// - 任意一次成功 -> 恢复可用
+    /// - 冷却 `cooldown_secs` 结束 -> 恢复可用以放行探测请求；探测仍失败则立即重新熔断
+    ///
+    /// `failure_threshold` 为 0 时不跟踪，始终可用
+    pub fn availability(
+        &self,
+        backend: Backend,
+        now: i64,
+        failure_threshold: u32,
+        cooldown_secs: u64,
+    ) -> Availability {
+        let stats = self.stats(backend);
+        if failure_threshold == 0 || stats.consecutive_failures < failure_threshold {
+            return Availability::Available;
+        }
+
+        let until = stats.last_failure_at.unwrap_or(now) + cooldown_secs as i64;
+        if now < until {
+            Availability::Unavailable { until }
+        } else {
+            Availability::Available
+        }
+    }
+}
+
+static UPSTREAM_HEALTH: Lazy<UpstreamHealth> = Lazy::new(UpstreamHealth::new);
+
+/// 全局上游健康统计
+pub fn global() -> &'static UpstreamHealth {
+    &UPSTREAM_HEALTH
+}
+
+/// 按上游响应状态记录结果: 2xx 为成功，429/5xx 为失败，其它客户端错误不计入
+pub fn record_status(backend: Backend, status: u16) {
+    let now = chrono::Utc::now().timestamp();
+    if (200..300).contains(&status) {
+        global().record_success(backend, now);
+    } else if status == 429 || status >= 500 {
+        global().record_failure(backend, now);
+    }
+}
+
+/// 记录网络层错误 (连接失败、超时等)
+pub fn record_error(backend: Backend) {
+    global().record_failure(backend, chrono::Utc::now().timestamp());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_reports_error_rate_per_upstream() {
+        let health = UpstreamHealth::new();
+        health.record_success(Backend::Google, 100);
+        health.record_failure(Backend::Google, 110);
+        health.record_success(Backend::Google, 120);
+        health.record_failure(Backend::Google, 130);
+        health.record_success(Backend::Zai, 125);
+
+        let google = health.snapshot(Backend::Google, 140);
+        assert_eq!(google.last_success_at, Some(120));
+        assert_eq!(google.last_failure_at, Some(130));
+        assert_eq!(google.recent_requests, 4);
+        assert_eq!(google.recent_errors, 2);
+        assert!((google.error_rate - 0.5).abs() < f64::EPSILON);
+
+        let zai = health.snapshot(Backend::Zai, 140);
+        assert_eq!(zai.recent_requests, 1);
+        assert_eq!(zai.error_rate, 0.0);
+        assert_eq!(zai.last_failure_at, None);
+    }
+
+    #[test]
+    fn test_outcomes_outside_window_expire() {
+        let health = UpstreamHealth::new();
+        health.record_failure(Backend::Google, 0);
+        health.record_success(Backend::Google, 200);
+
+        let snapshot = health.snapshot(Backend::Google, ERROR_RATE_WINDOW_SECS + 100);
+        assert_eq!(snapshot.recent_requests, 1);
+        assert_eq!(snapshot.recent_errors, 0);
+        // 最近失败时间不随窗口过期
+        assert_eq!(snapshot.last_failure_at, Some(0));
+
+        let empty = UpstreamHealth::new().snapshot(Backend::Zai, 0);
+        assert_eq!(empty.recent_requests, 0);
+        assert_eq!(empty.error_rate, 0.0);
+    }
+
+    #[test]
+    fn test_pool_unavailable_after_consecutive_failures() {
+        let health = UpstreamHealth::new();
+        health.record_failure(Backend::Google, 10);
+        health.record_failure(Backend::Google, 11);
+        assert_eq!(health.availability(Backend::Google, 12, 3, 60), Availability::Available);
+
+        health.record_failure(Backend::Google, 12);
+        assert_eq!(
+            health.availability(Backend::Google, 13, 3, 60),
+            Availability::Unavailable { until: 72 }
+        );
+        // 其它上游不受影响
+        assert_eq!(health.availability(Backend::Zai, 13, 3, 60), Availability::Available);
+    }
+
+    #[test]
+    fn test_interleaved_success_resets_failure_count() {
+        let health = UpstreamHealth::new();
+        health.record_failure(Backend::Google, 10);
+        health.record_failure(Backend::Google, 11);
+        health.record_success(Backend::Google, 12);
+        health.record_failure(Backend::Google, 13);
+        health.record_failure(Backend::Google, 14);
+        assert_eq!(health.availability(Backend::Google, 15, 3, 60), Availability::Available);
+    }
+
+    #[test]
+    fn test_pool_recovers_after_successful_probe() {
+        let health = UpstreamHealth::new();
+        for t in 0..3 {
+            health.record_failure(Backend::Google, t);
+        }
+        assert!(matches!(
+            health.availability(Backend::Google, 5, 3, 60),
+            Availability::Unavailable { .. }
+        ));
+
+        health.record_success(Backend::Google, 6);
+        assert_eq!(health.availability(Backend::Google, 7, 3, 60), Availability::Available);
+    }
+
+    #[test]
+    fn test_cooldown_allows_probe_and_failed_probe_retrips() {
+        let health = UpstreamHealth::new();
+        for t in 0..3 {
+            health.record_failure(Backend::Google, t);
+        }
+        assert_eq!(
+            health.availability(Backend::Google, 61, 3, 60),
+            Availability::Unavailable { until: 62 }
+        );
+        // 冷却结束，放行探测
+        assert_eq!(health.availability(Backend::Google, 62, 3, 60), Availability::Available);
+
+        // 探测失败，立即重新熔断
+        health.record_failure(Backend::Google, 63);
+        assert_eq!(
+            health.availability(Backend::Google, 64, 3, 60),
+            Availability::Unavailable { until: 123 }
+        );
+    }
+
+    #[test]
+    fn test_zero_threshold_disables_tracking() {
+        let health = UpstreamHealth::new();
+        for t in 0..10 {
+            health.record_failure(Backend::Google, t);
+        }
+        assert_eq!(health.availability(Backend::Google, 10, 0, 60), Availability::Available);
+    }
+}
@@ -1,2 +1,4 @@
+pub mod dispatch;
+pub mod health;
 pub mod zai_anthropic;
 
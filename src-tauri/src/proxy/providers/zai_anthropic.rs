@@ -5,35 +5,147 @@ use axum::{
 };
 use bytes::Bytes;
 use futures::StreamExt;
-use serde_json::Value;
+use serde_json::{json, Value};
 use tokio::time::Duration;
 
+use crate::proxy::common::model_mapping::MappingSource;
 use crate::proxy::server::AppState;
 
-fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String {
+pub(crate) fn map_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> String {
+    trace_model_for_zai(original, state).0
+}
+
+/// Same as `map_model_for_zai`, also reporting which mapping layer decided the target
+pub(crate) fn trace_model_for_zai(original: &str, state: &crate::proxy::ZaiConfig) -> (String, MappingSource) {
     let m = original.to_lowercase();
     if let Some(mapped) = state.model_mapping.get(original) {
-        return mapped.clone();
+        return (mapped.clone(), MappingSource::ZaiModelMapping);
     }
     if let Some(mapped) = state.model_mapping.get(&m) {
-        return mapped.clone();
+        return (mapped.clone(), MappingSource::ZaiModelMapping);
     }
     if m.starts_with("zai:") {
-        return original[4..].to_string();
+        return (original[4..].to_string(), MappingSource::ZaiNative);
     }
     if m.starts_with("glm-") {
-        return original.to_string();
+        return (original.to_string(), MappingSource::ZaiNative);
     }
-    if !m.starts_with("claude-") {
-        return original.to_string();
+    match crate::proxy::common::model_mapping::claude_family(original) {
+        Some(family) => (state.models.for_family(family).to_string(), MappingSource::ZaiFamilyDefault),
+        // Claude ids without a recognizable family keep using the sonnet default
+        None if m.starts_with("claude-") => (state.models.sonnet.clone(), MappingSource::ZaiFamilyDefault),
+        None => (original.to_string(), MappingSource::Passthrough),
     }
-    if m.contains("opus") {
-        return state.models.opus.clone();
+}
+
+/// Reverse of the model mapping applied to one request: z.ai answers with the model it
+/// actually ran (e.g. `glm-4.7`), clients expect the id they asked for
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct ModelRewrite {
+    requested: String,
+    upstream: String,
+}
+
+impl ModelRewrite {
+    /// None when the request was not remapped (nothing to rewrite)
+    pub(crate) fn new(requested: &str, upstream: &str) -> Option<Self> {
+        (requested != upstream).then(|| Self {
+            requested: requested.to_string(),
+            upstream: upstream.to_string(),
+        })
     }
-    if m.contains("haiku") {
-        return state.models.haiku.clone();
+
+    /// Rewrite `model` of a Messages response, or of the `message` carried by a
+    /// `message_start` event. Returns whether anything changed.
+    pub(crate) fn apply(&self, value: &mut Value) -> bool {
+        let is_message_start = value.get("type").and_then(|t| t.as_str()) == Some("message_start");
+        let target = if is_message_start {
+            value.get_mut("message")
+        } else {
+            Some(value)
+        };
+        match target.and_then(|t| t.get_mut("model")) {
+            Some(model) if model.is_string() => {
+                tracing::debug!(
+                    "[z.ai] Reporting model {} as requested {}",
+                    model.as_str().unwrap_or(&self.upstream),
+                    self.requested
+                );
+                *model = Value::String(self.requested.clone());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Rewrite a single SSE line (`data: {...}`); other lines are returned unchanged
+    fn rewrite_sse_line(&self, line: &[u8]) -> Option<Vec<u8>> {
+        let text = std::str::from_utf8(line).ok()?;
+        let payload = text.trim_end().strip_prefix("data:")?.trim_start();
+        let mut json: Value = serde_json::from_str(payload).ok()?;
+        if json.get("type").and_then(|t| t.as_str()) != Some("message_start") || !self.apply(&mut json) {
+            return None;
+        }
+        let line_ending = &text[text.trim_end().len()..];
+        Some(format!("data: {}{}", json, line_ending).into_bytes())
+    }
+}
+
+/// Rewrite the model reported by the `message_start` event of an SSE stream. Lines are
+/// buffered only until that event has been seen, the rest of the stream passes through.
+pub(crate) fn rewrite_sse_model<S>(
+    mut upstream: S,
+    rewrite: ModelRewrite,
+) -> impl futures::Stream<Item = Result<Bytes, std::io::Error>>
+where
+    S: futures::Stream<Item = Result<Bytes, std::io::Error>> + Unpin,
+{
+    async_stream::stream! {
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut rewritten = false;
+
+        while let Some(chunk) = upstream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    yield Err(e);
+                    continue;
+                }
+            };
+            if rewritten {
+                yield Ok(chunk);
+                continue;
+            }
+
+            buffer.extend_from_slice(&chunk);
+            let mut out: Vec<u8> = Vec::with_capacity(buffer.len());
+            while let Some(pos) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=pos).collect();
+                if rewritten {
+                    out.extend_from_slice(&line);
+                    continue;
+                }
+                match rewrite.rewrite_sse_line(&line) {
+                    Some(line) => {
+                        out.extend_from_slice(&line);
+                        rewritten = true;
+                    }
+                    None => out.extend_from_slice(&line),
+                }
+            }
+            if rewritten {
+                // Incomplete tail line, forwarded as is from now on
+                out.append(&mut buffer);
+            }
+            if !out.is_empty() {
+                yield Ok(Bytes::from(out));
+            }
+        }
+
+        if !buffer.is_empty() {
+            yield Ok(Bytes::from(buffer));
+        }
     }
-    state.models.sonnet.clone()
 }
 
 fn join_base_url(base: &str, path: &str) -> Result<String, String> {
@@ -54,9 +166,7 @@ fn build_client(
         .timeout(Duration::from_secs(timeout_secs.max(5)));
 
     if let Some(config) = upstream_proxy {
-        if config.enabled && !config.url.is_empty() {
-            let proxy = reqwest::Proxy::all(&config.url)
-                .map_err(|e| format!("Invalid upstream proxy url: {}", e))?;
+        if let Some(proxy) = config.build_proxy()? {
             builder = builder.proxy(proxy);
         }
     }
@@ -130,6 +240,98 @@ pub fn deep_remove_cache_control(value: &mut Value) {
     }
 }
 
+const WEB_SEARCH_TOOL: &str = "web_search_prime";
+const WEB_READER_TOOL: &str = "webReader";
+
+/// Anthropic-protocol tool definitions for the enabled z.ai MCP services
+fn mcp_tool_definitions(mcp: &crate::proxy::config::ZaiMcpConfig) -> Vec<Value> {
+    if !mcp.enabled {
+        return Vec::new();
+    }
+
+    let mut tools = Vec::new();
+    if mcp.web_search_enabled {
+        tools.push(json!({
+            "name": WEB_SEARCH_TOOL,
+            "description": "Search the web and return result titles, URLs and summaries.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "search_query": { "type": "string", "description": "The search query" }
+                },
+                "required": ["search_query"]
+            }
+        }));
+    }
+    if mcp.web_reader_enabled {
+        tools.push(json!({
+            "name": WEB_READER_TOOL,
+            "description": "Fetch a web page and return its main content as markdown.",
+            "input_schema": {
+                "type": "object",
+                "properties": {
+                    "url": { "type": "string", "description": "The URL to read" }
+                },
+                "required": ["url"]
+            }
+        }));
+    }
+    if mcp.vision_enabled {
+        // MCP uses `inputSchema`, the Anthropic Messages API expects `input_schema`
+        tools.extend(crate::proxy::zai_vision_tools::tool_specs().into_iter().map(|mut spec| {
+            if let Some(obj) = spec.as_object_mut() {
+                if let Some(schema) = obj.remove("inputSchema") {
+                    obj.insert("input_schema".to_string(), schema);
+                }
+            }
+            spec
+        }));
+    }
+    tools
+}
+
+/// Add the enabled MCP tools to the request's `tools` array.
+/// Tools the client already declared (same name) are left untouched, so
+/// injecting twice is a no-op. Returns the number of tools added.
+pub(crate) fn inject_mcp_tools(body: &mut Value, mcp: &crate::proxy::config::ZaiMcpConfig) -> usize {
+    let definitions = mcp_tool_definitions(mcp);
+    if definitions.is_empty() {
+        return 0;
+    }
+    let Some(obj) = body.as_object_mut() else {
+        return 0;
+    };
+
+    let tools = obj
+        .entry("tools")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if tools.is_null() {
+        *tools = Value::Array(Vec::new());
+    }
+    let Some(tools) = tools.as_array_mut() else {
+        return 0;
+    };
+
+    let mut added = 0;
+    for tool in definitions {
+        let name = tool.get("name").and_then(|v| v.as_str()).unwrap_or_default();
+        let declared = tools
+            .iter()
+            .any(|t| t.get("name").and_then(|v| v.as_str()) == Some(name));
+        if !declared {
+            tools.push(tool);
+            added += 1;
+        }
+    }
+    added
+}
+
+/// MCP tools are only added to actual message requests, not to auxiliary endpoints
+/// such as `/v1/messages/count_tokens`
+fn injects_mcp_tools(path: &str) -> bool {
+    path == "/v1/messages"
+}
+
 pub async fn forward_anthropic_json(
     state: &AppState,
     method: Method,
@@ -139,7 +341,7 @@ pub async fn forward_anthropic_json(
     message_count: usize, // [NEW v4.0.0] Pass message count for rewind detection
 ) -> Response {
     let zai = state.zai.read().await.clone();
-    if !zai.enabled || zai.dispatch_mode == crate::proxy::ZaiDispatchMode::Off {
+    if !zai.dispatch_enabled() {
         return (StatusCode::BAD_REQUEST, "z.ai is disabled").into_response();
     }
 
@@ -147,8 +349,10 @@ pub async fn forward_anthropic_json(
         return (StatusCode::BAD_REQUEST, "z.ai api_key is not set").into_response();
     }
 
+    let mut model_rewrite = None;
     if let Some(model) = body.get("model").and_then(|v| v.as_str()) {
         let mapped = map_model_for_zai(model, &zai);
+        model_rewrite = ModelRewrite::new(model, &mapped);
         body["model"] = Value::String(mapped.clone());
 
         // [FIX] Caching for z.ai (to support thinking-filter)
@@ -162,6 +366,13 @@ pub async fn forward_anthropic_json(
         }
     }
 
+    if injects_mcp_tools(path) {
+        let injected = inject_mcp_tools(&mut body, &zai.mcp);
+        if injected > 0 {
+            tracing::debug!("[z.ai] Injected {} MCP tool definition(s)", injected);
+        }
+    }
+
     let url = match join_base_url(&zai.base_url, path) {
         Ok(u) => u,
         Err(e) => return (StatusCode::BAD_REQUEST, e).into_response(),
@@ -196,13 +407,19 @@ pub async fn forward_anthropic_json(
     
     tracing::debug!("Forwarding request to z.ai (len: {} bytes): {}", body_len, url);
 
-    let req = client.request(method, &url)
-        .headers(headers)
-        .body(body_bytes); // Use .body(Vec<u8>) instead of .json()
+    // Retries only happen before the response is streamed back to the client.
+    let policy = crate::proxy::upstream::retry::current_policy();
+    let send = crate::proxy::upstream::retry::send_with_retry(policy, "z.ai", || {
+        client
+            .request(method.clone(), &url)
+            .headers(headers.clone())
+            .body(body_bytes.clone()) // Use .body(Vec<u8>) instead of .json()
+    });
 
-    let resp = match req.send().await {
+    let resp = match send.await {
         Ok(r) => r,
         Err(e) => {
+            super::health::record_error(super::dispatch::Backend::Zai);
             return (
                 StatusCode::BAD_GATEWAY,
                 format!("Upstream request failed: {}", e),
@@ -212,19 +429,160 @@ pub async fn forward_anthropic_json(
     };
 
     let status = StatusCode::from_u16(resp.status().as_u16()).unwrap_or(StatusCode::BAD_GATEWAY);
+    super::health::record_status(super::dispatch::Backend::Zai, status.as_u16());
 
+    let content_type = resp.headers().get(header::CONTENT_TYPE).cloned();
+    let is_sse = content_type
+        .as_ref()
+        .and_then(|ct| ct.to_str().ok())
+        .is_some_and(|ct| ct.starts_with("text/event-stream"));
     let mut out = Response::builder().status(status);
-    if let Some(ct) = resp.headers().get(header::CONTENT_TYPE) {
-        out = out.header(header::CONTENT_TYPE, ct.clone());
+    if let Some(ct) = content_type {
+        out = out.header(header::CONTENT_TYPE, ct);
     }
 
     // Stream response body to the client (covers SSE and non-SSE).
-    let stream = resp.bytes_stream().map(|chunk| match chunk {
+    let stream = Box::pin(resp.bytes_stream().map(|chunk| match chunk {
         Ok(b) => Ok::<Bytes, std::io::Error>(b),
         Err(e) => Ok(Bytes::from(format!("Upstream stream error: {}", e))),
-    });
+    }));
+
+    let body = match model_rewrite.filter(|_| status.is_success()) {
+        None => Body::from_stream(stream),
+        Some(rewrite) if is_sse => Body::from_stream(rewrite_sse_model(stream, rewrite)),
+        Some(rewrite) => match read_json_with_model(stream, &rewrite).await {
+            Ok(bytes) => Body::from(bytes),
+            Err(e) => return (StatusCode::BAD_GATEWAY, e).into_response(),
+        },
+    };
 
-    out.body(Body::from_stream(stream)).unwrap_or_else(|_| {
+    out.body(body).unwrap_or_else(|_| {
         (StatusCode::INTERNAL_SERVER_ERROR, "Failed to build response").into_response()
     })
 }
+
+/// Buffer a non-streaming response and report the requested model in it.
+/// Bodies that are not JSON are returned unchanged.
+async fn read_json_with_model<S>(mut stream: S, rewrite: &ModelRewrite) -> Result<Vec<u8>, String>
+where
+    S: futures::Stream<Item = Result<Bytes, std::io::Error>> + Unpin,
+{
+    let mut budget = crate::proxy::common::body_limit::ByteBudget::new(
+        crate::proxy::common::body_limit::max_response_bytes(),
+    );
+    let mut raw = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Upstream stream error: {}", e))?;
+        budget.consume(chunk.len())?;
+        raw.extend_from_slice(&chunk);
+    }
+
+    match serde_json::from_slice::<Value>(&raw) {
+        Ok(mut json) if rewrite.apply(&mut json) => serde_json::to_vec(&json).map_err(|e| e.to_string()),
+        _ => Ok(raw),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::config::ZaiMcpConfig;
+
+    fn web_search_only() -> ZaiMcpConfig {
+        ZaiMcpConfig {
+            enabled: true,
+            web_search_enabled: true,
+            ..ZaiMcpConfig::default()
+        }
+    }
+
+    fn count_named(body: &Value, name: &str) -> usize {
+        body["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|t| t["name"] == name)
+            .count()
+    }
+
+    #[test]
+    fn test_web_search_tool_injected_once() {
+        let mut body = json!({ "model": "glm-4.7", "messages": [] });
+        assert_eq!(inject_mcp_tools(&mut body, &web_search_only()), 1);
+        assert_eq!(body["tools"].as_array().unwrap().len(), 1);
+        assert_eq!(count_named(&body, WEB_SEARCH_TOOL), 1);
+
+        // Idempotent
+        assert_eq!(inject_mcp_tools(&mut body, &web_search_only()), 0);
+        assert_eq!(count_named(&body, WEB_SEARCH_TOOL), 1);
+    }
+
+    #[test]
+    fn test_mcp_tools_only_injected_for_messages() {
+        assert!(injects_mcp_tools("/v1/messages"));
+        assert!(!injects_mcp_tools("/v1/messages/count_tokens"));
+    }
+
+    #[test]
+    fn test_client_declared_tool_not_duplicated() {
+        let mut body = json!({
+            "messages": [],
+            "tools": [
+                { "name": "get_weather", "input_schema": { "type": "object" } },
+                { "name": WEB_SEARCH_TOOL, "input_schema": { "type": "object" } }
+            ]
+        });
+        assert_eq!(inject_mcp_tools(&mut body, &web_search_only()), 0);
+        assert_eq!(body["tools"].as_array().unwrap().len(), 2);
+        assert_eq!(count_named(&body, WEB_SEARCH_TOOL), 1);
+    }
+
+    fn sonnet_to_glm() -> ModelRewrite {
+        let zai = crate::proxy::ZaiConfig::default();
+        let mapped = map_model_for_zai("claude-3-5-sonnet", &zai);
+        assert_eq!(mapped, "glm-4.7");
+        ModelRewrite::new("claude-3-5-sonnet", &mapped).expect("model was remapped")
+    }
+
+    #[tokio::test]
+    async fn test_response_reports_requested_model() {
+        let rewrite = sonnet_to_glm();
+        assert!(ModelRewrite::new("glm-4.7", "glm-4.7").is_none());
+
+        // Non-streaming JSON body
+        let body = json!({ "id": "msg_1", "type": "message", "model": "glm-4.7", "content": [] });
+        let upstream = futures::stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from(body.to_string()))]);
+        let bytes = read_json_with_model(upstream, &rewrite).await.unwrap();
+        let json: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(json["model"], "claude-3-5-sonnet");
+
+        // SSE, with the message_start event split across chunks
+        let sse = "event: message_start\n\
+                   data: {\"type\":\"message_start\",\"message\":{\"id\":\"msg_1\",\"model\":\"glm-4.7\"}}\n\n\
+                   event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n";
+        let (head, tail) = sse.split_at(60);
+        let upstream = futures::stream::iter(vec![
+            Ok::<_, std::io::Error>(Bytes::from(head.to_string())),
+            Ok(Bytes::from(tail.to_string())),
+        ]);
+        let chunks: Vec<Bytes> = rewrite_sse_model(upstream, rewrite)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let text = String::from_utf8(chunks.concat()).unwrap();
+        assert!(text.contains("\"model\":\"claude-3-5-sonnet\""));
+        assert!(!text.contains("glm-4.7"));
+        assert!(text.ends_with("data: {\"type\":\"message_stop\"}\n\n"));
+    }
+
+    #[test]
+    fn test_nothing_injected_when_mcp_disabled() {
+        let mut body = json!({ "messages": [] });
+        let config = ZaiMcpConfig {
+            enabled: false,
+            ..web_search_only()
+        };
+        assert_eq!(inject_mcp_tools(&mut body, &config), 0);
+        assert!(body.get("tools").is_none());
+    }
+}
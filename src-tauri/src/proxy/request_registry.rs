@@ -0,0 +1,187 @@
+// In-flight chat request tracking (request id -> cancellation signal)
+// Lets a client cancel one request precisely without touching the rest of its session.
+
+use dashmap::mapref::entry::Entry;
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::watch;
+
+/// Cancellation signal handed to an executing request (carries the reason)
+#[derive(Clone)]
+pub struct CancelToken {
+    rx: watch::Receiver<Option<String>>,
+}
+
+impl CancelToken {
+    pub fn is_cancelled(&self) -> bool {
+        self.rx.borrow().is_some()
+    }
+
+    /// Cancellation reason, if cancelled
+    pub fn reason(&self) -> Option<String> {
+        self.rx.borrow().clone()
+    }
+
+    /// Resolves once the request is cancelled (never resolves if it is not)
+    pub async fn cancelled(&mut self) {
+        while self.rx.borrow().is_none() {
+            if self.rx.changed().await.is_err() {
+                // Registry entry dropped without cancelling: wait forever
+                std::future::pending::<()>().await;
+            }
+        }
+    }
+}
+
+/// Tokens for one registered request
+pub struct RequestTokens {
+    /// Hard abort for this request id (`CancelRequest`): the task is dropped
+    pub abort: CancelToken,
+    /// Cooperative stop for the whole session (`CancelSession`): workflows check it
+    /// and return a clean `Cancelled` result
+    pub session: CancelToken,
+    /// Identifies this registration; `complete` only removes the entry it was issued for
+    pub generation: u64,
+}
+
+struct InflightRequest {
+    session_id: String,
+    generation: u64,
+    abort: watch::Sender<Option<String>>,
+    session: watch::Sender<Option<String>>,
+}
+
+/// Registry of in-flight chat requests, keyed by request id
+#[derive(Default)]
+pub struct RequestRegistry {
+    inflight: DashMap<String, InflightRequest>,
+    next_generation: AtomicU64,
+}
+
+impl RequestRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Track a request and return its cancellation tokens.
+    /// Fails if a request with the same id is still in flight.
+    pub fn register(&self, request_id: &str, session_id: &str) -> Result<RequestTokens, String> {
+        let Entry::Vacant(slot) = self.inflight.entry(request_id.to_string()) else {
+            return Err(format!("Request {} is already in flight", request_id));
+        };
+        let generation = self.next_generation.fetch_add(1, Ordering::Relaxed);
+        let (abort_tx, abort_rx) = watch::channel(None);
+        let (session_tx, session_rx) = watch::channel(None);
+        slot.insert(InflightRequest {
+            session_id: session_id.to_string(),
+            generation,
+            abort: abort_tx,
+            session: session_tx,
+        });
+        Ok(RequestTokens {
+            abort: CancelToken { rx: abort_rx },
+            session: CancelToken { rx: session_rx },
+            generation,
+        })
+    }
+
+    /// Abort a single request. Returns its session id if it was in flight.
+    pub fn cancel(&self, request_id: &str) -> Option<String> {
+        let (_, request) = self.inflight.remove(request_id)?;
+        let _ = request.abort.send(Some("Request cancelled".to_string()));
+        Some(request.session_id)
+    }
+
+    /// Signal a cooperative stop to every in-flight request of a session.
+    /// Returns the number of requests signalled.
+    pub fn cancel_session(&self, session_id: &str, reason: &str) -> usize {
+        let mut signalled = 0;
+        for request in self.inflight.iter().filter(|r| r.session_id == session_id) {
+            let _ = request.session.send(Some(reason.to_string()));
+            signalled += 1;
+        }
+        signalled
+    }
+
+    /// Stop tracking a finished request. A newer registration that reused the id
+    /// (after this one was cancelled) is left alone.
+    pub fn complete(&self, request_id: &str, generation: u64) {
+        self.inflight.remove_if(request_id, |_, request| request.generation == generation);
+    }
+
+    pub fn is_inflight(&self, request_id: &str) -> bool {
+        self.inflight.contains_key(request_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    /// Simulated workflow execution that honours cancellation
+    async fn run(mut token: CancelToken, work: Duration) -> &'static str {
+        tokio::select! {
+            _ = tokio::time::sleep(work) => "completed",
+            _ = token.cancelled() => "cancelled",
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancel_one_of_two_concurrent_requests() {
+        let registry = Arc::new(RequestRegistry::new());
+        let first = registry.register("req-1", "session-a").unwrap().abort;
+        let second = registry.register("req-2", "session-a").unwrap();
+
+        let first_task = tokio::spawn(run(first, Duration::from_secs(5)));
+        let second_task = tokio::spawn(run(second.abort, Duration::from_millis(50)));
+
+        assert_eq!(registry.cancel("req-1"), Some("session-a".to_string()));
+
+        assert_eq!(first_task.await.unwrap(), "cancelled");
+        assert_eq!(second_task.await.unwrap(), "completed");
+        assert!(!registry.is_inflight("req-1"));
+        assert!(registry.is_inflight("req-2"));
+
+        // Unknown / already finished ids are a no-op
+        registry.complete("req-2", second.generation);
+        assert_eq!(registry.cancel("req-2"), None);
+    }
+
+    #[test]
+    fn test_cancel_session_signals_only_that_session() {
+        let registry = RequestRegistry::new();
+        let a1 = registry.register("req-1", "session-a").unwrap();
+        let a2 = registry.register("req-2", "session-a").unwrap();
+        let b1 = registry.register("req-3", "session-b").unwrap();
+
+        assert_eq!(registry.cancel_session("session-a", "Stopped by user"), 2);
+
+        assert_eq!(a1.session.reason(), Some("Stopped by user".to_string()));
+        assert!(a2.session.is_cancelled());
+        assert!(!b1.session.is_cancelled());
+        // Session stop is cooperative: nothing is aborted
+        assert!(!a1.abort.is_cancelled());
+    }
+
+    #[test]
+    fn test_reused_request_id() {
+        let registry = RequestRegistry::new();
+        let first = registry.register("req-1", "session-a").unwrap();
+
+        // Still in flight: the id cannot be taken over
+        assert!(registry.register("req-1", "session-b").is_err());
+        assert_eq!(registry.cancel_session("session-a", "stop"), 1);
+
+        // Once cancelled the id is free again, and the old task finishing late
+        // must not drop the new registration
+        registry.cancel("req-1");
+        let second = registry.register("req-1", "session-a").unwrap();
+        registry.complete("req-1", first.generation);
+        assert!(registry.is_inflight("req-1"));
+
+        registry.complete("req-1", second.generation);
+        assert!(!registry.is_inflight("req-1"));
+    }
+}
@@ -1,10 +1,108 @@
 use crate::proxy::config::{ProxyAuthMode, ProxyConfig, SecurityMonitorConfig};
+use base64::{engine::general_purpose, Engine as _};
+use once_cell::sync::Lazy;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::sync::Mutex;
+use subtle::ConstantTimeEq;
+use tokio::sync::Semaphore;
+
+/// 管理密码哈希格式: `pbkdf2-sha256$<迭代次数>$<salt_b64>$<digest_b64>`
+const ADMIN_PASSWORD_SCHEME: &str = "pbkdf2-sha256";
+#[cfg(not(test))]
+const ADMIN_PASSWORD_ROUNDS: u32 = 600_000;
+#[cfg(test)]
+const ADMIN_PASSWORD_ROUNDS: u32 = 1_000;
+const ADMIN_PASSWORD_SALT_BYTES: usize = 16;
+
+/// 已校验通过的 (哈希, 密码) 指纹，命中后不再重复执行 KDF
+static VERIFIED_ADMIN_KEYS: Lazy<Mutex<HashSet<[u8; 32]>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+const VERIFIED_ADMIN_KEYS_LIMIT: usize = 64;
+/// 同时执行的 KDF 校验上限，避免错误猜测占满阻塞线程池
+static ADMIN_KDF_PERMITS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(2));
+
+fn derive_admin_password(plaintext: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut digest = [0u8; 32];
+    pbkdf2::pbkdf2_hmac::<Sha256>(plaintext.as_bytes(), salt, rounds, &mut digest);
+    digest
+}
+
+/// 生成管理密码的加盐哈希 (随机 salt，PBKDF2-HMAC-SHA256)
+pub fn hash_admin_password(plaintext: &str) -> String {
+    let mut salt = [0u8; ADMIN_PASSWORD_SALT_BYTES];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let digest = derive_admin_password(plaintext, &salt, ADMIN_PASSWORD_ROUNDS);
+    format!(
+        "{}${}${}${}",
+        ADMIN_PASSWORD_SCHEME,
+        ADMIN_PASSWORD_ROUNDS,
+        general_purpose::STANDARD_NO_PAD.encode(salt),
+        general_purpose::STANDARD_NO_PAD.encode(digest)
+    )
+}
+
+/// 校验管理密码 (常量时间比较)；哈希格式无法解析时一律拒绝。
+/// 计算量较大，异步上下文中请使用 [`verify_admin_password_async`]
+pub fn verify_admin_password(plaintext: &str, hash: &str) -> bool {
+    let mut parts = hash.split('$');
+    let (Some(scheme), Some(rounds), Some(salt), Some(expected), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+    let (Ok(rounds), Ok(salt), Ok(expected)) = (
+        rounds.parse::<u32>(),
+        general_purpose::STANDARD_NO_PAD.decode(salt),
+        general_purpose::STANDARD_NO_PAD.decode(expected),
+    ) else {
+        return false;
+    };
+    if scheme != ADMIN_PASSWORD_SCHEME || rounds == 0 {
+        return false;
+    }
+    let derived = derive_admin_password(plaintext, &salt, rounds);
+    bool::from(derived[..].ct_eq(&expected[..]))
+}
+
+/// 异步校验管理密码: KDF 在阻塞线程池中执行 (限制并发)，校验通过的凭据会被缓存
+pub async fn verify_admin_password_async(plaintext: &str, hash: &str) -> bool {
+    let fingerprint: [u8; 32] = Sha256::new()
+        .chain_update(hash.as_bytes())
+        .chain_update([0u8])
+        .chain_update(plaintext.as_bytes())
+        .finalize()
+        .into();
+    if VERIFIED_ADMIN_KEYS.lock().unwrap().contains(&fingerprint) {
+        return true;
+    }
+
+    let Ok(_permit) = ADMIN_KDF_PERMITS.acquire().await else {
+        return false;
+    };
+    let (plaintext, hash) = (plaintext.to_string(), hash.to_string());
+    let verified = tokio::task::spawn_blocking(move || verify_admin_password(&plaintext, &hash))
+        .await
+        .unwrap_or(false);
+
+    if verified {
+        let mut cache = VERIFIED_ADMIN_KEYS.lock().unwrap();
+        if cache.len() >= VERIFIED_ADMIN_KEYS_LIMIT {
+            cache.clear();
+        }
+        cache.insert(fingerprint);
+    }
+    verified
+}
 
 #[derive(Debug, Clone)]
 pub struct ProxySecurityConfig {
     pub auth_mode: ProxyAuthMode,
     pub api_key: String,
-    pub admin_password: Option<String>,
+    /// 轮换前的 api_key (宽限期内仍被接受)
+    pub retired_api_key: Option<crate::proxy::config::RetiredApiKey>,
+    /// 管理密码哈希 (未设置则管理接口回退使用 api_key)
+    pub admin_password_hash: Option<String>,
     pub allow_lan_access: bool,
     pub port: u16,
     pub security_monitor: SecurityMonitorConfig,
@@ -15,7 +113,13 @@ impl ProxySecurityConfig {
         Self {
             auth_mode: config.auth_mode.clone(),
             api_key: config.api_key.clone(),
-            admin_password: config.admin_password.clone(),
+            retired_api_key: config.retired_api_key.clone(),
+            // 新设置的明文密码 (如环境变量注入后尚未迁移) 优先于已保存的哈希，
+            // 只在内存中哈希，不保留明文
+            admin_password_hash: match config.admin_password.as_deref().filter(|p| !p.is_empty()) {
+                Some(plaintext) => Some(hash_admin_password(plaintext)),
+                None => config.admin_password_hash.clone(),
+            },
             allow_lan_access: config.allow_lan_access,
             port: config.port,
             security_monitor: config.security_monitor.clone(),
@@ -45,7 +149,8 @@ mod tests {
         let s = ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
-            admin_password: None,
+            retired_api_key: None,
+            admin_password_hash: None,
             allow_lan_access: false,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
@@ -58,7 +163,8 @@ mod tests {
         let s = ProxySecurityConfig {
             auth_mode: ProxyAuthMode::Auto,
             api_key: "sk-test".to_string(),
-            admin_password: None,
+            retired_api_key: None,
+            admin_password_hash: None,
             allow_lan_access: true,
             port: 8080,
             security_monitor: crate::proxy::config::SecurityMonitorConfig::default(),
@@ -68,5 +174,49 @@ mod tests {
             ProxyAuthMode::AllExceptHealth
         ));
     }
+
+    #[test]
+    fn admin_password_hash_verifies_only_correct_password() {
+        let hash = hash_admin_password("admin123");
+        assert!(hash.starts_with("pbkdf2-sha256$"));
+        assert!(!hash.contains("admin123"));
+
+        assert!(verify_admin_password("admin123", &hash));
+        assert!(!verify_admin_password("admin124", &hash));
+        assert!(!verify_admin_password("", &hash));
+
+        // 同一密码每次哈希使用不同 salt
+        assert_ne!(hash, hash_admin_password("admin123"));
+        // 格式错误的哈希 (例如直接写入的明文) 一律拒绝
+        assert!(!verify_admin_password("admin123", "admin123"));
+    }
+
+    #[test]
+    fn plaintext_admin_password_replaces_stored_hash() {
+        let mut config = ProxyConfig::default();
+        config.admin_password_hash = Some(hash_admin_password("old-password"));
+        config.admin_password = Some("new-password".to_string());
+
+        let security = ProxySecurityConfig::from_proxy_config(&config);
+        let hash = security.admin_password_hash.unwrap();
+        assert!(verify_admin_password("new-password", &hash));
+        assert!(!verify_admin_password("old-password", &hash));
+
+        // 没有明文时沿用已保存的哈希
+        config.admin_password = None;
+        let security = ProxySecurityConfig::from_proxy_config(&config);
+        assert!(verify_admin_password("old-password", &security.admin_password_hash.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn async_verification_caches_only_successes() {
+        let hash = hash_admin_password("admin123");
+        assert!(verify_admin_password_async("admin123", &hash).await);
+        // 第二次命中缓存
+        assert!(verify_admin_password_async("admin123", &hash).await);
+        assert!(!verify_admin_password_async("admin124", &hash).await);
+        // 缓存按哈希区分: 新哈希不会被旧的校验结果放行
+        assert!(!verify_admin_password_async("admin123", &hash_admin_password("other")).await);
+    }
 }
 
@@ -79,6 +79,50 @@ pub struct AppState {
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>, // [NEW] Cloudflared 插件状态
     pub is_running: Arc<RwLock<bool>>, // [NEW] 运行状态标识
     pub port: u16,                     // [NEW] 本地监听端口 (v4.0.8 修复)
+    pub chat_requests: Arc<crate::proxy::request_registry::RequestRegistry>, // In-flight chat requests
+    pub model_limiter: Arc<crate::proxy::model_limiter::ModelRateLimiter>, // 按模型限流
+    pub(crate) chat_sessions: Arc<crate::proxy::handlers::chat::ChatSessionHub>, // Chat session subscribers
+    pub shutdown: Arc<crate::proxy::shutdown::ShutdownCoordinator>, // 优雅关闭 (排空 WebSocket 连接)
+}
+
+#[cfg(test)]
+impl AppState {
+    /// 默认配置的应用状态 (无 Tauri 句柄、不连接日志数据库)，账号数据位于 `data_dir`
+    pub(crate) fn for_tests(data_dir: std::path::PathBuf) -> Self {
+        let proxy_config = crate::proxy::config::ProxyConfig::default();
+        let integration = crate::modules::integration::SystemManager::Headless;
+        Self {
+            token_manager: Arc::new(TokenManager::new(data_dir)),
+            custom_mapping: Arc::new(tokio::sync::RwLock::new(std::collections::HashMap::new())),
+            request_timeout: 300,
+            thought_signature_map: Arc::new(tokio::sync::Mutex::new(
+                std::collections::HashMap::new(),
+            )),
+            upstream_proxy: Arc::new(tokio::sync::RwLock::new(Default::default())),
+            upstream: Arc::new(crate::proxy::upstream::client::UpstreamClient::new(None)),
+            zai: Arc::new(RwLock::new(Default::default())),
+            provider_rr: Arc::new(AtomicUsize::new(0)),
+            zai_vision_mcp: Arc::new(crate::proxy::zai_vision_mcp::ZaiVisionMcpState::new()),
+            monitor: Arc::new(crate::proxy::monitor::ProxyMonitor::detached(100)),
+            experimental: Arc::new(RwLock::new(Default::default())),
+            debug_logging: Arc::new(RwLock::new(Default::default())),
+            switching: Arc::new(RwLock::new(false)),
+            integration: integration.clone(),
+            account_service: Arc::new(crate::modules::account_service::AccountService::new(
+                integration,
+            )),
+            security: Arc::new(RwLock::new(
+                crate::proxy::ProxySecurityConfig::from_proxy_config(&proxy_config),
+            )),
+            cloudflared_state: Arc::new(crate::commands::cloudflared::CloudflaredState::new()),
+            is_running: Arc::new(RwLock::new(true)),
+            port: proxy_config.port,
+            chat_requests: Arc::new(crate::proxy::request_registry::RequestRegistry::new()),
+            model_limiter: Arc::new(crate::proxy::model_limiter::ModelRateLimiter::new()),
+            chat_sessions: Arc::new(crate::proxy::session_hub::SessionHub::new()),
+            shutdown: Arc::new(crate::proxy::shutdown::ShutdownCoordinator::new()),
+        }
+    }
 }
 
 // 为 AppState 实现 FromRef，以便中间件提取 security 状态
@@ -177,11 +221,14 @@ pub struct AxumServer {
     upstream: Arc<crate::proxy::upstream::client::UpstreamClient>,
     security_state: Arc<RwLock<crate::proxy::ProxySecurityConfig>>,
     zai_state: Arc<RwLock<crate::proxy::ZaiConfig>>,
+    provider_rr: Arc<AtomicUsize>,
     experimental: Arc<RwLock<crate::proxy::config::ExperimentalConfig>>,
     debug_logging: Arc<RwLock<crate::proxy::config::DebugLoggingConfig>>,
     pub cloudflared_state: Arc<crate::commands::cloudflared::CloudflaredState>,
     pub is_running: Arc<RwLock<bool>>,
     pub token_manager: Arc<TokenManager>, // [NEW] 暴露出 TokenManager 供反代服务复用
+    model_limiter: Arc<crate::proxy::model_limiter::ModelRateLimiter>,
+    shutdown: Arc<crate::proxy::shutdown::ShutdownCoordinator>,
 }
 
 impl AxumServer {
@@ -190,6 +237,9 @@ impl AxumServer {
             let mut m = self.custom_mapping.write().await;
             *m = config.custom_mapping.clone();
         }
+        if let Err(e) = crate::proxy::common::model_mapping::set_regex_mappings(&config.custom_mapping_regex) {
+            tracing::error!("正则模型映射更新失败: {}", e);
+        }
         tracing::debug!("模型映射 (Custom) 已全量热更新");
     }
 
@@ -206,6 +256,12 @@ impl AxumServer {
         tracing::info!("反代服务安全配置已热更新");
     }
 
+    /// 更新按模型限流配置
+    pub fn update_rate_limits(&self, config: &crate::proxy::config::ProxyConfig) {
+        self.model_limiter.update_limits(&config.rate_limits);
+        tracing::info!("按模型限流配置已热更新: {} 条规则", config.rate_limits.len());
+    }
+
     pub async fn update_zai(&self, config: &crate::proxy::config::ProxyConfig) {
         let mut zai = self.zai_state.write().await;
         *zai = config.zai.clone();
@@ -234,6 +290,12 @@ impl AxumServer {
         self.upstream
             .update_ua_rotation(config.user_agent_pool.clone(), config.ua_rotation_mode.clone())
             .await;
+        self.upstream
+            .update_ua_weights(config.user_agent_weights.clone())
+            .await;
+        self.upstream
+            .set_saved_user_agent(config.saved_user_agent.clone())
+            .await;
 
         tracing::info!(
             "User-Agent 配置已热更新: override={:?}, rotation_mode={:?}, pool_size={}",
@@ -243,6 +305,23 @@ impl AxumServer {
         );
     }
 
+    /// Inspect where a request for `model` would be dispatched right now, without sending it
+    pub async fn current_backend_for(
+        &self,
+        model: &str,
+    ) -> crate::proxy::providers::dispatch::BackendSelection {
+        let zai = self.zai_state.read().await.clone();
+        let custom_mapping = self.custom_mapping.read().await.clone();
+        crate::proxy::providers::dispatch::current_backend_for(
+            &zai,
+            &self.token_manager,
+            &custom_mapping,
+            &self.provider_rr,
+            model,
+        )
+        .await
+    }
+
     pub async fn set_running(&self, running: bool) {
         let mut r = self.is_running.write().await;
         *r = running;
@@ -275,6 +354,8 @@ impl AxumServer {
         let experimental_state = Arc::new(RwLock::new(experimental_config));
         let debug_logging_state = Arc::new(RwLock::new(debug_logging));
         let is_running_state = Arc::new(RwLock::new(true));
+        let model_limiter = Arc::new(crate::proxy::model_limiter::ModelRateLimiter::new());
+        let shutdown = Arc::new(crate::proxy::shutdown::ShutdownCoordinator::new());
 
         let state = AppState {
             token_manager: token_manager.clone(),
@@ -309,19 +390,24 @@ impl AxumServer {
             cloudflared_state: cloudflared_state.clone(),
             is_running: is_running_state.clone(),
             port,
+            chat_requests: Arc::new(crate::proxy::request_registry::RequestRegistry::new()),
+            model_limiter: model_limiter.clone(),
+            chat_sessions: Arc::new(crate::proxy::session_hub::SessionHub::new()),
+            shutdown: shutdown.clone(),
         };
 
         // 构建路由 - 使用新架构的 handlers！
         use crate::proxy::handlers;
         use crate::proxy::middleware::{
-            admin_auth_middleware, auth_middleware, cors_layer, ip_filter_middleware,
-            monitor_middleware, service_status_middleware,
+            admin_auth_middleware, auth_middleware, body_limit_middleware, cors_layer,
+            ip_filter_middleware, monitor_middleware, service_status_middleware,
         };
 
         // 1. 构建主 AI 代理路由 (遵循 auth_mode 配置)
         let proxy_routes = Router::new()
             .route("/health", get(health_check_handler))
             .route("/healthz", get(health_check_handler))
+            .route("/health/detailed", get(detailed_health_handler))
             // OpenAI Protocol
             .route("/v1/models", get(handlers::openai::handle_list_models))
             .route(
@@ -457,6 +543,7 @@ impl AxumServer {
             .route("/proxy/stop", post(admin_stop_proxy_service))
             .route("/proxy/mapping", post(admin_update_model_mapping))
             .route("/proxy/api-key/generate", post(admin_generate_api_key))
+            .route("/proxy/admin-password", post(admin_set_admin_password))
             .route(
                 "/proxy/session-bindings/clear",
                 post(admin_clear_proxy_session_bindings),
@@ -506,6 +593,7 @@ impl AxumServer {
             )
             .route("/stats/token/summary", get(admin_get_token_stats_summary))
             .route("/stats/token/by-model", get(admin_get_token_stats_by_model))
+            .route("/stats/token/usage", get(admin_get_usage_stats))
             .route(
                 "/stats/token/model-trend/hourly",
                 get(admin_get_token_stats_model_trend_hourly),
@@ -555,6 +643,7 @@ impl AxumServer {
             .route("/system/antigravity/args", get(admin_get_antigravity_args))
             // WebSocket endpoints
             .route("/ws/realtime", get(ws_handler))
+            .route("/ws/chat", get(handlers::chat::handle_chat_ws))
             // OAuth (Web) - Admin 接口
             .route("/auth/url", get(admin_prepare_oauth_url_web))
             // 应用管理特定鉴权层 (强制校验)
@@ -564,11 +653,11 @@ impl AxumServer {
             ));
 
         // 3. 整合并应用全局层
-        // 从环境变量读取 body 大小限制，默认 50MB
+        // body 大小限制: 环境变量优先，否则使用配置中的 max_request_bytes (默认 100MB)
         let max_body_size: usize = std::env::var("ABV_MAX_BODY_SIZE")
             .ok()
             .and_then(|s| s.parse().ok())
-            .unwrap_or(100 * 1024 * 1024); // 默认 100MB
+            .unwrap_or_else(crate::proxy::common::body_limit::max_request_bytes);
         tracing::info!("请求体大小限制: {} MB", max_body_size / 1024 / 1024);
 
         let app = Router::new()
@@ -581,6 +670,8 @@ impl AxumServer {
                 state.clone(),
                 service_status_middleware,
             ))
+            // 请求体大小限制 (在监控层缓冲请求体之前拒绝超大请求)
+            .layer(axum::middleware::from_fn(body_limit_middleware))
             .layer(cors_layer())
             .layer(DefaultBodyLimit::max(max_body_size)) // 放宽 body 大小限制
             .with_state(state.clone());
@@ -614,11 +705,14 @@ impl AxumServer {
             upstream: state.upstream.clone(),
             security_state,
             zai_state,
+            provider_rr: provider_rr.clone(),
             experimental: experimental_state.clone(),
             debug_logging: debug_logging_state.clone(),
             cloudflared_state,
             is_running: is_running_state,
             token_manager: token_manager.clone(),
+            model_limiter,
+            shutdown,
         };
 
         // 在新任务中启动服务器
@@ -670,6 +764,20 @@ impl AxumServer {
         Ok((server_instance, handle))
     }
 
+    /// 通知聊天 WebSocket 连接收尾 (完成进行中的工作流并发送 Close 帧)，最多等待 `timeout`。
+    /// 返回 false 表示超时时仍有连接未关闭。
+    pub async fn drain_connections(&self, timeout: std::time::Duration) -> bool {
+        let drained = self.shutdown.drain(timeout).await;
+        if !drained {
+            tracing::warn!(
+                "{} 个聊天连接在 {}s 内未完成排空",
+                self.shutdown.active_connections(),
+                timeout.as_secs()
+            );
+        }
+        drained
+    }
+
     /// 停止服务器
     pub fn stop(&self) {
         let tx_mutex = self.shutdown_tx.clone();
@@ -694,6 +802,43 @@ async fn health_check_handler() -> Response {
     .into_response()
 }
 
+/// 详细健康检查: 各上游最近成功时间、错误率与当前调度模式
+/// 与 /healthz 不同，该端点受鉴权模式保护
+async fn detailed_health_handler(State(state): State<AppState>) -> Response {
+    use crate::proxy::providers::{dispatch::Backend, health};
+
+    let zai = state.zai.read().await.clone();
+    let now = chrono::Utc::now().timestamp();
+    let google_accounts = state.token_manager.len();
+
+    let mut google = serde_json::to_value(health::global().snapshot(Backend::Google, now))
+        .unwrap_or_else(|_| serde_json::json!({}));
+    google["configured"] = serde_json::json!(google_accounts > 0);
+    google["accounts"] = serde_json::json!(google_accounts);
+    google["fallback"] = serde_json::json!(health::global().availability(
+        Backend::Google,
+        now,
+        zai.fallback_failure_threshold,
+        zai.fallback_cooldown_secs,
+    ));
+
+    let mut zai_upstream = serde_json::to_value(health::global().snapshot(Backend::Zai, now))
+        .unwrap_or_else(|_| serde_json::json!({}));
+    zai_upstream["configured"] = serde_json::json!(zai.enabled && !zai.api_key.trim().is_empty());
+    zai_upstream["dispatch_enabled"] = serde_json::json!(zai.dispatch_enabled());
+
+    Json(serde_json::json!({
+        "status": "ok",
+        "version": env!("CARGO_PKG_VERSION"),
+        "dispatch_mode": zai.dispatch_mode,
+        "upstreams": {
+            "google": google,
+            "zai": zai_upstream,
+        }
+    }))
+    .into_response()
+}
+
 /// 静默成功处理器 (用于拦截遥测日志等)
 async fn silent_ok_handler() -> Response {
     StatusCode::OK.into_response()
@@ -1173,6 +1318,9 @@ async fn admin_save_config(
         let mut mapping = state.custom_mapping.write().await;
         *mapping = new_config.clone().proxy.custom_mapping;
     }
+    if let Err(e) = crate::proxy::common::model_mapping::set_regex_mappings(&new_config.proxy.custom_mapping_regex) {
+        tracing::error!("正则模型映射更新失败: {}", e);
+    }
 
     // 更新上游代理
     {
@@ -1198,6 +1346,19 @@ async fn admin_save_config(
         *exp = new_config.clone().proxy.experimental;
     }
 
+    // 更新按模型限流
+    state.model_limiter.update_limits(&new_config.proxy.rate_limits);
+
+    // 更新请求/响应体大小限制
+    crate::proxy::common::body_limit::apply_config(&new_config.proxy);
+    crate::proxy::middleware::cors::apply_config(&new_config.proxy);
+    // 更新上游重试策略
+    crate::proxy::upstream::retry::apply_config(&new_config.proxy);
+
+    // 同步聊天会话配置
+    crate::modules::chat_db::apply_config(&new_config.proxy.chat);
+    crate::commands::workflows::apply_widget_config(&new_config.proxy.widget);
+
     Ok(StatusCode::OK)
 }
 
@@ -1241,8 +1402,15 @@ async fn admin_stop_proxy_service(State(state): State<AppState>) -> impl IntoRes
         let _ = crate::modules::config::save_app_config(&config);
     }
 
-    let mut running = state.is_running.write().await;
-    *running = false;
+    {
+        let mut running = state.is_running.write().await;
+        *running = false;
+    }
+    // 等待聊天连接保存完进行中的回复
+    state
+        .shutdown
+        .drain(crate::proxy::shutdown::DRAIN_TIMEOUT)
+        .await;
     logger::log_info("[API] 反代服务功能已禁用 (Axum 模式 / 持久化已同步)");
     StatusCode::OK
 }
@@ -1260,6 +1428,12 @@ async fn admin_update_model_mapping(
     let config = payload.config;
 
     // 1. 更新内存状态 (热更新)
+    crate::proxy::common::model_mapping::set_regex_mappings(&config.custom_mapping_regex).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
     {
         let mut mapping = state.custom_mapping.write().await;
         *mapping = config.custom_mapping.clone();
@@ -1275,6 +1449,7 @@ async fn admin_update_model_mapping(
     })?;
 
     app_config.proxy.custom_mapping = config.custom_mapping;
+    app_config.proxy.custom_mapping_regex = config.custom_mapping_regex;
 
     crate::modules::config::save_app_config(&app_config).map_err(|e| {
         (
@@ -1292,6 +1467,37 @@ async fn admin_generate_api_key() -> impl IntoResponse {
     Json(new_key)
 }
 
+#[derive(Deserialize)]
+struct SetAdminPasswordRequest {
+    password: String,
+}
+
+async fn admin_set_admin_password(
+    State(state): State<AppState>,
+    Json(payload): Json<SetAdminPasswordRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let mut app_config = config::load_app_config().map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+    app_config.proxy.set_admin_password(&payload.password);
+    config::save_app_config(&app_config).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse { error: e }),
+        )
+    })?;
+
+    {
+        let mut security = state.security.write().await;
+        *security = crate::proxy::ProxySecurityConfig::from_proxy_config(&app_config.proxy);
+    }
+    logger::log_info("[API] 已更新 Web UI 管理密码");
+    Ok(StatusCode::OK)
+}
+
 async fn admin_clear_proxy_session_bindings(State(state): State<AppState>) -> impl IntoResponse {
     state.token_manager.clear_all_sessions();
     logger::log_info("[API] 已清除所有会话绑定");
@@ -1580,6 +1786,32 @@ struct StatsPeriodQuery {
     weeks: Option<i64>,
 }
 
+#[derive(Deserialize)]
+struct UsageRangeQuery {
+    from: i64,
+    to: i64,
+}
+
+async fn admin_get_usage_stats(
+    Query(p): Query<UsageRangeQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let res = tokio::task::spawn_blocking(move || token_stats::get_usage_stats(p.from, p.to)).await;
+
+    match res {
+        Ok(Ok(stats)) => Ok(Json(stats)),
+        Ok(Err(e)) => Err((
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse { error: e }),
+        )),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+            }),
+        )),
+    }
+}
+
 async fn admin_get_token_stats_hourly(
     Query(p): Query<StatsPeriodQuery>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
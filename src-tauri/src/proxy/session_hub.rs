@@ -0,0 +1,153 @@
+// Per-session broadcast of chat events (session id -> subscribers)
+// Lets every connection watching a session see messages appended by another connection.
+
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+/// Buffered events per session before slow subscribers start lagging
+const SESSION_CHANNEL_CAPACITY: usize = 64;
+
+/// An event published to a session, tagged with the connection that produced it
+#[derive(Debug, Clone)]
+pub struct SessionEvent<T> {
+    pub origin: u64,
+    pub payload: T,
+}
+
+/// Broadcast channels keyed by session id
+pub struct SessionHub<T: Clone> {
+    channels: DashMap<String, broadcast::Sender<SessionEvent<T>>>,
+    next_connection: AtomicU64,
+}
+
+impl<T: Clone> Default for SessionHub<T> {
+    fn default() -> Self {
+        Self {
+            channels: DashMap::new(),
+            next_connection: AtomicU64::new(1),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> SessionHub<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allocate an id for a new connection (used as the event origin)
+    pub fn register_connection(&self) -> u64 {
+        self.next_connection.fetch_add(1, Ordering::Relaxed)
+    }
+
+    pub fn subscribe(&self, session_id: &str) -> broadcast::Receiver<SessionEvent<T>> {
+        self.channels
+            .entry(session_id.to_string())
+            .or_insert_with(|| broadcast::channel(SESSION_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    /// Publish to everyone watching the session. Returns the number of subscribers reached
+    /// (0 when nobody is watching).
+    pub fn publish(&self, session_id: &str, origin: u64, payload: T) -> usize {
+        self.channels
+            .get(session_id)
+            .and_then(|tx| tx.send(SessionEvent { origin, payload }).ok())
+            .unwrap_or(0)
+    }
+
+    /// Forward the session's events from other connections into `outbox` until the
+    /// session is removed or the outbox closes
+    pub fn spawn_forwarder(
+        &self,
+        session_id: &str,
+        origin: u64,
+        outbox: mpsc::UnboundedSender<T>,
+    ) -> JoinHandle<()> {
+        let mut rx = self.subscribe(session_id);
+        let session_id = session_id.to_string();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if event.origin == origin => {}
+                    Ok(event) => {
+                        if outbox.send(event.payload).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Session {} subscriber lagged, dropped {} events", session_id, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Drop the session's channel if nobody is subscribed any more
+    pub fn prune(&self, session_id: &str) {
+        self.channels.remove_if(session_id, |_, tx| tx.receiver_count() == 0);
+    }
+
+    /// Drop the session's channel unconditionally (subscribers see it close)
+    pub fn remove(&self, session_id: &str) {
+        self.channels.remove(session_id);
+    }
+
+    pub fn is_watched(&self, session_id: &str) -> bool {
+        self.channels.contains_key(session_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_two_connections_on_one_session() {
+        let hub: SessionHub<String> = SessionHub::new();
+        let (first_conn, second_conn) = (hub.register_connection(), hub.register_connection());
+        let (first_tx, mut first_rx) = mpsc::unbounded_channel();
+        let (second_tx, mut second_rx) = mpsc::unbounded_channel();
+
+        let first = hub.spawn_forwarder("session-a", first_conn, first_tx);
+        let second = hub.spawn_forwarder("session-a", second_conn, second_tx);
+
+        // Appended via the first connection: only the second one is notified
+        assert_eq!(hub.publish("session-a", first_conn, "hello".to_string()), 2);
+        let received = tokio::time::timeout(Duration::from_secs(1), second_rx.recv())
+            .await
+            .unwrap();
+        assert_eq!(received.as_deref(), Some("hello"));
+        assert!(first_rx.try_recv().is_err());
+
+        // Other sessions are not affected
+        assert_eq!(hub.publish("session-b", first_conn, "ignored".to_string()), 0);
+
+        // Disconnect: once every subscriber is gone the channel is dropped
+        first.abort();
+        let _ = first.await;
+        hub.prune("session-a");
+        assert!(hub.is_watched("session-a"));
+        second.abort();
+        let _ = second.await;
+        hub.prune("session-a");
+        assert!(!hub.is_watched("session-a"));
+    }
+
+    #[tokio::test]
+    async fn test_removed_session_closes_forwarders() {
+        let hub: SessionHub<String> = SessionHub::new();
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let forwarder = hub.spawn_forwarder("session-a", hub.register_connection(), tx);
+
+        hub.remove("session-a");
+        tokio::time::timeout(Duration::from_secs(1), forwarder)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
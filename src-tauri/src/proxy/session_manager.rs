@@ -3,10 +3,30 @@ use crate::proxy::mappers::claude::models::{ClaudeRequest, MessageContent};
 use crate::proxy::mappers::openai::models::{OpenAIRequest, OpenAIContent};
 use serde_json::Value;
 
+/// 客户端会话头生成的 session_id 前缀，与基于内容指纹的 session_id 区分
+pub const HEADER_SESSION_PREFIX: &str = "hdr:";
+/// 会话头取值的最大长度，超出视为无效
+const MAX_HEADER_SESSION_LEN: usize = 256;
+
 /// 会话管理器工具
 pub struct SessionManager;
 
 impl SessionManager {
+    /// 从客户端会话头 (默认 `X-Session-Id`) 提取 session_id。
+    /// 头缺失、为空或超长时返回 None，由调用方回退到内容指纹
+    pub fn session_id_from_header(headers: &axum::http::HeaderMap, header_name: &str) -> Option<String> {
+        let value = headers.get(header_name)?.to_str().ok()?.trim();
+        if value.is_empty() || value.len() > MAX_HEADER_SESSION_LEN {
+            return None;
+        }
+        Some(format!("{}{}", HEADER_SESSION_PREFIX, value))
+    }
+
+    /// 是否为客户端通过会话头显式指定的会话
+    pub fn is_header_session(session_id: &str) -> bool {
+        session_id.starts_with(HEADER_SESSION_PREFIX)
+    }
+
     /// 根据 Claude 请求生成稳定的会话指纹 (Session Fingerprint)
     /// 
     /// 设计理念:
@@ -0,0 +1,136 @@
+// Coordinated shutdown of long-lived connections (chat WebSockets)
+// The server is stopped logically and restarted in place, so the signal is an epoch:
+// triggering it ends the connections open at that moment, later connections are unaffected.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{watch, Notify};
+
+/// How long a stop waits for chat connections to finish their in-flight workflows
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Counts outstanding work; `wait_idle` resolves once every guard has been dropped
+#[derive(Clone, Default)]
+pub struct DrainTracker {
+    active: Arc<AtomicUsize>,
+    idle: Arc<Notify>,
+}
+
+impl DrainTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Mark one unit of work as in progress until the guard is dropped
+    pub fn guard(&self) -> DrainGuard {
+        self.active.fetch_add(1, Ordering::SeqCst);
+        DrainGuard {
+            tracker: self.clone(),
+        }
+    }
+
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::SeqCst)
+    }
+
+    pub async fn wait_idle(&self) {
+        loop {
+            // Registered before the check, so a release in between is not missed
+            let idle = self.idle.notified();
+            if self.active() == 0 {
+                return;
+            }
+            idle.await;
+        }
+    }
+
+    /// Wait for all guards up to `timeout`. Returns false if work was still running.
+    pub async fn wait_idle_timeout(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, self.wait_idle()).await.is_ok()
+    }
+}
+
+pub struct DrainGuard {
+    tracker: DrainTracker,
+}
+
+impl Drop for DrainGuard {
+    fn drop(&mut self) {
+        if self.tracker.active.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.tracker.idle.notify_waiters();
+        }
+    }
+}
+
+/// Shutdown signal plus the set of connections that still have to drain
+pub struct ShutdownCoordinator {
+    epoch: watch::Sender<u64>,
+    connections: DrainTracker,
+}
+
+impl Default for ShutdownCoordinator {
+    fn default() -> Self {
+        Self {
+            epoch: watch::channel(0).0,
+            connections: DrainTracker::new(),
+        }
+    }
+}
+
+impl ShutdownCoordinator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a connection: the receiver fires on the next `trigger`, the guard keeps
+    /// `drain` waiting until the connection has finished
+    pub fn track_connection(&self) -> (watch::Receiver<u64>, DrainGuard) {
+        (self.epoch.subscribe(), self.connections.guard())
+    }
+
+    /// Number of connections that have not finished yet
+    pub fn active_connections(&self) -> usize {
+        self.connections.active()
+    }
+
+    /// Signal every tracked connection to finish its in-flight work and close
+    pub fn trigger(&self) {
+        self.epoch.send_modify(|epoch| *epoch += 1);
+    }
+
+    /// Trigger and wait up to `timeout` for the connections to drain.
+    /// Returns false if some were still open when the timeout elapsed.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.trigger();
+        self.connections.wait_idle_timeout(timeout).await
+    }
+}
+
+/// Resolves once shutdown is triggered after `signal` was obtained
+/// (a closed channel counts as shutdown)
+pub async fn triggered(signal: &mut watch::Receiver<u64>) {
+    let _ = signal.changed().await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_drain_times_out_and_rearms() {
+        let coordinator = ShutdownCoordinator::new();
+        let (_signal, stuck) = coordinator.track_connection();
+        assert!(!coordinator.drain(Duration::from_millis(20)).await);
+        drop(stuck);
+        assert!(coordinator.drain(Duration::from_millis(20)).await);
+
+        // A connection opened after a shutdown only reacts to the next one
+        let (mut signal, _guard) = coordinator.track_connection();
+        assert!(!signal.has_changed().unwrap());
+        coordinator.trigger();
+        tokio::time::timeout(Duration::from_secs(1), triggered(&mut signal))
+            .await
+            .unwrap();
+    }
+}
@@ -25,6 +25,10 @@ pub struct StickySessionConfig {
     pub mode: SchedulingMode,
     /// 缓存优先模式下的最大等待时间 (秒)
     pub max_wait_seconds: u64,
+    /// 客户端会话头名称：携带该头的请求按其值固定到同一账号 (任何调度模式下均生效)
+    pub session_header: String,
+    /// 会话头建立的账号绑定有效期 (秒)，过期后重新分配，0 表示不过期
+    pub session_header_ttl_seconds: u64,
 }
 
 impl Default for StickySessionConfig {
@@ -32,6 +36,8 @@ impl Default for StickySessionConfig {
         Self {
             mode: SchedulingMode::Balance,
             max_wait_seconds: 60,
+            session_header: "X-Session-Id".to_string(),
+            session_header_ttl_seconds: 1800,
         }
     }
 }
@@ -211,6 +211,64 @@ mod security_db_tests {
         cleanup_test_data();
     }
 
+    #[test]
+    fn test_cidr_boundaries() {
+        use security_db::ip_matches_pattern;
+
+        // /24 网段的首尾地址都在网段内，相邻地址不在
+        assert!(ip_matches_pattern("192.168.1.0", "192.168.1.0/24"));
+        assert!(ip_matches_pattern("192.168.1.255", "192.168.1.0/24"));
+        assert!(!ip_matches_pattern("192.168.0.255", "192.168.1.0/24"));
+        assert!(!ip_matches_pattern("192.168.2.0", "192.168.1.0/24"));
+
+        // 非对齐的网段地址按网络前缀匹配
+        assert!(ip_matches_pattern("192.168.1.7", "192.168.1.100/24"));
+
+        // IPv6 /64 边界
+        assert!(ip_matches_pattern("2001:db8:0:1::", "2001:db8:0:1::/64"));
+        assert!(ip_matches_pattern("2001:db8:0:1:ffff:ffff:ffff:ffff", "2001:db8:0:1::/64"));
+        assert!(!ip_matches_pattern("2001:db8:0:2::", "2001:db8:0:1::/64"));
+        assert!(!ip_matches_pattern("2001:db8:0:0:ffff:ffff:ffff:ffff", "2001:db8:0:1::/64"));
+
+        // IPv4 与 IPv6 互不匹配
+        assert!(!ip_matches_pattern("10.0.0.1", "::/0"));
+        assert!(!ip_matches_pattern("::1", "0.0.0.0/0"));
+
+        // IPv4-mapped IPv6 地址按 IPv4 匹配
+        assert!(ip_matches_pattern("::ffff:10.1.2.3", "10.0.0.0/8"));
+    }
+
+    #[test]
+    fn test_malformed_patterns_rejected() {
+        let _ = init_db();
+        cleanup_test_data();
+
+        for pattern in ["", "10.0.0.0/33", "10.0.0/8", "2001:db8::/129", "not-an-ip", "10.0.0.1/"] {
+            assert!(security_db::parse_ip_pattern(pattern).is_err(), "{} should be rejected", pattern);
+            assert!(add_to_blacklist(pattern, None, None, "test").is_err(), "{} should not be stored", pattern);
+            assert!(add_to_whitelist(pattern, None).is_err(), "{} should not be stored", pattern);
+        }
+
+        // 无法解析的客户端 IP 不匹配任何网段
+        assert!(!security_db::ip_matches_pattern("garbage", "0.0.0.0/0"));
+    }
+
+    #[test]
+    fn test_most_specific_entry_wins() {
+        let _ = init_db();
+        cleanup_test_data();
+
+        let _ = add_to_blacklist("10.0.0.0/8", Some("Broad"), None, "test");
+        let _ = add_to_blacklist("10.1.0.0/16", Some("Narrow"), None, "test");
+
+        let entry = get_blacklist_entry_for_ip("10.1.2.3").unwrap().unwrap();
+        assert_eq!(entry.reason.as_deref(), Some("Narrow"));
+        let entry = get_blacklist_entry_for_ip("10.2.0.1").unwrap().unwrap();
+        assert_eq!(entry.reason.as_deref(), Some("Broad"));
+
+        cleanup_test_data();
+    }
+
     // ============================================================================
     // 测试类别 4: 过期时间处理
     // ============================================================================
@@ -222,14 +280,14 @@ mod security_db_tests {
 
         // 添加一个已过期的条目
         let _ = add_to_blacklist(
-            "expired.test.ip",
+            "198.51.100.11",
             Some("Already expired"),
             Some(now_timestamp() - 60), // 1分钟前过期
             "test",
         );
 
         // 过期条目应该被自动清理
-        let is_blocked = is_ip_in_blacklist("expired.test.ip");
+        let is_blocked = is_ip_in_blacklist("198.51.100.11");
         // 注意：取决于实现，过期条目可能在查询时被清理
         // 根据 security_db.rs 的实现，get_blacklist_entry_for_ip 会先清理过期条目
         assert!(!is_blocked.unwrap(), "Expired entry should be cleaned up");
@@ -244,14 +302,14 @@ mod security_db_tests {
 
         // 添加一个未过期的条目
         let _ = add_to_blacklist(
-            "not.expired.ip",
+            "198.51.100.12",
             Some("Will expire later"),
             Some(now_timestamp() + 3600), // 1小时后过期
             "test",
         );
 
         // 未过期条目应该仍然生效
-        assert!(is_ip_in_blacklist("not.expired.ip").unwrap());
+        assert!(is_ip_in_blacklist("198.51.100.12").unwrap());
 
         cleanup_test_data();
     }
@@ -263,14 +321,14 @@ mod security_db_tests {
 
         // 添加永久封禁 (无过期时间)
         let _ = add_to_blacklist(
-            "permanent.block.ip",
+            "198.51.100.13",
             Some("Permanent ban"),
             None, // 无过期时间
             "test",
         );
 
         // 永久封禁应该始终生效
-        assert!(is_ip_in_blacklist("permanent.block.ip").unwrap());
+        assert!(is_ip_in_blacklist("198.51.100.13").unwrap());
 
         cleanup_test_data();
     }
@@ -313,6 +371,29 @@ mod security_db_tests {
         cleanup_test_data();
     }
 
+    #[test]
+    fn test_list_changes_visible_after_cached_lookup() {
+        let _ = init_db();
+        cleanup_test_data();
+
+        // 先查询一次，让名单缓存被加载
+        assert!(!is_ip_in_whitelist("172.20.1.1").unwrap());
+        assert!(!is_ip_in_blacklist("172.21.1.1").unwrap());
+
+        // 增删条目后缓存应立即失效
+        let white = add_to_whitelist("172.20.0.0/16", None).unwrap();
+        let black = add_to_blacklist("172.21.0.0/16", Some("Cache test"), None, "test").unwrap();
+        assert!(is_ip_in_whitelist("172.20.1.1").unwrap());
+        assert!(is_ip_in_blacklist("172.21.1.1").unwrap());
+
+        remove_from_whitelist(&white.id).unwrap();
+        remove_from_blacklist(&black.id).unwrap();
+        assert!(!is_ip_in_whitelist("172.20.1.1").unwrap());
+        assert!(!is_ip_in_blacklist("172.21.1.1").unwrap());
+
+        cleanup_test_data();
+    }
+
     // ============================================================================
     // 测试类别 6: IP 访问日志
     // ============================================================================
@@ -425,9 +506,9 @@ mod security_db_tests {
         }
 
         // 添加黑名单和白名单条目
-        let _ = add_to_blacklist("stats.black.1", None, None, "test");
-        let _ = add_to_blacklist("stats.black.2", None, None, "test");
-        let _ = add_to_whitelist("stats.white.1", None);
+        add_to_blacklist("198.51.100.17", None, None, "test").unwrap();
+        add_to_blacklist("198.51.100.18", None, None, "test").unwrap();
+        let _ = add_to_whitelist("198.51.100.16", None);
 
         // 获取统计
         let stats = get_ip_stats();
@@ -515,7 +596,7 @@ mod security_db_tests {
             .map(|i| {
                 thread::spawn(move || {
                     // 每个线程添加不同的 IP
-                    let ip = format!("concurrent.test.{}", i);
+                    let ip = format!("203.0.113.{}", i);
                     let _ = add_to_blacklist(&ip, Some("Concurrent test"), None, "test");
                     
                     // 验证自己添加的 IP
@@ -542,11 +623,11 @@ mod security_db_tests {
         cleanup_test_data();
 
         // 第一次添加应该成功
-        let result1 = add_to_blacklist("duplicate.test.ip", Some("First"), None, "test");
+        let result1 = add_to_blacklist("198.51.100.10", Some("First"), None, "test");
         assert!(result1.is_ok());
 
         // 第二次添加相同 IP 应该失败 (UNIQUE constraint)
-        let result2 = add_to_blacklist("duplicate.test.ip", Some("Second"), None, "test");
+        let result2 = add_to_blacklist("198.51.100.10", Some("Second"), None, "test");
         assert!(result2.is_err(), "Duplicate IP should fail");
 
         cleanup_test_data();
@@ -557,11 +638,9 @@ mod security_db_tests {
         let _ = init_db();
         cleanup_test_data();
 
-        // 空 IP 模式应该仍然可以添加 (取决于业务需求)
-        // 这里只测试不会 panic
+        // 空 IP 模式不是合法的 IP / CIDR，应被拒绝
         let result = add_to_blacklist("", Some("Empty IP"), None, "test");
-        // 结果可能成功或失败，但不应该 panic
-        let _ = result;
+        assert!(result.is_err());
 
         cleanup_test_data();
     }
@@ -573,10 +652,10 @@ mod security_db_tests {
 
         // 测试包含特殊字符的原因
         let reason = "Test with 'quotes' and \"double quotes\" and emoji 🚫";
-        let result = add_to_blacklist("special.char.test", Some(reason), None, "test");
+        let result = add_to_blacklist("198.51.100.14", Some(reason), None, "test");
         assert!(result.is_ok());
 
-        let entry = get_blacklist_entry_for_ip("special.char.test").unwrap().unwrap();
+        let entry = get_blacklist_entry_for_ip("198.51.100.14").unwrap().unwrap();
         assert_eq!(entry.reason.as_deref(), Some(reason));
 
         cleanup_test_data();
@@ -588,16 +667,16 @@ mod security_db_tests {
         cleanup_test_data();
 
         // 添加一个黑名单条目
-        add_to_blacklist("hit.count.test", Some("Count test"), None, "test").expect("Failed to add blacklist entry");
+        add_to_blacklist("198.51.100.15", Some("Count test"), None, "test").expect("Failed to add blacklist entry");
 
         // 多次查询应该增加 hit_count
         for _ in 0..5 {
             // use check_and_update_blacklist_entry to trigger hit count update
-            let _ = check_and_update_blacklist_entry("hit.count.test");
+            let _ = check_and_update_blacklist_entry("198.51.100.15");
         }
 
         // 检查 hit_count
-        let entry = get_blacklist_entry_for_ip("hit.count.test").unwrap();
+        let entry = get_blacklist_entry_for_ip("198.51.100.15").unwrap();
         assert!(entry.is_some());
         assert!(entry.unwrap().hit_count >= 5, "Hit count should be at least 5");
 
@@ -660,7 +739,7 @@ mod performance_benchmarks {
 
         for i in 0..100 {
             let _ = add_to_blacklist(
-                &format!("bench.ip.{}", i),
+                &format!("198.18.0.{}", i),
                 Some("Benchmark"),
                 None,
                 "test",
@@ -670,7 +749,7 @@ mod performance_benchmarks {
         // 执行 1000 次查找
         let start = Instant::now();
         for _ in 0..1000 {
-            let _ = is_ip_in_blacklist("bench.ip.50");
+            let _ = is_ip_in_blacklist("198.18.0.50");
         }
         let duration = start.elapsed();
 
@@ -6,6 +6,7 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
 use crate::proxy::rate_limit::RateLimitTracker;
+use crate::proxy::session_manager::SessionManager;
 use crate::proxy::sticky_config::StickySessionConfig;
 
 #[derive(Debug, Clone)]
@@ -35,6 +36,7 @@ pub struct TokenManager {
     rate_limit_tracker: Arc<RateLimitTracker>, // 新增: 限流跟踪器
     sticky_config: Arc<tokio::sync::RwLock<StickySessionConfig>>, // 新增：调度配置
     session_accounts: Arc<DashMap<String, String>>, // 新增：会话与账号映射 (SessionID -> AccountID)
+    session_bound_at: Arc<DashMap<String, std::time::Instant>>, // 会话绑定建立时间 (用于会话头绑定过期)
     preferred_account_id: Arc<tokio::sync::RwLock<Option<String>>>, // [FIX #820] 优先使用的账号ID（固定账号模式）
     health_scores: Arc<DashMap<String, f32>>,                       // account_id -> health_score
     circuit_breaker_config: Arc<tokio::sync::RwLock<crate::models::CircuitBreakerConfig>>, // [NEW] 熔断配置缓存
@@ -51,6 +53,7 @@ impl TokenManager {
             rate_limit_tracker: Arc::new(RateLimitTracker::new()),
             sticky_config: Arc::new(tokio::sync::RwLock::new(StickySessionConfig::default())),
             session_accounts: Arc::new(DashMap::new()),
+            session_bound_at: Arc::new(DashMap::new()),
             preferred_account_id: Arc::new(tokio::sync::RwLock::new(None)), // [FIX #820]
             health_scores: Arc::new(DashMap::new()),
             circuit_breaker_config: Arc::new(tokio::sync::RwLock::new(
@@ -946,13 +949,25 @@ impl TokenManager {
             let normalized_target = crate::proxy::common::model_mapping::normalize_to_standard_id(target_model)
                 .unwrap_or_else(|| target_model.to_string());
 
-            // 模式 A: 粘性会话处理 (CacheFirst 或 Balance 且有 session_id)
+            // 模式 A: 粘性会话处理 (CacheFirst 或 Balance 且有 session_id；
+            // 客户端通过会话头显式指定的会话在任何模式下都保持粘性)
             if !rotate
-                && session_id.is_some()
-                && scheduling.mode != SchedulingMode::PerformanceFirst
+                && session_id.is_some_and(|sid| {
+                    scheduling.mode != SchedulingMode::PerformanceFirst
+                        || SessionManager::is_header_session(sid)
+                })
             {
                 let sid = session_id.unwrap();
 
+                // 会话头绑定超过有效期后解绑，重新分配账号
+                if self.session_binding_expired(sid, scheduling.session_header_ttl_seconds) {
+                    tracing::debug!(
+                        "Sticky Session: Binding for header session {:?} expired, unbinding",
+                        crate::utils::mask::mask_secret(sid)
+                    );
+                    self.unbind_session(sid);
+                }
+
                 // 1. 检查会话是否已绑定账号
                 if let Some(bound_id) = self.session_accounts.get(sid).map(|v| v.clone()) {
                     // 【修复】先通过 account_id 找到对应的账号，获取其 email
@@ -972,7 +987,7 @@ impl TokenManager {
                                 "Sticky Session: Bound account {} is rate-limited ({}s), unbinding and switching.",
                                 bound_token.email, reset_sec
                             );
-                            self.session_accounts.remove(sid);
+                            self.unbind_session(sid);
                         } else if !attempted.contains(&bound_id)
                             && !(quota_protection_enabled
                                 && bound_token.protected_models.contains(&normalized_target))
@@ -984,7 +999,7 @@ impl TokenManager {
                             && bound_token.protected_models.contains(&normalized_target)
                         {
                             tracing::debug!("Sticky Session: Bound account {} is quota-protected for model {} [{}], unbinding and switching.", bound_token.email, normalized_target, target_model);
-                            self.session_accounts.remove(sid);
+                            self.unbind_session(sid);
                         }
                     } else {
                         // 绑定的账号已不存在（可能被删除），解绑
@@ -992,7 +1007,7 @@ impl TokenManager {
                             "Sticky Session: Bound account not found for session {:?}, unbinding",
                             crate::utils::mask::mask_secret(sid)
                         );
-                        self.session_accounts.remove(sid);
+                        self.unbind_session(sid);
                     }
                 }
             }
@@ -1079,8 +1094,7 @@ impl TokenManager {
                         // 如果是会话首次分配且需要粘性，在此建立绑定
                         if let Some(sid) = session_id {
                             if scheduling.mode != SchedulingMode::PerformanceFirst {
-                                self.session_accounts
-                                    .insert(sid.to_string(), candidate.account_id.clone());
+                                self.bind_session(sid, &candidate.account_id);
                                 tracing::debug!(
                                     "Sticky Session: Bound new account {} to session {}",
                                     candidate.email,
@@ -1312,6 +1326,13 @@ impl TokenManager {
                 }
             };
 
+            // 会话头指定的会话: 首次成功分配即建立绑定 (含 60s 锁定复用和纯轮询模式)
+            if let Some(sid) = session_id.filter(|sid| SessionManager::is_header_session(sid)) {
+                if !self.session_accounts.contains_key(sid) {
+                    self.bind_session(sid, &token.account_id);
+                }
+            }
+
             // 【优化】在成功返回前，统一更新 last_used_account（如果需要）
             if let Some((new_account_id, new_time)) = need_update_last_used {
                 if quota_group != "image_gen" {
@@ -1901,12 +1922,41 @@ impl TokenManager {
     /// 清除特定会话的粘性映射
     #[allow(dead_code)]
     pub fn clear_session_binding(&self, session_id: &str) {
-        self.session_accounts.remove(session_id);
+        self.unbind_session(session_id);
     }
 
     /// 清除所有会话的粘性映射
     pub fn clear_all_sessions(&self) {
         self.session_accounts.clear();
+        self.session_bound_at.clear();
+    }
+
+    fn bind_session(&self, session_id: &str, account_id: &str) {
+        self.session_accounts
+            .insert(session_id.to_string(), account_id.to_string());
+        self.session_bound_at
+            .insert(session_id.to_string(), std::time::Instant::now());
+    }
+
+    fn unbind_session(&self, session_id: &str) {
+        self.session_accounts.remove(session_id);
+        self.session_bound_at.remove(session_id);
+    }
+
+    /// 会话头建立的绑定是否已超过有效期 (ttl 为 0 表示不过期；基于内容指纹的会话不过期)
+    fn session_binding_expired(&self, session_id: &str, ttl_secs: u64) -> bool {
+        ttl_secs > 0
+            && SessionManager::is_header_session(session_id)
+            && self
+                .session_bound_at
+                .get(session_id)
+                .is_some_and(|bound_at| bound_at.elapsed().as_secs() >= ttl_secs)
+    }
+
+    /// 从请求头提取客户端指定的会话 id (头名称来自调度配置)
+    pub async fn header_session_id(&self, headers: &axum::http::HeaderMap) -> Option<String> {
+        let header_name = self.sticky_config.read().await.session_header.clone();
+        SessionManager::session_id_from_header(headers, &header_name)
     }
 
     // ===== [FIX #820] 固定账号模式相关方法 =====
@@ -2099,6 +2149,8 @@ impl TokenManager {
 
         // Clear sticky session if blocked
         self.session_accounts.retain(|_, v| *v != account_id);
+        self.session_bound_at
+            .retain(|k, _| self.session_accounts.contains_key(k));
 
         let json_str = serde_json::to_string_pretty(&account)
              .map_err(|e| format!("Failed to serialize account JSON: {}", e))?;
@@ -2332,6 +2384,51 @@ mod tests {
         assert_eq!(tokens[1].email, "a@test.com");
     }
 
+    #[tokio::test]
+    async fn test_same_session_header_hits_same_account() {
+        let manager = TokenManager::new(PathBuf::from("/tmp/test"));
+        for email in ["a@test.com", "b@test.com", "c@test.com"] {
+            let mut token = create_test_token(email, Some("PRO"), 1.0, None, Some(80));
+            token.project_id = Some("test-project".to_string());
+            manager.tokens.insert(token.account_id.clone(), token);
+        }
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("X-Session-Id", "client-session-1".parse().unwrap());
+        let sid = manager.header_session_id(&headers).await.unwrap();
+        let model = "claude-sonnet-4-5";
+
+        let (_, _, first, _) = manager.get_token("claude", false, Some(&sid), model).await.unwrap();
+        // Another client moves the shared rotation to a different account in between
+        let (_, _, other, _) = manager.get_token("claude", true, None, model).await.unwrap();
+        assert_ne!(other, first);
+        let (_, _, second, _) = manager.get_token("claude", false, Some(&sid), model).await.unwrap();
+        assert_eq!(second, first);
+
+        // The binding expires after session_header_ttl_seconds
+        assert!(!manager.session_binding_expired(&sid, 1800));
+        manager.session_bound_at.insert(
+            sid.clone(),
+            std::time::Instant::now() - std::time::Duration::from_secs(1801),
+        );
+        assert!(manager.session_binding_expired(&sid, 1800));
+        assert!(!manager.session_binding_expired(&sid, 0));
+    }
+
+    #[test]
+    fn test_session_id_from_header() {
+        let mut headers = axum::http::HeaderMap::new();
+        assert_eq!(SessionManager::session_id_from_header(&headers, "X-Session-Id"), None);
+
+        headers.insert("x-session-id", " abc ".parse().unwrap());
+        let sid = SessionManager::session_id_from_header(&headers, "X-Session-Id").unwrap();
+        assert_eq!(sid, "hdr:abc");
+        assert!(SessionManager::is_header_session(&sid));
+
+        headers.insert("x-session-id", "".parse().unwrap());
+        assert_eq!(SessionManager::session_id_from_header(&headers, "X-Session-Id"), None);
+    }
+
     #[test]
     fn test_extract_earliest_reset_time() {
         let manager = TokenManager::new(PathBuf::from("/tmp/test"));
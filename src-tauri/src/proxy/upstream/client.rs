@@ -5,11 +5,11 @@ use reqwest::{header, Client, Response, StatusCode};
 use serde_json::Value;
 use tokio::time::Duration;
 use tokio::sync::RwLock;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
 use rand::Rng;
+use sha2::{Digest, Sha256};
 
-use crate::proxy::config::UaRotationMode;
+use crate::proxy::config::{validate_user_agent, UaRotationMode};
+use crate::proxy::providers::{dispatch::Backend, health};
 
 // Cloud Code v1internal endpoints (fallback order: Sandbox → Daily → Prod)
 // 优先使用 Sandbox/Daily 环境以避免 Prod环境的 429 错误 (Ref: Issue #1176)
@@ -23,11 +23,20 @@ const V1_INTERNAL_BASE_URL_FALLBACKS: [&str; 3] = [
     V1_INTERNAL_BASE_URL_PROD,    // 优先级 3: Prod (仅作为兜底)
 ];
 
+tokio::task_local! {
+    /// 当前请求的下游客户端 IP (由 IP 过滤中间件设置，供 PerIp UA 轮换使用)
+    pub static CLIENT_IP: String;
+}
+
 pub struct UpstreamClient {
     http_client: Client,
     user_agent_override: RwLock<Option<String>>,
     user_agent_pool: RwLock<Vec<String>>,
+    /// Per-entry weights for `PerRequest` selection (parallel to the pool)
+    user_agent_weights: RwLock<Vec<u32>>,
     ua_rotation_mode: RwLock<UaRotationMode>,
+    /// Fallback UA when rotation is enabled but the pool is empty
+    saved_user_agent: RwLock<Option<String>>,
 }
 
 impl UpstreamClient {
@@ -42,11 +51,13 @@ impl UpstreamClient {
             .user_agent(crate::constants::USER_AGENT.as_str());
 
         if let Some(config) = proxy_config {
-            if config.enabled && !config.url.is_empty() {
-                if let Ok(proxy) = reqwest::Proxy::all(&config.url) {
+            match config.build_proxy() {
+                Ok(Some(proxy)) => {
                     builder = builder.proxy(proxy);
                     tracing::info!("UpstreamClient enabled proxy: {}", config.url);
                 }
+                Ok(None) => {}
+                Err(e) => tracing::error!("UpstreamClient proxy disabled: {}", e),
             }
         }
 
@@ -56,12 +67,25 @@ impl UpstreamClient {
             http_client,
             user_agent_override: RwLock::new(None),
             user_agent_pool: RwLock::new(Vec::new()),
+            user_agent_weights: RwLock::new(Vec::new()),
             ua_rotation_mode: RwLock::new(UaRotationMode::Off),
+            saved_user_agent: RwLock::new(None),
         }
     }
 
-    /// Update UA rotation settings from config
+    /// Update UA rotation settings from config (malformed pool entries are dropped)
     pub async fn update_ua_rotation(&self, pool: Vec<String>, mode: UaRotationMode) {
+        let pool: Vec<String> = pool
+            .into_iter()
+            .filter(|ua| match validate_user_agent(ua) {
+                Ok(()) => true,
+                Err(e) => {
+                    tracing::warn!("Dropping User-Agent pool entry: {}", e);
+                    false
+                }
+            })
+            .collect();
+
         let mut pool_lock = self.user_agent_pool.write().await;
         *pool_lock = pool;
         drop(pool_lock);
@@ -72,8 +96,22 @@ impl UpstreamClient {
         tracing::info!("UA rotation updated: mode={:?}, pool_size={}", mode_for_log, self.user_agent_pool.read().await.len());
     }
 
-    /// 设置动态 User-Agent 覆盖
+    /// Update per-entry weights of the UA pool (empty = uniform)
+    pub async fn update_ua_weights(&self, weights: Vec<u32>) {
+        *self.user_agent_weights.write().await = weights;
+    }
+
+    /// 设置轮换池为空时使用的 User-Agent
+    pub async fn set_saved_user_agent(&self, ua: Option<String>) {
+        *self.saved_user_agent.write().await = ua.filter(|s| !s.trim().is_empty());
+    }
+
+    /// 设置动态 User-Agent 覆盖 (格式非法时忽略并保留原值)
     pub async fn set_user_agent_override(&self, ua: Option<String>) {
+        if let Some(Err(e)) = ua.as_deref().map(validate_user_agent) {
+            tracing::error!("Ignoring User-Agent override: {}", e);
+            return;
+        }
         let mut lock = self.user_agent_override.write().await;
         *lock = ua;
         tracing::debug!("UpstreamClient User-Agent override updated: {:?}", lock);
@@ -84,7 +122,13 @@ impl UpstreamClient {
     /// # Arguments
     /// * `session_id` - Optional session ID for per-session rotation
     /// * `account_id` - Optional account ID for per-account rotation
-    pub async fn get_user_agent_rotated(&self, session_id: Option<&str>, account_id: Option<&str>) -> String {
+    /// * `client_ip` - Optional downstream client IP for per-IP rotation
+    pub async fn get_user_agent_rotated(
+        &self,
+        session_id: Option<&str>,
+        account_id: Option<&str>,
+        client_ip: Option<&str>,
+    ) -> String {
         // Priority 1: Static override always wins
         let ua_override = self.user_agent_override.read().await;
         if let Some(ua) = ua_override.as_ref() {
@@ -96,38 +140,93 @@ impl UpstreamClient {
         let mode = self.ua_rotation_mode.read().await.clone();
         let pool = self.user_agent_pool.read().await;
 
-        if pool.is_empty() || mode == UaRotationMode::Off {
+        if mode == UaRotationMode::Off {
             return crate::constants::USER_AGENT.clone();
         }
+        if pool.is_empty() {
+            return self
+                .saved_user_agent
+                .read()
+                .await
+                .clone()
+                .unwrap_or_else(|| crate::constants::USER_AGENT.clone());
+        }
 
         let index = match mode {
             UaRotationMode::Off => 0,
             UaRotationMode::PerRequest => {
-                rand::thread_rng().gen_range(0..pool.len())
+                let weights = self.user_agent_weights.read().await;
+                Self::weighted_pick(&mut rand::thread_rng(), pool.len(), &weights)
             }
             UaRotationMode::PerSession => {
                 let key = session_id.unwrap_or("default-session");
-                Self::stable_hash(key) as usize % pool.len()
+                Self::stable_pick(&pool, key)
             }
             UaRotationMode::PerAccount => {
                 let key = account_id.unwrap_or("default-account");
-                Self::stable_hash(key) as usize % pool.len()
+                Self::stable_pick(&pool, key)
+            }
+            UaRotationMode::PerIp => {
+                let key = client_ip.unwrap_or("default-ip");
+                Self::stable_pick(&pool, key)
             }
         };
 
         pool.get(index).cloned().unwrap_or_else(|| crate::constants::USER_AGENT.clone())
     }
 
-    /// Stable hash for deterministic selection
-    fn stable_hash(input: &str) -> u64 {
-        let mut hasher = DefaultHasher::new();
-        input.hash(&mut hasher);
-        hasher.finish()
+    /// Random pool index for per-request rotation, proportional to `weights`.
+    /// Falls back to uniform selection when weights are absent, mismatched in length,
+    /// or all zero.
+    fn weighted_pick<R: Rng>(rng: &mut R, len: usize, weights: &[u32]) -> usize {
+        let total: u64 = weights.iter().map(|w| *w as u64).sum();
+        if weights.len() != len || total == 0 {
+            return rng.gen_range(0..len);
+        }
+
+        let mut target = rng.gen_range(0..total);
+        for (idx, weight) in weights.iter().enumerate() {
+            let weight = *weight as u64;
+            if target < weight {
+                return idx;
+            }
+            target -= weight;
+        }
+        len - 1
+    }
+
+    /// Deterministic pool selection for per-session/per-account/per-IP rotation.
+    ///
+    /// Assignments are not persisted; stability comes purely from hashing. Each pool entry
+    /// is scored with SHA-256(key, ua) and the highest score wins (rendezvous hashing), so:
+    /// - the same key maps to the same UA across restarts (SHA-256 is version-independent,
+    ///   unlike `DefaultHasher`)
+    /// - reordering the pool never changes an assignment; only adding/removing the
+    ///   assigned UA moves a key
+    fn stable_pick(pool: &[String], key: &str) -> usize {
+        pool.iter()
+            .enumerate()
+            .max_by_key(|(_, ua)| Self::stable_score(key, ua))
+            .map(|(idx, _)| idx)
+            .unwrap_or(0)
+    }
+
+    /// Stable 64-bit score for a (key, UA) pair
+    fn stable_score(key: &str, ua: &str) -> u64 {
+        let mut hasher = Sha256::new();
+        hasher.update(key.as_bytes());
+        hasher.update([0u8]);
+        hasher.update(ua.as_bytes());
+        let digest = hasher.finalize();
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&digest[..8]);
+        u64::from_be_bytes(bytes)
     }
 
-    /// 获取当前生效的 User-Agent (legacy - no rotation context)
+    /// 获取当前生效的 User-Agent (仅有客户端 IP 上下文，来自 `CLIENT_IP`)
     pub async fn get_user_agent(&self) -> String {
-        self.get_user_agent_rotated(None, None).await
+        let client_ip = CLIENT_IP.try_with(|ip| ip.clone()).ok();
+        self.get_user_agent_rotated(None, None, client_ip.as_deref()).await
     }
 
     /// 构建 v1internal URL
@@ -237,6 +336,7 @@ impl UpstreamClient {
                         } else {
                             tracing::debug!("✓ Upstream request succeeded | Endpoint: {} | Status: {}", base_url, status);
                         }
+                        health::record_status(Backend::Google, status.as_u16());
                         return Ok(resp);
                     }
 
@@ -253,6 +353,7 @@ impl UpstreamClient {
                     }
 
                     // 不可重试的错误或已是最后一个端点，直接返回
+                    health::record_status(Backend::Google, status.as_u16());
                     return Ok(resp);
                 }
                 Err(e) => {
@@ -269,6 +370,7 @@ impl UpstreamClient {
             }
         }
 
+        health::record_error(Backend::Google);
         Err(last_err.unwrap_or_else(|| "All endpoints failed".to_string()))
     }
 
@@ -405,4 +507,133 @@ mod tests {
         );
     }
 
+    fn test_pool() -> Vec<String> {
+        vec![
+            "ua-chrome-windows".to_string(),
+            "ua-chrome-macos".to_string(),
+            "ua-firefox-windows".to_string(),
+            "ua-safari-macos".to_string(),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_per_session_ua_stable_across_restart() {
+        let before = UpstreamClient::new(None);
+        before.update_ua_rotation(test_pool(), UaRotationMode::PerSession).await;
+        let ua_before = before.get_user_agent_rotated(Some("session-abc"), None, None).await;
+
+        // Simulated restart: a fresh client with the same pool
+        let after = UpstreamClient::new(None);
+        after.update_ua_rotation(test_pool(), UaRotationMode::PerSession).await;
+        let ua_after = after.get_user_agent_rotated(Some("session-abc"), None, None).await;
+
+        assert_eq!(ua_before, ua_after);
+    }
+
+    #[tokio::test]
+    async fn test_per_account_ua_stable_when_pool_reordered() {
+        let client = UpstreamClient::new(None);
+        client.update_ua_rotation(test_pool(), UaRotationMode::PerAccount).await;
+        let original = client.get_user_agent_rotated(None, Some("account-1"), None).await;
+
+        let mut reordered = test_pool();
+        reordered.reverse();
+        client.update_ua_rotation(reordered, UaRotationMode::PerAccount).await;
+        let after_reorder = client.get_user_agent_rotated(None, Some("account-1"), None).await;
+
+        assert_eq!(original, after_reorder);
+    }
+
+    #[tokio::test]
+    async fn test_per_ip_ua_deterministic() {
+        let client = UpstreamClient::new(None);
+        client.update_ua_rotation(test_pool(), UaRotationMode::PerIp).await;
+
+        let first = client.get_user_agent_rotated(None, None, Some("203.0.113.7")).await;
+        for _ in 0..20 {
+            assert_eq!(client.get_user_agent_rotated(None, None, Some("203.0.113.7")).await, first);
+        }
+        assert!(test_pool().contains(&first));
+
+        // The task-local client IP drives the legacy accessor
+        let scoped = CLIENT_IP
+            .scope("203.0.113.7".to_string(), client.get_user_agent())
+            .await;
+        assert_eq!(scoped, first);
+    }
+
+    #[tokio::test]
+    async fn test_empty_pool_falls_back_to_saved_ua() {
+        let client = UpstreamClient::new(None);
+        client.update_ua_rotation(Vec::new(), UaRotationMode::PerIp).await;
+        assert_eq!(
+            client.get_user_agent_rotated(None, None, Some("203.0.113.7")).await,
+            *crate::constants::USER_AGENT
+        );
+
+        client.set_saved_user_agent(Some("saved-ua/1.0".to_string())).await;
+        assert_eq!(
+            client.get_user_agent_rotated(None, None, Some("203.0.113.7")).await,
+            "saved-ua/1.0"
+        );
+    }
+
+    #[test]
+    fn test_weighted_pick_distribution() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let weights = [60, 25, 10, 5];
+        let iterations = 100_000;
+        let mut counts = [0usize; 4];
+        for _ in 0..iterations {
+            counts[UpstreamClient::weighted_pick(&mut rng, 4, &weights)] += 1;
+        }
+
+        for (count, weight) in counts.iter().zip(weights) {
+            let observed = *count as f64 / iterations as f64;
+            let expected = weight as f64 / 100.0;
+            assert!((observed - expected).abs() < 0.01, "observed {} expected {}", observed, expected);
+        }
+    }
+
+    #[test]
+    fn test_weighted_pick_uniform_fallback() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let iterations = 40_000;
+        for weights in [&[][..], &[1, 2][..], &[0, 0, 0, 0][..]] {
+            let mut counts = [0usize; 4];
+            for _ in 0..iterations {
+                counts[UpstreamClient::weighted_pick(&mut rng, 4, weights)] += 1;
+            }
+            for count in counts {
+                let observed = count as f64 / iterations as f64;
+                assert!((observed - 0.25).abs() < 0.02, "weights {:?}: observed {}", weights, observed);
+            }
+        }
+
+        // Zero-weight entries are never picked
+        for _ in 0..1000 {
+            assert_ne!(UpstreamClient::weighted_pick(&mut rng, 3, &[1, 0, 1]), 1);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_user_agents_rejected() {
+        let client = UpstreamClient::new(None);
+        client.set_user_agent_override(Some("bad\r\nX-Injected: 1".to_string())).await;
+        assert_eq!(client.get_user_agent().await, *crate::constants::USER_AGENT);
+
+        client
+            .update_ua_rotation(
+                vec!["ua-good".to_string(), "ua-bad\0".to_string()],
+                UaRotationMode::PerRequest,
+            )
+            .await;
+        for _ in 0..20 {
+            assert_eq!(client.get_user_agent().await, "ua-good");
+        }
+    }
 }
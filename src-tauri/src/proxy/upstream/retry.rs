@@ -1,8 +1,12 @@
 // 429 重试策略
 // Duration 解析
+// 上游转发的瞬时错误重试 (指数退避 + 抖动，支持 Retry-After)
 
 use regex::Regex;
 use once_cell::sync::Lazy;
+use rand::Rng;
+use std::sync::RwLock;
+use std::time::Duration;
 
 static DURATION_RE: Lazy<Regex> = Lazy::new(|| {
     Regex::new(r"([\d.]+)\s*(ms|s|m|h)").unwrap()
@@ -66,6 +70,137 @@ pub fn parse_retry_delay(error_text: &str) -> Option<u64> {
     None
 }
 
+/// Retry-After 超过该值时不再等待，直接把错误返回给客户端
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+/// 单次退避等待上限
+const MAX_BACKOFF_MS: u64 = 30_000;
+
+/// 上游转发重试策略 (来自 `ProxyConfig.upstream_retry`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_backoff_ms: u64,
+}
+
+impl From<&crate::proxy::config::UpstreamRetryConfig> for RetryPolicy {
+    fn from(config: &crate::proxy::config::UpstreamRetryConfig) -> Self {
+        Self {
+            max_retries: config.max_retries,
+            base_backoff_ms: config.base_backoff_ms,
+        }
+    }
+}
+
+static RETRY_POLICY: Lazy<RwLock<RetryPolicy>> = Lazy::new(|| {
+    RwLock::new(RetryPolicy::from(&crate::proxy::config::UpstreamRetryConfig::default()))
+});
+
+/// 应用配置中的重试策略 (启动及热更新时调用)
+pub fn apply_config(config: &crate::proxy::config::ProxyConfig) {
+    *RETRY_POLICY.write().unwrap() = RetryPolicy::from(&config.upstream_retry);
+}
+
+pub fn current_policy() -> RetryPolicy {
+    *RETRY_POLICY.read().unwrap()
+}
+
+/// 可重试的瞬时状态码
+pub fn is_transient_status(status: u16) -> bool {
+    matches!(status, 429 | 502 | 503)
+}
+
+/// 解析 Retry-After (秒数或 HTTP 日期)
+pub fn parse_retry_after(value: &str, now: chrono::DateTime<chrono::Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    Some((at.with_timezone(&chrono::Utc) - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// 第 `attempt` 次重试 (从 0 开始) 的退避时间: base * 2^attempt，上限 30s，
+/// 再加上 [0, 50%] 的随机抖动。`jitter` 为 [0, 1) 内的采样值
+pub fn backoff_delay(policy: RetryPolicy, attempt: u32, jitter: f64) -> Duration {
+    let exp_ms = policy
+        .base_backoff_ms
+        .saturating_mul(1u64 << attempt.min(20))
+        .min(MAX_BACKOFF_MS);
+    let jitter_ms = (exp_ms as f64 * 0.5 * jitter.clamp(0.0, 1.0)) as u64;
+    Duration::from_millis(exp_ms + jitter_ms)
+}
+
+/// 请求是否在发出任何字节前就失败 (连接失败，包括连接超时)。
+/// 发送之后的错误 (读响应超时、连接被重置等) 上游可能已处理了请求，
+/// 对非幂等的 POST 不能重试
+fn failed_before_send(e: &reqwest::Error) -> bool {
+    e.is_connect()
+}
+
+/// 发送请求，遇到 429/502/503 或连接阶段失败时按策略重试。
+/// 收到响应头即返回，调用方此后才开始向客户端输出 (包括 SSE)，
+/// 因此重试只会发生在任何字节发送给客户端之前。
+/// `build` 每次重试都会重新构建请求
+pub async fn send_with_retry<F>(
+    policy: RetryPolicy,
+    label: &str,
+    mut build: F,
+) -> Result<reqwest::Response, reqwest::Error>
+where
+    F: FnMut() -> reqwest::RequestBuilder,
+{
+    let mut attempt = 0u32;
+    loop {
+        let result = build().send().await;
+        if attempt >= policy.max_retries {
+            return result;
+        }
+
+        let delay = match &result {
+            Ok(resp) if is_transient_status(resp.status().as_u16()) => {
+                let retry_after = resp
+                    .headers()
+                    .get(reqwest::header::RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(|v| parse_retry_after(v, chrono::Utc::now()));
+                match retry_after {
+                    Some(wait) if wait > MAX_RETRY_AFTER => {
+                        tracing::warn!(
+                            "[{}] Upstream asked to retry after {:?}, longer than {:?}; giving up",
+                            label,
+                            wait,
+                            MAX_RETRY_AFTER
+                        );
+                        return result;
+                    }
+                    Some(wait) => wait,
+                    None => backoff_delay(policy, attempt, rand::thread_rng().gen()),
+                }
+            }
+            Err(e) if failed_before_send(e) => {
+                backoff_delay(policy, attempt, rand::thread_rng().gen())
+            }
+            _ => return result,
+        };
+
+        let reason = match &result {
+            Ok(resp) => resp.status().as_u16().to_string(),
+            Err(e) => e.to_string(),
+        };
+        tracing::warn!(
+            "[{}] Transient upstream failure ({}), retry {}/{} in {}ms",
+            label,
+            reason,
+            attempt + 1,
+            policy.max_retries,
+            delay.as_millis()
+        );
+        drop(result);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,4 +226,127 @@ mod tests {
 
         assert_eq!(parse_retry_delay(error_json), Some(1204));
     }
+
+    #[test]
+    fn test_parse_retry_after_seconds_and_http_date() {
+        let now = chrono::DateTime::parse_from_rfc3339("2015-10-21T07:27:50Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc);
+        assert_eq!(parse_retry_after("5", now), Some(Duration::from_secs(5)));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT", now),
+            Some(Duration::from_secs(10))
+        );
+        // A date in the past means retry immediately
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+
+    #[test]
+    fn test_backoff_grows_exponentially_with_bounded_jitter() {
+        let policy = RetryPolicy { max_retries: 5, base_backoff_ms: 100 };
+        assert_eq!(backoff_delay(policy, 0, 0.0), Duration::from_millis(100));
+        assert_eq!(backoff_delay(policy, 2, 0.0), Duration::from_millis(400));
+        assert_eq!(backoff_delay(policy, 2, 0.999), Duration::from_millis(599));
+        assert_eq!(backoff_delay(policy, 30, 0.0), Duration::from_millis(MAX_BACKOFF_MS));
+    }
+
+    async fn spawn_flaky_upstream(failures: usize) -> (String, std::sync::Arc<std::sync::atomic::AtomicUsize>) {
+        use axum::{http::StatusCode, response::IntoResponse, routing::post, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/v1/messages",
+            post(move || {
+                let counter = counter.clone();
+                async move {
+                    match counter.fetch_add(1, Ordering::SeqCst) {
+                        n if n >= failures => (StatusCode::OK, "ok").into_response(),
+                        // First failure carries Retry-After, later ones fall back to backoff
+                        0 => (StatusCode::SERVICE_UNAVAILABLE, [("Retry-After", "0")], "busy").into_response(),
+                        _ => (StatusCode::BAD_GATEWAY, "bad gateway").into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+        (format!("http://{}/v1/messages", addr), hits)
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_recovers_after_two_failures() {
+        let (url, hits) = spawn_flaky_upstream(2).await;
+        let client = reqwest::Client::new();
+        let policy = RetryPolicy { max_retries: 3, base_backoff_ms: 1 };
+
+        let resp = send_with_retry(policy, "test", || client.post(&url).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 200);
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_returns_last_failure_when_exhausted() {
+        let (url, hits) = spawn_flaky_upstream(2).await;
+        let client = reqwest::Client::new();
+        let policy = RetryPolicy { max_retries: 1, base_backoff_ms: 1 };
+
+        let resp = send_with_retry(policy, "test", || client.post(&url).body("{}"))
+            .await
+            .unwrap();
+        assert_eq!(resp.status().as_u16(), 502);
+        assert_eq!(hits.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_retries_connect_failures_only() {
+        use tokio::io::AsyncReadExt;
+
+        let client = reqwest::Client::new();
+        let policy = RetryPolicy { max_retries: 2, base_backoff_ms: 1 };
+
+        // Nothing listening: the request never left, so it is retried
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let refused_url = format!("http://{}/v1/messages", closed.local_addr().unwrap());
+        drop(closed);
+        let mut attempts = 0;
+        let err = send_with_retry(policy, "test", || {
+            attempts += 1;
+            client.post(&refused_url).body("{}")
+        })
+        .await
+        .unwrap_err();
+        assert!(err.is_connect());
+        assert_eq!(attempts, 3);
+
+        // Connection dropped after the request was read: upstream may have acted on it
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1/messages", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf).await;
+            }
+        });
+        let mut attempts = 0;
+        let err = send_with_retry(policy, "test", || {
+            attempts += 1;
+            client.post(&url).body("{}")
+        })
+        .await
+        .unwrap_err();
+        assert!(!err.is_connect());
+        assert_eq!(attempts, 1);
+    }
 }
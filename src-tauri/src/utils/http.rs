@@ -1,6 +1,6 @@
 use crate::modules::config::load_app_config;
 use once_cell::sync::Lazy;
-use reqwest::{Client, Proxy};
+use reqwest::Client;
 
 /// Global shared HTTP client (15s timeout)
 /// Client has a built-in connection pool; cloning it is light and shares the pool
@@ -15,18 +15,17 @@ fn create_base_client(timeout_secs: u64) -> Client {
 
     if let Ok(config) = load_app_config() {
         let proxy_config = config.proxy.upstream_proxy;
-        if proxy_config.enabled && !proxy_config.url.is_empty() {
-            match Proxy::all(&proxy_config.url) {
-                Ok(proxy) => {
-                    builder = builder.proxy(proxy);
-                    tracing::info!(
-                        "HTTP shared client enabled upstream proxy: {}",
-                        proxy_config.url
-                    );
-                }
-                Err(e) => {
-                    tracing::error!("invalid_proxy_url: {}, error: {}", proxy_config.url, e);
-                }
+        match proxy_config.build_proxy() {
+            Ok(Some(proxy)) => {
+                builder = builder.proxy(proxy);
+                tracing::info!(
+                    "HTTP shared client enabled upstream proxy: {}",
+                    proxy_config.url
+                );
+            }
+            Ok(None) => {}
+            Err(e) => {
+                tracing::error!("invalid_proxy_url: {}, error: {}", proxy_config.url, e);
             }
         }
     }
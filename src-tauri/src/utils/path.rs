@@ -1,4 +1,4 @@
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 
 /// Validates that a path is safe and within expected boundaries.
 /// This prevents path-injection attacks where user-controlled input
@@ -14,7 +14,7 @@ use std::path::{Path, PathBuf};
 ///
 /// # Security
 /// This function:
-/// 1. Rejects paths containing `..` or null bytes
+/// 1. Rejects paths with a `..` component or null bytes
 /// 2. Canonicalizes the path to resolve symlinks
 /// 3. Validates the resulting path is within the allowed base directory
 pub fn validate_path<P: AsRef<Path>>(path: P, allowed_base: Option<&Path>) -> Result<PathBuf, String> {
@@ -26,9 +26,8 @@ pub fn validate_path<P: AsRef<Path>>(path: P, allowed_base: Option<&Path>) -> Re
     }
 
     // Check for explicit path traversal in the original input
-    let path_str = path.to_string_lossy();
-    if path_str.contains("..") {
-        return Err("Path traversal detected (contains '..')".to_string());
+    if has_parent_component(path) {
+        return Err("Path traversal detected (contains '..' component)".to_string());
     }
 
     // For validation against a base directory
@@ -47,6 +46,13 @@ pub fn validate_path<P: AsRef<Path>>(path: P, allowed_base: Option<&Path>) -> Re
                 if parent.exists() {
                     let canonical_parent = parent.canonicalize()
                         .map_err(|e| format!("Failed to canonicalize parent path: {}", e))?;
+                    // The parent may be a symlink inside the base that resolves outside it
+                    if !canonical_parent.starts_with(&canonical_base) {
+                        return Err(format!(
+                            "Path escapes allowed directory: {:?} is not within {:?}",
+                            canonical_parent, canonical_base
+                        ));
+                    }
                     if let Some(filename) = path.file_name() {
                         canonical_parent.join(filename)
                     } else {
@@ -81,6 +87,33 @@ pub fn validate_path<P: AsRef<Path>>(path: P, allowed_base: Option<&Path>) -> Re
     }
 }
 
+/// Validates every path against the same base directory.
+/// Results are returned in input order, one per path.
+pub fn validate_paths<P: AsRef<Path>>(paths: &[P], allowed_base: &Path) -> Vec<Result<PathBuf, String>> {
+    paths
+        .iter()
+        .map(|path| validate_path(path, Some(allowed_base)))
+        .collect()
+}
+
+/// Validates every path against the same base directory, stopping at the
+/// first invalid one. The error names the offending path.
+pub fn all_valid<P: AsRef<Path>>(paths: &[P], allowed_base: &Path) -> Result<Vec<PathBuf>, String> {
+    paths
+        .iter()
+        .map(|path| {
+            validate_path(path, Some(allowed_base))
+                .map_err(|e| format!("{}: {}", path.as_ref().display(), e))
+        })
+        .collect()
+}
+
+/// Whether the path contains a `..` component. Names that merely contain
+/// two dots (e.g. `my..backup.txt`) are not traversal.
+fn has_parent_component(path: &Path) -> bool {
+    path.components().any(|c| matches!(c, Component::ParentDir))
+}
+
 /// Validates a path is within a data directory.
 /// Convenience wrapper for common case of validating paths within app data.
 pub fn validate_data_path<P: AsRef<Path>>(path: P, data_dir: &Path) -> Result<PathBuf, String> {
@@ -89,7 +122,23 @@ pub fn validate_data_path<P: AsRef<Path>>(path: P, data_dir: &Path) -> Result<Pa
 
 /// Validates that a user-provided path string is safe.
 /// Does basic sanitization before converting to PathBuf.
+///
+/// Windows device paths (`\\?\`, `\\.\`, including the `\\?\UNC\` form)
+/// bypass Win32 path normalization and are rejected; use
+/// [`sanitize_path_string_with`] to opt in explicitly.
+///
+/// On Windows, 8.3 short names (e.g. `PROGRA~1`) can name the same directory as
+/// a long name and slip past string-based allowlists. This function cannot
+/// expand them, so it rejects them: short names must be resolved to their long
+/// form (e.g. via [`validate_path`], which canonicalizes) before any comparison.
 pub fn sanitize_path_string(path_str: &str) -> Result<PathBuf, String> {
+    sanitize_path_string_with(path_str, false)
+}
+
+/// Same as [`sanitize_path_string`], optionally accepting Windows device paths.
+/// Device paths are normalized to backslashes throughout (`//?/C:/data` becomes
+/// `\\?\C:\data`): Windows does not normalize separators after a `\\?\` prefix.
+pub fn sanitize_path_string_with(path_str: &str, allow_device_paths: bool) -> Result<PathBuf, String> {
     if path_str.is_empty() {
         return Err("Path cannot be empty".to_string());
     }
@@ -99,8 +148,35 @@ pub fn sanitize_path_string(path_str: &str) -> Result<PathBuf, String> {
     }
 
     // Check for path traversal
-    if path_str.contains("..") {
-        return Err("Path traversal detected (contains '..')".to_string());
+    if has_parent_component(Path::new(path_str)) {
+        return Err("Path traversal detected (contains '..' component)".to_string());
+    }
+
+    let mut path_str = path_str.to_string();
+    if let Some(rest) = strip_device_prefix(&path_str) {
+        if !allow_device_paths {
+            return Err(format!("Device paths are not allowed: {}", path_str));
+        }
+        // `\\?\` paths skip normalization, so `..` must also be rejected textually
+        if rest.split(['\\', '/']).any(|part| part == "..") {
+            return Err("Path traversal detected (contains '..' component)".to_string());
+        }
+        path_str = format!("{}{}", &path_str[..4].replace('/', "\\"), rest.replace('/', "\\"));
+    }
+
+    #[cfg(windows)]
+    if let Some(name) = Path::new(&path_str)
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        })
+        .find(|name| is_short_name(name))
+    {
+        return Err(format!(
+            "8.3 short name '{}' must be resolved to its long form before validation",
+            name
+        ));
     }
 
     // Reject some dangerous patterns (Unix and Windows)
@@ -112,6 +188,33 @@ pub fn sanitize_path_string(path_str: &str) -> Result<PathBuf, String> {
     Ok(PathBuf::from(path_str))
 }
 
+/// Returns the remainder after a `\\?\` or `\\.\` prefix (either slash style)
+fn strip_device_prefix(path_str: &str) -> Option<&str> {
+    let prefix = path_str.get(..4)?;
+    let mut chars = prefix.chars().map(|c| if c == '/' { '\\' } else { c });
+    match (chars.next(), chars.next(), chars.next(), chars.next()) {
+        (Some('\\'), Some('\\'), Some('?' | '.'), Some('\\')) => Some(&path_str[4..]),
+        _ => None,
+    }
+}
+
+/// Whether a path component looks like an 8.3 short name (`PROGRA~1`, `LONGFI~2.TXT`)
+#[cfg(any(windows, test))]
+fn is_short_name(name: &str) -> bool {
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) => (stem, Some(ext)),
+        None => (name, None),
+    };
+    let Some((base, digits)) = stem.rsplit_once('~') else {
+        return false;
+    };
+    !base.is_empty()
+        && stem.len() <= 8
+        && !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && ext.map_or(true, |ext| ext.len() <= 3)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -125,6 +228,26 @@ mod tests {
         assert!(result.unwrap_err().contains("Path traversal"));
     }
 
+    #[test]
+    fn test_parent_dir_component_rejected() {
+        for path in ["../etc/passwd", "a/../../b", "a/.."] {
+            let result = validate_path(path, None);
+            assert!(result.unwrap_err().contains("Path traversal"), "{}", path);
+            assert!(sanitize_path_string(path).is_err(), "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_double_dot_in_filename_accepted() {
+        let tmp = tempdir().unwrap();
+        let file = tmp.path().join("my..file.txt");
+        fs::write(&file, "test").unwrap();
+
+        assert!(validate_path(&file, Some(tmp.path())).is_ok());
+        assert!(validate_path(tmp.path().join("config..old"), Some(tmp.path())).is_ok());
+        assert!(sanitize_path_string("backups/my..file.txt").is_ok());
+    }
+
     #[test]
     fn test_null_byte_rejected() {
         let result = validate_path("foo\0bar", None);
@@ -156,4 +279,100 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().contains("escapes allowed directory"));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinked_parent_escaping_base_rejected() {
+        let tmp = tempdir().unwrap();
+        let base = tmp.path().join("allowed");
+        let outside = tmp.path().join("outside");
+        fs::create_dir_all(&base).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+
+        // allowed/link -> outside
+        let link = base.join("link");
+        std::os::unix::fs::symlink(&outside, &link).unwrap();
+
+        // Existing target behind the symlink
+        fs::write(outside.join("existing.txt"), "test").unwrap();
+        let result = validate_path(link.join("existing.txt"), Some(&base));
+        assert!(result.unwrap_err().contains("escapes allowed directory"));
+
+        // Write target (does not exist yet) behind the symlink
+        let result = validate_path(link.join("new.txt"), Some(&base));
+        assert!(result.is_err());
+        assert!(result.unwrap_err().contains("escapes allowed directory"));
+        assert!(!outside.join("new.txt").exists());
+    }
+
+    #[test]
+    fn test_validate_paths_mixed() {
+        let tmp = tempdir().unwrap();
+        let base = tmp.path().join("allowed");
+        fs::create_dir_all(&base).unwrap();
+        let outside = tmp.path().join("outside.txt");
+        fs::write(&outside, "test").unwrap();
+
+        let paths = vec![
+            base.join("a.txt"),
+            outside.clone(),
+            base.join("b.txt"),
+            PathBuf::from("../etc/passwd"),
+        ];
+        let results = validate_paths(&paths, &base);
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert!(results[1].as_ref().unwrap_err().contains("escapes allowed directory"));
+        assert!(results[2].is_ok());
+        assert!(results[3].as_ref().unwrap_err().contains("Path traversal"));
+
+        let err = all_valid(&paths, &base).unwrap_err();
+        assert!(err.contains("outside.txt"));
+        assert!(err.contains("escapes allowed directory"));
+
+        let valid = all_valid(&[base.join("a.txt"), base.join("b.txt")], &base).unwrap();
+        assert_eq!(valid.len(), 2);
+        assert!(valid[1].ends_with("b.txt"));
+    }
+
+    #[test]
+    fn test_device_paths_require_opt_in() {
+        for path in [r"\\?\C:\Windows", r"\\.\PhysicalDrive0", "//?/C:/Windows"] {
+            let err = sanitize_path_string(path).unwrap_err();
+            assert!(err.contains("Device paths are not allowed"), "{}", path);
+        }
+
+        let allowed = sanitize_path_string_with("//?/C:/data", true).unwrap();
+        assert_eq!(allowed, PathBuf::from(r"\\?\C:\data"));
+        let mixed = sanitize_path_string_with(r"\\?\C:\data/sub/file.txt", true).unwrap();
+        assert_eq!(mixed, PathBuf::from(r"\\?\C:\data\sub\file.txt"));
+        assert!(sanitize_path_string_with(r"\\?\C:\data\..\secrets", true).is_err());
+    }
+
+    #[test]
+    fn test_short_name_detection() {
+        assert!(is_short_name("PROGRA~1"));
+        assert!(is_short_name("LONGFI~2.TXT"));
+        assert!(!is_short_name("Program Files"));
+        assert!(!is_short_name("~1"));
+        assert!(!is_short_name("backup~old"));
+        assert!(!is_short_name("verylongname~1"));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_unc_device_path_rejected() {
+        let err = sanitize_path_string(r"\\?\UNC\server\share\file.txt").unwrap_err();
+        assert!(err.contains("Device paths are not allowed"));
+        // Plain UNC shares are ordinary absolute paths
+        assert!(sanitize_path_string(r"\\server\share\file.txt").is_ok());
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_short_name_rejected() {
+        let err = sanitize_path_string(r"C:\PROGRA~1\App\config.json").unwrap_err();
+        assert!(err.contains("short name"));
+        assert!(sanitize_path_string(r"C:\Program Files\App\config.json").is_ok());
+    }
 }
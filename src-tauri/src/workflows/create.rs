@@ -0,0 +1,83 @@
+use super::{dry_run_preview, stream_text, DeltaSender, PromptContext, TaskResult};
+use crate::modules;
+use crate::proxy::request_registry::CancelToken;
+use std::path::Path;
+
+/// File name of the scaffold inside the session workspace
+const SCAFFOLD_ARTIFACT: &str = "feature_scaffold.md";
+
+/// Execute the /create workflow
+/// 1. Interpret the feature request (mock)
+/// 2. Draft the scaffold (files, modules, tests)
+/// 3. Save the scaffold as an artifact in the session workspace for review
+///
+/// With `dry_run` the files that would be generated are listed and no artifact is saved.
+pub async fn execute(
+    user_request: String,
+    prompt: &PromptContext<'_>,
+    session_id: &str,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+) -> Result<TaskResult, String> {
+    let data_dir = modules::account::get_data_dir()?;
+    execute_in(&data_dir, user_request, prompt, session_id, dry_run, cancel, deltas).await
+}
+
+/// [`execute`] with the scaffold saved under `data_dir` instead of the app data dir
+pub(crate) async fn execute_in(
+    data_dir: &Path,
+    user_request: String,
+    prompt: &PromptContext<'_>,
+    session_id: &str,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    modules::logger::log_info(&format!(
+        "Executing /create workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
+    ));
+
+    if dry_run {
+        return Ok(dry_run_preview(
+            "create",
+            &[
+                format!("Draft a scaffold for: {}", user_request),
+                "Generate the module skeleton, public API surface, unit tests and documentation".to_string(),
+                format!("Save the scaffold as {} in the session workspace for review", SCAFFOLD_ARTIFACT),
+            ],
+            deltas,
+        ));
+    }
+
+    // In valid implementation (Phase 5.2):
+    // Call LLM with "builder" persona + skills to generate the scaffold
+
+    // For Phase 5.1 (Mock/Stub):
+    let scaffold_content = format!(
+        "# Feature Scaffold: {}\n\n## Request\n{}\n\n## Files to Generate\n- [ ] Module skeleton\n- [ ] Public API surface\n- [ ] Unit tests\n- [ ] Documentation\n\n## Skills Used\n{}\n",
+        user_request,
+        user_request,
+        prompt.skills.skills.iter().map(|s| format!("- {}", s.name)).collect::<Vec<_>>().join("\n")
+    );
+    stream_text(deltas, &scaffold_content);
+    stream_text(deltas, "\n");
+
+    // Checkpoint: don't write an artifact for a cancelled session
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    let artifact = modules::artifacts::save_in(data_dir, session_id, SCAFFOLD_ARTIFACT, &scaffold_content)?;
+
+    Ok(TaskResult::RequiresReview {
+        artifact: artifact.path,
+        next_step: "Review the scaffold and approve to generate the files".to_string(),
+    })
+}
@@ -1,29 +1,238 @@
-use super::TaskResult;
-use crate::commands::skills::SkillSelection;
+use super::{dry_run_preview, stream_text, summarize, DeltaSender, PromptContext, TaskResult};
 use crate::modules;
+use crate::proxy::config::WorkflowConfig;
+use crate::proxy::request_registry::CancelToken;
+
+/// Logs attached to a /debug message, after applying the byte cap
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugLogs {
+    pub text: String,
+    /// Size of the logs as sent by the client
+    pub original_bytes: usize,
+    /// Whether the start of the logs was cut to fit the cap
+    pub truncated: bool,
+}
+
+impl DebugLogs {
+    /// Keep at most the last `max_bytes` bytes (the most recent output), cut on a
+    /// character boundary
+    pub fn capped(logs: &str, max_bytes: usize) -> Self {
+        let mut start = logs.len().saturating_sub(max_bytes);
+        while !logs.is_char_boundary(start) {
+            start += 1;
+        }
+        Self {
+            text: logs[start..].to_string(),
+            original_bytes: logs.len(),
+            truncated: start > 0,
+        }
+    }
+}
 
 /// Execute the /debug workflow
-/// 1. Analyze error logs (stub)
+/// 1. Analyze the attached error logs, if any
 /// 2. Reproduce issue (stub)
 /// 3. Root cause analysis
+///
+/// Diagnoses below `debug_confidence_threshold` come back as `RequiresReview` asking for
+/// more logs. With `dry_run` the investigation steps are only listed, nothing is reproduced.
 pub async fn execute(
     user_request: String,
-    skills: &SkillSelection,
+    logs: Option<String>,
+    prompt: &PromptContext<'_>,
+    config: &WorkflowConfig,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
 ) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
     modules::logger::log_info(&format!(
-        "Executing /debug workflow with {} skills",
-        skills.skills.len()
+        "Executing /debug workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
     ));
 
+    let logs = logs
+        .filter(|logs| !logs.trim().is_empty())
+        .map(|logs| DebugLogs::capped(&logs, config.debug_max_log_bytes));
+
+    if dry_run {
+        let read_logs = match &logs {
+            Some(logs) => format!("Analyze the attached logs ({} bytes)", logs.text.len()),
+            None => "Read recent error logs and the active configuration".to_string(),
+        };
+        return Ok(dry_run_preview(
+            "debug",
+            &[
+                format!("Investigate: {}", user_request),
+                read_logs,
+                "Reproduce the issue".to_string(),
+                "Report the root cause and a proposed fix".to_string(),
+            ],
+            deltas,
+        ));
+    }
+
     // Phase 5.2: Call LLM with "troubleshooter" persona
 
+    stream_text(
+        deltas,
+        &format!("Investigating: {}\n\nChecking logs and configuration...\n\n", user_request),
+    );
+
+    // Error lines of the attached logs come first in the key points
+    let log_points = logs
+        .as_ref()
+        .map(|logs| summarize::key_points(&logs.text))
+        .unwrap_or_default();
+    if let Some(logs) = &logs {
+        stream_text(
+            deltas,
+            &format!(
+                "Attached logs ({}):\n{}\n\n",
+                log_size_note(logs),
+                log_points.iter().map(|p| format!("- {}", p)).collect::<Vec<_>>().join("\n")
+            ),
+        );
+    }
+
     // Phase 5.1: Simulation
-    let diagnosis = "Hypothetical Root Cause: Configuration mismatch";
+    let mut diagnosis = match log_points.first() {
+        Some(line) => format!("First error in the attached logs: {}", line),
+        None => "Hypothetical Root Cause: Configuration mismatch".to_string(),
+    };
+    if let Some(logs) = logs.as_ref().filter(|logs| logs.truncated) {
+        diagnosis.push_str(&format!(" (logs {})", log_size_note(logs)));
+    }
     let fix = "Update config.toml with correct port";
 
-    Ok(TaskResult::DebugDiagnosis {
-        root_cause: diagnosis.to_string(),
-        proposed_fix: fix.to_string(),
-        confidence: 0.85,
-    })
+    // Checkpoint: skip the diagnosis if the session was cancelled meanwhile
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    Ok(diagnosis_result(&diagnosis, fix, 0.85, config.debug_confidence_threshold))
+}
+
+fn log_size_note(logs: &DebugLogs) -> String {
+    if logs.truncated {
+        format!(
+            "truncated to the last {} of {} bytes",
+            logs.text.len(),
+            logs.original_bytes
+        )
+    } else {
+        format!("{} bytes", logs.original_bytes)
+    }
+}
+
+/// Report a diagnosis, or hand it back for review when its confidence is below `threshold`
+fn diagnosis_result(root_cause: &str, proposed_fix: &str, confidence: f64, threshold: f64) -> TaskResult {
+    if confidence < threshold {
+        return TaskResult::RequiresReview {
+            artifact: format!(
+                "Tentative root cause ({:.0}% confidence): {}. Possible fix: {}",
+                confidence * 100.0,
+                root_cause,
+                proposed_fix
+            ),
+            next_step: format!(
+                "Confidence is below {:.0}%: share more logs (full error output, stack traces, recent changes) and run /debug again",
+                threshold * 100.0
+            ),
+        };
+    }
+
+    TaskResult::DebugDiagnosis {
+        root_cause: root_cause.to_string(),
+        proposed_fix: proposed_fix.to_string(),
+        confidence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_low_confidence_requires_review() {
+        let result = diagnosis_result("Port clash", "Change the port", 0.4, 0.6);
+        match result {
+            TaskResult::RequiresReview { artifact, next_step } => {
+                assert!(artifact.contains("Port clash"));
+                assert!(next_step.contains("more logs"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let result = diagnosis_result("Port clash", "Change the port", 0.85, 0.6);
+        assert!(matches!(
+            result,
+            TaskResult::DebugDiagnosis { confidence, .. } if confidence == 0.85
+        ));
+        // At the threshold the diagnosis is trusted
+        assert!(matches!(
+            diagnosis_result("Port clash", "Change the port", 0.6, 0.6),
+            TaskResult::DebugDiagnosis { .. }
+        ));
+    }
+
+    #[test]
+    fn test_oversized_logs_truncated() {
+        let logs = format!("{}ERROR: bind failed: address in use\n", "started ok\n".repeat(100));
+        let capped = DebugLogs::capped(&logs, 64);
+        assert!(capped.truncated);
+        assert_eq!(capped.original_bytes, logs.len());
+        assert!(capped.text.len() <= 64);
+        assert!(capped.text.ends_with("ERROR: bind failed: address in use\n"));
+
+        // Never split a multi-byte character
+        let capped = DebugLogs::capped("é".repeat(10).as_str(), 5);
+        assert!(capped.truncated);
+        assert_eq!(capped.text, "éé");
+
+        let small = DebugLogs::capped("ERROR: x", 64);
+        assert!(!small.truncated);
+        assert_eq!(small.text, "ERROR: x");
+    }
+
+    #[tokio::test]
+    async fn test_truncation_noted_in_diagnosis() {
+        let registry = crate::proxy::request_registry::RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let selection = crate::commands::skills::SkillSelection {
+            persona: "troubleshooter".to_string(),
+            category: "debugging".to_string(),
+            skills: Vec::new(),
+            total_bytes: 0,
+            limits: crate::commands::skills::SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: 0,
+                actual_bytes: 0,
+            },
+        };
+        let config = WorkflowConfig {
+            debug_max_log_bytes: 64,
+            ..WorkflowConfig::default()
+        };
+        let logs = format!("{}ERROR: bind failed\n", "noise\n".repeat(100));
+        let (tx, _rx) = tokio::sync::mpsc::unbounded_channel();
+
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+
+        let result = execute("server won't start".to_string(), Some(logs), &prompt, &config, false, &cancel, &tx)
+            .await
+            .unwrap();
+        match result {
+            TaskResult::DebugDiagnosis { root_cause, .. } => {
+                assert!(root_cause.contains("ERROR: bind failed"));
+                assert!(root_cause.contains("truncated to the last"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
 }
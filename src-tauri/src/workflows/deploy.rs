@@ -0,0 +1,277 @@
+use super::{dry_run_preview, resolve_working_dir, run_command, stream_text, DeltaSender, PromptContext, TaskResult};
+use crate::modules;
+use crate::proxy::config::DeployWorkflowConfig;
+use crate::proxy::request_registry::CancelToken;
+use std::time::Duration;
+
+/// Execute the /deploy workflow
+/// 1. Pick an allowlisted deploy target (never a command from the message)
+/// 2. Run it in dry-run mode inside the configured working directory
+/// 3. Hand the planned actions back for review
+///
+/// With `dry_run` the resolved command is reported without being run.
+pub async fn execute(
+    user_request: String,
+    prompt: &PromptContext<'_>,
+    config: &DeployWorkflowConfig,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    modules::logger::log_info(&format!(
+        "Executing /deploy workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
+    ));
+
+    let (target, template) = resolve_target(config, &user_request)?;
+    let working_dir = resolve_working_dir(config.working_dir.as_deref(), "deploy")?;
+
+    let (program, args) = template
+        .split_first()
+        .ok_or_else(|| format!("Deploy target '{}' has an empty command", target))?;
+    let mut args = args.to_vec();
+    if !config.dry_run_flag.is_empty() {
+        args.push(config.dry_run_flag.clone());
+    }
+    let command_line = std::iter::once(program.as_str())
+        .chain(args.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if dry_run {
+        return Ok(dry_run_preview(
+            "deploy",
+            &[
+                format!("Run deploy target `{}`: `{}`", target, command_line),
+                format!("Working directory: {}", working_dir.display()),
+            ],
+            deltas,
+        ));
+    }
+
+    stream_text(
+        deltas,
+        &format!("Dry-running deploy target `{}`: `{}`\n\n", target, command_line),
+    );
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let output = match run_command(program, &args, &working_dir, timeout, cancel).await {
+        Ok(output) => output,
+        // The process was killed because the session was cancelled
+        Err(_) if cancel.is_cancelled() => {
+            return Ok(TaskResult::Cancelled {
+                reason: cancel.reason().unwrap_or_default(),
+            })
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Checkpoint: don't report a plan for a cancelled session
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    if !output.success {
+        return Err(format!(
+            "Deploy dry-run failed ({}): {}",
+            output.status,
+            output.stderr.trim()
+        ));
+    }
+
+    let planned = output.stdout.trim();
+    let planned = if planned.is_empty() {
+        "(the dry run reported no actions)"
+    } else {
+        planned
+    };
+    stream_text(deltas, &format!("```\n{}\n```\n\n", planned));
+
+    Ok(TaskResult::RequiresReview {
+        artifact: planned.to_string(),
+        next_step: format!(
+            "Review the planned actions, then run target `{}` without `{}` to apply them",
+            target, config.dry_run_flag
+        ),
+    })
+}
+
+/// Choose the deploy target: the first word after the slash command, which must name a
+/// configured target. The configured default is used only when no target is named.
+fn resolve_target<'a>(
+    config: &'a DeployWorkflowConfig,
+    user_request: &str,
+) -> Result<(&'a str, &'a Vec<String>), String> {
+    if config.commands.is_empty() {
+        return Err(
+            "No deploy command configured. Add a target under workflows.deploy.commands to enable /deploy"
+                .to_string(),
+        );
+    }
+
+    let usage = || {
+        let mut targets: Vec<&str> = config.commands.keys().map(String::as_str).collect();
+        targets.sort();
+        format!("/deploy <{}>", targets.join("|"))
+    };
+
+    let requested = user_request
+        .split_whitespace()
+        .find(|word| !word.starts_with('/'));
+    if let Some(word) = requested {
+        return config
+            .commands
+            .get_key_value(word)
+            .map(|(name, template)| (name.as_str(), template))
+            .ok_or_else(|| format!("Unknown deploy target '{}'. Use {}", word, usage()));
+    }
+
+    match config.default_target.as_deref() {
+        Some(default) => config
+            .commands
+            .get_key_value(default)
+            .map(|(name, template)| (name.as_str(), template))
+            .ok_or_else(|| format!("Default deploy target '{}' is not configured", default)),
+        None => Err(format!("Specify a deploy target: {}", usage())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::skills::{SelectionLimits, SkillSelection};
+    use crate::proxy::request_registry::RequestRegistry;
+    use tokio::sync::mpsc;
+
+    fn empty_selection() -> SkillSelection {
+        SkillSelection {
+            persona: "devops-engineer".to_string(),
+            category: "devops".to_string(),
+            skills: Vec::new(),
+            total_bytes: 0,
+            limits: SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: 0,
+                actual_bytes: 0,
+            },
+        }
+    }
+
+    async fn run(
+        config: &DeployWorkflowConfig,
+        dry_run: bool,
+        cancel: &CancelToken,
+        tx: &DeltaSender,
+    ) -> Result<TaskResult, String> {
+        let selection = empty_selection();
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+        execute("/deploy".to_string(), &prompt, config, dry_run, cancel, tx).await
+    }
+
+    fn config_with(targets: &[(&str, &[&str])], dir: &std::path::Path) -> DeployWorkflowConfig {
+        DeployWorkflowConfig {
+            commands: targets
+                .iter()
+                .map(|(name, argv)| (name.to_string(), argv.iter().map(|a| a.to_string()).collect()))
+                .collect(),
+            default_target: Some("staging".to_string()),
+            working_dir: Some(dir.to_string_lossy().to_string()),
+            ..DeployWorkflowConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_target_comes_from_allowlist_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with(
+            &[("staging", &["echo", "staging"]), ("prod", &["echo", "prod"])],
+            dir.path(),
+        );
+
+        let (name, _) = resolve_target(&config, "/deploy prod please").unwrap();
+        assert_eq!(name, "prod");
+        // The default applies only when no target is named
+        let (name, _) = resolve_target(&config, "/deploy").unwrap();
+        assert_eq!(name, "staging");
+        // A named target that is not configured is an error, never the default
+        let err = resolve_target(&config, "/deploy rm -rf /").unwrap_err();
+        assert!(err.contains("Unknown deploy target 'rm'"), "unexpected error: {}", err);
+        assert!(resolve_target(&config, "/deploy production").is_err());
+
+        let err = resolve_target(&DeployWorkflowConfig::default(), "/deploy").unwrap_err();
+        assert!(err.contains("No deploy command configured"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dry_run_output_requires_review() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = config_with(&[("staging", &["echo", "would restart api"])], dir.path());
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let (tx, _rx) = mpsc::unbounded_channel();
+
+        let result = run(&config, false, &cancel, &tx)
+            .await
+            .unwrap();
+        match result {
+            TaskResult::RequiresReview { artifact, .. } => {
+                assert_eq!(artifact, "would restart api --dry-run");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_dry_run_executes_no_subprocess() {
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("deployed");
+        let mut config = config_with(
+            &[("staging", &["touch", marker.to_str().unwrap()])],
+            dir.path(),
+        );
+        config.dry_run_flag = String::new();
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let (tx, _rx) = mpsc::unbounded_channel();
+
+        let result = run(&config, true, &cancel, &tx)
+            .await
+            .unwrap();
+        match result {
+            TaskResult::RequiresReview { artifact, .. } => {
+                assert!(artifact.contains(&format!("touch {}", marker.display())));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(!marker.exists(), "dry run must not execute the deploy command");
+
+        // The same target without dry_run does run
+        run(&config, false, &cancel, &tx)
+            .await
+            .unwrap();
+        assert!(marker.exists());
+    }
+
+    #[tokio::test]
+    async fn test_missing_working_dir_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = config_with(&[("staging", &["echo", "x"])], dir.path());
+        config.working_dir = Some(dir.path().join("missing").to_string_lossy().to_string());
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let (tx, _rx) = mpsc::unbounded_channel();
+
+        let err = run(&config, false, &cancel, &tx)
+            .await
+            .unwrap_err();
+        assert!(err.contains("not a directory"));
+    }
+}
@@ -1,5 +1,157 @@
 use crate::commands::skills::SkillSelection;
+use crate::proxy::request_registry::CancelToken;
 use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use tokio::sync::mpsc;
+
+/// Incremental assistant output from a running workflow
+pub type DeltaSender = mpsc::UnboundedSender<String>;
+
+/// Stream `text` as word-sized deltas. Whitespace stays attached to the preceding word, so
+/// the deltas concatenate back to exactly `text`. A closed receiver is ignored.
+pub fn stream_text(tx: &DeltaSender, text: &str) {
+    for chunk in text.split_inclusive(char::is_whitespace) {
+        if tx.send(chunk.to_string()).is_err() {
+            return;
+        }
+    }
+}
+
+/// Inputs of a workflow's LLM call
+#[derive(Debug, Clone, Copy)]
+pub struct PromptContext<'a> {
+    /// Skills selected for the request
+    pub skills: &'a SkillSelection,
+    /// System message: persona prompt (if configured) followed by the skill contents
+    pub system_prompt: &'a str,
+}
+
+/// Captured result of a workflow-run process
+#[derive(Debug, Clone)]
+pub struct CommandOutput {
+    pub success: bool,
+    /// Exit status as displayed to the user (e.g. "exit status: 1")
+    pub status: String,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+/// Resolve and validate a configured working directory (never taken from user input)
+pub(crate) fn resolve_working_dir(dir: Option<&str>, workflow: &str) -> Result<PathBuf, String> {
+    let dir = dir
+        .filter(|d| !d.trim().is_empty())
+        .ok_or_else(|| format!("No working directory configured for the /{} workflow", workflow))?;
+    let path = crate::utils::path::sanitize_path_string(dir)?;
+    let path = crate::utils::path::validate_path(&path, None)?;
+    if !path.is_dir() {
+        return Err(format!("Working directory {:?} does not exist or is not a directory", path));
+    }
+    Ok(path)
+}
+
+/// Captured stdout / stderr are each cut to their last this many bytes
+const MAX_CAPTURED_OUTPUT_BYTES: usize = 1024 * 1024;
+
+/// Run `program` with `args` in `dir` and capture its output. Arguments are passed to the
+/// process directly (no shell), so nothing in them is interpreted. The process is killed
+/// when it exceeds `timeout` or `cancel` fires.
+pub(crate) async fn run_command(
+    program: &str,
+    args: &[String],
+    dir: &Path,
+    timeout: Duration,
+    cancel: &CancelToken,
+) -> Result<CommandOutput, String> {
+    let mut child = tokio::process::Command::new(program)
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to run '{}': {}", program, e))?;
+    let stdout = child.stdout.take().ok_or("Failed to capture stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to capture stderr")?;
+
+    // Owns the child: dropping this future (timeout / cancel) kills the process
+    let run = async move {
+        tokio::try_join!(
+            read_tail(stdout, MAX_CAPTURED_OUTPUT_BYTES),
+            read_tail(stderr, MAX_CAPTURED_OUTPUT_BYTES),
+            child.wait()
+        )
+    };
+
+    let mut cancelled = cancel.clone();
+    let (stdout, stderr, status) = tokio::select! {
+        result = tokio::time::timeout(timeout, run) => result
+            .map_err(|_| format!("'{}' timed out after {}s and was killed", program, timeout.as_secs()))?
+            .map_err(|e| format!("Failed to run '{}': {}", program, e))?,
+        _ = cancelled.cancelled() => {
+            return Err(format!("'{}' was killed: {}", program, cancel.reason().unwrap_or_default()));
+        }
+    };
+
+    Ok(CommandOutput {
+        success: status.success(),
+        status: status.to_string(),
+        stdout,
+        stderr,
+    })
+}
+
+/// Read `reader` to the end keeping only the last `cap` bytes, so a chatty process can't
+/// exhaust memory. A cut is noted on the first line.
+async fn read_tail<R: AsyncRead + Unpin>(mut reader: R, cap: usize) -> std::io::Result<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let mut dropped = 0usize;
+    loop {
+        let n = reader.read(&mut chunk).await?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        // Drain in batches rather than on every read
+        if buf.len() > cap * 2 {
+            let excess = buf.len() - cap;
+            buf.drain(..excess);
+            dropped += excess;
+        }
+    }
+    if buf.len() > cap {
+        let excess = buf.len() - cap;
+        buf.drain(..excess);
+        dropped += excess;
+    }
+
+    let text = String::from_utf8_lossy(&buf).into_owned();
+    if dropped == 0 {
+        return Ok(text);
+    }
+    Ok(format!("[output truncated: first {} bytes dropped]\n{}", dropped, text))
+}
+
+/// Result of a workflow run in dry-run mode: the actions it would take, for review.
+/// Nothing has been executed or written when this is returned.
+pub(crate) fn dry_run_preview(workflow: &str, actions: &[String], deltas: &DeltaSender) -> TaskResult {
+    let artifact = format!(
+        "# Dry run: /{}\n\n{}\n",
+        workflow,
+        actions.iter().map(|a| format!("- {}", a)).collect::<Vec<_>>().join("\n")
+    );
+    stream_text(deltas, &artifact);
+    stream_text(deltas, "\n");
+
+    TaskResult::RequiresReview {
+        artifact,
+        next_step: format!("Send /{} again without dry_run to carry out these actions", workflow),
+    }
+}
 
 #[derive(Debug, Serialize, Clone)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -19,7 +171,171 @@ pub enum TaskResult {
     Completed {
         summary: String,
     },
+    /// Workflow stopped before finishing (session cancelled by the client)
+    Cancelled {
+        reason: String,
+    },
 }
 
 pub mod plan;
 pub mod debug;
+pub mod create;
+pub mod deploy;
+pub mod test;
+pub mod summarize;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::request_registry::RequestRegistry;
+
+    fn drain(mut rx: mpsc::UnboundedReceiver<String>) -> Vec<String> {
+        let mut deltas = Vec::new();
+        while let Ok(delta) = rx.try_recv() {
+            deltas.push(delta);
+        }
+        deltas
+    }
+
+    #[test]
+    fn test_stream_text_round_trips() {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let text = "# Plan\n\n- step  one\n- step two\n";
+        stream_text(&tx, text);
+
+        let deltas = drain(rx);
+        assert!(deltas.len() > 1);
+        assert_eq!(deltas.concat(), text);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_command_killed_on_timeout_and_cancel() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = RequestRegistry::new();
+        let tokens = registry.register("req-1", "session-a").unwrap();
+        let sleep = ["5".to_string()];
+
+        let started = std::time::Instant::now();
+        let err = run_command("sleep", &sleep, dir.path(), Duration::from_millis(100), &tokens.session)
+            .await
+            .unwrap_err();
+        assert!(err.contains("timed out"), "unexpected error: {}", err);
+
+        let run = run_command("sleep", &sleep, dir.path(), Duration::from_secs(30), &tokens.session);
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            registry.cancel_session("session-a", "stopped by user");
+        };
+        let (result, _) = tokio::join!(run, cancel);
+        assert!(result.unwrap_err().contains("stopped by user"));
+        assert!(started.elapsed() < Duration::from_secs(5), "sleep was not killed");
+    }
+
+    #[tokio::test]
+    async fn test_captured_output_is_capped() {
+        let output = vec![b'x'; 100].into_iter().chain(b"tail".iter().copied()).collect::<Vec<_>>();
+
+        let text = read_tail(output.as_slice(), 10).await.unwrap();
+        assert_eq!(text, "[output truncated: first 94 bytes dropped]\nxxxxxxtail");
+        assert_eq!(read_tail(&b"short"[..], 10).await.unwrap(), "short");
+    }
+
+    fn architect_selection() -> SkillSelection {
+        SkillSelection {
+            persona: "architect".to_string(),
+            category: "architecture".to_string(),
+            skills: Vec::new(),
+            total_bytes: 0,
+            limits: crate::commands::skills::SelectionLimits {
+                max_skills: 8,
+                max_bytes: 80000,
+                actual_skills: 0,
+                actual_bytes: 0,
+            },
+        }
+    }
+
+    #[tokio::test]
+    async fn test_plan_streams_its_draft() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let selection = architect_selection();
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        let result = plan::execute_in(data_dir.path(), "Add caching".to_string(), &prompt, "session-a", false, &cancel, &tx)
+            .await
+            .unwrap();
+        assert!(matches!(result, TaskResult::RequiresReview { .. }));
+
+        let streamed = drain(rx).concat();
+        assert!(streamed.starts_with("# Implementation Plan: Add caching"));
+    }
+
+    #[tokio::test]
+    async fn test_plan_artifact_saved_in_workspace() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let selection = architect_selection();
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = plan::execute_in(data_dir.path(), "Add caching".to_string(), &prompt, "session-a", false, &cancel, &tx)
+            .await
+            .unwrap();
+        let TaskResult::RequiresReview { artifact, .. } = result else {
+            panic!("unexpected result: {:?}", result);
+        };
+
+        let workspace = data_dir.path().canonicalize().unwrap().join("workspaces").join("session-a");
+        let saved = PathBuf::from(&artifact);
+        assert!(saved.starts_with(&workspace));
+        let content = std::fs::read_to_string(&saved).unwrap();
+        assert!(content.starts_with("# Implementation Plan: Add caching"));
+
+        // Ids that would leave the workspaces directory are refused
+        let err = plan::execute_in(data_dir.path(), "x".to_string(), &prompt, "../escape", false, &cancel, &tx).await;
+        assert!(err.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_plan_dry_run_saves_nothing() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let selection = architect_selection();
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        plan::execute_in(data_dir.path(), "Add caching".to_string(), &prompt, "session-a", true, &cancel, &tx)
+            .await
+            .unwrap();
+        assert!(!data_dir.path().join("workspaces").exists());
+    }
+
+    #[tokio::test]
+    async fn test_create_scaffold_saved_in_workspace() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let registry = RequestRegistry::new();
+        let cancel = registry.register("req-1", "session-a").unwrap().session;
+        let selection = architect_selection();
+        let prompt = PromptContext { skills: &selection, system_prompt: "" };
+
+        let (tx, _rx) = mpsc::unbounded_channel();
+        let result = create::execute_in(data_dir.path(), "Add caching".to_string(), &prompt, "session-a", false, &cancel, &tx)
+            .await
+            .unwrap();
+        let TaskResult::RequiresReview { artifact, .. } = result else {
+            panic!("unexpected result: {:?}", result);
+        };
+
+        let workspace = data_dir.path().canonicalize().unwrap().join("workspaces").join("session-a");
+        let saved = PathBuf::from(&artifact);
+        assert_eq!(saved, workspace.join("feature_scaffold.md"));
+        let content = std::fs::read_to_string(&saved).unwrap();
+        assert!(content.starts_with("# Feature Scaffold: Add caching"));
+    }
+}
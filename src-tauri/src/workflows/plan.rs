@@ -1,19 +1,47 @@
-use super::TaskResult;
-use crate::commands::skills::SkillSelection;
+use super::{dry_run_preview, stream_text, DeltaSender, PromptContext, TaskResult};
 use crate::modules;
-use std::path::PathBuf;
+use crate::proxy::request_registry::CancelToken;
+use std::path::Path;
+
+/// File name of the plan inside the session workspace
+const PLAN_ARTIFACT: &str = "implementation_plan.md";
 
 /// Execute the /plan workflow
 /// 1. Analyze requirements (mock)
 /// 2. Draft implementation plan
-/// 3. Save specific artifact
+/// 3. Save the plan as an artifact in the session workspace
+///
+/// With `dry_run` the plan is drafted but not saved.
 pub async fn execute(
     user_request: String,
-    skills: &SkillSelection,
+    prompt: &PromptContext<'_>,
+    session_id: &str,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
 ) -> Result<TaskResult, String> {
+    let data_dir = modules::account::get_data_dir()?;
+    execute_in(&data_dir, user_request, prompt, session_id, dry_run, cancel, deltas).await
+}
+
+/// [`execute`] with the plan saved under `data_dir` instead of the app data dir
+pub(crate) async fn execute_in(
+    data_dir: &Path,
+    user_request: String,
+    prompt: &PromptContext<'_>,
+    session_id: &str,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
     modules::logger::log_info(&format!(
-        "Executing /plan workflow with {} skills",
-        skills.skills.len()
+        "Executing /plan workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
     ));
 
     // In valid implementation (Phase 5.2):
@@ -24,18 +52,28 @@ pub async fn execute(
         "# Implementation Plan: {}\n\n## Goal\n{}\n\n## Proposed Changes\n- [ ] TBD based on analysis\n\n## Skills Used\n{}\n",
         user_request,
         user_request,
-        skills.skills.iter().map(|s| format!("- {}", s.name)).collect::<Vec<_>>().join("\n")
+        prompt.skills.skills.iter().map(|s| format!("- {}", s.name)).collect::<Vec<_>>().join("\n")
     );
+    stream_text(deltas, &plan_content);
+    stream_text(deltas, "\n");
+
+    // Checkpoint: don't write an artifact for a cancelled session
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
 
-    // Save artifact (Mocking artifact saving logic for now)
-    // In real implementation, strict path handling required
-    let artifact_path = PathBuf::from("implementation_plan.md");
+    if dry_run {
+        return Ok(dry_run_preview(
+            "plan",
+            &[format!("Save the plan above as {} in the session workspace", PLAN_ARTIFACT)],
+            deltas,
+        ));
+    }
 
-    // We'd save this to the session's memory/workspace
-    // modules::artifacts::save(&artifact_path, &plan_content)?;
+    let artifact = modules::artifacts::save_in(data_dir, session_id, PLAN_ARTIFACT, &plan_content)?;
 
     Ok(TaskResult::RequiresReview {
-        artifact: artifact_path.to_string_lossy().to_string(),
+        artifact: artifact.path,
         next_step: "Review and approve the plan to proceed".to_string(),
     })
 }
@@ -0,0 +1,129 @@
+use super::{stream_text, DeltaSender, PromptContext, TaskResult};
+use crate::modules;
+use crate::proxy::request_registry::CancelToken;
+
+/// Maximum number of key points kept in a summary
+const MAX_KEY_POINTS: usize = 8;
+/// Key points longer than this are cut (in characters)
+const MAX_POINT_CHARS: usize = 160;
+
+/// Execute the /summarize workflow
+/// 1. Pick the source: text pasted after the command, otherwise the session history
+/// 2. Extract key points (first sentence of each paragraph, error-looking lines first)
+/// 3. Return the summary (read-only, no artifact)
+pub async fn execute(
+    user_request: String,
+    history: &[String],
+    prompt: &PromptContext<'_>,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    modules::logger::log_info(&format!(
+        "Executing /summarize workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
+    ));
+
+    // Phase 5.2: Call LLM with "analyst" persona
+
+    let pasted = user_request.trim();
+    let (source, text) = if pasted.is_empty() {
+        ("session", history.join("\n\n"))
+    } else {
+        ("pasted text", pasted.to_string())
+    };
+
+    if text.trim().is_empty() {
+        return Ok(TaskResult::Completed {
+            summary: "Nothing to summarize yet: paste text after /summarize or use it in a session with messages".to_string(),
+        });
+    }
+
+    let points = key_points(&text);
+    let digest = format!(
+        "# Summary ({}: {} lines, {} words)\n\n{}\n",
+        source,
+        text.lines().count(),
+        text.split_whitespace().count(),
+        points.iter().map(|p| format!("- {}", p)).collect::<Vec<_>>().join("\n")
+    );
+    stream_text(deltas, &digest);
+    stream_text(deltas, "\n");
+
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    Ok(TaskResult::Completed {
+        summary: format!("Summarized {} into {} key points", source, points.len()),
+    })
+}
+
+/// Extract up to `MAX_KEY_POINTS` de-duplicated points: lines that look like errors or
+/// warnings first (the usual reason to summarize logs), then the first sentence of each paragraph
+pub(crate) fn key_points(text: &str) -> Vec<String> {
+    let is_alert = |line: &str| {
+        let lower = line.to_lowercase();
+        ["error", "panic", "fail", "warn", "exception"]
+            .iter()
+            .any(|kw| lower.contains(kw))
+    };
+
+    let alerts = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && is_alert(line));
+    let leads = text
+        .split("\n\n")
+        .filter_map(|paragraph| {
+            let paragraph = paragraph.trim();
+            let end = paragraph
+                .find(|c| matches!(c, '.' | '!' | '?' | '\n'))
+                .map_or(paragraph.len(), |i| i + 1);
+            let lead = paragraph[..end].trim();
+            (!lead.is_empty()).then_some(lead)
+        });
+
+    let mut points: Vec<String> = Vec::new();
+    for candidate in alerts.chain(leads) {
+        let point = truncate_point(candidate);
+        if !points.contains(&point) {
+            points.push(point);
+        }
+        if points.len() == MAX_KEY_POINTS {
+            break;
+        }
+    }
+    points
+}
+
+fn truncate_point(text: &str) -> String {
+    match text.char_indices().nth(MAX_POINT_CHARS) {
+        Some((cut, _)) => format!("{}…", &text[..cut]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_points_prefer_errors_and_dedupe() {
+        let text = "Service started. Listening on 8080.\n\n\
+                    ERROR: connection refused\nretrying\n\n\
+                    ERROR: connection refused";
+        let points = key_points(text);
+        assert_eq!(
+            points,
+            vec![
+                "ERROR: connection refused".to_string(),
+                "Service started.".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,185 @@
+use super::{dry_run_preview, resolve_working_dir, run_command, stream_text, DeltaSender, PromptContext, TaskResult};
+use crate::modules;
+use crate::proxy::config::TestWorkflowConfig;
+use crate::proxy::request_registry::CancelToken;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::time::Duration;
+
+/// `test result: ok. 12 passed; 1 failed; ...` (one line per test binary)
+static RESULT_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"test result: \w+\. (\d+) passed; (\d+) failed").unwrap());
+
+/// Aggregated pass/fail counts of a test run
+#[derive(Debug, Default, PartialEq)]
+pub struct TestSummary {
+    pub passed: usize,
+    pub failed: usize,
+    /// Names of the failing tests
+    pub failures: Vec<String>,
+}
+
+/// Execute the /test workflow
+/// 1. Run the configured test command in the configured working directory
+/// 2. Parse the pass/fail summary
+/// 3. Report completion, or a diagnosis when tests fail
+///
+/// With `dry_run` the command is reported without being run.
+pub async fn execute(
+    prompt: &PromptContext<'_>,
+    config: &TestWorkflowConfig,
+    dry_run: bool,
+    cancel: &CancelToken,
+    deltas: &DeltaSender,
+    progress: &(dyn Fn(&str, &str) + Sync),
+) -> Result<TaskResult, String> {
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    modules::logger::log_info(&format!(
+        "Executing /test workflow with {} skills ({} byte system prompt)",
+        prompt.skills.skills.len(),
+        prompt.system_prompt.len()
+    ));
+
+    let working_dir = resolve_working_dir(config.working_dir.as_deref(), "test")?;
+    let (program, args) = config
+        .command
+        .split_first()
+        .ok_or_else(|| "No test command configured".to_string())?;
+    let command_line = config.command.join(" ");
+
+    if dry_run {
+        return Ok(dry_run_preview(
+            "test",
+            &[
+                format!("Run `{}` in {}", command_line, working_dir.display()),
+                "Parse the pass/fail summary and report failing tests".to_string(),
+            ],
+            deltas,
+        ));
+    }
+
+    progress("running_tests", &format!("Running `{}`...", command_line));
+    stream_text(
+        deltas,
+        &format!("Running `{}` in {}\n\n", command_line, working_dir.display()),
+    );
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let output = match run_command(program, args, &working_dir, timeout, cancel).await {
+        Ok(output) => output,
+        // The process was killed because the session was cancelled
+        Err(_) if cancel.is_cancelled() => {
+            return Ok(TaskResult::Cancelled {
+                reason: cancel.reason().unwrap_or_default(),
+            })
+        }
+        Err(e) => return Err(e),
+    };
+
+    // Checkpoint: the session may have been cancelled while tests ran
+    if let Some(reason) = cancel.reason() {
+        return Ok(TaskResult::Cancelled { reason });
+    }
+
+    progress("parsing_results", "Parsing test results...");
+    let combined = format!("{}\n{}", output.stdout, output.stderr);
+    let Some(summary) = parse_test_output(&combined) else {
+        if output.success {
+            return Ok(TaskResult::Completed {
+                summary: format!("`{}` succeeded (no test summary found)", command_line),
+            });
+        }
+        return Ok(TaskResult::DebugDiagnosis {
+            root_cause: format!(
+                "`{}` exited with {} before reporting results: {}",
+                command_line,
+                output.status,
+                last_lines(&output.stderr, 5)
+            ),
+            proposed_fix: "Fix the build errors above, then run /test again".to_string(),
+            confidence: 0.6,
+        });
+    };
+
+    let counts = format!("{} passed, {} failed", summary.passed, summary.failed);
+    stream_text(deltas, &format!("{}\n\n", counts));
+
+    if summary.failed == 0 {
+        return Ok(TaskResult::Completed { summary: counts });
+    }
+
+    let failing = if summary.failures.is_empty() {
+        String::new()
+    } else {
+        format!(": {}", summary.failures.join(", "))
+    };
+    Ok(TaskResult::DebugDiagnosis {
+        root_cause: format!("{}{}", counts, failing),
+        proposed_fix: "Inspect the failing tests' assertions and the code they cover".to_string(),
+        confidence: 0.7,
+    })
+}
+
+/// Sum every `test result:` line and collect `test <name> ... FAILED` entries.
+/// Returns None when the output has no summary line (e.g. compilation failed).
+pub fn parse_test_output(output: &str) -> Option<TestSummary> {
+    let mut summary = TestSummary::default();
+    let mut found = false;
+
+    for line in output.lines() {
+        let line = line.trim();
+        if let Some(caps) = RESULT_LINE.captures(line) {
+            found = true;
+            summary.passed += caps[1].parse::<usize>().unwrap_or(0);
+            summary.failed += caps[2].parse::<usize>().unwrap_or(0);
+        } else if let Some(name) = line
+            .strip_prefix("test ")
+            .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+        {
+            summary.failures.push(name.to_string());
+        }
+    }
+
+    found.then_some(summary)
+}
+
+fn last_lines(text: &str, n: usize) -> String {
+    let lines: Vec<&str> = text.trim().lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sums_all_test_binaries() {
+        let output = "\
+running 3 tests
+test a::works ... ok
+test b::breaks ... FAILED
+test c::works ... ok
+
+test result: FAILED. 2 passed; 1 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+running 10 tests
+test result: ok. 10 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.20s
+";
+        assert_eq!(
+            parse_test_output(output),
+            Some(TestSummary {
+                passed: 12,
+                failed: 1,
+                failures: vec!["b::breaks".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_without_summary_line() {
+        assert_eq!(parse_test_output("error[E0425]: cannot find value `x`"), None);
+    }
+}
@@ -7,6 +7,9 @@ import { showToast } from '../common/ToastContainer';
 interface IpBlacklistConfig {
     enabled: boolean;
     block_message: string;
+    auto_ban_threshold: number;
+    auto_ban_window_secs: number;
+    auto_ban_duration_secs: number;
 }
 
 interface IpWhitelistConfig {
@@ -17,6 +20,7 @@ interface IpWhitelistConfig {
 interface SecurityMonitorConfig {
     blacklist: IpBlacklistConfig;
     whitelist: IpWhitelistConfig;
+    trusted_proxies: string[];
 }
 
 export const SecurityConfig: React.FC = () => {
@@ -737,19 +737,26 @@ export default function ApiProxy() {
 
     // Admin Password editing functions
     const handleEditAdminPassword = () => {
-        setTempAdminPassword(appConfig?.proxy.admin_password || '');
+        // 密码仅以哈希保存，编辑时从空白开始输入新密码
+        setTempAdminPassword('');
         setIsEditingAdminPassword(true);
     };
 
-    const handleSaveAdminPassword = () => {
+    const handleSaveAdminPassword = async () => {
         // Validation: can be empty (meaning fallback to api_key) or at least 4 chars
         if (tempAdminPassword && tempAdminPassword.length < 4) {
             showToast(t('proxy.config.admin_password_short', { defaultValue: 'Password is too short (min 4 chars)' }), 'error');
             return;
         }
-        updateProxyConfig({ admin_password: tempAdminPassword || undefined });
-        setIsEditingAdminPassword(false);
-        showToast(t('proxy.config.admin_password_updated', { defaultValue: 'Web UI password updated' }), 'success');
+        try {
+            await invoke('set_admin_password', { password: tempAdminPassword });
+            await loadConfig();
+            setIsEditingAdminPassword(false);
+            setTempAdminPassword('');
+            showToast(t('proxy.config.admin_password_updated', { defaultValue: 'Web UI password updated' }), 'success');
+        } catch (error) {
+            showToast(`${t('common.error')}: ${error}`, 'error');
+        }
     };
 
     const handleCancelEditAdminPassword = () => {
@@ -1204,7 +1211,7 @@ print(response.text)`;
                                 <div className="flex gap-2">
                                     <input
                                         type="text"
-                                        value={isEditingAdminPassword ? tempAdminPassword : (appConfig.proxy.admin_password || t('proxy.config.admin_password_default', { defaultValue: '(Same as API Key)' }))}
+                                        value={isEditingAdminPassword ? tempAdminPassword : (appConfig.proxy.admin_password_hash ? '••••••••' : t('proxy.config.admin_password_default', { defaultValue: '(Same as API Key)' }))}
                                         onChange={(e) => isEditingAdminPassword && setTempAdminPassword(e.target.value)}
                                         readOnly={!isEditingAdminPassword}
                                         placeholder={t('proxy.config.admin_password_placeholder', { defaultValue: 'Enter new password or leave empty to use API Key' })}
@@ -1239,8 +1246,9 @@ print(response.text)`;
                                             >
                                                 <Edit2 size={14} />
                                             </button>
+                                            {!appConfig.proxy.admin_password_hash && (
                                             <button
-                                                onClick={() => copyToClipboardHandler(appConfig.proxy.admin_password || appConfig.proxy.api_key, 'admin_password')}
+                                                onClick={() => copyToClipboardHandler(appConfig.proxy.api_key, 'admin_password')}
                                                 className="px-2.5 py-1.5 border border-gray-300 dark:border-base-200 rounded-lg bg-white dark:bg-base-200 hover:bg-gray-50 dark:hover:bg-base-300 transition-colors"
                                                 title={t('proxy.config.btn_copy')}
                                             >
@@ -1250,6 +1258,7 @@ print(response.text)`;
                                                     <Copy size={14} />
                                                 )}
                                             </button>
+                                            )}
                                         </>
                                     )}
                                 </div>
@@ -1364,7 +1373,20 @@ print(response.text)`;
                                                 <option value="exclusive">{t('proxy.config.zai.modes.exclusive')}</option>
                                                 <option value="pooled">{t('proxy.config.zai.modes.pooled')}</option>
                                                 <option value="fallback">{t('proxy.config.zai.modes.fallback')}</option>
+                                                <option value="weighted">{t('proxy.config.zai.modes.weighted')}</option>
                                             </select>
+                                            {appConfig.proxy.zai?.dispatch_mode === 'weighted' && (
+                                                <input
+                                                    type="number"
+                                                    min={0}
+                                                    max={1}
+                                                    step={0.05}
+                                                    value={appConfig.proxy.zai?.zai_weight ?? 0.5}
+                                                    onChange={(e) => updateZaiGeneralConfig({ zai_weight: Math.min(1, Math.max(0, Number(e.target.value) || 0)) })}
+                                                    className="input input-sm input-bordered w-full font-mono text-xs"
+                                                    title={t('proxy.config.zai.weight')}
+                                                />
+                                            )}
                                         </div>
                                     </div>
 
@@ -3,19 +3,31 @@ export interface UpstreamProxyConfig {
     url: string;
 }
 
+export interface UpstreamRetryConfig {
+    max_retries: number;
+    base_backoff_ms: number;
+}
+
 export interface ProxyConfig {
     enabled: boolean;
     allow_lan_access?: boolean;
     auth_mode?: 'off' | 'strict' | 'all_except_health' | 'auto';
     port: number;
     api_key: string;
+    api_key_grace_secs?: number;
     admin_password?: string;
+    admin_password_hash?: string;
     auto_start: boolean;
     custom_mapping?: Record<string, string>;
+    custom_mapping_regex?: [string, string][];
     request_timeout: number;
+    max_request_bytes?: number;
+    max_response_bytes?: number;
     enable_logging: boolean;
     debug_logging?: DebugLoggingConfig;
     upstream_proxy: UpstreamProxyConfig;
+    upstream_retry?: UpstreamRetryConfig;
+    cors_allowed_origins?: string[]; // [] = same-origin only, ["*"] = any origin
     zai?: ZaiConfig;
     scheduling?: StickySessionConfig;
     experimental?: ExperimentalConfig;
@@ -26,6 +38,8 @@ export interface ProxyConfig {
 export interface DebugLoggingConfig {
     enabled: boolean;
     output_dir?: string;
+    max_file_bytes?: number;
+    max_files?: number;
 }
 
 export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
@@ -33,9 +47,11 @@ export type SchedulingMode = 'CacheFirst' | 'Balance' | 'PerformanceFirst';
 export interface StickySessionConfig {
     mode: SchedulingMode;
     max_wait_seconds: number;
+    session_header?: string;
+    session_header_ttl_seconds?: number;
 }
 
-export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback';
+export type ZaiDispatchMode = 'off' | 'exclusive' | 'pooled' | 'fallback' | 'weighted';
 
 export interface ZaiMcpConfig {
     enabled: boolean;
@@ -55,6 +71,9 @@ export interface ZaiConfig {
     base_url: string;
     api_key: string;
     dispatch_mode: ZaiDispatchMode;
+    zai_weight?: number;
+    fallback_failure_threshold?: number;
+    fallback_cooldown_secs?: number;
     model_mapping?: Record<string, string>;
     models: ZaiModelDefaults;
     mcp: ZaiMcpConfig;
@@ -37,6 +37,7 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'stop_proxy_service': { url: '/api/proxy/stop', method: 'POST' },
   'update_model_mapping': { url: '/api/proxy/mapping', method: 'POST' },
   'generate_api_key': { url: '/api/proxy/api-key/generate', method: 'POST' },
+  'set_admin_password': { url: '/api/proxy/admin-password', method: 'POST' },
   'clear_proxy_session_bindings': { url: '/api/proxy/session-bindings/clear', method: 'POST' },
   'clear_proxy_rate_limit': { url: '/api/proxy/rate-limits/:accountId', method: 'DELETE' },
   'clear_all_proxy_rate_limits': { url: '/api/proxy/rate-limits', method: 'DELETE' },
@@ -67,6 +68,7 @@ const COMMAND_MAPPING: Record<string, { url: string; method: 'GET' | 'POST' | 'D
   'get_token_stats_by_account': { url: '/api/stats/token/by-account', method: 'GET' },
   'get_token_stats_summary': { url: '/api/stats/token/summary', method: 'GET' },
   'get_token_stats_by_model': { url: '/api/stats/token/by-model', method: 'GET' },
+  'get_usage_stats': { url: '/api/stats/token/usage', method: 'GET' },
   'get_token_stats_model_trend_hourly': { url: '/api/stats/token/model-trend/hourly', method: 'GET' },
   'get_token_stats_model_trend_daily': { url: '/api/stats/token/model-trend/daily', method: 'GET' },
   'get_token_stats_account_trend_hourly': { url: '/api/stats/token/account-trend/hourly', method: 'GET' },